@@ -0,0 +1,581 @@
+//!
+//! [Cloudflare](https://cloudflare.com) hosts DNS zones for domains that were registered
+//! elsewhere.
+//!
+//! This crate implements the part of the Cloudflare API that deals with DNS zones and records. It
+//! authenticates using an API token.
+//!
+//! ```no_run
+//! let client = cloudflare::Builder::new()
+//!     .token("my-api-token")
+//!     .build();
+//!
+//! let zone = client.find_zone("example.com").await?;
+//!
+//! for record in client.list_records(&zone.id).await? {
+//!     eprintln!("{} {:?} {}", record.name, record.ty, record.content);
+//! }
+//! ```
+
+#[cfg(feature = "mock")]
+pub mod mock;
+
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+use async_trait::async_trait;
+use registrar_common::{retry, Cassette, DEFAULT_MAX_RETRIES, DnsRecord, DnsRecordId, DnsRecordType, Error, Registrar, Result, Timeouts, ValueExt};
+use reqwest::Method;
+use serde::{Deserialize, Serialize};
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+
+const DEFAULT_BASE_URL: &str = "https://api.cloudflare.com/client/v4";
+
+/// The amount of objects that are requested for each page of a listing.
+const PAGE_SIZE: u32 = 50;
+
+/// The TTL value Cloudflare uses to indicate that it manages the TTL of a record.
+pub const TTL_AUTOMATIC: u32 = 1;
+
+/// Error codes the Cloudflare API returns whenever the token is missing or not accepted.
+const CODES_AUTH_FAILED: &[u32] = &[ 9103, 9106, 9109, 10000 ];
+
+/// Constructs an [API client](Client).
+///
+/// ```no_run
+/// let client = cloudflare::Builder::new()
+///     .token("my-api-token")
+///     .build();
+/// ```
+pub struct Builder {
+    base_url: String,
+    token: Option<String>,
+    max_retries: u32,
+    timeouts: Timeouts,
+    cassette: Option<Arc<Cassette>>,
+}
+
+impl Default for Builder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Builder {
+
+    /// Create a new API client builder object.
+    pub fn new() -> Self {
+        Self {
+            base_url: DEFAULT_BASE_URL.to_string(),
+            token: None,
+            max_retries: DEFAULT_MAX_RETRIES,
+            timeouts: Timeouts::default(),
+            cassette: None,
+        }
+    }
+
+    /// Send requests to another server than the official Cloudflare API.
+    ///
+    /// The URL should include the version of the API, e.g. `http://localhost:8080/client/v4`.
+    pub fn base_url<S: Into<String>>(mut self, base_url: S) -> Self {
+        self.base_url = base_url.into().trim_end_matches('/').to_string();
+        self
+    }
+
+    /// Authenticate using the given API token.
+    pub fn token<S: Into<String>>(mut self, token: S) -> Self {
+        self.token = Some(token.into());
+        self
+    }
+
+    /// Retry a request at most this many times if it failed in a way that is
+    /// [transient](Error::is_transient).
+    pub fn max_retries(mut self, max_retries: u32) -> Self {
+        self.max_retries = max_retries;
+        self
+    }
+
+    /// Give up on a request if no connection could be made within the given duration.
+    pub fn connect_timeout(mut self, timeout: Duration) -> Self {
+        self.timeouts.connect = Some(timeout);
+        self
+    }
+
+    /// Give up on a request if the server does not send any data within the given duration.
+    pub fn read_timeout(mut self, timeout: Duration) -> Self {
+        self.timeouts.read = Some(timeout);
+        self
+    }
+
+    /// Give up on a request if it did not complete within the given duration.
+    ///
+    /// Use [`Client::with_timeout`] to override this deadline for specific requests.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeouts.total = Some(timeout);
+        self
+    }
+
+    /// Record all requests to the given cassette or play them back from it.
+    ///
+    /// See [`Cassette`] for more information.
+    pub fn cassette(mut self, cassette: Arc<Cassette>) -> Self {
+        self.cassette = Some(cassette);
+        self
+    }
+
+    /// Build the actual API client. This is a destructive operation.
+    pub fn build(self) -> Client {
+        let mut builder = reqwest::Client::builder();
+        if let Some(timeout) = self.timeouts.connect {
+            builder = builder.connect_timeout(timeout);
+        }
+        if let Some(timeout) = self.timeouts.read {
+            builder = builder.read_timeout(timeout);
+        }
+        if let Some(timeout) = self.timeouts.total {
+            builder = builder.timeout(timeout);
+        }
+        Client {
+            client: builder.build().expect("failed to initialize the HTTP client"),
+            base_url: self.base_url,
+            token: self.token,
+            max_retries: self.max_retries,
+            request_timeout: None,
+            cassette: self.cassette,
+            zone_ids: Arc::new(RwLock::new(HashMap::new())),
+        }
+    }
+
+}
+
+/// Represents a DNS record type that is supported by Cloudflare.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum RecordType {
+    A,
+    AAAA,
+    CAA,
+    CERT,
+    CNAME,
+    DNSKEY,
+    DS,
+    HTTPS,
+    LOC,
+    MX,
+    NAPTR,
+    NS,
+    PTR,
+    SMIMEA,
+    SRV,
+    SSHFP,
+    SVCB,
+    TLSA,
+    TXT,
+    URI,
+}
+
+/// Represents a DNS zone that is hosted on Cloudflare.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Zone {
+    pub id: String,
+    pub name: String,
+    pub status: String,
+    #[serde(default)]
+    pub paused: bool,
+    #[serde(default)]
+    pub name_servers: Vec<String>,
+}
+
+/// Represents a DNS record as it is returned by Cloudflare.
+///
+/// The name is fully qualified.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Record {
+    pub id: String,
+    pub name: String,
+    #[serde(rename = "type")]
+    pub ty: RecordType,
+    pub content: String,
+    #[serde(default)]
+    pub proxied: bool,
+    /// The TTL in seconds, or [`TTL_AUTOMATIC`].
+    pub ttl: u32,
+    pub priority: Option<u16>,
+    pub comment: Option<String>,
+    pub created_on: Option<String>,
+    pub modified_on: Option<String>,
+}
+
+/// The attributes of a DNS record that is about to be created or overwritten.
+///
+/// The name may be relative to the zone or fully qualified. Use `@` for the zone apex.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct NewRecord {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub ty: RecordType,
+    pub content: String,
+    /// The TTL in seconds, or [`TTL_AUTOMATIC`].
+    pub ttl: u32,
+    /// Whether traffic should go through Cloudflare. Only A, AAAA and CNAME records can be proxied.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub proxied: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub priority: Option<u16>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub comment: Option<String>,
+}
+
+/// Communicates with the Cloudflare API.
+///
+/// Clients are cheap to clone. All clones share the same connection pool.
+#[derive(Clone)]
+pub struct Client {
+    client: reqwest::Client,
+    base_url: String,
+    token: Option<String>,
+    max_retries: u32,
+    request_timeout: Option<Duration>,
+    cassette: Option<Arc<Cassette>>,
+    /// Maps zone names to their identifiers, so that the [`Registrar`] implementation does not
+    /// have to look them up every time.
+    zone_ids: Arc<RwLock<HashMap<String, String>>>,
+}
+
+impl Client {
+
+    /// Get a copy of this client whose requests must complete within the given duration.
+    ///
+    /// This overrides the deadline set with [`Builder::timeout`].
+    pub fn with_timeout(&self, timeout: Duration) -> Self {
+        let mut client = self.clone();
+        client.request_timeout = Some(timeout);
+        client
+    }
+
+    /// Perform a request and return the full response envelope.
+    async fn request<P: AsRef<str>>(&self, method: Method, path: P, body: Option<Value>) -> Result<Value> {
+        let path = path.as_ref();
+        let response = match &self.cassette {
+            Some(cassette) if cassette.is_replaying() => {
+                log::info!("Replaying request to {}", path);
+                cassette.find(method.as_str(), path, body.as_ref())?
+            },
+            _ => {
+                let response = retry(self.max_retries, || self.send(method.clone(), path, body.as_ref())).await?;
+                if let Some(cassette) = &self.cassette {
+                    cassette.push(method.as_str(), path, body.as_ref(), &response);
+                }
+                response
+            },
+        };
+        if response.get_ok("success")?.as_bool_ok()? {
+            return Ok(response);
+        }
+        let error = response
+            .get_ok("errors")?
+            .as_array_ok()?
+            .first()
+            .cloned()
+            .unwrap_or(Value::Null);
+        let code = error.get("code").and_then(Value::as_u64).map(|code| code as u32);
+        if code.is_some_and(|code| CODES_AUTH_FAILED.contains(&code)) {
+            return Err(Error::AuthenticationFailed);
+        }
+        Err(Error::Api {
+            code,
+            message: error.get("message").and_then(Value::as_str).unwrap_or("unknown error").to_string(),
+        })
+    }
+
+    async fn send(&self, method: Method, path: &str, body: Option<&Value>) -> Result<Value> {
+        let url = format!("{}{}", self.base_url, path);
+        log::info!("Starting request to {}", url);
+        let mut builder = self.client
+            .request(method, url)
+            .header("Accept", "application/json");
+        if let Some(timeout) = self.request_timeout {
+            builder = builder.timeout(timeout);
+        }
+        if let Some(body) = body {
+            builder = builder.json(body);
+        }
+        if let Some(token) = &self.token {
+            builder = builder.bearer_auth(token);
+        }
+        let response = builder.send().await?;
+        // Proxies in front of the API answer overload without the usual body.
+        let error = Error::StatusCode(response.status().as_u16());
+        if error.is_transient() {
+            return Err(error);
+        }
+        Ok(response.json().await?)
+    }
+
+    /// Fetch every page of a listing.
+    ///
+    /// The path may already contain a query string.
+    async fn list_all<T: DeserializeOwned>(&self, path: &str) -> Result<Vec<T>> {
+        let separator = if path.contains('?') { '&' } else { '?' };
+        let mut items = Vec::new();
+        let mut page = 1;
+        loop {
+            let response = self.request(
+                Method::GET,
+                format!("{}{}page={}&per_page={}", path, separator, page, PAGE_SIZE),
+                None
+            ).await?;
+            for item in response.get_ok("result")?.as_array_ok()? {
+                items.push(serde_json::from_value::<T>(item.clone())?);
+            }
+            let total_pages = match response.get("result_info") {
+                Some(info) => info.get_ok("total_pages")?.as_u32_ok()?,
+                None => page,
+            };
+            if page >= total_pages {
+                break;
+            }
+            page += 1;
+        }
+        Ok(items)
+    }
+
+    /// List all DNS zones the token has access to.
+    pub async fn list_zones(&self) -> Result<Vec<Zone>> {
+        self.list_all("/zones").await
+    }
+
+    /// Get a DNS zone by its identifier.
+    pub async fn get_zone<S: AsRef<str>>(&self, zone_id: S) -> Result<Zone> {
+        let response = self.request(
+            Method::GET,
+            format!("/zones/{}", zone_id.as_ref()),
+            None
+        ).await?;
+        Ok(serde_json::from_value(response.get_ok("result")?.clone())?)
+    }
+
+    /// Get a DNS zone by its name, e.g. `example.com`.
+    pub async fn find_zone<S: AsRef<str>>(&self, name: S) -> Result<Zone> {
+        let name = name.as_ref();
+        let zones: Vec<Zone> = self.list_all(&format!("/zones?name={}", name)).await?;
+        let zone = zones.into_iter()
+            .find(|zone| zone.name == name)
+            .ok_or_else(|| Error::ZoneNotFound(name.to_string()))?;
+        self.zone_ids.write().unwrap().insert(zone.name.clone(), zone.id.clone());
+        Ok(zone)
+    }
+
+    /// List all DNS records of the zone with the given identifier.
+    pub async fn list_records<S: AsRef<str>>(&self, zone_id: S) -> Result<Vec<Record>> {
+        self.list_all(&format!("/zones/{}/dns_records", zone_id.as_ref())).await
+    }
+
+    /// Get a single DNS record.
+    pub async fn get_record<S1: AsRef<str>, S2: AsRef<str>>(&self, zone_id: S1, id: S2) -> Result<Record> {
+        let response = self.request(
+            Method::GET,
+            format!("/zones/{}/dns_records/{}", zone_id.as_ref(), id.as_ref()),
+            None
+        ).await?;
+        Ok(serde_json::from_value(response.get_ok("result")?.clone())?)
+    }
+
+    /// Add a DNS record to the zone with the given identifier.
+    pub async fn create_record<S: AsRef<str>>(&self, zone_id: S, record: &NewRecord) -> Result<Record> {
+        let response = self.request(
+            Method::POST,
+            format!("/zones/{}/dns_records", zone_id.as_ref()),
+            Some(serde_json::to_value(record)?)
+        ).await?;
+        Ok(serde_json::from_value(response.get_ok("result")?.clone())?)
+    }
+
+    /// Overwrite all attributes of an existing DNS record.
+    pub async fn update_record<S1: AsRef<str>, S2: AsRef<str>>(&self, zone_id: S1, id: S2, record: &NewRecord) -> Result<Record> {
+        let response = self.request(
+            Method::PUT,
+            format!("/zones/{}/dns_records/{}", zone_id.as_ref(), id.as_ref()),
+            Some(serde_json::to_value(record)?)
+        ).await?;
+        Ok(serde_json::from_value(response.get_ok("result")?.clone())?)
+    }
+
+    /// Delete a DNS record.
+    pub async fn delete_record<S1: AsRef<str>, S2: AsRef<str>>(&self, zone_id: S1, id: S2) -> Result<()> {
+        self.request(
+            Method::DELETE,
+            format!("/zones/{}/dns_records/{}", zone_id.as_ref(), id.as_ref()),
+            None
+        ).await?;
+        Ok(())
+    }
+
+    /// Look up the identifier of a zone by its name, consulting the cache first.
+    async fn zone_id(&self, name: &str) -> Result<String> {
+        if let Some(id) = self.zone_ids.read().unwrap().get(name) {
+            return Ok(id.clone());
+        }
+        Ok(self.find_zone(name).await?.id)
+    }
+
+}
+
+impl From<RecordType> for DnsRecordType {
+    fn from(ty: RecordType) -> Self {
+        match ty {
+            RecordType::A => DnsRecordType::A,
+            RecordType::AAAA => DnsRecordType::AAAA,
+            RecordType::CAA => DnsRecordType::CAA,
+            RecordType::CERT => DnsRecordType::CERT,
+            RecordType::CNAME => DnsRecordType::CNAME,
+            RecordType::DNSKEY => DnsRecordType::DNSKEY,
+            RecordType::DS => DnsRecordType::DS,
+            RecordType::HTTPS => DnsRecordType::HTTPS,
+            RecordType::LOC => DnsRecordType::LOC,
+            RecordType::MX => DnsRecordType::MX,
+            RecordType::NAPTR => DnsRecordType::NAPTR,
+            RecordType::NS => DnsRecordType::NS,
+            RecordType::PTR => DnsRecordType::PTR,
+            RecordType::SMIMEA => DnsRecordType::SMIMEA,
+            RecordType::SRV => DnsRecordType::SRV,
+            RecordType::SSHFP => DnsRecordType::SSHFP,
+            RecordType::SVCB => DnsRecordType::SVCB,
+            RecordType::TLSA => DnsRecordType::TLSA,
+            RecordType::TXT => DnsRecordType::TXT,
+            RecordType::URI => DnsRecordType::URI,
+        }
+    }
+}
+
+impl TryFrom<DnsRecordType> for RecordType {
+
+    type Error = Error;
+
+    fn try_from(ty: DnsRecordType) -> Result<Self> {
+        Ok(match ty {
+            DnsRecordType::A => RecordType::A,
+            DnsRecordType::AAAA => RecordType::AAAA,
+            DnsRecordType::CAA => RecordType::CAA,
+            DnsRecordType::CERT => RecordType::CERT,
+            DnsRecordType::CNAME => RecordType::CNAME,
+            DnsRecordType::DNSKEY => RecordType::DNSKEY,
+            DnsRecordType::DS => RecordType::DS,
+            DnsRecordType::HTTPS => RecordType::HTTPS,
+            DnsRecordType::LOC => RecordType::LOC,
+            DnsRecordType::MX => RecordType::MX,
+            DnsRecordType::NAPTR => RecordType::NAPTR,
+            DnsRecordType::NS => RecordType::NS,
+            DnsRecordType::PTR => RecordType::PTR,
+            DnsRecordType::SMIMEA => RecordType::SMIMEA,
+            DnsRecordType::SRV => RecordType::SRV,
+            DnsRecordType::SSHFP => RecordType::SSHFP,
+            DnsRecordType::SVCB => RecordType::SVCB,
+            DnsRecordType::TLSA => RecordType::TLSA,
+            DnsRecordType::TXT => RecordType::TXT,
+            DnsRecordType::URI => RecordType::URI,
+            other => return Err(Error::UnsupportedRecordType(other)),
+        })
+    }
+
+}
+
+/// Strip the zone from a fully qualified record name, using the empty string for the zone apex.
+fn relative_name(name: &str, zone: &str) -> String {
+    if name == zone {
+        String::new()
+    } else {
+        name.strip_suffix(zone)
+            .and_then(|name| name.strip_suffix('.'))
+            .unwrap_or(name)
+            .to_string()
+    }
+}
+
+impl TryFrom<&DnsRecord> for NewRecord {
+
+    type Error = Error;
+
+    fn try_from(record: &DnsRecord) -> Result<Self> {
+        let priority = match record.priority {
+            None => None,
+            Some(priority) => Some(u16::try_from(priority).map_err(|_| Error::Generic(format!("priority {} is too large", priority)))?),
+        };
+        Ok(NewRecord {
+            name: if record.name.is_empty() { "@".to_string() } else { record.name.clone() },
+            ty: RecordType::try_from(record.ty.clone())?,
+            content: record.content.clone(),
+            ttl: record.ttl.unwrap_or(TTL_AUTOMATIC),
+            proxied: None,
+            priority,
+            comment: None,
+        })
+    }
+
+}
+
+#[async_trait]
+impl Registrar for Client {
+
+    fn supports_type(&self, ty: &DnsRecordType) -> bool {
+        RecordType::try_from(ty.clone()).is_ok()
+    }
+
+    async fn list_zones(&self) -> Result<Vec<String>> {
+        let zones = Client::list_zones(self).await?;
+        let mut zone_ids = self.zone_ids.write().unwrap();
+        Ok(zones.into_iter()
+            .map(|zone| {
+                zone_ids.insert(zone.name.clone(), zone.id);
+                zone.name
+            })
+            .collect())
+    }
+
+    async fn list_records_with_ids(&self, zone: &str) -> Result<Vec<(DnsRecordId, DnsRecord)>> {
+        let zone_id = self.zone_id(zone).await?;
+        Ok(Client::list_records(self, zone_id)
+            .await?
+            .into_iter()
+            .map(|record| (record.id, DnsRecord {
+                name: relative_name(&record.name, zone),
+                ty: record.ty.into(),
+                content: record.content,
+                ttl: if record.ttl == TTL_AUTOMATIC { None } else { Some(record.ttl) },
+                priority: record.priority.map(u32::from),
+            }))
+            .collect())
+    }
+
+    async fn create_record(&self, zone: &str, record: &DnsRecord) -> Result<DnsRecordId> {
+        let zone_id = self.zone_id(zone).await?;
+        let created = Client::create_record(self, zone_id, &NewRecord::try_from(record)?).await?;
+        Ok(created.id)
+    }
+
+    /// Whether the record is proxied and its comment are kept, since the shared record model has
+    /// no place for them.
+    async fn update_record(&self, zone: &str, id: &str, record: &DnsRecord) -> Result<DnsRecordId> {
+        let zone_id = self.zone_id(zone).await?;
+        let existing = self.get_record(&zone_id, id).await?;
+        let record = NewRecord {
+            proxied: Some(existing.proxied),
+            comment: existing.comment,
+            ..NewRecord::try_from(record)?
+        };
+        let updated = Client::update_record(self, zone_id, id, &record).await?;
+        Ok(updated.id)
+    }
+
+    async fn delete_record(&self, zone: &str, id: &str) -> Result<()> {
+        let zone_id = self.zone_id(zone).await?;
+        Client::delete_record(self, zone_id, id).await
+    }
+
+    /// Cloudflare does not list the NS records of a zone, but assigns the name servers to it.
+    async fn name_servers(&self, zone: &str) -> Result<Vec<String>> {
+        Ok(self.find_zone(zone).await?.name_servers)
+    }
+
+}
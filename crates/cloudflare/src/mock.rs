@@ -0,0 +1,360 @@
+//! An in-process fake of the Cloudflare API, meant for testing.
+//!
+//! The fake keeps all of its state in memory, checks the bearer token of each request and answers
+//! with the same `{success, errors, messages, result}` envelope as the real API. Listings are
+//! paginated just like the real ones.
+//!
+//! ```no_run
+//! use cloudflare::mock::MockServer;
+//!
+//! let server = MockServer::start("my-api-token").await;
+//! let zone_id = server.add_zone("example.com");
+//!
+//! let client = cloudflare::Builder::new()
+//!     .base_url(server.base_url())
+//!     .token("my-api-token")
+//!     .build();
+//!
+//! assert_eq!(client.list_records(zone_id).await?.len(), 0);
+//! ```
+
+use std::collections::{BTreeMap, VecDeque};
+use std::sync::{Arc, Mutex};
+
+use registrar_common::mock::{MockHttpServer, MockRequest, MockResponse};
+use reqwest::{Method, StatusCode};
+use serde_json::{json, Value};
+
+use crate::{NewRecord, Record, RecordType, Zone, TTL_AUTOMATIC};
+
+/// The error code the fake returns when the token is missing or wrong.
+pub const CODE_INVALID_TOKEN: u32 = 10000;
+
+/// The error code the fake returns when a record could not be created or updated.
+pub const CODE_INVALID_RECORD: u32 = 9004;
+
+/// The error code the fake returns when the requested object does not exist.
+pub const CODE_NOT_FOUND: u32 = 7003;
+
+const TIMESTAMP: &str = "2024-01-01T00:00:00Z";
+
+const DEFAULT_PAGE_SIZE: usize = 20;
+
+const MAX_PAGE_SIZE: usize = 5000;
+
+struct ZoneState {
+    zone: Zone,
+    records: Vec<Record>,
+}
+
+struct State {
+    token: String,
+    zones: BTreeMap<String, ZoneState>,
+    unavailable: usize,
+    errors: VecDeque<(u32, String)>,
+    requests: usize,
+    next_id: u64,
+}
+
+impl State {
+
+    fn next_id(&mut self) -> String {
+        self.next_id += 1;
+        format!("{:032x}", self.next_id)
+    }
+
+}
+
+/// A fake Cloudflare API server running on a random port on the loopback interface.
+///
+/// The server stops when this object is dropped.
+pub struct MockServer {
+    server: MockHttpServer,
+    state: Arc<Mutex<State>>,
+}
+
+impl MockServer {
+
+    /// Start a new server in the background of the current Tokio runtime that only accepts the
+    /// given API token.
+    pub async fn start<S: Into<String>>(token: S) -> Self {
+        let state = Arc::new(Mutex::new(State {
+            token: token.into(),
+            zones: BTreeMap::new(),
+            unavailable: 0,
+            errors: VecDeque::new(),
+            requests: 0,
+            next_id: 0,
+        }));
+        let handler_state = state.clone();
+        let server = MockHttpServer::start(move |request| handle(&mut handler_state.lock().unwrap(), request)).await;
+        Self { server, state }
+    }
+
+    /// The URL that should be passed to [`Builder::base_url`](crate::Builder::base_url) to talk to
+    /// this server.
+    pub fn base_url(&self) -> String {
+        format!("{}/client/v4", self.server.url())
+    }
+
+    /// Create an empty zone and return its identifier.
+    pub fn add_zone<S: Into<String>>(&self, name: S) -> String {
+        let mut state = self.state.lock().unwrap();
+        let id = state.next_id();
+        state.zones.insert(id.clone(), ZoneState {
+            zone: Zone {
+                id: id.clone(),
+                name: name.into(),
+                status: "active".to_string(),
+                paused: false,
+                name_servers: vec![
+                    "ada.ns.cloudflare.com".to_string(),
+                    "bob.ns.cloudflare.com".to_string(),
+                ],
+            },
+            records: Vec::new(),
+        });
+        id
+    }
+
+    /// Replace the name servers Cloudflare assigned to a zone.
+    pub fn set_name_servers<S: AsRef<str>>(&self, zone_id: S, name_servers: Vec<String>) {
+        let mut state = self.state.lock().unwrap();
+        let zone = state.zones.get_mut(zone_id.as_ref()).expect("zone does not exist on mock server");
+        zone.zone.name_servers = name_servers;
+    }
+
+    /// Add a record to a zone that was created with [`add_zone`](Self::add_zone).
+    ///
+    /// The name is relative to the zone, with an empty name referring to the zone apex. Returns
+    /// the identifier of the new record.
+    pub fn add_record<S: AsRef<str>>(&self, zone_id: S, name: &str, ty: RecordType, content: &str) -> String {
+        let mut state = self.state.lock().unwrap();
+        let id = state.next_id();
+        let zone = state.zones.get_mut(zone_id.as_ref()).expect("zone does not exist on mock server");
+        let name = qualify(name, &zone.zone.name);
+        zone.records.push(Record {
+            id: id.clone(),
+            name,
+            ty,
+            content: content.to_string(),
+            proxied: false,
+            ttl: TTL_AUTOMATIC,
+            priority: None,
+            comment: None,
+            created_on: Some(TIMESTAMP.to_string()),
+            modified_on: Some(TIMESTAMP.to_string()),
+        });
+        id
+    }
+
+    /// Get the records of the given zone as they are currently stored on the server.
+    pub fn records<S: AsRef<str>>(&self, zone_id: S) -> Vec<Record> {
+        self.state.lock().unwrap()
+            .zones
+            .get(zone_id.as_ref())
+            .map(|zone| zone.records.clone())
+            .unwrap_or_default()
+    }
+
+    /// Answer the next `count` requests with 503 Service Unavailable and a body that is not JSON,
+    /// like the edge of Cloudflare does when the API behind it is overloaded.
+    pub fn fail_unavailable(&self, count: usize) {
+        self.state.lock().unwrap().unavailable = count;
+    }
+
+    /// Make the next request fail with the given error code and message.
+    ///
+    /// Calling this multiple times queues up errors for the requests that follow.
+    pub fn push_error<S: Into<String>>(&self, code: u32, message: S) {
+        self.state.lock().unwrap().errors.push_back((code, message.into()));
+    }
+
+    /// The amount of requests the server has received so far.
+    pub fn request_count(&self) -> usize {
+        self.state.lock().unwrap().requests
+    }
+
+}
+
+fn qualify(name: &str, zone: &str) -> String {
+    if name.is_empty() || name == "@" || name == zone {
+        zone.to_string()
+    } else if name.ends_with(&format!(".{}", zone)) {
+        name.to_string()
+    } else {
+        format!("{}.{}", name, zone)
+    }
+}
+
+fn success(result: Value) -> MockResponse {
+    MockResponse::json(StatusCode::OK, json!({
+        "success": true,
+        "errors": [],
+        "messages": [],
+        "result": result,
+    }))
+}
+
+fn failure<S: Into<String>>(code: u32, message: S) -> MockResponse {
+    let status = match code {
+        CODE_INVALID_TOKEN => StatusCode::UNAUTHORIZED,
+        CODE_NOT_FOUND => StatusCode::NOT_FOUND,
+        _ => StatusCode::BAD_REQUEST,
+    };
+    MockResponse::json(status, json!({
+        "success": false,
+        "errors": [{ "code": code, "message": message.into() }],
+        "messages": [],
+        "result": null,
+    }))
+}
+
+/// Answer with one page of the given items, as selected by the `page` and `per_page` parameters.
+fn paginate<T: serde::Serialize>(request: &MockRequest, items: &[T]) -> MockResponse {
+    let page = request.query_param("page")
+        .and_then(|page| page.parse::<usize>().ok())
+        .unwrap_or(1)
+        .max(1);
+    let per_page = request.query_param("per_page")
+        .and_then(|per_page| per_page.parse::<usize>().ok())
+        .unwrap_or(DEFAULT_PAGE_SIZE)
+        .clamp(1, MAX_PAGE_SIZE);
+    let result: Vec<&T> = items.iter().skip((page - 1) * per_page).take(per_page).collect();
+    let mut response = json!({
+        "success": true,
+        "errors": [],
+        "messages": [],
+        "result": result,
+        "result_info": {
+            "page": page,
+            "per_page": per_page,
+            "count": result.len(),
+            "total_count": items.len(),
+            "total_pages": items.len().div_ceil(per_page),
+        },
+    });
+    if items.is_empty() {
+        response["result_info"]["total_pages"] = json!(0);
+    }
+    MockResponse::json(StatusCode::OK, response)
+}
+
+/// Turn the body of a create or update request into a record, applying the same checks as the
+/// real API.
+fn parse_record(body: Value, id: String, zone: &str) -> Result<Record, MockResponse> {
+    let record: NewRecord = serde_json::from_value(body)
+        .map_err(|error| failure(CODE_INVALID_RECORD, format!("Invalid record: {}", error)))?;
+    let proxied = record.proxied.unwrap_or(false);
+    if proxied && !matches!(record.ty, RecordType::A | RecordType::AAAA | RecordType::CNAME) {
+        return Err(failure(CODE_INVALID_RECORD, format!("Records of type {:?} cannot be proxied.", record.ty)));
+    }
+    if record.ttl != TTL_AUTOMATIC && !(60..=86400).contains(&record.ttl) {
+        return Err(failure(CODE_INVALID_RECORD, "TTL must be between 60 and 86400 seconds, or 1 for Automatic."));
+    }
+    Ok(Record {
+        id,
+        name: qualify(&record.name, zone),
+        ty: record.ty,
+        content: record.content,
+        proxied,
+        ttl: record.ttl,
+        priority: record.priority,
+        comment: record.comment,
+        created_on: Some(TIMESTAMP.to_string()),
+        modified_on: Some(TIMESTAMP.to_string()),
+    })
+}
+
+fn handle(state: &mut State, request: MockRequest) -> MockResponse {
+
+    state.requests += 1;
+
+    if state.unavailable > 0 {
+        state.unavailable -= 1;
+        return MockResponse::text(StatusCode::SERVICE_UNAVAILABLE, "Service Unavailable");
+    }
+
+    if let Some((code, message)) = state.errors.pop_front() {
+        return failure(code, message);
+    }
+
+    let Some(path) = request.path.strip_prefix("/client/v4") else {
+        return failure(CODE_NOT_FOUND, "Unknown API version");
+    };
+
+    if request.bearer() != Some(state.token.as_str()) {
+        return failure(CODE_INVALID_TOKEN, "Invalid API Token");
+    }
+
+    let path = path.trim_end_matches('/').to_string();
+    let segments: Vec<&str> = path.split('/').skip(1).collect();
+
+    match (request.method.clone(), segments.as_slice()) {
+        (Method::GET, ["zones"]) => {
+            let zones: Vec<&Zone> = state.zones.values()
+                .map(|zone| &zone.zone)
+                .filter(|zone| request.query_param("name").is_none_or(|name| zone.name == name))
+                .collect();
+            paginate(&request, &zones)
+        },
+        (Method::GET, ["zones", zone_id]) => match state.zones.get(*zone_id) {
+            None => failure(CODE_NOT_FOUND, "Invalid zone identifier"),
+            Some(zone) => success(serde_json::to_value(&zone.zone).unwrap()),
+        },
+        (Method::GET, ["zones", zone_id, "dns_records"]) => match state.zones.get(*zone_id) {
+            None => failure(CODE_NOT_FOUND, "Invalid zone identifier"),
+            Some(zone) => paginate(&request, &zone.records),
+        },
+        (Method::POST, ["zones", zone_id, "dns_records"]) => {
+            let zone_id = zone_id.to_string();
+            let id = state.next_id();
+            let Some(zone) = state.zones.get_mut(&zone_id) else {
+                return failure(CODE_NOT_FOUND, "Invalid zone identifier");
+            };
+            match parse_record(request.json(), id, &zone.zone.name) {
+                Err(response) => response,
+                Ok(record) => {
+                    let result = serde_json::to_value(&record).unwrap();
+                    zone.records.push(record);
+                    success(result)
+                },
+            }
+        },
+        (method, ["zones", zone_id, "dns_records", id]) => {
+            let Some(zone) = state.zones.get_mut(*zone_id) else {
+                return failure(CODE_NOT_FOUND, "Invalid zone identifier");
+            };
+            let Some(index) = zone.records.iter().position(|record| record.id == *id) else {
+                return failure(CODE_NOT_FOUND, "Record not found");
+            };
+            match method {
+                Method::GET => success(serde_json::to_value(&zone.records[index]).unwrap()),
+                Method::PUT | Method::PATCH => {
+                    let mut body = request.json();
+                    if method == Method::PATCH {
+                        let mut merged = serde_json::to_value(&zone.records[index]).unwrap();
+                        if let (Some(merged), Some(changes)) = (merged.as_object_mut(), body.as_object()) {
+                            merged.extend(changes.clone());
+                        }
+                        body = merged;
+                    }
+                    match parse_record(body, id.to_string(), &zone.zone.name) {
+                        Err(response) => response,
+                        Ok(record) => {
+                            let created_on = zone.records[index].created_on.clone();
+                            zone.records[index] = Record { created_on, ..record };
+                            success(serde_json::to_value(&zone.records[index]).unwrap())
+                        },
+                    }
+                },
+                Method::DELETE => {
+                    zone.records.remove(index);
+                    success(json!({ "id": id }))
+                },
+                _ => failure(CODE_NOT_FOUND, format!("No route for {} {}", method, path)),
+            }
+        },
+        (method, _) => failure(CODE_NOT_FOUND, format!("No route for {} {}", method, path)),
+    }
+}
@@ -0,0 +1,79 @@
+
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+
+use cloudflare::mock::MockServer;
+use cloudflare::{Builder, RecordType};
+use registrar_common::{AcmeChallenge, Registrar};
+use tokio::net::UdpSocket;
+
+/// Answer every TXT query with the TXT records of the given zone on the mock server, like the
+/// name servers Cloudflare assigned to the zone would.
+async fn name_server(server: Arc<MockServer>, zone_id: String) -> SocketAddr {
+    let socket = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+    let address = socket.local_addr().unwrap();
+    tokio::spawn(async move {
+        let mut buf = [0; 512];
+        loop {
+            let (length, peer) = socket.recv_from(&mut buf).await.unwrap();
+            let query = &buf[..length];
+            let mut labels = Vec::new();
+            let mut pos = 12;
+            while query[pos] != 0 {
+                let length = query[pos] as usize;
+                labels.push(String::from_utf8_lossy(&query[pos + 1..pos + 1 + length]).into_owned());
+                pos += 1 + length;
+            }
+            let name = labels.join(".");
+            let answers: Vec<String> = server.records(&zone_id).into_iter()
+                .filter(|record| record.ty == RecordType::TXT && record.name == name)
+                .map(|record| record.content)
+                .collect();
+            let mut response = query[..2].to_vec();
+            response.extend_from_slice(&[0x84, 0, 0, 1, 0, answers.len() as u8, 0, 0, 0, 0]);
+            response.extend_from_slice(&query[12..pos + 5]);
+            for answer in answers {
+                response.extend_from_slice(&[0xc0, 12, 0, 16, 0, 1, 0, 0, 0, 60, 0, answer.len() as u8 + 1, answer.len() as u8]);
+                response.extend_from_slice(answer.as_bytes());
+            }
+            socket.send_to(&response, peer).await.unwrap();
+        }
+    });
+    address
+}
+
+#[tokio::test]
+async fn name_servers_are_the_ones_cloudflare_assigned() {
+    let server = MockServer::start("token").await;
+    server.add_zone("example.com");
+    let client = Builder::new()
+        .base_url(server.base_url())
+        .token("token")
+        .build();
+    assert_eq!(
+        client.name_servers("example.com").await.unwrap(),
+        vec!["ada.ns.cloudflare.com".to_string(), "bob.ns.cloudflare.com".to_string()]
+    );
+}
+
+#[tokio::test]
+async fn challenges_are_awaited_on_the_assigned_name_servers() {
+    let server = Arc::new(MockServer::start("token").await);
+    let zone_id = server.add_zone("example.com");
+    let address = name_server(server.clone(), zone_id.clone()).await;
+    server.set_name_servers(&zone_id, vec![address.ip().to_string()]);
+    let client = Builder::new()
+        .base_url(server.base_url())
+        .token("token")
+        .build();
+
+    let challenge = AcmeChallenge::new(&client)
+        .port(address.port())
+        .interval(Duration::from_millis(10))
+        .timeout(Duration::from_secs(5));
+    challenge.present("*.example.com", "value").await.unwrap();
+    let records = server.records(&zone_id);
+    assert_eq!(records.len(), 1);
+    assert_eq!(records[0].name, "_acme-challenge.example.com");
+}
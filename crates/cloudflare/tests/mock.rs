@@ -0,0 +1,176 @@
+
+use cloudflare::mock::{MockServer, CODE_INVALID_RECORD};
+use cloudflare::{Builder, Client, NewRecord, RecordType, TTL_AUTOMATIC};
+use registrar_common::{DnsRecord, DnsRecordType, Error, Registrar};
+
+async fn setup() -> (MockServer, Client, String) {
+    let server = MockServer::start("token").await;
+    let zone_id = server.add_zone("example.com");
+    server.add_record(&zone_id, "", RecordType::A, "93.184.216.34");
+    server.add_record(&zone_id, "wiki", RecordType::A, "93.184.216.35");
+    let client = Builder::new()
+        .base_url(server.base_url())
+        .token("token")
+        .build();
+    (server, client, zone_id)
+}
+
+fn new_record(name: &str, ty: RecordType, content: &str) -> NewRecord {
+    NewRecord {
+        name: name.to_string(),
+        ty,
+        content: content.to_string(),
+        ttl: TTL_AUTOMATIC,
+        proxied: None,
+        priority: None,
+        comment: None,
+    }
+}
+
+#[tokio::test]
+async fn wrong_token_is_rejected() {
+    let (server, _, _) = setup().await;
+    let client = Builder::new()
+        .base_url(server.base_url())
+        .token("wrong")
+        .build();
+    assert!(matches!(client.list_zones().await, Err(Error::AuthenticationFailed)));
+}
+
+#[tokio::test]
+async fn zones_can_be_found_by_name() {
+    let (server, client, zone_id) = setup().await;
+    server.add_zone("example.org");
+    let zone = client.find_zone("example.com").await.unwrap();
+    assert_eq!(zone.id, zone_id);
+    assert_eq!(client.list_zones().await.unwrap().len(), 2);
+    assert!(matches!(client.find_zone("example.net").await, Err(Error::ZoneNotFound(_))));
+}
+
+#[tokio::test]
+async fn listings_follow_all_pages() {
+    let (server, client, zone_id) = setup().await;
+    for i in 0..120 {
+        server.add_record(&zone_id, &format!("host{}", i), RecordType::AAAA, "2001:db8::1");
+    }
+    let before = server.request_count();
+    assert_eq!(client.list_records(&zone_id).await.unwrap().len(), 122);
+    assert_eq!(server.request_count() - before, 3);
+}
+
+#[tokio::test]
+async fn records_can_be_created_updated_and_deleted() {
+    let (server, client, zone_id) = setup().await;
+    let mut record = new_record("www", RecordType::CNAME, "example.com");
+    record.proxied = Some(true);
+    record.comment = Some("served by the CDN".to_string());
+    let created = client.create_record(&zone_id, &record).await.unwrap();
+    assert_eq!(created.name, "www.example.com");
+    assert!(created.proxied);
+    assert_eq!(created.comment.as_deref(), Some("served by the CDN"));
+
+    record.content = "wiki.example.com".to_string();
+    record.proxied = Some(false);
+    let updated = client.update_record(&zone_id, &created.id, &record).await.unwrap();
+    assert_eq!(updated.id, created.id);
+    assert_eq!(client.get_record(&zone_id, &created.id).await.unwrap().content, "wiki.example.com");
+
+    client.delete_record(&zone_id, &created.id).await.unwrap();
+    assert_eq!(server.records(&zone_id).len(), 2);
+    assert!(matches!(client.get_record(&zone_id, &created.id).await, Err(Error::Api { .. })));
+}
+
+#[tokio::test]
+async fn only_address_records_can_be_proxied() {
+    let (_server, client, zone_id) = setup().await;
+    let mut record = new_record("", RecordType::TXT, "v=spf1 -all");
+    record.proxied = Some(true);
+    let result = client.create_record(&zone_id, &record).await;
+    assert!(matches!(result, Err(Error::Api { code: Some(CODE_INVALID_RECORD), .. })));
+}
+
+#[tokio::test]
+async fn injected_errors_are_reported() {
+    let (server, client, _) = setup().await;
+    server.push_error(10013, "Rate limited");
+    assert!(matches!(client.list_zones().await, Err(Error::Api { code: Some(10013), .. })));
+    assert!(client.list_zones().await.is_ok());
+}
+
+#[tokio::test]
+async fn unavailable_servers_are_retried() {
+    let (server, _, _) = setup().await;
+    let client = Builder::new()
+        .base_url(server.base_url())
+        .token("token")
+        .max_retries(1)
+        .build();
+
+    server.fail_unavailable(1);
+    assert!(client.list_zones().await.is_ok());
+    assert_eq!(server.request_count(), 2);
+
+    server.fail_unavailable(2);
+    assert!(matches!(client.list_zones().await, Err(Error::StatusCode(503))));
+    assert_eq!(server.request_count(), 4);
+}
+
+#[tokio::test]
+async fn client_is_a_registrar() {
+    let (server, client, zone_id) = setup().await;
+    let registrar: &dyn Registrar = &client;
+    assert_eq!(registrar.list_zones().await.unwrap(), vec!["example.com".to_string()]);
+
+    let id = registrar.create_record("example.com", &DnsRecord {
+        name: "".to_string(),
+        ty: DnsRecordType::MX,
+        content: "mail.example.com".to_string(),
+        ttl: Some(300),
+        priority: Some(10),
+    }).await.unwrap();
+    let stored = server.records(&zone_id).into_iter().find(|record| record.id == id).unwrap();
+    assert_eq!(stored.name, "example.com");
+    assert_eq!(stored.priority, Some(10));
+
+    let records = registrar.list_records("example.com").await.unwrap();
+    assert_eq!(records.len(), 3);
+    assert!(records.iter().any(|record| record.name.is_empty() && record.ty == DnsRecordType::A && record.ttl.is_none()));
+    assert!(records.iter().any(|record| record.name == "wiki"));
+    assert!(records.iter().any(|record| record.ty == DnsRecordType::MX && record.ttl == Some(300)));
+
+    let unsupported = registrar.create_record("example.com", &DnsRecord {
+        name: "old".to_string(),
+        ty: DnsRecordType::HINFO,
+        content: "PC Linux".to_string(),
+        ttl: None,
+        priority: None,
+    }).await;
+    assert!(matches!(unsupported, Err(Error::UnsupportedRecordType(DnsRecordType::HINFO))));
+}
+
+#[tokio::test]
+async fn registrar_updates_keep_cloudflare_settings() {
+    let (server, client, zone_id) = setup().await;
+    let wiki = server.records(&zone_id).into_iter().find(|record| record.name == "wiki.example.com").unwrap();
+    client.update_record(&zone_id, &wiki.id, &NewRecord {
+        proxied: Some(true),
+        comment: Some("behind the CDN".to_string()),
+        ..new_record("wiki", RecordType::A, &wiki.content)
+    }).await.unwrap();
+
+    let registrar: &dyn Registrar = &client;
+    let (id, mut record) = registrar.list_records_with_ids("example.com").await.unwrap()
+        .into_iter()
+        .find(|(_, record)| record.name == "wiki")
+        .unwrap();
+    assert_eq!(id, wiki.id);
+    record.content = "93.184.216.36".to_string();
+    assert_eq!(registrar.update_record("example.com", &id, &record).await.unwrap(), id);
+    let stored = server.records(&zone_id).into_iter().find(|record| record.id == id).unwrap();
+    assert_eq!(stored.content, "93.184.216.36");
+    assert!(stored.proxied);
+    assert_eq!(stored.comment.as_deref(), Some("behind the CDN"));
+
+    registrar.delete_record("example.com", &id).await.unwrap();
+    assert_eq!(server.records(&zone_id).len(), 1);
+}
@@ -0,0 +1,629 @@
+//!
+//! [Hetzner](https://www.hetzner.com) hosts DNS zones for domains that were registered elsewhere.
+//!
+//! This crate implements the Hetzner DNS API, which manages zones and their records, including
+//! bulk changes and the import and export of BIND zone files. It authenticates using an API token
+//! that is sent in the `Auth-API-Token` header.
+//!
+//! ```no_run
+//! let client = hetzner::Builder::new()
+//!     .token("my-api-token")
+//!     .build();
+//!
+//! let zone = client.find_zone("example.com").await?;
+//!
+//! for record in client.list_records(&zone.id).await? {
+//!     eprintln!("{} {:?} {}", record.name, record.ty, record.value);
+//! }
+//! ```
+
+#[cfg(feature = "mock")]
+pub mod mock;
+
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+use async_trait::async_trait;
+use registrar_common::{retry, Cassette, DEFAULT_MAX_RETRIES, DnsRecord, DnsRecordId, DnsRecordType, Error, Registrar, Result, Timeouts, ValueExt};
+use reqwest::Method;
+use serde::{Deserialize, Serialize};
+use serde::de::DeserializeOwned;
+use serde_json::{json, Value};
+
+const DEFAULT_BASE_URL: &str = "https://dns.hetzner.com/api/v1";
+
+/// The amount of objects that are requested for each page of a listing.
+const PAGE_SIZE: u32 = 100;
+
+/// The name Hetzner uses for records at the zone apex.
+const APEX: &str = "@";
+
+/// Constructs an [API client](Client).
+///
+/// ```no_run
+/// let client = hetzner::Builder::new()
+///     .token("my-api-token")
+///     .build();
+/// ```
+pub struct Builder {
+    base_url: String,
+    token: Option<String>,
+    max_retries: u32,
+    timeouts: Timeouts,
+    cassette: Option<Arc<Cassette>>,
+}
+
+impl Default for Builder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Builder {
+
+    /// Create a new API client builder object.
+    pub fn new() -> Self {
+        Self {
+            base_url: DEFAULT_BASE_URL.to_string(),
+            token: None,
+            max_retries: DEFAULT_MAX_RETRIES,
+            timeouts: Timeouts::default(),
+            cassette: None,
+        }
+    }
+
+    /// Send requests to another server than the official Hetzner DNS API.
+    ///
+    /// The URL should include the version of the API, e.g. `http://localhost:8080/api/v1`.
+    pub fn base_url<S: Into<String>>(mut self, base_url: S) -> Self {
+        self.base_url = base_url.into().trim_end_matches('/').to_string();
+        self
+    }
+
+    /// Authenticate using the given API token.
+    pub fn token<S: Into<String>>(mut self, token: S) -> Self {
+        self.token = Some(token.into());
+        self
+    }
+
+    /// Retry a request at most this many times if it failed in a way that is
+    /// [transient](Error::is_transient).
+    pub fn max_retries(mut self, max_retries: u32) -> Self {
+        self.max_retries = max_retries;
+        self
+    }
+
+    /// Give up on a request if no connection could be made within the given duration.
+    pub fn connect_timeout(mut self, timeout: Duration) -> Self {
+        self.timeouts.connect = Some(timeout);
+        self
+    }
+
+    /// Give up on a request if the server does not send any data within the given duration.
+    pub fn read_timeout(mut self, timeout: Duration) -> Self {
+        self.timeouts.read = Some(timeout);
+        self
+    }
+
+    /// Give up on a request if it did not complete within the given duration.
+    ///
+    /// Use [`Client::with_timeout`] to override this deadline for specific requests.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeouts.total = Some(timeout);
+        self
+    }
+
+    /// Record all requests to the given cassette or play them back from it.
+    ///
+    /// See [`Cassette`] for more information.
+    pub fn cassette(mut self, cassette: Arc<Cassette>) -> Self {
+        self.cassette = Some(cassette);
+        self
+    }
+
+    /// Build the actual API client. This is a destructive operation.
+    pub fn build(self) -> Client {
+        let mut builder = reqwest::Client::builder();
+        if let Some(timeout) = self.timeouts.connect {
+            builder = builder.connect_timeout(timeout);
+        }
+        if let Some(timeout) = self.timeouts.read {
+            builder = builder.read_timeout(timeout);
+        }
+        if let Some(timeout) = self.timeouts.total {
+            builder = builder.timeout(timeout);
+        }
+        Client {
+            client: builder.build().expect("failed to initialize the HTTP client"),
+            base_url: self.base_url,
+            token: self.token,
+            max_retries: self.max_retries,
+            request_timeout: None,
+            cassette: self.cassette,
+            zone_ids: Arc::new(RwLock::new(HashMap::new())),
+        }
+    }
+
+}
+
+/// Represents a DNS record type that is supported by Hetzner.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum RecordType {
+    A,
+    AAAA,
+    CAA,
+    CNAME,
+    /// An alias of [`TLSA`](RecordType::TLSA) that older zones still use.
+    DANE,
+    DS,
+    HINFO,
+    MX,
+    NS,
+    PTR,
+    RP,
+    SOA,
+    SRV,
+    TLSA,
+    TXT,
+}
+
+/// Represents a DNS zone that is hosted on Hetzner.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Zone {
+    pub id: String,
+    pub name: String,
+    /// The default TTL of the records in this zone.
+    pub ttl: Option<u32>,
+    pub status: String,
+    #[serde(default)]
+    pub paused: bool,
+    /// The name servers the zone should be delegated to.
+    #[serde(default)]
+    pub ns: Vec<String>,
+    #[serde(default)]
+    pub records_count: u32,
+}
+
+/// Represents a DNS record as it is returned by Hetzner.
+///
+/// The name is relative to the zone, with `@` referring to the zone apex. For MX and SRV records,
+/// the priority is the first field of the value.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Record {
+    pub id: String,
+    pub zone_id: String,
+    pub name: String,
+    #[serde(rename = "type")]
+    pub ty: RecordType,
+    pub value: String,
+    /// The TTL in seconds, or `None` to use the default TTL of the zone.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ttl: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub created: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub modified: Option<String>,
+}
+
+/// The attributes of a DNS record that is about to be created or overwritten.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct NewRecord {
+    pub zone_id: String,
+    pub name: String,
+    #[serde(rename = "type")]
+    pub ty: RecordType,
+    pub value: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ttl: Option<u32>,
+}
+
+/// The outcome of a bulk operation on records.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct BulkRecords {
+    /// The records that were created or updated.
+    #[serde(default)]
+    pub records: Vec<Record>,
+    /// The records that were rejected by the API.
+    #[serde(default, alias = "invalid_records", alias = "failed_records")]
+    pub failed: Vec<NewRecord>,
+}
+
+/// Communicates with the Hetzner DNS API.
+///
+/// Clients are cheap to clone. All clones share the same connection pool.
+#[derive(Clone)]
+pub struct Client {
+    client: reqwest::Client,
+    base_url: String,
+    token: Option<String>,
+    max_retries: u32,
+    request_timeout: Option<Duration>,
+    cassette: Option<Arc<Cassette>>,
+    /// Maps zone names to their identifiers, so that the [`Registrar`] implementation does not
+    /// have to look them up every time.
+    zone_ids: Arc<RwLock<HashMap<String, String>>>,
+}
+
+impl Client {
+
+    /// Get a copy of this client whose requests must complete within the given duration.
+    ///
+    /// This overrides the deadline set with [`Builder::timeout`].
+    pub fn with_timeout(&self, timeout: Duration) -> Self {
+        let mut client = self.clone();
+        client.request_timeout = Some(timeout);
+        client
+    }
+
+    /// Perform a request and return the body of the response.
+    ///
+    /// Bodies that are not JSON, such as zone files, are returned as a JSON string.
+    async fn request<P: AsRef<str>>(&self, method: Method, path: P, body: Option<Value>) -> Result<Value> {
+        let path = path.as_ref();
+        let response = match &self.cassette {
+            Some(cassette) if cassette.is_replaying() => {
+                log::info!("Replaying request to {}", path);
+                cassette.find(method.as_str(), path, body.as_ref())?
+            },
+            _ => {
+                let response = retry(self.max_retries, || self.send(method.clone(), path, body.as_ref())).await?;
+                if let Some(cassette) = &self.cassette {
+                    cassette.push(method.as_str(), path, body.as_ref(), &response);
+                }
+                response
+            },
+        };
+        let status = response.get_ok("status")?.as_u32_ok()?;
+        let body = response.get_ok("body")?;
+        if (200..300).contains(&status) {
+            return Ok(body.clone());
+        }
+        if status == 401 || status == 403 {
+            return Err(Error::AuthenticationFailed);
+        }
+        let message = body.get("error")
+            .and_then(|error| error.get("message"))
+            .or_else(|| body.get("message"))
+            .and_then(Value::as_str)
+            .or_else(|| body.as_str())
+            .filter(|message| !message.is_empty())
+            .unwrap_or("unknown error");
+        Err(Error::Api {
+            code: Some(status),
+            message: message.to_string(),
+        })
+    }
+
+    /// Send a request to the API and wrap the response in a `{status, body}` object.
+    ///
+    /// A string body is sent as a zone file instead of JSON.
+    async fn send(&self, method: Method, path: &str, body: Option<&Value>) -> Result<Value> {
+        let url = format!("{}{}", self.base_url, path);
+        log::info!("Starting request to {}", url);
+        let mut builder = self.client
+            .request(method, url)
+            .header("Accept", "application/json");
+        if let Some(timeout) = self.request_timeout {
+            builder = builder.timeout(timeout);
+        }
+        match body {
+            Some(Value::String(text)) => {
+                builder = builder
+                    .header("Content-Type", "text/plain")
+                    .body(text.clone());
+            },
+            Some(body) => builder = builder.json(body),
+            None => {},
+        }
+        if let Some(token) = &self.token {
+            builder = builder.header("Auth-API-Token", token);
+        }
+        let response = builder.send().await?;
+        let status = response.status().as_u16();
+        // These are left to `retry` rather than reported as errors of the API.
+        let error = Error::StatusCode(status);
+        if error.is_transient() {
+            return Err(error);
+        }
+        let is_json = response.headers()
+            .get("Content-Type")
+            .and_then(|value| value.to_str().ok())
+            .is_some_and(|value| value.contains("json"));
+        let text = response.text().await?;
+        let body = if is_json {
+            serde_json::from_str(&text)?
+        } else {
+            Value::String(text)
+        };
+        Ok(json!({ "status": status, "body": body }))
+    }
+
+    /// Fetch every page of a listing.
+    ///
+    /// The path may already contain a query string. The items are read from the given key of each
+    /// response.
+    async fn list_all<T: DeserializeOwned>(&self, path: &str, key: &str) -> Result<Vec<T>> {
+        let separator = if path.contains('?') { '&' } else { '?' };
+        let mut items = Vec::new();
+        let mut page = 1;
+        loop {
+            let response = self.request(
+                Method::GET,
+                format!("{}{}page={}&per_page={}", path, separator, page, PAGE_SIZE),
+                None
+            ).await?;
+            for item in response.get_ok(key)?.as_array_ok()? {
+                items.push(serde_json::from_value::<T>(item.clone())?);
+            }
+            let last_page = match response.get("meta").and_then(|meta| meta.get("pagination")) {
+                Some(pagination) => pagination.get_ok("last_page")?.as_u32_ok()?,
+                None => page,
+            };
+            if page >= last_page {
+                break;
+            }
+            page += 1;
+        }
+        Ok(items)
+    }
+
+    /// List all DNS zones the token has access to.
+    pub async fn list_zones(&self) -> Result<Vec<Zone>> {
+        self.list_all("/zones", "zones").await
+    }
+
+    /// Get a DNS zone by its identifier.
+    pub async fn get_zone<S: AsRef<str>>(&self, zone_id: S) -> Result<Zone> {
+        let response = self.request(
+            Method::GET,
+            format!("/zones/{}", zone_id.as_ref()),
+            None
+        ).await?;
+        Ok(serde_json::from_value(response.get_ok("zone")?.clone())?)
+    }
+
+    /// Get a DNS zone by its name, e.g. `example.com`.
+    pub async fn find_zone<S: AsRef<str>>(&self, name: S) -> Result<Zone> {
+        let name = name.as_ref();
+        let zones: Vec<Zone> = self.list_all(&format!("/zones?name={}", name), "zones").await?;
+        let zone = zones.into_iter()
+            .find(|zone| zone.name == name)
+            .ok_or_else(|| Error::ZoneNotFound(name.to_string()))?;
+        self.zone_ids.write().unwrap().insert(zone.name.clone(), zone.id.clone());
+        Ok(zone)
+    }
+
+    /// Download the zone with the given identifier as a BIND zone file.
+    pub async fn export_zone<S: AsRef<str>>(&self, zone_id: S) -> Result<String> {
+        let response = self.request(
+            Method::GET,
+            format!("/zones/{}/export", zone_id.as_ref()),
+            None
+        ).await?;
+        Ok(response.as_str_ok()?.to_string())
+    }
+
+    /// Replace all records of the zone with the given identifier with the ones in a BIND zone
+    /// file.
+    pub async fn import_zone<S1: AsRef<str>, S2: Into<String>>(&self, zone_id: S1, zone_file: S2) -> Result<Zone> {
+        let response = self.request(
+            Method::POST,
+            format!("/zones/{}/import", zone_id.as_ref()),
+            Some(Value::String(zone_file.into()))
+        ).await?;
+        Ok(serde_json::from_value(response.get_ok("zone")?.clone())?)
+    }
+
+    /// List all DNS records of the zone with the given identifier.
+    pub async fn list_records<S: AsRef<str>>(&self, zone_id: S) -> Result<Vec<Record>> {
+        self.list_all(&format!("/records?zone_id={}", zone_id.as_ref()), "records").await
+    }
+
+    /// Get a single DNS record.
+    pub async fn get_record<S: AsRef<str>>(&self, id: S) -> Result<Record> {
+        let response = self.request(
+            Method::GET,
+            format!("/records/{}", id.as_ref()),
+            None
+        ).await?;
+        Ok(serde_json::from_value(response.get_ok("record")?.clone())?)
+    }
+
+    /// Add a DNS record to the zone it refers to.
+    pub async fn create_record(&self, record: &NewRecord) -> Result<Record> {
+        let response = self.request(
+            Method::POST,
+            "/records",
+            Some(serde_json::to_value(record)?)
+        ).await?;
+        Ok(serde_json::from_value(response.get_ok("record")?.clone())?)
+    }
+
+    /// Overwrite all attributes of an existing DNS record.
+    pub async fn update_record<S: AsRef<str>>(&self, id: S, record: &NewRecord) -> Result<Record> {
+        let response = self.request(
+            Method::PUT,
+            format!("/records/{}", id.as_ref()),
+            Some(serde_json::to_value(record)?)
+        ).await?;
+        Ok(serde_json::from_value(response.get_ok("record")?.clone())?)
+    }
+
+    /// Delete a DNS record.
+    pub async fn delete_record<S: AsRef<str>>(&self, id: S) -> Result<()> {
+        self.request(
+            Method::DELETE,
+            format!("/records/{}", id.as_ref()),
+            None
+        ).await?;
+        Ok(())
+    }
+
+    /// Create many records at once.
+    ///
+    /// Records that are rejected do not cause the whole operation to fail. They are reported in
+    /// [`BulkRecords::failed`] instead.
+    pub async fn create_records(&self, records: &[NewRecord]) -> Result<BulkRecords> {
+        let response = self.request(
+            Method::POST,
+            "/records/bulk",
+            Some(json!({ "records": records }))
+        ).await?;
+        Ok(serde_json::from_value(response)?)
+    }
+
+    /// Update many records at once, using the identifiers of the given records.
+    ///
+    /// Records that are rejected do not cause the whole operation to fail. They are reported in
+    /// [`BulkRecords::failed`] instead.
+    pub async fn update_records(&self, records: &[Record]) -> Result<BulkRecords> {
+        let response = self.request(
+            Method::PUT,
+            "/records/bulk",
+            Some(json!({ "records": records }))
+        ).await?;
+        Ok(serde_json::from_value(response)?)
+    }
+
+    /// Look up the identifier of a zone by its name, consulting the cache first.
+    async fn zone_id(&self, name: &str) -> Result<String> {
+        if let Some(id) = self.zone_ids.read().unwrap().get(name) {
+            return Ok(id.clone());
+        }
+        Ok(self.find_zone(name).await?.id)
+    }
+
+}
+
+impl From<RecordType> for DnsRecordType {
+    fn from(ty: RecordType) -> Self {
+        match ty {
+            RecordType::A => DnsRecordType::A,
+            RecordType::AAAA => DnsRecordType::AAAA,
+            RecordType::CAA => DnsRecordType::CAA,
+            RecordType::CNAME => DnsRecordType::CNAME,
+            RecordType::DANE | RecordType::TLSA => DnsRecordType::TLSA,
+            RecordType::DS => DnsRecordType::DS,
+            RecordType::HINFO => DnsRecordType::HINFO,
+            RecordType::MX => DnsRecordType::MX,
+            RecordType::NS => DnsRecordType::NS,
+            RecordType::PTR => DnsRecordType::PTR,
+            RecordType::RP => DnsRecordType::RP,
+            RecordType::SOA => DnsRecordType::SOA,
+            RecordType::SRV => DnsRecordType::SRV,
+            RecordType::TXT => DnsRecordType::TXT,
+        }
+    }
+}
+
+impl TryFrom<DnsRecordType> for RecordType {
+
+    type Error = Error;
+
+    fn try_from(ty: DnsRecordType) -> Result<Self> {
+        Ok(match ty {
+            DnsRecordType::A => RecordType::A,
+            DnsRecordType::AAAA => RecordType::AAAA,
+            DnsRecordType::CAA => RecordType::CAA,
+            DnsRecordType::CNAME => RecordType::CNAME,
+            DnsRecordType::DS => RecordType::DS,
+            DnsRecordType::HINFO => RecordType::HINFO,
+            DnsRecordType::MX => RecordType::MX,
+            DnsRecordType::NS => RecordType::NS,
+            DnsRecordType::PTR => RecordType::PTR,
+            DnsRecordType::RP => RecordType::RP,
+            DnsRecordType::SOA => RecordType::SOA,
+            DnsRecordType::SRV => RecordType::SRV,
+            DnsRecordType::TLSA => RecordType::TLSA,
+            DnsRecordType::TXT => RecordType::TXT,
+            other => return Err(Error::UnsupportedRecordType(other)),
+        })
+    }
+
+}
+
+/// Whether the priority of records of the given type is stored in front of their value.
+fn has_priority(ty: &RecordType) -> bool {
+    matches!(ty, RecordType::MX | RecordType::SRV)
+}
+
+impl From<Record> for DnsRecord {
+    fn from(record: Record) -> Self {
+        let (priority, content) = match record.value.split_once(' ') {
+            Some((priority, content)) if has_priority(&record.ty) => match priority.parse() {
+                Ok(priority) => (Some(priority), content.to_string()),
+                Err(_) => (None, record.value),
+            },
+            _ => (None, record.value),
+        };
+        DnsRecord {
+            name: if record.name == APEX { String::new() } else { record.name },
+            ty: record.ty.into(),
+            content,
+            ttl: record.ttl,
+            priority,
+        }
+    }
+}
+
+/// Convert a record of the shared model, which keeps the priority in a separate field.
+fn new_record(zone_id: String, record: &DnsRecord) -> Result<NewRecord> {
+    let ty = RecordType::try_from(record.ty.clone())?;
+    let value = match record.priority {
+        Some(priority) if has_priority(&ty) => format!("{} {}", priority, record.content),
+        _ => record.content.clone(),
+    };
+    Ok(NewRecord {
+        zone_id,
+        name: if record.name.is_empty() { APEX.to_string() } else { record.name.clone() },
+        ty,
+        value,
+        ttl: record.ttl,
+    })
+}
+
+#[async_trait]
+impl Registrar for Client {
+
+    fn supports_type(&self, ty: &DnsRecordType) -> bool {
+        RecordType::try_from(ty.clone()).is_ok()
+    }
+
+    async fn list_zones(&self) -> Result<Vec<String>> {
+        let zones = Client::list_zones(self).await?;
+        let mut zone_ids = self.zone_ids.write().unwrap();
+        Ok(zones.into_iter()
+            .map(|zone| {
+                zone_ids.insert(zone.name.clone(), zone.id);
+                zone.name
+            })
+            .collect())
+    }
+
+    async fn list_records_with_ids(&self, zone: &str) -> Result<Vec<(DnsRecordId, DnsRecord)>> {
+        let zone_id = self.zone_id(zone).await?;
+        Ok(Client::list_records(self, zone_id)
+            .await?
+            .into_iter()
+            .map(|record| (record.id.clone(), DnsRecord::from(record)))
+            .collect())
+    }
+
+    async fn create_record(&self, zone: &str, record: &DnsRecord) -> Result<DnsRecordId> {
+        let zone_id = self.zone_id(zone).await?;
+        let created = Client::create_record(self, &new_record(zone_id, record)?).await?;
+        Ok(created.id)
+    }
+
+    async fn update_record(&self, zone: &str, id: &str, record: &DnsRecord) -> Result<DnsRecordId> {
+        let zone_id = self.zone_id(zone).await?;
+        let updated = Client::update_record(self, id, &new_record(zone_id, record)?).await?;
+        Ok(updated.id)
+    }
+
+    async fn delete_record(&self, _zone: &str, id: &str) -> Result<()> {
+        Client::delete_record(self, id).await
+    }
+
+}
@@ -0,0 +1,413 @@
+//! An in-process fake of the Hetzner DNS API, meant for testing.
+//!
+//! The fake keeps all of its state in memory, checks the `Auth-API-Token` header of each request
+//! and answers with the same objects and status codes as the real API. Zone files are exported
+//! and imported in a simplified BIND format with one record per line.
+//!
+//! ```no_run
+//! use hetzner::mock::MockServer;
+//!
+//! let server = MockServer::start("my-api-token").await;
+//! let zone_id = server.add_zone("example.com");
+//!
+//! let client = hetzner::Builder::new()
+//!     .base_url(server.base_url())
+//!     .token("my-api-token")
+//!     .build();
+//!
+//! assert_eq!(client.list_records(zone_id).await?.len(), 0);
+//! ```
+
+use std::collections::{BTreeMap, VecDeque};
+use std::sync::{Arc, Mutex};
+
+use registrar_common::mock::{MockHttpServer, MockRequest, MockResponse};
+use reqwest::{Method, StatusCode};
+use serde_json::{json, Value};
+
+use crate::{NewRecord, Record, RecordType, Zone};
+
+const DEFAULT_TTL: u32 = 86400;
+
+const TIMESTAMP: &str = "2024-01-01 00:00:00.000 +0000 UTC";
+
+const DEFAULT_PAGE_SIZE: usize = 100;
+
+struct ZoneState {
+    zone: Zone,
+    records: Vec<Record>,
+}
+
+struct State {
+    token: String,
+    zones: BTreeMap<String, ZoneState>,
+    errors: VecDeque<(StatusCode, String)>,
+    requests: usize,
+    next_id: u64,
+}
+
+impl State {
+
+    fn next_id(&mut self) -> String {
+        self.next_id += 1;
+        format!("{:032x}", self.next_id)
+    }
+
+    fn find_record(&mut self, id: &str) -> Option<&mut Record> {
+        self.zones.values_mut()
+            .flat_map(|zone| zone.records.iter_mut())
+            .find(|record| record.id == id)
+    }
+
+}
+
+/// A fake Hetzner DNS API server running on a random port on the loopback interface.
+///
+/// The server stops when this object is dropped.
+pub struct MockServer {
+    server: MockHttpServer,
+    state: Arc<Mutex<State>>,
+}
+
+impl MockServer {
+
+    /// Start a new server in the background of the current Tokio runtime that only accepts the
+    /// given API token.
+    pub async fn start<S: Into<String>>(token: S) -> Self {
+        let state = Arc::new(Mutex::new(State {
+            token: token.into(),
+            zones: BTreeMap::new(),
+            errors: VecDeque::new(),
+            requests: 0,
+            next_id: 0,
+        }));
+        let handler_state = state.clone();
+        let server = MockHttpServer::start(move |request| handle(&mut handler_state.lock().unwrap(), request)).await;
+        Self { server, state }
+    }
+
+    /// The URL that should be passed to [`Builder::base_url`](crate::Builder::base_url) to talk to
+    /// this server.
+    pub fn base_url(&self) -> String {
+        format!("{}/api/v1", self.server.url())
+    }
+
+    /// Create an empty zone and return its identifier.
+    pub fn add_zone<S: Into<String>>(&self, name: S) -> String {
+        let mut state = self.state.lock().unwrap();
+        let id = state.next_id();
+        state.zones.insert(id.clone(), ZoneState {
+            zone: Zone {
+                id: id.clone(),
+                name: name.into(),
+                ttl: Some(DEFAULT_TTL),
+                status: "verified".to_string(),
+                paused: false,
+                ns: vec![
+                    "hydrogen.ns.hetzner.com".to_string(),
+                    "oxygen.ns.hetzner.com".to_string(),
+                    "helium.ns.hetzner.de".to_string(),
+                ],
+                records_count: 0,
+            },
+            records: Vec::new(),
+        });
+        id
+    }
+
+    /// Add a record to a zone that was created with [`add_zone`](Self::add_zone).
+    ///
+    /// The name is relative to the zone, with `@` referring to the zone apex. Returns the
+    /// identifier of the new record.
+    pub fn add_record<S: AsRef<str>>(&self, zone_id: S, name: &str, ty: RecordType, value: &str) -> String {
+        let zone_id = zone_id.as_ref();
+        let mut state = self.state.lock().unwrap();
+        let id = state.next_id();
+        let zone = state.zones.get_mut(zone_id).expect("zone does not exist on mock server");
+        zone.records.push(Record {
+            id: id.clone(),
+            zone_id: zone_id.to_string(),
+            name: name.to_string(),
+            ty,
+            value: value.to_string(),
+            ttl: None,
+            created: Some(TIMESTAMP.to_string()),
+            modified: Some(TIMESTAMP.to_string()),
+        });
+        zone.zone.records_count = zone.records.len() as u32;
+        id
+    }
+
+    /// Get the records of the given zone as they are currently stored on the server.
+    pub fn records<S: AsRef<str>>(&self, zone_id: S) -> Vec<Record> {
+        self.state.lock().unwrap()
+            .zones
+            .get(zone_id.as_ref())
+            .map(|zone| zone.records.clone())
+            .unwrap_or_default()
+    }
+
+    /// Make the next request fail with the given HTTP status and message.
+    ///
+    /// Calling this multiple times queues up errors for the requests that follow.
+    pub fn push_error<S: Into<String>>(&self, status: StatusCode, message: S) {
+        self.state.lock().unwrap().errors.push_back((status, message.into()));
+    }
+
+    /// The amount of requests the server has received so far.
+    pub fn request_count(&self) -> usize {
+        self.state.lock().unwrap().requests
+    }
+
+}
+
+fn failure<S: Into<String>>(status: StatusCode, message: S) -> MockResponse {
+    MockResponse::json(status, json!({ "error": { "message": message.into(), "code": status.as_u16() } }))
+}
+
+fn not_found(what: &str) -> MockResponse {
+    failure(StatusCode::NOT_FOUND, format!("{} not found", what))
+}
+
+/// Answer with one page of the given items, as selected by the `page` and `per_page` parameters.
+fn paginate<T: serde::Serialize>(request: &MockRequest, key: &str, items: &[T]) -> MockResponse {
+    let page = request.query_param("page")
+        .and_then(|page| page.parse::<usize>().ok())
+        .unwrap_or(1)
+        .max(1);
+    let per_page = request.query_param("per_page")
+        .and_then(|per_page| per_page.parse::<usize>().ok())
+        .unwrap_or(DEFAULT_PAGE_SIZE)
+        .max(1);
+    let last_page = items.len().div_ceil(per_page).max(1);
+    let result: Vec<&T> = items.iter().skip((page - 1) * per_page).take(per_page).collect();
+    MockResponse::json(StatusCode::OK, json!({
+        key: result,
+        "meta": {
+            "pagination": {
+                "page": page,
+                "per_page": per_page,
+                "previous_page": page.saturating_sub(1).max(1),
+                "next_page": (page + 1).min(last_page),
+                "last_page": last_page,
+                "total_entries": items.len(),
+            },
+        },
+    }))
+}
+
+/// Check the fields of a record that is about to be stored.
+fn parse_record(value: Value, state: &State) -> Result<NewRecord, String> {
+    let record: NewRecord = serde_json::from_value(value).map_err(|error| format!("invalid record: {}", error))?;
+    if !state.zones.contains_key(&record.zone_id) {
+        return Err("zone not found".to_string());
+    }
+    if record.name.is_empty() || record.value.is_empty() {
+        return Err("name and value must not be empty".to_string());
+    }
+    if matches!(record.ty, RecordType::MX | RecordType::SRV)
+        && record.value.split_once(' ').and_then(|(priority, _)| priority.parse::<u16>().ok()).is_none() {
+        return Err(format!("{:?} records must start with a priority", record.ty));
+    }
+    Ok(record)
+}
+
+fn store_record(state: &mut State, id: String, record: NewRecord) -> Record {
+    let stored = Record {
+        id,
+        zone_id: record.zone_id,
+        name: record.name,
+        ty: record.ty,
+        value: record.value,
+        ttl: record.ttl,
+        created: Some(TIMESTAMP.to_string()),
+        modified: Some(TIMESTAMP.to_string()),
+    };
+    if let Some(existing) = state.find_record(&stored.id) {
+        *existing = Record { created: existing.created.clone(), ..stored.clone() };
+    } else {
+        let zone = state.zones.get_mut(&stored.zone_id).unwrap();
+        zone.records.push(stored.clone());
+        zone.zone.records_count = zone.records.len() as u32;
+    }
+    stored
+}
+
+fn export(zone: &ZoneState) -> String {
+    let mut output = format!("$ORIGIN {}.\n$TTL {}\n", zone.zone.name, zone.zone.ttl.unwrap_or(DEFAULT_TTL));
+    for record in &zone.records {
+        let ttl = record.ttl.map(|ttl| ttl.to_string()).unwrap_or_default();
+        output.push_str(&format!("{}\t{}\tIN\t{:?}\t{}\n", record.name, ttl, record.ty, record.value));
+    }
+    output
+}
+
+/// Parse a zone file in the format produced by [`export`].
+fn import(zone_id: &str, text: &str) -> Result<Vec<NewRecord>, String> {
+    let mut records = Vec::new();
+    for line in text.lines() {
+        let line = line.split(';').next().unwrap_or("").trim_end();
+        if line.trim().is_empty() || line.starts_with('$') {
+            continue;
+        }
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        let (name, rest) = match fields.split_first() {
+            Some((name, rest)) if !line.starts_with(char::is_whitespace) => (name.to_string(), rest),
+            _ => return Err(format!("record without a name: {}", line)),
+        };
+        let (ttl, rest) = match rest.split_first() {
+            Some((ttl, rest)) if ttl.parse::<u32>().is_ok() => (ttl.parse().ok(), rest),
+            _ => (None, rest),
+        };
+        let rest = rest.strip_prefix(&["IN"]).unwrap_or(rest);
+        let Some((ty, value)) = rest.split_first() else {
+            return Err(format!("record without a type: {}", line));
+        };
+        let ty = serde_json::from_value::<RecordType>(Value::String(ty.to_string()))
+            .map_err(|_| format!("unsupported record type {}", ty))?;
+        records.push(NewRecord {
+            zone_id: zone_id.to_string(),
+            name,
+            ty,
+            value: value.join(" "),
+            ttl,
+        });
+    }
+    Ok(records)
+}
+
+fn handle(state: &mut State, request: MockRequest) -> MockResponse {
+
+    state.requests += 1;
+
+    if let Some((status, message)) = state.errors.pop_front() {
+        return failure(status, message);
+    }
+
+    let Some(path) = request.path.strip_prefix("/api/v1") else {
+        return not_found("API version");
+    };
+
+    if request.header("auth-api-token") != Some(state.token.as_str()) {
+        return MockResponse::json(StatusCode::UNAUTHORIZED, json!({ "message": "Invalid authentication credentials" }));
+    }
+
+    let path = path.trim_end_matches('/').to_string();
+    let segments: Vec<&str> = path.split('/').skip(1).collect();
+
+    match (request.method.clone(), segments.as_slice()) {
+        (Method::GET, ["zones"]) => {
+            let zones: Vec<&Zone> = state.zones.values()
+                .map(|zone| &zone.zone)
+                .filter(|zone| request.query_param("name").is_none_or(|name| zone.name == name))
+                .collect();
+            paginate(&request, "zones", &zones)
+        },
+        (Method::GET, ["zones", zone_id]) => match state.zones.get(*zone_id) {
+            None => not_found("zone"),
+            Some(zone) => MockResponse::json(StatusCode::OK, json!({ "zone": zone.zone })),
+        },
+        (Method::GET, ["zones", zone_id, "export"]) => match state.zones.get(*zone_id) {
+            None => not_found("zone"),
+            Some(zone) => MockResponse::text(StatusCode::OK, export(zone)),
+        },
+        (Method::POST, ["zones", zone_id, "import"]) => {
+            let zone_id = zone_id.to_string();
+            if !state.zones.contains_key(&zone_id) {
+                return not_found("zone");
+            }
+            let records = match import(&zone_id, &request.text()) {
+                Ok(records) => records,
+                Err(message) => return failure(StatusCode::UNPROCESSABLE_ENTITY, message),
+            };
+            state.zones.get_mut(&zone_id).unwrap().records.clear();
+            for record in records {
+                let id = state.next_id();
+                store_record(state, id, record);
+            }
+            let zone = state.zones.get_mut(&zone_id).unwrap();
+            zone.zone.records_count = zone.records.len() as u32;
+            MockResponse::json(StatusCode::OK, json!({ "zone": zone.zone }))
+        },
+        (Method::GET, ["records"]) => {
+            let Some(zone) = request.query_param("zone_id").and_then(|zone_id| state.zones.get(zone_id)) else {
+                return not_found("zone");
+            };
+            paginate(&request, "records", &zone.records)
+        },
+        (Method::POST, ["records"]) => match parse_record(request.json(), state) {
+            Err(message) => failure(StatusCode::UNPROCESSABLE_ENTITY, message),
+            Ok(record) => {
+                let id = state.next_id();
+                let record = store_record(state, id, record);
+                MockResponse::json(StatusCode::OK, json!({ "record": record }))
+            },
+        },
+        (Method::POST, ["records", "bulk"]) => {
+            let body = request.json();
+            let mut records = Vec::new();
+            let mut valid = Vec::new();
+            let mut invalid = Vec::new();
+            for value in body.get("records").and_then(Value::as_array).cloned().unwrap_or_default() {
+                match parse_record(value.clone(), state) {
+                    Err(_) => invalid.push(value),
+                    Ok(record) => {
+                        let id = state.next_id();
+                        valid.push(value);
+                        records.push(store_record(state, id, record));
+                    },
+                }
+            }
+            MockResponse::json(StatusCode::OK, json!({
+                "records": records,
+                "valid_records": valid,
+                "invalid_records": invalid,
+            }))
+        },
+        (Method::PUT, ["records", "bulk"]) => {
+            let body = request.json();
+            let mut records = Vec::new();
+            let mut failed = Vec::new();
+            for value in body.get("records").and_then(Value::as_array).cloned().unwrap_or_default() {
+                let id = value.get("id").and_then(Value::as_str).unwrap_or("").to_string();
+                match parse_record(value.clone(), state) {
+                    Ok(record) if state.find_record(&id).is_some() => records.push(store_record(state, id, record)),
+                    _ => failed.push(value),
+                }
+            }
+            MockResponse::json(StatusCode::OK, json!({
+                "records": records,
+                "failed_records": failed,
+            }))
+        },
+        (Method::GET, ["records", id]) => match state.find_record(id) {
+            None => not_found("record"),
+            Some(record) => MockResponse::json(StatusCode::OK, json!({ "record": record })),
+        },
+        (Method::PUT, ["records", id]) => {
+            let id = id.to_string();
+            if state.find_record(&id).is_none() {
+                return not_found("record");
+            }
+            match parse_record(request.json(), state) {
+                Err(message) => failure(StatusCode::UNPROCESSABLE_ENTITY, message),
+                Ok(record) => {
+                    let record = store_record(state, id, record);
+                    MockResponse::json(StatusCode::OK, json!({ "record": record }))
+                },
+            }
+        },
+        (Method::DELETE, ["records", id]) => {
+            for zone in state.zones.values_mut() {
+                let count = zone.records.len();
+                zone.records.retain(|record| record.id != *id);
+                if zone.records.len() != count {
+                    zone.zone.records_count = zone.records.len() as u32;
+                    return MockResponse::json(StatusCode::OK, json!({}));
+                }
+            }
+            not_found("record")
+        },
+        (method, _) => failure(StatusCode::NOT_FOUND, format!("no route for {} {}", method, path)),
+    }
+}
@@ -0,0 +1,206 @@
+
+use hetzner::mock::MockServer;
+use hetzner::{Builder, Client, NewRecord, RecordType};
+use registrar_common::{DnsRecord, DnsRecordType, Error, Registrar};
+use reqwest::StatusCode;
+
+async fn setup() -> (MockServer, Client, String) {
+    let server = MockServer::start("token").await;
+    let zone_id = server.add_zone("example.com");
+    server.add_record(&zone_id, "@", RecordType::A, "93.184.216.34");
+    server.add_record(&zone_id, "wiki", RecordType::A, "93.184.216.35");
+    let client = Builder::new()
+        .base_url(server.base_url())
+        .token("token")
+        .build();
+    (server, client, zone_id)
+}
+
+fn new_record(zone_id: &str, name: &str, ty: RecordType, value: &str) -> NewRecord {
+    NewRecord {
+        zone_id: zone_id.to_string(),
+        name: name.to_string(),
+        ty,
+        value: value.to_string(),
+        ttl: None,
+    }
+}
+
+#[tokio::test]
+async fn wrong_token_is_rejected() {
+    let (server, _, _) = setup().await;
+    let client = Builder::new()
+        .base_url(server.base_url())
+        .token("wrong")
+        .build();
+    assert!(matches!(client.list_zones().await, Err(Error::AuthenticationFailed)));
+}
+
+#[tokio::test]
+async fn zones_can_be_found_by_name() {
+    let (server, client, zone_id) = setup().await;
+    server.add_zone("example.org");
+    let zone = client.find_zone("example.com").await.unwrap();
+    assert_eq!(zone.id, zone_id);
+    assert_eq!(zone.records_count, 2);
+    assert_eq!(client.list_zones().await.unwrap().len(), 2);
+    assert!(matches!(client.find_zone("example.net").await, Err(Error::ZoneNotFound(_))));
+}
+
+#[tokio::test]
+async fn listings_follow_all_pages() {
+    let (server, client, zone_id) = setup().await;
+    for i in 0..250 {
+        server.add_record(&zone_id, &format!("host{}", i), RecordType::AAAA, "2001:db8::1");
+    }
+    let before = server.request_count();
+    assert_eq!(client.list_records(&zone_id).await.unwrap().len(), 252);
+    assert_eq!(server.request_count() - before, 3);
+}
+
+#[tokio::test]
+async fn records_can_be_created_updated_and_deleted() {
+    let (server, client, zone_id) = setup().await;
+    let mut record = new_record(&zone_id, "www", RecordType::CNAME, "example.com.");
+    let created = client.create_record(&record).await.unwrap();
+    assert_eq!(created.name, "www");
+
+    record.value = "wiki.example.com.".to_string();
+    record.ttl = Some(300);
+    client.update_record(&created.id, &record).await.unwrap();
+    let updated = client.get_record(&created.id).await.unwrap();
+    assert_eq!(updated.value, "wiki.example.com.");
+    assert_eq!(updated.ttl, Some(300));
+
+    client.delete_record(&created.id).await.unwrap();
+    assert_eq!(server.records(&zone_id).len(), 2);
+    assert!(matches!(client.get_record(&created.id).await, Err(Error::Api { code: Some(404), .. })));
+}
+
+#[tokio::test]
+async fn bulk_operations_report_rejected_records() {
+    let (server, client, zone_id) = setup().await;
+    let result = client.create_records(&[
+        new_record(&zone_id, "a", RecordType::A, "192.0.2.1"),
+        new_record(&zone_id, "@", RecordType::MX, "mail.example.com."),
+        new_record(&zone_id, "b", RecordType::A, "192.0.2.2"),
+    ]).await.unwrap();
+    assert_eq!(result.records.len(), 2);
+    assert_eq!(result.failed.len(), 1);
+    assert_eq!(result.failed[0].ty, RecordType::MX);
+
+    let mut records = result.records;
+    for record in &mut records {
+        record.ttl = Some(60);
+    }
+    let result = client.update_records(&records).await.unwrap();
+    assert_eq!(result.records.len(), 2);
+    assert!(result.failed.is_empty());
+    assert_eq!(server.records(&zone_id).iter().filter(|record| record.ttl == Some(60)).count(), 2);
+}
+
+#[tokio::test]
+async fn zones_can_be_exported_and_imported() {
+    let (server, client, zone_id) = setup().await;
+    let zone_file = client.export_zone(&zone_id).await.unwrap();
+    assert!(zone_file.contains("$ORIGIN example.com."));
+    assert!(zone_file.contains("wiki\t\tIN\tA\t93.184.216.35"));
+
+    let other_id = server.add_zone("example.org");
+    let zone = client.import_zone(&other_id, zone_file + "@ 3600 IN MX 10 mail.example.org.\n").await.unwrap();
+    assert_eq!(zone.records_count, 3);
+    let records = client.list_records(&other_id).await.unwrap();
+    assert!(records.iter().any(|record| record.ty == RecordType::MX && record.ttl == Some(3600)));
+
+    let result = client.import_zone(&other_id, "@ IN BOGUS value\n").await;
+    assert!(matches!(result, Err(Error::Api { code: Some(422), .. })));
+}
+
+#[tokio::test]
+async fn injected_errors_are_reported() {
+    let (server, client, _) = setup().await;
+    server.push_error(StatusCode::UNPROCESSABLE_ENTITY, "invalid zone name");
+    match client.list_zones().await {
+        Err(Error::Api { code, message }) => {
+            assert_eq!(code, Some(422));
+            assert_eq!(message, "invalid zone name");
+        },
+        other => panic!("unexpected result: {:?}", other.map(|zones| zones.len())),
+    }
+    assert!(client.list_zones().await.is_ok());
+}
+
+#[tokio::test]
+async fn rate_limits_are_waited_out() {
+    let (server, _, _) = setup().await;
+    let client = Builder::new()
+        .base_url(server.base_url())
+        .token("token")
+        .max_retries(1)
+        .build();
+
+    server.push_error(StatusCode::TOO_MANY_REQUESTS, "rate limit exceeded");
+    assert!(client.list_zones().await.is_ok());
+    assert_eq!(server.request_count(), 2);
+
+    server.push_error(StatusCode::TOO_MANY_REQUESTS, "rate limit exceeded");
+    server.push_error(StatusCode::TOO_MANY_REQUESTS, "rate limit exceeded");
+    assert!(matches!(client.list_zones().await, Err(Error::StatusCode(429))));
+    assert_eq!(server.request_count(), 4);
+}
+
+#[tokio::test]
+async fn client_is_a_registrar() {
+    let (server, client, zone_id) = setup().await;
+    let registrar: &dyn Registrar = &client;
+    assert_eq!(registrar.list_zones().await.unwrap(), vec!["example.com".to_string()]);
+
+    let id = registrar.create_record("example.com", &DnsRecord {
+        name: "".to_string(),
+        ty: DnsRecordType::MX,
+        content: "mail.example.com.".to_string(),
+        ttl: Some(300),
+        priority: Some(10),
+    }).await.unwrap();
+    let stored = server.records(&zone_id).into_iter().find(|record| record.id == id).unwrap();
+    assert_eq!(stored.name, "@");
+    assert_eq!(stored.value, "10 mail.example.com.");
+
+    let records = registrar.list_records("example.com").await.unwrap();
+    assert_eq!(records.len(), 3);
+    assert!(records.iter().any(|record| record.name.is_empty() && record.ty == DnsRecordType::A));
+    assert!(records.iter().any(|record| record.name == "wiki"));
+    assert!(records.iter().any(|record| {
+        record.ty == DnsRecordType::MX && record.priority == Some(10) && record.content == "mail.example.com."
+    }));
+
+    let unsupported = registrar.create_record("example.com", &DnsRecord {
+        name: "www".to_string(),
+        ty: DnsRecordType::HTTPS,
+        content: "1 . alpn=h2".to_string(),
+        ttl: None,
+        priority: None,
+    }).await;
+    assert!(matches!(unsupported, Err(Error::UnsupportedRecordType(DnsRecordType::HTTPS))));
+}
+
+#[tokio::test]
+async fn registrar_addresses_records_by_native_id() {
+    let (server, client, zone_id) = setup().await;
+    let mx = server.add_record(&zone_id, "@", RecordType::MX, "10 mail.example.com.");
+    let registrar: &dyn Registrar = &client;
+    let (id, mut record) = registrar.list_records_with_ids("example.com").await.unwrap()
+        .into_iter()
+        .find(|(_, record)| record.ty == DnsRecordType::MX)
+        .unwrap();
+    assert_eq!(id, mx);
+
+    record.priority = Some(20);
+    assert_eq!(registrar.update_record("example.com", &id, &record).await.unwrap(), id);
+    let stored = server.records(&zone_id).into_iter().find(|record| record.id == id).unwrap();
+    assert_eq!(stored.value, "20 mail.example.com.");
+
+    registrar.delete_record("example.com", &id).await.unwrap();
+    assert_eq!(server.records(&zone_id).len(), 2);
+    assert!(matches!(registrar.delete_record("example.com", &id).await, Err(Error::Api { code: Some(404), .. })));
+}
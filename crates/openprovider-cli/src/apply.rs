@@ -0,0 +1,79 @@
+
+//! Making a zone match the records in a file, as `zone apply` and `zone import` do.
+
+use std::path::Path;
+
+use anyhow::{bail, Result};
+use registrar_common::{Change, DnsRecord, MemoryRegistrar, Mirror, MirrorReport};
+
+use crate::formats::{self, FileFormat};
+
+/// Read the desired records of a zone from a file, and return them together with the name of
+/// the zone.
+///
+/// The format is detected from the name and contents of the file unless it is given. The name of
+/// the zone is taken from the file unless it is given.
+pub fn read_records_file(path: &Path, format: Option<FileFormat>, zone: Option<&str>) -> Result<(String, Vec<DnsRecord>)> {
+    let text = std::fs::read_to_string(path)?;
+    let format = format.unwrap_or_else(|| formats::detect(path, &text));
+    let (origin, records) = formats::read(format, &text, zone)?;
+    match zone.map(str::to_string).or(origin) {
+        Some(zone) => Ok((zone.trim_end_matches('.').to_string(), records)),
+        None => bail!("{} does not say which zone it describes; please pass --zone", path.display()),
+    }
+}
+
+/// A registrar that holds nothing but the given records in the given zone, to
+/// [mirror](Mirror) a zone from.
+pub fn desired_zone(zone: &str, records: Vec<DnsRecord>) -> MemoryRegistrar {
+    let desired = MemoryRegistrar::new();
+    desired.add_zone(zone);
+    for record in records {
+        desired.add_record(zone, record);
+    }
+    desired
+}
+
+/// What became of a plan.
+#[derive(Debug)]
+pub enum PlanOutcome {
+    /// The zone already matched, so there was nothing to do.
+    UpToDate,
+    /// The zone has drifted, but nothing was changed because this was a dry run.
+    Drifted,
+    /// The changes were not confirmed.
+    Declined,
+    /// The changes were applied, though some of them may have been refused.
+    Applied(MirrorReport),
+}
+
+impl PlanOutcome {
+
+    /// The exit status of the CLI: 2 if a dry run found drift, 1 if changes were declined or
+    /// refused and 0 otherwise.
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            PlanOutcome::UpToDate => 0,
+            PlanOutcome::Drifted => 2,
+            PlanOutcome::Declined => 1,
+            PlanOutcome::Applied(report) if !report.unsupported.is_empty() || !report.failed.is_empty() => 1,
+            PlanOutcome::Applied(_) => 0,
+        }
+    }
+
+}
+
+/// Apply a plan to a zone, unless it is empty, this is a dry run or `confirm` does not agree to
+/// the changes.
+pub async fn run_plan<F: FnOnce(&[Change]) -> bool>(mirror: &Mirror<'_>, zone: &str, plan: Vec<Change>, dry_run: bool, confirm: F) -> PlanOutcome {
+    if plan.is_empty() {
+        return PlanOutcome::UpToDate;
+    }
+    if dry_run {
+        return PlanOutcome::Drifted;
+    }
+    if !confirm(&plan) {
+        return PlanOutcome::Declined;
+    }
+    PlanOutcome::Applied(mirror.apply(zone, plan).await)
+}
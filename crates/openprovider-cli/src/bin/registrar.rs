@@ -0,0 +1,466 @@
+
+use std::net::{IpAddr, SocketAddr};
+use std::path::PathBuf;
+use std::time::Duration;
+
+use anyhow::{bail, Result};
+use clap::{arg, command, value_parser, ArgAction, ArgMatches, Command};
+use registrar_common::{dns01_value, AcmeChallenge, DnsRecord, DnsRecordId, Registrar};
+use serde::Serialize;
+use serde_json::json;
+use tokio::signal::unix::{signal, SignalKind};
+
+use openprovider_cli::config::{Config, Flags};
+use openprovider_cli::ddns::{IpSource, Updater};
+use openprovider_cli::formats::{self, FileFormat};
+use openprovider_cli::output::Output;
+use openprovider_cli::providers::{self, PROVIDERS};
+use openprovider_cli::records::{describe, relative_name};
+
+const NAME_COLUMNS: [&str; 1] = ["name"];
+
+/// Answers with the address the request came from, over IPv4 as well as IPv6.
+const DEFAULT_IP_URL: &str = "https://api64.ipify.org";
+
+const RECORD_COLUMNS: [&str; 5] = ["name", "type", "content", "ttl", "priority"];
+
+/// A record together with the identifier its provider knows it by.
+#[derive(Serialize)]
+struct RecordRow<'a> {
+    id: &'a str,
+    #[serde(flatten)]
+    record: &'a DnsRecord,
+}
+
+/// Find the records of a zone with the given name and type, and optionally the given content.
+async fn find_records(registrar: &dyn Registrar, zone: &str, matches: &ArgMatches, value: Option<&String>) -> Result<Vec<(DnsRecordId, DnsRecord)>> {
+    let name = relative_name(matches.get_one::<String>("name").unwrap(), zone);
+    let ty = matches.get_one::<String>("type").unwrap().parse()?;
+    Ok(registrar.list_records_with_ids(zone)
+        .await?
+        .into_iter()
+        .filter(|(_, record)| record.name.eq_ignore_ascii_case(&name) && record.ty == ty)
+        .filter(|(_, record)| value.is_none_or(|value| record.content.trim_end_matches('.') == value.trim_end_matches('.')))
+        .collect())
+}
+
+/// Make sure exactly one record matched.
+fn single_record(mut records: Vec<(DnsRecordId, DnsRecord)>) -> Result<(DnsRecordId, DnsRecord)> {
+    match records.len() {
+        0 => bail!("no matching record found"),
+        1 => Ok(records.remove(0)),
+        _ => {
+            let found: Vec<String> = records.iter().map(|(_, record)| format!("  {}", describe(record))).collect();
+            bail!("more than one record matches, please give the current value as well:\n{}", found.join("\n"))
+        },
+    }
+}
+
+fn parse_name_server(text: &str) -> Result<SocketAddr> {
+    match text.parse::<IpAddr>() {
+        Ok(address) => Ok(SocketAddr::new(address, 53)),
+        Err(_) => Ok(text.parse()?),
+    }
+}
+
+fn acme_command(name: &'static str, about: &'static str) -> Command {
+    Command::new(name)
+        .about(about)
+        .arg(arg!([domain] "The domain being validated; certbot passes it in CERTBOT_DOMAIN instead"))
+        .arg(arg!([value] "The value of the TXT record, or the token if the key authorization follows; certbot passes it in CERTBOT_VALIDATION instead"))
+        .arg(arg!([key_authorization] "The key authorization, from which the value is computed instead"))
+}
+
+fn parse_format(text: &str) -> Result<FileFormat> {
+    text.parse()
+}
+
+fn cli() -> Command {
+    let provider_names: Vec<&str> = PROVIDERS.iter().map(|provider| provider.name).collect();
+    Output::args(command!("registrar"))
+        .about("Manage DNS zones and domains at any supported provider")
+        .subcommand_required(true)
+        .arg(arg!(--provider <NAME> "The provider to manage, instead of the one of the profile").value_parser(provider_names))
+        .arg(arg!(--profile <NAME> "Use the settings and credentials of this profile"))
+        .arg(arg!(--timeout <SECONDS> "Give up on a request after this many seconds").value_parser(value_parser!(u64)))
+        .arg(arg!(--"max-retries" <COUNT> "Retry a failed request at most this many times").value_parser(value_parser!(u32)))
+        .subcommand(Config::command())
+        .subcommand(
+            Command::new("providers")
+                .about("list the supported providers and the credentials they take")
+        )
+        .subcommand(
+            Command::new("zone")
+                .about("manage DNS zones")
+                .subcommand_required(true)
+                .subcommand(
+                    Command::new("list")
+                        .about("list all DNS zones")
+                )
+                .subcommand(
+                    Command::new("export")
+                        .about("write all records of a DNS zone to a file")
+                        .arg(arg!(<name> "The name of the zone"))
+                        .arg(arg!(--format <FORMAT> "The format to write: bind, json, yaml, csv or toml").value_parser(parse_format).default_value("bind"))
+                        .arg(arg!(-o --output <FILE> "Write to this file instead of the standard output"))
+                )
+        )
+        .subcommand(
+            Command::new("record")
+                .about("manage DNS records")
+                .subcommand_required(true)
+                .subcommand(
+                    Command::new("list")
+                        .about("list all DNS records of a zone")
+                        .arg(arg!(<zone> "Name of the DNS zone"))
+                )
+                .subcommand(
+                    Command::new("add")
+                        .about("add a DNS record to a zone")
+                        .arg(arg!(<zone> "Name of the DNS zone the record belongs to"))
+                        .arg(arg!(<name> "Name of the record relative to the zone, or @ for the zone itself"))
+                        .arg(arg!(<type> "Type of the record, such as A or MX"))
+                        .arg(arg!(<value> "Value of the record"))
+                        .arg(arg!(--ttl <SECONDS> "Time to live of the record").value_parser(value_parser!(u32)))
+                        .arg(arg!(--priority <PRIORITY> "Priority of the record, for MX and SRV records").value_parser(value_parser!(u32)))
+                )
+                .subcommand(
+                    Command::new("set")
+                        .about("update a DNS record")
+                        .arg(arg!(<zone> "Name of the DNS zone the record belongs to"))
+                        .arg(arg!(<name> "Name of the record relative to the zone, or @ for the zone itself"))
+                        .arg(arg!(<type> "Type of the record, such as A or MX"))
+                        .arg(arg!(<value> "New value of the record"))
+                        .arg(arg!(--old <VALUE> "Current value of the record, if there are several with the same name and type"))
+                        .arg(arg!(--ttl <SECONDS> "New time to live of the record").value_parser(value_parser!(u32)))
+                        .arg(arg!(--priority <PRIORITY> "New priority of the record").value_parser(value_parser!(u32)))
+                )
+                .subcommand(
+                    Command::new("delete")
+                        .about("remove a DNS record from a zone")
+                        .arg(arg!(<zone> "Name of the DNS zone the record belongs to"))
+                        .arg(arg!(<name> "Name of the record relative to the zone, or @ for the zone itself"))
+                        .arg(arg!(<type> "Type of the record, such as A or MX"))
+                        .arg(arg!([value] "Value of the record, if there are several with the same name and type"))
+                        .arg(arg!(--all "Remove all records that match instead of exactly one").action(ArgAction::SetTrue))
+                )
+        )
+        .subcommand(
+            Command::new("domain")
+                .about("manage the delegation of domains")
+                .subcommand_required(true)
+                .subcommand(
+                    Command::new("nameservers")
+                        .about("list the name servers a domain is delegated to")
+                        .arg(arg!(<domain> "The name of the domain"))
+                )
+                .subcommand(
+                    Command::new("set-nameservers")
+                        .about("delegate a domain to other name servers")
+                        .arg(arg!(<domain> "The name of the domain"))
+                        .arg(arg!(<servers> ... "The host names of the name servers"))
+                )
+        )
+        .subcommand(
+            Command::new("ddns")
+                .about("keep A and AAAA records pointed at the public addresses of this host")
+                .arg(arg!(<zone> "Name of the DNS zone the records belong to"))
+                .arg(arg!(<names> ... "Names of the records relative to the zone, or @ for the zone itself"))
+                .arg(arg!(--source <SOURCE> "How to find the public addresses").value_parser(["http", "porkbun", "interface"]).default_value("http"))
+                .arg(arg!(--url <URL> "The endpoint that tells the address with --source http").default_value(DEFAULT_IP_URL))
+                .arg(arg!(--interface <NAME> "The network interface to read with --source interface").required_if_eq("source", "interface"))
+                .arg(arg!(--ipv4 "Only update A records").action(ArgAction::SetTrue).conflicts_with("ipv6"))
+                .arg(arg!(--ipv6 "Only update AAAA records").action(ArgAction::SetTrue))
+                .arg(arg!(--ttl <SECONDS> "Time to live of the records").value_parser(value_parser!(u32)))
+                .arg(arg!(--interval <SECONDS> "How long to wait between checks").value_parser(value_parser!(u64).range(1..)).default_value("300"))
+                .arg(arg!(--once "Check and update once instead of running until stopped").action(ArgAction::SetTrue))
+                .arg(arg!(--cache <FILE> "Where to remember the last addresses"))
+        )
+        .subcommand(
+            Command::new("acme")
+                .about("answer ACME DNS-01 challenges, as a hook of certbot, lego or acme.sh")
+                .subcommand_required(true)
+                .arg(arg!(--ttl <SECONDS> "Time to live of the TXT records").value_parser(value_parser!(u32)).global(true))
+                .arg(arg!(--"no-wait" "Return without waiting for the name servers to serve the record").action(ArgAction::SetTrue).global(true))
+                .arg(arg!(--"wait-timeout" <SECONDS> "Give up waiting for the name servers after this long").value_parser(value_parser!(u64)).default_value("300").global(true))
+                .arg(arg!(--"name-server" <ADDRESS> "Check this name server instead of those in the NS records of the zone").value_parser(parse_name_server).action(ArgAction::Append).global(true))
+                .subcommand(acme_command("present", "create the TXT record for a challenge and wait until it is served"))
+                .subcommand(acme_command("cleanup", "remove the TXT record for a challenge"))
+        )
+        .subcommand(
+            Command::new("openprovider")
+                .about("commands that only OpenProvider supports")
+                .subcommand_required(true)
+                .subcommand(
+                    Command::new("login")
+                        .about("authenticate with OpenProvider")
+                        .arg(arg!(<username> "The username you use to login"))
+                        .arg(arg!(<password> "The password you use to login"))
+                        .arg(arg!(--save "Save credentials in the credential store of the profile for later use").action(ArgAction::SetTrue))
+                        .arg(arg!(--"token-only" "Only save the token, never the password").action(ArgAction::SetTrue).requires("save"))
+                )
+                .subcommand(
+                    Command::new("zone-info")
+                        .about("show everything OpenProvider knows about a DNS zone")
+                        .arg(arg!(<name> "The name of the zone"))
+                )
+        )
+        .subcommand(
+            Command::new("porkbun")
+                .about("commands that only Porkbun supports")
+                .subcommand_required(true)
+                .subcommand(
+                    Command::new("ping")
+                        .about("check the API keys and show the IP address Porkbun sees")
+                )
+        )
+}
+
+/// Check the addresses every interval and update the records when they changed, until SIGTERM or
+/// Ctrl-C arrives.
+async fn ddns(config: &Config, matches: &ArgMatches) -> Result<()> {
+    let zone = matches.get_one::<String>("zone").unwrap();
+    let names = matches.get_many::<String>("names").unwrap().map(|name| relative_name(name, zone)).collect();
+    let source = match matches.get_one::<String>("source").unwrap().as_str() {
+        "porkbun" => IpSource::Porkbun(providers::porkbun(config)?),
+        "interface" => IpSource::Interface(matches.get_one::<String>("interface").unwrap().clone()),
+        _ => IpSource::Http {
+            url: matches.get_one::<String>("url").unwrap().clone(),
+            timeout: config.timeout.value.map(Duration::from_secs),
+        },
+    };
+    let (ipv4, ipv6) = match (matches.get_flag("ipv4"), matches.get_flag("ipv6")) {
+        (false, false) => (true, true),
+        flags => flags,
+    };
+    let cache = match matches.get_one::<String>("cache") {
+        Some(path) => PathBuf::from(path),
+        None => dirs::cache_dir()
+            .unwrap_or(std::env::temp_dir())
+            .join("registrar")
+            .join(format!("ddns-{}-{}.json", config.profile, zone)),
+    };
+    let mut updater = Updater::new(zone, names)
+        .ttl(matches.get_one::<u32>("ttl").copied())
+        .cache(cache);
+    let registrar = registrar(config).await?;
+    let interval = Duration::from_secs(*matches.get_one::<u64>("interval").unwrap());
+    let mut terminate = signal(SignalKind::terminate())?;
+
+    loop {
+        let result = async {
+            let addresses = source.detect(ipv4, ipv6).await?;
+            for record in updater.update(&*registrar, addresses).await? {
+                println!("Updated {}", describe(&record));
+            }
+            anyhow::Ok(())
+        }.await;
+        if matches.get_flag("once") {
+            return result;
+        }
+        // A daemon should outlive a network outage, so it only reports the failure.
+        if let Err(error) = result {
+            eprintln!("Error: {}", error);
+        }
+        tokio::select! {
+            _ = tokio::time::sleep(interval) => {},
+            _ = terminate.recv() => break,
+            _ = tokio::signal::ctrl_c() => break,
+        }
+    }
+
+    log::info!("stopping");
+    Ok(())
+}
+
+/// Create or remove the TXT record of a challenge, taking the domain and value the way certbot,
+/// lego and acme.sh hand them to hooks.
+async fn acme(config: &Config, matches: &ArgMatches) -> Result<()> {
+    let Some((action, args)) = matches.subcommand() else {
+        bail!("please provide a subcommand");
+    };
+    let (domain, value) = match (args.get_one::<String>("domain"), args.get_one::<String>("value")) {
+        (Some(domain), Some(value)) => match args.get_one::<String>("key_authorization") {
+            Some(key_authorization) => (domain.clone(), dns01_value(key_authorization)),
+            None => (domain.clone(), value.clone()),
+        },
+        (None, None) => match (std::env::var("CERTBOT_DOMAIN"), std::env::var("CERTBOT_VALIDATION")) {
+            (Ok(domain), Ok(value)) => (domain, value),
+            _ => bail!("please give the domain and value, or set CERTBOT_DOMAIN and CERTBOT_VALIDATION"),
+        },
+        _ => bail!("please give the value of the TXT record as well"),
+    };
+    let registrar = registrar(config).await?;
+    let mut challenge = AcmeChallenge::new(&*registrar)
+        .wait(!args.get_flag("no-wait"))
+        .timeout(Duration::from_secs(*args.get_one::<u64>("wait-timeout").unwrap()));
+    if let Some(ttl) = args.get_one::<u32>("ttl") {
+        challenge = challenge.ttl(*ttl);
+    }
+    if let Some(servers) = args.get_many::<SocketAddr>("name-server") {
+        challenge = challenge.name_servers(servers.copied().collect());
+    }
+    match action {
+        "present" => {
+            challenge.present(&domain, &value).await?;
+            println!("Presented the challenge for {}", domain);
+        },
+        _ => {
+            challenge.cleanup(&domain, &value).await?;
+            println!("Cleaned up the challenge for {}", domain);
+        },
+    }
+    Ok(())
+}
+
+/// The provider the generic commands manage.
+async fn registrar(config: &Config) -> Result<Box<dyn Registrar>> {
+    let Some(provider) = &config.provider.value else {
+        bail!("no provider selected; pass --provider or use `registrar config set provider NAME`");
+    };
+    providers::connect(config, provider).await
+}
+
+async fn run(matches: ArgMatches) -> Result<()> {
+
+    let mut config = Config::load(Config::default_dir(), "REGISTRAR", Flags {
+        profile: matches.get_one::<String>("profile").cloned(),
+        provider: matches.get_one::<String>("provider").cloned(),
+        max_retries: matches.get_one::<u32>("max-retries").copied(),
+        timeout: matches.get_one::<u64>("timeout").copied(),
+    })?;
+    let output = Output::from_matches(&matches);
+
+    match matches.subcommand() {
+        Some(("config", matches)) => config.run(matches)?,
+        Some(("providers", _)) => {
+            let rows: Vec<_> = PROVIDERS.iter()
+                .map(|provider| {
+                    let fields: Vec<String> = provider.fields.iter().map(|(field, _)| format!("{}.{}", provider.name, field)).collect();
+                    json!({ "name": provider.name, "credentials": fields.join(" ") })
+                })
+                .collect();
+            output.list(&rows, &["name", "credentials"])?;
+        },
+        Some(("zone", matches)) => match matches.subcommand() {
+            Some(("list", _)) => {
+                let zones: Vec<_> = registrar(&config).await?.list_zones().await?
+                    .into_iter()
+                    .map(|zone| json!({ "name": zone }))
+                    .collect();
+                output.list(&zones, &NAME_COLUMNS)?;
+            },
+            Some(("export", matches)) => {
+                let name = matches.get_one::<String>("name").unwrap();
+                let format = *matches.get_one::<FileFormat>("format").unwrap();
+                let records = registrar(&config).await?.list_records(name).await?;
+                let text = formats::write(format, name, records)?;
+                match matches.get_one::<String>("output") {
+                    Some(path) => std::fs::write(path, text)?,
+                    None => print!("{}", text),
+                }
+            },
+            _ => bail!("unrecognised subcommand"),
+        },
+        Some(("record", matches)) => match matches.subcommand() {
+            Some(("list", matches)) => {
+                let records = registrar(&config).await?.list_records_with_ids(matches.get_one::<String>("zone").unwrap()).await?;
+                let rows: Vec<_> = records.iter().map(|(id, record)| RecordRow { id, record }).collect();
+                output.list(&rows, &RECORD_COLUMNS)?;
+            },
+            Some(("add", matches)) => {
+                let zone = matches.get_one::<String>("zone").unwrap();
+                let record = DnsRecord {
+                    name: relative_name(matches.get_one::<String>("name").unwrap(), zone),
+                    ty: matches.get_one::<String>("type").unwrap().parse()?,
+                    content: matches.get_one::<String>("value").unwrap().clone(),
+                    ttl: matches.get_one::<u32>("ttl").copied(),
+                    priority: matches.get_one::<u32>("priority").copied(),
+                };
+                registrar(&config).await?.create_record(zone, &record).await?;
+                println!("Added {}", describe(&record));
+            },
+            Some(("set", matches)) => {
+                let zone = matches.get_one::<String>("zone").unwrap();
+                let registrar = registrar(&config).await?;
+                let (id, original) = single_record(find_records(&*registrar, zone, matches, matches.get_one::<String>("old")).await?)?;
+                let record = DnsRecord {
+                    content: matches.get_one::<String>("value").unwrap().clone(),
+                    ttl: matches.get_one::<u32>("ttl").copied().or(original.ttl),
+                    priority: matches.get_one::<u32>("priority").copied().or(original.priority),
+                    ..original
+                };
+                registrar.update_record(zone, &id, &record).await?;
+                println!("Updated {}", describe(&record));
+            },
+            Some(("delete", matches)) => {
+                let zone = matches.get_one::<String>("zone").unwrap();
+                let registrar = registrar(&config).await?;
+                let records = find_records(&*registrar, zone, matches, matches.get_one::<String>("value")).await?;
+                let records = if matches.get_flag("all") && !records.is_empty() { records } else { vec![single_record(records)?] };
+                for (id, record) in records {
+                    registrar.delete_record(zone, &id).await?;
+                    println!("Deleted {}", describe(&record));
+                }
+            },
+            _ => bail!("unrecognised subcommand"),
+        },
+        Some(("domain", matches)) => match matches.subcommand() {
+            Some(("nameservers", matches)) => {
+                let servers: Vec<_> = registrar(&config).await?.delegated_name_servers(matches.get_one::<String>("domain").unwrap()).await?
+                    .into_iter()
+                    .map(|server| json!({ "name": server }))
+                    .collect();
+                output.list(&servers, &NAME_COLUMNS)?;
+            },
+            Some(("set-nameservers", matches)) => {
+                let domain = matches.get_one::<String>("domain").unwrap();
+                let servers: Vec<String> = matches.get_many::<String>("servers").unwrap().cloned().collect();
+                registrar(&config).await?.set_name_servers(domain, &servers).await?;
+                println!("Delegated {} to {}", domain, servers.join(", "));
+            },
+            _ => bail!("unrecognised subcommand"),
+        },
+        Some(("ddns", matches)) => ddns(&config, matches).await?,
+        Some(("acme", matches)) => acme(&config, matches).await?,
+        Some(("openprovider", matches)) => match matches.subcommand() {
+            Some(("login", matches)) => {
+                let username = matches.get_one::<String>("username").unwrap();
+                let password = matches.get_one::<String>("password").unwrap();
+                let client = providers::openprovider(&config, false).await?;
+                let token = client.login(username, password).await?;
+                println!("Authentication successful!");
+                if matches.get_flag("save") {
+                    providers::save_openprovider_login(&mut config, username, password, &token, matches.get_flag("token-only"))?;
+                    println!("Credentials of profile {} saved", config.profile);
+                }
+            },
+            Some(("zone-info", matches)) => {
+                let client = providers::openprovider(&config, true).await?;
+                let zone = client.get_zone(matches.get_one::<String>("name").unwrap()).await?;
+                output.item(&zone, &["name", "id", "active", "type", "provider", "modification_date"])?;
+            },
+            _ => bail!("unrecognised subcommand"),
+        },
+        Some(("porkbun", matches)) => match matches.subcommand() {
+            Some(("ping", _)) => {
+                let ip = providers::porkbun(&config)?.ping().await?;
+                output.item(&json!({ "ip": ip }), &["ip"])?;
+            },
+            _ => bail!("unrecognised subcommand"),
+        },
+        _ => bail!("unrecognised command"),
+    }
+
+    Ok(())
+
+}
+
+#[tokio::main]
+async fn main() {
+    env_logger::init();
+    if let Err(error) = run(cli().get_matches()).await {
+        eprintln!("Error: {}", error);
+        std::process::exit(1);
+    }
+}
@@ -0,0 +1,637 @@
+
+//! Settings and credentials of the CLI.
+//!
+//! Both are kept in TOML files in the configuration directory, grouped into named profiles:
+//!
+//! ```toml
+//! # config.toml
+//! default_profile = "work"
+//!
+//! [profiles.work]
+//! max_retries = 3
+//! timeout = 30
+//! credential_store = "keyring"
+//! ```
+//!
+//! ```toml
+//! # credentials.toml, only readable by its owner
+//! [profiles.work.openprovider]
+//! username = "bob"
+//! password = "123456789"
+//!
+//! [profiles.work.porkbun]
+//! api_key = "pk1_..."
+//! secret_api_key = "sk1_..."
+//! ```
+//!
+//! Every value is taken from the first place that has it: a command-line flag, an environment
+//! variable, the selected profile and finally the built-in default. Passwords, tokens and API keys
+//! are kept in `credentials.toml`, unless the profile names another [credential
+//! store](CredentialStore).
+
+use std::cell::OnceCell;
+use std::collections::BTreeMap;
+use std::env::VarError;
+use std::io::{BufRead, IsTerminal, Write};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use anyhow::{anyhow, bail, Context, Result};
+use clap::{arg, ArgMatches, Command};
+use registrar_common::{create_private, CommandStore, EncryptedFileStore, IOResultExt, KeyringStore, SecretStore, DEFAULT_MAX_RETRIES};
+
+use crate::providers::{find_provider, PROVIDERS};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+/// The profile that is used when none was selected.
+pub const DEFAULT_PROFILE: &str = "default";
+
+/// The keys of settings that can be used with `config get` and `config set`.
+const SETTINGS: [&str; 9] = [
+    "default_profile",
+    "provider",
+    "max_retries",
+    "timeout",
+    "credential_store",
+    "credential_get_command",
+    "credential_set_command",
+    "credential_delete_command",
+    "save_password",
+];
+
+/// All keys that can be used with `config get` and `config set`, and whether their values are
+/// secret. Credentials are named after their provider, as in `openprovider.username`.
+pub fn keys() -> Vec<(String, bool)> {
+    let settings = SETTINGS.iter().map(|key| (key.to_string(), false));
+    let credentials = PROVIDERS.iter().flat_map(|provider| {
+        provider.fields.iter().map(|(field, secret)| (format!("{}.{}", provider.name, field), *secret))
+    });
+    settings.chain(credentials).collect()
+}
+
+pub fn get_env_string<S: AsRef<str>>(name: S) -> Option<String> {
+    let name_ref = name.as_ref();
+    match std::env::var(name_ref) {
+        Ok(string) => Some(string),
+        Err(VarError::NotUnicode(_)) => {
+            log::warn!("environment variable {} was set but could not be decoded, so it is ignored.", name_ref);
+            None
+        },
+        Err(VarError::NotPresent) => None,
+    }
+}
+
+fn get_env_number<T: std::str::FromStr>(name: &str) -> Option<T> {
+    let string = get_env_string(name)?;
+    match string.parse() {
+        Ok(value) => Some(value),
+        Err(_) => {
+            log::warn!("environment variable {} is not a valid number, so it is ignored.", name);
+            None
+        },
+    }
+}
+
+/// Where the passwords, tokens and API keys of a profile are kept.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum CredentialStore {
+    /// In `credentials.toml`, in plain text.
+    #[default]
+    File,
+    /// In the keyring of the desktop, through the Secret Service API.
+    Keyring,
+    /// In `credentials.enc`, encrypted with a passphrase that is asked for or taken from an
+    /// environment variable such as `OPENPROVIDER_PASSPHRASE`.
+    Encrypted,
+    /// In a password manager, through the `credential_*_command` settings.
+    Command,
+}
+
+impl FromStr for CredentialStore {
+
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "file" => Ok(CredentialStore::File),
+            "keyring" => Ok(CredentialStore::Keyring),
+            "encrypted" => Ok(CredentialStore::Encrypted),
+            "command" => Ok(CredentialStore::Command),
+            _ => bail!("unknown credential store {}; expected file, keyring, encrypted or command", s),
+        }
+    }
+
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct ProfileSettings {
+    /// The provider that `registrar` manages when `--provider` is not given.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub provider: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_retries: Option<u32>,
+    /// Seconds after which a request is abandoned.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub timeout: Option<u64>,
+    /// One of `file`, `keyring`, `encrypted` or `command`; see [`CredentialStore`].
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub credential_store: Option<String>,
+    /// The command that prints a secret, with `{key}` standing for its name.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub credential_get_command: Option<String>,
+    /// The command that reads a secret from its standard input and stores it.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub credential_set_command: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub credential_delete_command: Option<String>,
+    /// Whether `login --save` keeps the password as well as the token.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub save_password: Option<bool>,
+}
+
+/// The contents of `config.toml`.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct SettingsFile {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub default_profile: Option<String>,
+    #[serde(default)]
+    pub profiles: BTreeMap<String, ProfileSettings>,
+}
+
+/// The credentials of a profile by provider and then by field, as in
+/// `credentials["openprovider"]["username"]`.
+pub type ProfileCredentials = BTreeMap<String, BTreeMap<String, String>>;
+
+/// The contents of `credentials.toml`.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct CredentialsFile {
+    #[serde(default)]
+    pub profiles: BTreeMap<String, ProfileCredentials>,
+}
+
+/// The JSON file that older versions wrote to `credentials.toml`.
+#[derive(Deserialize)]
+struct LegacyCredentials {
+    username: String,
+    password: String,
+    token: Option<String>,
+}
+
+/// Where the value of a [`Setting`] came from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Source {
+    Flag,
+    Env,
+    Profile,
+    Store,
+    Default,
+}
+
+impl std::fmt::Display for Source {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Source::Flag => "flag",
+            Source::Env => "environment",
+            Source::Profile => "profile",
+            Source::Store => "credential store",
+            Source::Default => "default",
+        })
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Setting<T> {
+    pub value: Option<T>,
+    pub source: Source,
+}
+
+impl<T> Setting<T> {
+
+    fn resolve(flag: Option<T>, env: Option<T>, profile: Option<T>, default: Option<T>) -> Self {
+        let candidates = [(flag, Source::Flag), (env, Source::Env), (profile, Source::Profile), (default, Source::Default)];
+        for (value, source) in candidates {
+            if value.is_some() {
+                return Self { value, source };
+            }
+        }
+        Self { value: None, source: Source::Default }
+    }
+
+}
+
+/// The values of command-line flags that take part in the configuration.
+#[derive(Default)]
+pub struct Flags {
+    pub profile: Option<String>,
+    pub provider: Option<String>,
+    pub max_retries: Option<u32>,
+    pub timeout: Option<u64>,
+}
+
+/// The configuration as it applies to this invocation of the CLI.
+pub struct Config {
+    dir: PathBuf,
+    /// The prefix of the environment variables that hold settings, such as `OPENPROVIDER` for
+    /// `OPENPROVIDER_TIMEOUT`.
+    env_prefix: &'static str,
+    /// The name of the selected profile.
+    pub profile: String,
+    pub settings: SettingsFile,
+    pub credentials: CredentialsFile,
+    pub provider: Setting<String>,
+    pub max_retries: Setting<u32>,
+    pub timeout: Setting<u64>,
+    /// The credential store of the profile, once it was needed.
+    store: OnceCell<Option<Box<dyn SecretStore>>>,
+}
+
+fn read_settings(path: &Path) -> Result<SettingsFile> {
+    match std::fs::read_to_string(path).ok_not_found()? {
+        None => Ok(SettingsFile::default()),
+        Some(text) => toml::from_str(&text).with_context(|| format!("failed to read {}", path.display())),
+    }
+}
+
+fn read_credentials(path: &Path) -> Result<CredentialsFile> {
+    let Some(text) = std::fs::read_to_string(path).ok_not_found()? else {
+        return Ok(CredentialsFile::default());
+    };
+    match toml::from_str(&text) {
+        Ok(credentials) => Ok(credentials),
+        Err(error) => match serde_json::from_str::<LegacyCredentials>(&text) {
+            Ok(legacy) => {
+                log::info!("{} is in the old JSON format; it will be rewritten as TOML the next time it is saved", path.display());
+                let mut credentials = CredentialsFile::default();
+                let mut fields = BTreeMap::from([
+                    ("username".to_string(), legacy.username),
+                    ("password".to_string(), legacy.password),
+                ]);
+                if let Some(token) = legacy.token {
+                    fields.insert("token".to_string(), token);
+                }
+                credentials.profiles.insert(DEFAULT_PROFILE.to_string(), BTreeMap::from([("openprovider".to_string(), fields)]));
+                Ok(credentials)
+            },
+            Err(_) => Err(error).with_context(|| format!("failed to read {}", path.display())),
+        },
+    }
+}
+
+/// Read a field of a struct by the name it has in TOML.
+fn get_field<T: Serialize>(value: &T, field: &str) -> Result<Option<String>> {
+    Ok(toml::Table::try_from(value)?.remove(field).map(|value| match value {
+        toml::Value::String(string) => string,
+        value => value.to_string(),
+    }))
+}
+
+/// Change a field of a struct by the name it has in TOML, parsing the value as whatever type the
+/// field has.
+fn set_field<T: Serialize + DeserializeOwned>(target: &mut T, field: &str, value: Option<&str>) -> Result<()> {
+    let mut table = toml::Table::try_from(&*target)?;
+    let Some(value) = value else {
+        table.remove(field);
+        *target = table.try_into()?;
+        return Ok(());
+    };
+    let candidates = [
+        value.parse().ok().map(toml::Value::Integer),
+        value.parse().ok().map(toml::Value::Boolean),
+        Some(toml::Value::String(value.to_string())),
+    ];
+    for candidate in candidates.into_iter().flatten() {
+        table.insert(field.to_string(), candidate);
+        if let Ok(result) = table.clone().try_into() {
+            *target = result;
+            return Ok(());
+        }
+    }
+    bail!("{} is not a valid value for {}", value, field)
+}
+
+/// Ask for a passphrase on the terminal without echoing it.
+fn read_passphrase(prompt: &str, variable: &str) -> Result<String> {
+    if !std::io::stdin().is_terminal() {
+        bail!("no passphrase given; please set {}", variable);
+    }
+    eprint!("{}: ", prompt);
+    std::io::stderr().flush()?;
+    let echo_off = std::process::Command::new("stty").arg("-echo").status().is_ok_and(|status| status.success());
+    let mut passphrase = String::new();
+    let result = std::io::stdin().lock().read_line(&mut passphrase);
+    if echo_off {
+        let _ = std::process::Command::new("stty").arg("echo").status();
+    }
+    eprintln!();
+    result?;
+    Ok(passphrase.trim_end_matches(['\r', '\n']).to_string())
+}
+
+fn check_key(key: &str) -> Result<bool> {
+    keys().into_iter()
+        .find(|(other, _)| other == key)
+        .map(|(_, secret)| secret)
+        .ok_or_else(|| anyhow!("unknown configuration key {}", key))
+}
+
+impl Config {
+
+    /// The directory the configuration files are kept in by default.
+    pub fn default_dir() -> PathBuf {
+        dirs::config_dir().unwrap_or(std::env::current_dir().unwrap()).join("openprovider-cli")
+    }
+
+    /// Read the configuration files in the given directory and combine them with the environment
+    /// variables that start with the given prefix and the given flags.
+    pub fn load(dir: PathBuf, env_prefix: &'static str, flags: Flags) -> Result<Self> {
+        let env = |name: &str| format!("{}_{}", env_prefix, name);
+        let settings = read_settings(&dir.join("config.toml"))?;
+        let credentials = read_credentials(&dir.join("credentials.toml"))?;
+        let profile = flags.profile
+            .or(get_env_string(env("PROFILE")))
+            .or(settings.default_profile.clone())
+            .unwrap_or(DEFAULT_PROFILE.to_string());
+        let profile_settings = settings.profiles.get(&profile).cloned().unwrap_or_default();
+        Ok(Self {
+            provider: Setting::resolve(flags.provider, get_env_string(env("PROVIDER")), profile_settings.provider, None),
+            max_retries: Setting::resolve(flags.max_retries, get_env_number(&env("MAX_RETRIES")), profile_settings.max_retries, Some(DEFAULT_MAX_RETRIES)),
+            timeout: Setting::resolve(flags.timeout, get_env_number(&env("TIMEOUT")), profile_settings.timeout, None),
+            dir,
+            env_prefix,
+            profile,
+            settings,
+            credentials,
+            store: OnceCell::new(),
+        })
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Whether the selected profile appears in any of the configuration files.
+    pub fn profile_exists(&self) -> bool {
+        self.settings.profiles.contains_key(&self.profile) || self.credentials.profiles.contains_key(&self.profile)
+    }
+
+    /// The names of all profiles in the configuration files.
+    pub fn profiles(&self) -> Vec<String> {
+        let mut profiles: Vec<String> = self.settings.profiles.keys()
+            .chain(self.credentials.profiles.keys())
+            .cloned()
+            .collect();
+        profiles.sort();
+        profiles.dedup();
+        profiles
+    }
+
+    fn profile_settings(&self) -> ProfileSettings {
+        self.settings.profiles.get(&self.profile).cloned().unwrap_or_default()
+    }
+
+    /// Whether `login --save` should keep the password as well as the token.
+    pub fn save_password(&self) -> bool {
+        self.profile_settings().save_password.unwrap_or(true)
+    }
+
+    /// The store that keeps the secrets of the selected profile, or `None` if they are kept in
+    /// `credentials.toml`.
+    fn store(&self) -> Result<Option<&dyn SecretStore>> {
+        if let Some(store) = self.store.get() {
+            return Ok(store.as_deref());
+        }
+        let settings = self.profile_settings();
+        let kind: CredentialStore = match &settings.credential_store {
+            Some(kind) => kind.parse()?,
+            None => CredentialStore::File,
+        };
+        let store: Option<Box<dyn SecretStore>> = match kind {
+            CredentialStore::File => None,
+            CredentialStore::Keyring => Some(Box::new(KeyringStore::new("openprovider-cli"))),
+            CredentialStore::Encrypted => {
+                let variable = format!("{}_PASSPHRASE", self.env_prefix);
+                let passphrase = match get_env_string(&variable) {
+                    Some(passphrase) => passphrase,
+                    None => read_passphrase(&format!("Passphrase for {}", self.dir.join("credentials.enc").display()), &variable)?,
+                };
+                Some(Box::new(EncryptedFileStore::new(self.dir.join("credentials.enc"), passphrase)))
+            },
+            CredentialStore::Command => {
+                let Some(get) = settings.credential_get_command else {
+                    bail!("the command credential store needs credential_get_command to be set");
+                };
+                let mut store = CommandStore::new(get);
+                if let Some(command) = settings.credential_set_command {
+                    store = store.set_command(command);
+                }
+                if let Some(command) = settings.credential_delete_command {
+                    store = store.delete_command(command);
+                }
+                Some(Box::new(store))
+            },
+        };
+        Ok(self.store.get_or_init(|| store).as_deref())
+    }
+
+    /// The key a secret of the selected profile has in the credential store.
+    fn store_key(&self, key: &str) -> String {
+        format!("{}/{}", self.profile, key)
+    }
+
+    /// Look up a credential such as `openprovider.token` in the environment, `credentials.toml`
+    /// and the credential store, in that order.
+    pub fn credential(&self, key: &str) -> Result<Setting<String>> {
+        let secret = check_key(key)?;
+        if !key.contains('.') {
+            bail!("{} is not a credential", key);
+        }
+        if let Some(value) = get_env_string(key.replace('.', "_").to_ascii_uppercase()) {
+            return Ok(Setting { value: Some(value), source: Source::Env });
+        }
+        if let Some(value) = self.get(key)? {
+            return Ok(Setting { value: Some(value), source: Source::Profile });
+        }
+        if secret {
+            if let Some(store) = self.store()? {
+                let value = store.get(&self.store_key(key))
+                    .map_err(|error| anyhow!("failed to look up {}: {}", key, error))?;
+                return Ok(Setting { value, source: Source::Store });
+            }
+        }
+        Ok(Setting { value: None, source: Source::Default })
+    }
+
+    /// The effective value of the given key together with where it came from.
+    pub fn effective(&self, key: &str) -> Result<(Option<String>, Source)> {
+        fn show<T: ToString>(setting: &Setting<T>) -> (Option<String>, Source) {
+            (setting.value.as_ref().map(T::to_string), setting.source)
+        }
+        check_key(key)?;
+        Ok(match key {
+            "provider" => show(&self.provider),
+            "max_retries" => show(&self.max_retries),
+            "timeout" => show(&self.timeout),
+            key if key.contains('.') => show(&self.credential(key)?),
+            key => (self.get(key)?, Source::Profile),
+        })
+    }
+
+    /// The value of the given key as written in the files of the selected profile.
+    pub fn get(&self, key: &str) -> Result<Option<String>> {
+        check_key(key)?;
+        if key == "default_profile" {
+            return Ok(self.settings.default_profile.clone());
+        }
+        let Some((provider, field)) = key.split_once('.') else {
+            return get_field(&self.profile_settings(), key);
+        };
+        Ok(self.credentials.profiles.get(&self.profile)
+            .and_then(|credentials| credentials.get(provider))
+            .and_then(|fields| fields.get(field))
+            .cloned())
+    }
+
+    /// Store a value for the given key in the selected profile, or remove it if the value is
+    /// `None`.
+    ///
+    /// Secrets go to the credential store of the profile if it has one, and are removed from
+    /// `credentials.toml` when they do.
+    pub fn set(&mut self, key: &str, value: Option<&str>) -> Result<()> {
+        let secret = check_key(key)?;
+        if key == "default_profile" {
+            self.settings.default_profile = value.map(str::to_string);
+            return self.save_settings();
+        }
+        let Some((provider, field)) = key.split_once('.') else {
+            let settings = self.settings.profiles.entry(self.profile.clone()).or_default();
+            set_field(settings, key, value)?;
+            if let Some(kind) = &settings.credential_store {
+                kind.parse::<CredentialStore>()?;
+            }
+            if let Some(provider) = &settings.provider {
+                find_provider(provider)?;
+            }
+            return self.save_settings();
+        };
+        let mut value = value;
+        if secret {
+            if let Some(store) = self.store()? {
+                let store_key = self.store_key(key);
+                match value {
+                    Some(value) => store.set(&store_key, value)?,
+                    None => store.delete(&store_key)?,
+                }
+                value = None;
+                if self.get(key)?.is_none() {
+                    return Ok(());
+                }
+            }
+        }
+        let credentials = self.credentials.profiles.entry(self.profile.clone()).or_default();
+        match value {
+            Some(value) => {
+                credentials.entry(provider.to_string()).or_default().insert(field.to_string(), value.to_string());
+            },
+            None => if let Some(fields) = credentials.get_mut(provider) {
+                fields.remove(field);
+                if fields.is_empty() {
+                    credentials.remove(provider);
+                }
+            },
+        }
+        self.save_credentials()?;
+        Ok(())
+    }
+
+    pub fn save_settings(&self) -> Result<()> {
+        std::fs::create_dir_all(&self.dir)?;
+        std::fs::write(self.dir.join("config.toml"), toml::to_string_pretty(&self.settings)?)?;
+        Ok(())
+    }
+
+    /// Write the credentials of all profiles to a file that only the current user can read.
+    pub fn save_credentials(&self) -> Result<PathBuf> {
+        std::fs::create_dir_all(&self.dir)?;
+        let path = self.dir.join("credentials.toml");
+        let mut file = create_private(&path)?;
+        file.write_all(toml::to_string_pretty(&self.credentials)?.as_bytes())?;
+        Ok(path)
+    }
+
+    /// The `config` subcommand, which views and changes the configuration.
+    pub fn command() -> Command {
+        Command::new("config")
+            .about("view and change settings and credentials")
+            .subcommand_required(true)
+            .subcommand(
+                Command::new("show")
+                    .about("show the settings of the profile in use and where they come from")
+            )
+            .subcommand(
+                Command::new("get")
+                    .about("print the value in use for a key")
+                    .arg(arg!(<key> "The key, such as timeout or openprovider.username"))
+            )
+            .subcommand(
+                Command::new("set")
+                    .about("store a value in the profile")
+                    .arg(arg!(<key> "The key, such as timeout or openprovider.username"))
+                    .arg(arg!(<value> "The new value"))
+            )
+            .subcommand(
+                Command::new("unset")
+                    .about("remove a value from the profile")
+                    .arg(arg!(<key> "The key, such as timeout or openprovider.username"))
+            )
+            .subcommand(
+                Command::new("profiles")
+                    .about("list all profiles")
+            )
+            .subcommand(
+                Command::new("path")
+                    .about("print the directory the configuration is kept in")
+            )
+    }
+
+    /// Carry out the `config` subcommand.
+    pub fn run(&mut self, matches: &ArgMatches) -> Result<()> {
+        match matches.subcommand() {
+            Some(("show", _)) => {
+                println!("profile = {}", self.profile);
+                for (key, secret) in keys() {
+                    let (value, source) = self.effective(&key)?;
+                    match value {
+                        Some(_) if secret => println!("{} = ******** ({})", key, source),
+                        Some(value) => println!("{} = {} ({})", key, value, source),
+                        None => println!("{} is not set", key),
+                    }
+                }
+            },
+            Some(("get", matches)) => {
+                let key = matches.get_one::<String>("key").unwrap();
+                match self.effective(key)?.0 {
+                    Some(value) => println!("{}", value),
+                    None => std::process::exit(1),
+                }
+            },
+            Some(("set", matches)) => {
+                let key = matches.get_one::<String>("key").unwrap();
+                self.set(key, matches.get_one::<String>("value").map(String::as_str))?;
+            },
+            Some(("unset", matches)) => {
+                self.set(matches.get_one::<String>("key").unwrap(), None)?;
+            },
+            Some(("profiles", _)) => {
+                for profile in self.profiles() {
+                    let marker = if profile == self.profile { "*" } else { " " };
+                    println!("{} {}", marker, profile);
+                }
+            },
+            Some(("path", _)) => println!("{}", self.dir.display()),
+            _ => bail!("unrecognised subcommand"),
+        }
+        Ok(())
+    }
+
+}
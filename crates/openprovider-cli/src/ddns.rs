@@ -0,0 +1,249 @@
+
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::path::PathBuf;
+use std::time::Duration;
+
+use anyhow::{anyhow, bail, Context, Result};
+use registrar_common::{DnsRecord, DnsRecordType, Registrar};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// The public addresses of this host, as far as they could be found.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Addresses {
+    pub ipv4: Option<Ipv4Addr>,
+    pub ipv6: Option<Ipv6Addr>,
+}
+
+impl Addresses {
+
+    fn insert(&mut self, address: IpAddr) {
+        match address {
+            IpAddr::V4(address) => self.ipv4 = Some(address),
+            IpAddr::V6(address) => self.ipv6 = Some(address),
+        }
+    }
+
+    /// Keep only the address families that were asked for.
+    pub fn only(self, ipv4: bool, ipv6: bool) -> Self {
+        Self {
+            ipv4: self.ipv4.filter(|_| ipv4),
+            ipv6: self.ipv6.filter(|_| ipv6),
+        }
+    }
+
+}
+
+/// Where to find out the public addresses of this host.
+pub enum IpSource {
+    /// An HTTP endpoint that answers with the address the request came from in plain text, asked
+    /// once over IPv4 and once over IPv6.
+    Http { url: String, timeout: Option<Duration> },
+    /// The `ping` command of the Porkbun API, which only tells the address of one family.
+    Porkbun(porkbun::Client),
+    /// The global addresses of a local network interface, as reported by `ip`.
+    Interface(String),
+}
+
+async fn fetch(url: &str, local: IpAddr, timeout: Option<Duration>) -> Result<IpAddr> {
+    // Binding to the unspecified address of a family forces the request over that family.
+    let mut builder = reqwest::Client::builder().local_address(local);
+    if let Some(timeout) = timeout {
+        builder = builder.timeout(timeout);
+    }
+    let text = builder.build()?
+        .get(url)
+        .send().await?
+        .error_for_status()?
+        .text().await?;
+    text.trim().parse().map_err(|_| anyhow!("{} answered with {:?} instead of an IP address", url, text.trim()))
+}
+
+/// Read the global addresses from the output of `ip -json addr show`, preferring stable IPv6
+/// addresses over temporary ones.
+pub fn parse_interface(output: &str) -> Result<Addresses> {
+    let interfaces: Vec<Value> = serde_json::from_str(output)?;
+    let mut addresses = Addresses::default();
+    let mut temporary = Addresses::default();
+    for info in interfaces.iter().flat_map(|interface| interface["addr_info"].as_array().into_iter().flatten()) {
+        if info["scope"] != "global" {
+            continue;
+        }
+        let Some(Ok(address)) = info["local"].as_str().map(str::parse::<IpAddr>) else {
+            continue;
+        };
+        let target = if info["temporary"] == true { &mut temporary } else { &mut addresses };
+        if match address { IpAddr::V4(_) => target.ipv4.is_none(), IpAddr::V6(_) => target.ipv6.is_none() } {
+            target.insert(address);
+        }
+    }
+    Ok(Addresses {
+        ipv4: addresses.ipv4.or(temporary.ipv4),
+        ipv6: addresses.ipv6.or(temporary.ipv6),
+    })
+}
+
+impl IpSource {
+
+    /// Look up the current addresses of the families that are asked for.
+    ///
+    /// A family that cannot be reached is left out, but failing to find any address at all is an
+    /// error.
+    pub async fn detect(&self, ipv4: bool, ipv6: bool) -> Result<Addresses> {
+        let mut addresses = Addresses::default();
+        match self {
+            Self::Http { url, timeout } => {
+                if ipv4 {
+                    match fetch(url, Ipv4Addr::UNSPECIFIED.into(), *timeout).await {
+                        Ok(address) => addresses.insert(address),
+                        Err(error) => log::warn!("could not find the IPv4 address: {}", error),
+                    }
+                }
+                if ipv6 {
+                    match fetch(url, Ipv6Addr::UNSPECIFIED.into(), *timeout).await {
+                        Ok(address) => addresses.insert(address),
+                        Err(error) => log::warn!("could not find the IPv6 address: {}", error),
+                    }
+                }
+            },
+            Self::Porkbun(client) => {
+                let address = client.ping().await?;
+                addresses.insert(address.parse().map_err(|_| anyhow!("Porkbun answered with {:?} instead of an IP address", address))?);
+            },
+            Self::Interface(name) => {
+                let output = std::process::Command::new("ip")
+                    .args(["-json", "addr", "show", "dev", name])
+                    .output()
+                    .context("failed to run ip")?;
+                if !output.status.success() {
+                    bail!("ip failed: {}", String::from_utf8_lossy(&output.stderr).trim());
+                }
+                addresses = parse_interface(&String::from_utf8_lossy(&output.stdout))?;
+            },
+        }
+        let addresses = addresses.only(ipv4, ipv6);
+        if addresses == Addresses::default() {
+            bail!("no public address found");
+        }
+        Ok(addresses)
+    }
+
+}
+
+/// What was last written to the records, so that unchanged addresses need no API calls at all.
+#[derive(Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+struct Cache {
+    zone: String,
+    names: Vec<String>,
+    #[serde(flatten)]
+    addresses: Addresses,
+}
+
+/// Keeps the A and AAAA records of some names in a zone pointed at the addresses of this host.
+pub struct Updater {
+    zone: String,
+    names: Vec<String>,
+    ttl: Option<u32>,
+    cache: Option<PathBuf>,
+    last: Option<Cache>,
+}
+
+impl Updater {
+
+    /// Update the given names, which are relative to the zone with the empty string for the zone
+    /// itself.
+    pub fn new<S: Into<String>>(zone: S, names: Vec<String>) -> Self {
+        Self {
+            zone: zone.into(),
+            names,
+            ttl: None,
+            cache: None,
+            last: None,
+        }
+    }
+
+    /// Give records that are created or changed this time to live.
+    pub fn ttl(mut self, ttl: Option<u32>) -> Self {
+        self.ttl = ttl;
+        self
+    }
+
+    /// Remember the last addresses in this file, so that they survive a restart.
+    pub fn cache<P: Into<PathBuf>>(mut self, path: P) -> Self {
+        let path = path.into();
+        self.last = std::fs::read_to_string(&path).ok()
+            .and_then(|text| serde_json::from_str::<Cache>(&text).ok())
+            .filter(|cache| cache.zone == self.zone && cache.names == self.names);
+        self.cache = Some(path);
+        self
+    }
+
+    /// Point the records at the given addresses, unless they were already pointed there.
+    ///
+    /// Returns the records that were created or changed.
+    pub async fn update(&mut self, registrar: &dyn Registrar, addresses: Addresses) -> Result<Vec<DnsRecord>> {
+        let wanted = [
+            (DnsRecordType::A, addresses.ipv4.map(IpAddr::from)),
+            (DnsRecordType::AAAA, addresses.ipv6.map(IpAddr::from)),
+        ];
+        let last = self.last.as_ref().map(|cache| cache.addresses).unwrap_or_default();
+        if addresses.ipv4.is_none_or(|address| last.ipv4 == Some(address))
+            && addresses.ipv6.is_none_or(|address| last.ipv6 == Some(address)) {
+            return Ok(Vec::new());
+        }
+
+        let existing = registrar.list_records_with_ids(&self.zone).await?;
+        let mut changed = Vec::new();
+        for name in &self.names {
+            for (ty, address) in &wanted {
+                let Some(address) = address else {
+                    continue;
+                };
+                let content = address.to_string();
+                let mut matching = existing.iter()
+                    .filter(|(_, record)| record.name.eq_ignore_ascii_case(name) && record.ty == *ty);
+                let record = DnsRecord {
+                    name: name.clone(),
+                    ty: ty.clone(),
+                    content: content.clone(),
+                    ttl: self.ttl,
+                    priority: None,
+                };
+                match matching.next() {
+                    Some((_, current)) if current.content == content && matching.clone().next().is_none() => {},
+                    Some((id, current)) => {
+                        registrar.update_record(&self.zone, id, &DnsRecord { ttl: self.ttl.or(current.ttl), ..record.clone() }).await?;
+                        // A name points at a single address, so any other records of the type go.
+                        for (id, _) in matching {
+                            registrar.delete_record(&self.zone, id).await?;
+                        }
+                        changed.push(record);
+                    },
+                    None => {
+                        registrar.create_record(&self.zone, &record).await?;
+                        changed.push(record);
+                    },
+                }
+            }
+        }
+
+        let cache = Cache {
+            zone: self.zone.clone(),
+            names: self.names.clone(),
+            addresses: Addresses {
+                ipv4: addresses.ipv4.or(last.ipv4),
+                ipv6: addresses.ipv6.or(last.ipv6),
+            },
+        };
+        if let Some(path) = &self.cache {
+            if let Some(parent) = path.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+            std::fs::write(path, serde_json::to_string_pretty(&cache)?)
+                .with_context(|| format!("failed to write {}", path.display()))?;
+        }
+        self.last = Some(cache);
+        Ok(changed)
+    }
+
+}
@@ -0,0 +1,187 @@
+
+use std::path::Path;
+use std::str::FromStr;
+
+use anyhow::{anyhow, bail, Result};
+use registrar_common::{parse_zone_file, write_zone_file, DnsRecord};
+use serde::{Deserialize, Serialize};
+
+/// The file formats the records of a zone can be read from and written to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FileFormat {
+    /// A zone file as used by BIND.
+    Bind,
+    Json,
+    Yaml,
+    Csv,
+    Toml,
+}
+
+impl FromStr for FileFormat {
+
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "bind" | "zone" => Ok(FileFormat::Bind),
+            "json" => Ok(FileFormat::Json),
+            "yaml" | "yml" => Ok(FileFormat::Yaml),
+            "csv" => Ok(FileFormat::Csv),
+            "toml" => Ok(FileFormat::Toml),
+            _ => bail!("unknown format {}", s),
+        }
+    }
+
+}
+
+/// The layout of JSON, YAML and TOML files with the records of a zone.
+#[derive(Serialize, Deserialize)]
+struct RecordsFile {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    zone: Option<String>,
+    #[serde(default)]
+    records: Vec<DnsRecord>,
+}
+
+const CSV_COLUMNS: [&str; 5] = ["name", "type", "content", "ttl", "priority"];
+
+/// Guess the format of a file from its extension, or else from its contents.
+pub fn detect(path: &Path, text: &str) -> FileFormat {
+    if let Some(format) = path.extension().and_then(|extension| extension.to_str()).and_then(|extension| extension.parse().ok()) {
+        return format;
+    }
+    let text = text.trim_start();
+    if text.starts_with('{') {
+        FileFormat::Json
+    } else if text.lines().next().is_some_and(|line| line.trim().eq_ignore_ascii_case(&CSV_COLUMNS.join(","))) {
+        FileFormat::Csv
+    } else if text.lines().any(|line| line.trim() == "[[records]]") {
+        FileFormat::Toml
+    } else if text.lines().any(|line| line.starts_with("records:")) {
+        FileFormat::Yaml
+    } else {
+        FileFormat::Bind
+    }
+}
+
+/// Read the records of a zone, together with the name of the zone if the file says.
+///
+/// Zone files are read relative to the given zone, unless they set their own origin.
+pub fn read(format: FileFormat, text: &str, zone: Option<&str>) -> Result<(Option<String>, Vec<DnsRecord>)> {
+    let file: RecordsFile = match format {
+        FileFormat::Bind => {
+            let file = parse_zone_file(text, zone)?;
+            return Ok((file.origin, file.records));
+        },
+        FileFormat::Json => serde_json::from_str(text)?,
+        FileFormat::Toml => toml::from_str(text)?,
+        FileFormat::Yaml => serde_yaml_ng::from_str(text)?,
+        FileFormat::Csv => RecordsFile { zone: None, records: read_csv(text)? },
+    };
+    Ok((file.zone, file.records))
+}
+
+/// Write the records of a zone.
+pub fn write(format: FileFormat, zone: &str, records: Vec<DnsRecord>) -> Result<String> {
+    let file = RecordsFile { zone: Some(zone.to_string()), records };
+    Ok(match format {
+        FileFormat::Bind => write_zone_file(zone, &file.records),
+        FileFormat::Json => serde_json::to_string_pretty(&file)? + "\n",
+        FileFormat::Toml => toml::to_string(&file)?,
+        FileFormat::Yaml => serde_yaml_ng::to_string(&file)?,
+        FileFormat::Csv => write_csv(&file.records),
+    })
+}
+
+pub fn csv_field(text: &str) -> String {
+    if text.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", text.replace('"', "\"\""))
+    } else {
+        text.to_string()
+    }
+}
+
+fn write_csv(records: &[DnsRecord]) -> String {
+    let mut text = CSV_COLUMNS.join(",") + "\n";
+    for record in records {
+        let fields = [
+            csv_field(&record.name),
+            record.ty.to_string(),
+            csv_field(&record.content),
+            record.ttl.map(|ttl| ttl.to_string()).unwrap_or_default(),
+            record.priority.map(|priority| priority.to_string()).unwrap_or_default(),
+        ];
+        text.push_str(&fields.join(","));
+        text.push('\n');
+    }
+    text
+}
+
+/// Split CSV text into rows of fields, following RFC 4180.
+fn csv_rows(text: &str) -> Result<Vec<Vec<String>>> {
+    let mut rows = Vec::new();
+    let mut row = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            },
+            '"' if quoted => quoted = false,
+            '"' if field.is_empty() => quoted = true,
+            ',' if !quoted => row.push(std::mem::take(&mut field)),
+            '\r' if !quoted => {},
+            '\n' if !quoted => {
+                row.push(std::mem::take(&mut field));
+                rows.push(std::mem::take(&mut row));
+            },
+            c => field.push(c),
+        }
+    }
+    if quoted {
+        bail!("unterminated quoted field in CSV");
+    }
+    if !field.is_empty() || !row.is_empty() {
+        row.push(field);
+        rows.push(row);
+    }
+    Ok(rows)
+}
+
+fn read_csv(text: &str) -> Result<Vec<DnsRecord>> {
+    let mut rows = csv_rows(text)?.into_iter().filter(|row| row.iter().any(|field| !field.is_empty()));
+    let header: Vec<String> = rows.next()
+        .ok_or_else(|| anyhow!("empty CSV file"))?
+        .into_iter()
+        .map(|column| column.trim().to_ascii_lowercase())
+        .collect();
+    for column in &CSV_COLUMNS[..3] {
+        if !header.iter().any(|other| other == column) {
+            bail!("CSV file has no {} column", column);
+        }
+    }
+    let mut records = Vec::new();
+    for (index, row) in rows.enumerate() {
+        let get = |column: &str| header.iter()
+            .position(|other| other == column)
+            .and_then(|position| row.get(position))
+            .map(|field| field.trim())
+            .filter(|field| !field.is_empty());
+        let number = |column: &str| -> Result<Option<u32>> {
+            get(column)
+                .map(|field| field.parse().map_err(|_| anyhow!("row {}: invalid {}", index + 1, column)))
+                .transpose()
+        };
+        records.push(DnsRecord {
+            name: get("name").unwrap_or_default().to_string(),
+            ty: get("type").unwrap_or_default().parse()?,
+            content: get("content").unwrap_or_default().to_string(),
+            ttl: number("ttl")?,
+            priority: number("priority")?,
+        });
+    }
+    Ok(records)
+}
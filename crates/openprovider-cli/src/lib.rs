@@ -0,0 +1,10 @@
+
+//! The parts of the `openprovider` and `registrar` command-line tools that they share.
+
+pub mod apply;
+pub mod config;
+pub mod ddns;
+pub mod formats;
+pub mod output;
+pub mod providers;
+pub mod records;
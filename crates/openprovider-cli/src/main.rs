@@ -1,59 +1,191 @@
 
-use std::env::VarError;
-use std::fmt::Debug;
-use std::fs::Permissions;
-use std::os::unix::fs::PermissionsExt;
+use std::io::{BufRead, IsTerminal, Write};
+use std::path::Path;
 
-use anyhow::Result;
-use serde_json::Value;
+use anyhow::{bail, Result};
 use clap::{command, Command, arg, ArgAction};
-use openprovider::{Client, Builder};
-use openprovider::json::ValueExt;
-use openprovider::io_result_ext::IOResultExt;
-use serde::{Serialize, Deserialize};
-
-#[derive(Serialize, Deserialize)]
-struct Credentials {
-    username: String,
-    password: String,
-    token: Option<String>,
+use openprovider::{Record, RecordType, DEFAULT_TTL};
+use registrar_common::{Change, Registrar, Migration, MigrationReport, Mirror, Snapshot, SnapshotStore};
+
+use openprovider_cli::apply::{self, PlanOutcome};
+use openprovider_cli::config::{Config, Flags};
+use openprovider_cli::formats::{self, FileFormat};
+use openprovider_cli::output::Output;
+use openprovider_cli::providers;
+use openprovider_cli::records::{describe, relative_name};
+
+/// Turn a record name as given on the command line into the form that
+/// [`list_records`](openprovider::Client::list_records) uses: relative to the zone, except for the
+/// zone apex, which is named after the zone itself.
+fn normalize_name(name: &str, zone: &str) -> String {
+    match relative_name(name, zone) {
+        name if name.is_empty() => zone.to_string(),
+        name => name,
+    }
+}
+
+/// Find the records of a zone with the given name and type, and optionally the given value.
+async fn find_records(client: &openprovider::Client, zone: &str, name: &str, ty: &RecordType, value: Option<&String>) -> Vec<Record> {
+    let name = normalize_name(name, zone);
+    client.list_records(zone)
+        .await
+        .unwrap_print()
+        .into_iter()
+        .filter(|record| record.name.eq_ignore_ascii_case(&name) && record.ty == *ty)
+        .filter(|record| value.is_none_or(|value| record.value.trim_end_matches('.') == value.trim_end_matches('.')))
+        .collect()
+}
+
+fn describe_record(record: &Record) -> String {
+    match record.prio {
+        Some(prio) => format!("{} {:?} {} {} (TTL {})", record.name, record.ty, prio, record.value, record.ttl),
+        None => format!("{} {:?} {} (TTL {})", record.name, record.ty, record.value, record.ttl),
+    }
 }
 
-fn get_env_string<S: AsRef<str>>(name: S) -> Option<String> {
-    let name_ref = name.as_ref();
-    match std::env::var(name_ref) {
-        Ok(string) => Some(string),
-        Err(VarError::NotUnicode(_)) => {
-            log::warn!("environment variable {} was set but could not be decoded, so it is ignored.", name_ref);
-            None
+/// Make sure exactly one record matched, or exit with an error.
+fn single_record(records: Vec<Record>) -> Record {
+    match records.len() {
+        0 => {
+            eprintln!("Error: no matching record found");
+            std::process::exit(1);
+        },
+        1 => records.into_iter().next().unwrap(),
+        _ => {
+            eprintln!("Error: more than one record matches, please give the current value as well:");
+            for record in &records {
+                eprintln!("  {}", describe_record(record));
+            }
+            std::process::exit(1);
+        },
+    }
+}
+
+/// Wrap text in an ANSI color code, unless the output does not go to a terminal or the user asked
+/// for no colors.
+fn paint(text: &str, color: u8) -> String {
+    if std::io::stdout().is_terminal() && std::env::var_os("NO_COLOR").is_none() {
+        format!("\x1b[{}m{}\x1b[0m", color, text)
+    } else {
+        text.to_string()
+    }
+}
+
+fn describe_ttl(ttl: Option<u32>) -> String {
+    ttl.map(|ttl| ttl.to_string()).unwrap_or_else(|| "default".to_string())
+}
+
+fn print_change(change: &Change) {
+    match change {
+        Change::Create(record) => println!("{}", paint(&format!("+ {}", describe(record)), 32)),
+        Change::Update { from, to, .. } => println!(
+            "{}",
+            paint(&format!("~ {} (TTL {} -> {})", describe(to), describe_ttl(from.ttl), describe_ttl(to.ttl)), 33),
+        ),
+        Change::Delete { record, .. } => println!("{}", paint(&format!("- {}", describe(record)), 31)),
+    }
+}
+
+/// Show a plan, ask for confirmation unless `yes` is set and apply it to the zone, exiting with a
+/// non-zero status if anything is left to do.
+async fn run_plan(mirror: &Mirror<'_>, zone: &str, plan: Vec<Change>, dry_run: bool, yes: bool) {
+    for change in &plan {
+        print_change(change);
+    }
+    let outcome = apply::run_plan(mirror, zone, plan, dry_run, |plan| {
+        if yes {
+            return true;
+        }
+        if !std::io::stdin().is_terminal() {
+            eprintln!("Error: refusing to apply changes without --yes when not running interactively");
+            return false;
+        }
+        confirm(&format!("Apply {} changes to {}?", plan.len(), zone))
+    }).await;
+    match &outcome {
+        PlanOutcome::UpToDate => println!("{} is up to date.", zone),
+        PlanOutcome::Drifted => {},
+        PlanOutcome::Declined => println!("Nothing was changed."),
+        PlanOutcome::Applied(report) => {
+            for change in &report.unsupported {
+                println!("Unsupported by OpenProvider: {}", describe(change.record()));
+            }
+            for (change, error) in &report.failed {
+                println!("Failed: {} ({})", describe(change.record()), error);
+            }
+            println!("Applied {} changes to {}.", report.applied.len(), zone);
         },
-        Err(VarError::NotPresent) => None,
     }
+    if outcome.exit_code() != 0 {
+        std::process::exit(outcome.exit_code());
+    }
+}
+
+fn parse_format(text: &str) -> anyhow::Result<FileFormat> {
+    text.parse()
 }
 
-fn print<T: Debug + ?Sized + Serialize>(value: &T, format: Format) {
-    match format {
-        Format::JSON => println!("{}", serde_json::to_string_pretty(value).unwrap()),
-        Format::HumanReadable => println!("{:#?}", value),
+/// Ask the user a yes/no question on the terminal. Anything but yes counts as no.
+fn confirm(question: &str) -> bool {
+    print!("{} [y/N] ", question);
+    std::io::stdout().flush().unwrap();
+    let mut answer = String::new();
+    if std::io::stdin().lock().read_line(&mut answer).is_err() {
+        return false;
     }
+    matches!(answer.trim().to_ascii_lowercase().as_str(), "y" | "yes")
 }
 
-enum Format {
-    HumanReadable,
-    JSON,
+fn print_migration_report(report: &MigrationReport, dry_run: bool) {
+    let verb = if dry_run { "Would copy" } else { "Copied" };
+    for record in &report.copied {
+        println!("{}: {}", verb, describe(record));
+    }
+    for (record, ty) in &report.converted {
+        println!("{} as {}: {}", verb, ty, describe(record));
+    }
+    for record in &report.unsupported {
+        println!("Unsupported by the destination: {}", describe(record));
+    }
+    for (record, error) in &report.failed {
+        println!("Failed: {} ({})", describe(record), error);
+    }
+    for record in &report.missing {
+        println!("Missing after copying: {}", describe(record));
+    }
+    println!(
+        "{} copied, {} converted, {} already present, {} skipped, {} unsupported, {} failed, {} missing",
+        report.copied.len(),
+        report.converted.len(),
+        report.existing.len(),
+        report.skipped.len(),
+        report.unsupported.len(),
+        report.failed.len(),
+        report.missing.len(),
+    );
+    if let Some(name_servers) = &report.name_servers {
+        let verb = if dry_run { "Would delegate" } else { "Delegated" };
+        println!("{} to {}", verb, name_servers.join(", "));
+    }
 }
 
+const ZONE_COLUMNS: [&str; 6] = ["name", "id", "active", "type", "provider", "modification_date"];
+
+const RECORD_COLUMNS: [&str; 5] = ["name", "type", "value", "ttl", "prio"];
+
+const SNAPSHOT_COLUMNS: [&str; 4] = ["id", "zone", "provider", "timestamp"];
+
 trait ResultExt<T> {
     fn unwrap_print(self) -> T;
 }
 
-impl <T, E: std::error::Error> ResultExt<T> for std::result::Result<T, E> {
+impl <T, E: std::fmt::Display> ResultExt<T> for std::result::Result<T, E> {
 
     fn unwrap_print(self) -> T {
         match self {
             Ok(value) => value,
             Err(error) => {
-                println!("Error: {}", error);
+                eprintln!("Error: {}", error);
                 std::process::exit(1);
             },
         }
@@ -66,19 +198,24 @@ async fn main() -> Result<()> {
 
     env_logger::init();
 
-    let matches = command!()
-        .arg(arg!(--json "Output data as JSON").action(ArgAction::SetTrue))
-        .arg(arg!(--human "Output data as human-readable text").action(ArgAction::SetTrue))
+    let matches = Output::args(command!())
+        .subcommand_required(true)
+        .arg(arg!(--profile <NAME> "Use the settings and credentials of this profile"))
+        .arg(arg!(--timeout <SECONDS> "Give up on a request after this many seconds").value_parser(clap::value_parser!(u64)))
+        .arg(arg!(--"max-retries" <COUNT> "Retry a failed request at most this many times").value_parser(clap::value_parser!(u32)))
         .subcommand(
             Command::new("login")
                 .about("authenticate with OpenProvider")
                 .arg(arg!(<username> "The username you use to login"))
                 .arg(arg!(<password> "The password you use to login"))
-                .arg(arg!(--save "Save credentials in $HOME for later use").action(ArgAction::SetTrue))
+                .arg(arg!(--save "Save credentials in the credential store of the profile for later use").action(ArgAction::SetTrue))
+                .arg(arg!(--"token-only" "Only save the token, never the password").action(ArgAction::SetTrue).requires("save"))
         )
+        .subcommand(Config::command())
         .subcommand(
             Command::new("zone")
                 .about("manage DNS zones")
+                .subcommand_required(true)
                 .subcommand(
                     Command::new("list")
                         .about("list all DNS zones")
@@ -88,70 +225,131 @@ async fn main() -> Result<()> {
                         .about("get specific information about a DNS zone")
                         .arg(arg!(<name> "The name of the zone"))
                 )
+                .subcommand(
+                    Command::new("apply")
+                        .about("make the records of a DNS zone match a zone file or a JSON, YAML, CSV or TOML file")
+                        .arg(arg!(<file> "The file with the desired records"))
+                        .arg(arg!(--zone <NAME> "The name of the zone, if the file does not say"))
+                        .arg(arg!(--yes "Apply the changes without asking for confirmation").action(ArgAction::SetTrue))
+                        .arg(arg!(--"dry-run" "Only show the plan, and exit with status 2 if the zone has drifted").action(ArgAction::SetTrue))
+                )
+                .subcommand(
+                    Command::new("export")
+                        .about("write all records of a DNS zone to a file")
+                        .arg(arg!(<name> "The name of the zone"))
+                        .arg(arg!(--format <FORMAT> "The format to write: bind, json, yaml, csv or toml").value_parser(parse_format).default_value("bind"))
+                        .arg(arg!(-o --output <FILE> "Write to this file instead of the standard output"))
+                )
+                .subcommand(
+                    Command::new("import")
+                        .about("add the records in a file to a DNS zone")
+                        .arg(arg!(<name> "The name of the zone"))
+                        .arg(arg!(<file> "The file with the records"))
+                        .arg(arg!(--format <FORMAT> "The format of the file, if it cannot be told from its name or contents").value_parser(parse_format))
+                        .arg(arg!(--replace "Also delete the records that are not in the file").action(ArgAction::SetTrue))
+                        .arg(arg!(--merge "Keep the records that are not in the file (the default)").action(ArgAction::SetTrue).conflicts_with("replace"))
+                        .arg(arg!(--yes "Apply the changes without asking for confirmation").action(ArgAction::SetTrue))
+                        .arg(arg!(--"dry-run" "Only show what would change").action(ArgAction::SetTrue))
+                )
+                .subcommand(
+                    Command::new("migrate")
+                        .about("copy all records of a DNS zone from another provider to OpenProvider")
+                        .arg(arg!(<name> "The name of the zone"))
+                        .arg(arg!(--from <PROVIDER> "The provider that currently hosts the zone").value_parser(["porkbun"]).required(true))
+                        .arg(arg!(--verify "List the records again afterwards to check that they all arrived").action(ArgAction::SetTrue))
+                        .arg(arg!(--"switch-nameservers" "Delegate the domain to OpenProvider once all records were copied").action(ArgAction::SetTrue))
+                        .arg(arg!(--"dry-run" "Only show what would be copied").action(ArgAction::SetTrue))
+                )
+        )
+        .subcommand(
+            Command::new("snapshot")
+                .about("back up and restore DNS zones")
+                .subcommand_required(true)
+                .subcommand(
+                    Command::new("take")
+                        .about("save a copy of all records of a DNS zone")
+                        .arg(arg!(<name> "The name of the zone"))
+                )
+                .subcommand(
+                    Command::new("list")
+                        .about("list the saved snapshots")
+                        .arg(arg!([name] "Only list the snapshots of this zone"))
+                )
+                .subcommand(
+                    Command::new("diff")
+                        .about("show what changed between two snapshots")
+                        .arg(arg!(<from> "ID of the older snapshot"))
+                        .arg(arg!(<to> "ID of the newer snapshot"))
+                )
+                .subcommand(
+                    Command::new("restore")
+                        .about("make a DNS zone identical to a snapshot again")
+                        .arg(arg!(<id> "ID of the snapshot"))
+                        .arg(arg!(--"dry-run" "Only show what would change").action(ArgAction::SetTrue))
+                )
         )
         .subcommand(
             Command::new("record")
                 .about("manage DNS records")
+                .subcommand_required(true)
                 .subcommand(
                     Command::new("list")
                         .about("list all DNS records of a specific zone")
                         .arg(arg!(<name> "ID of the DNS zone that should be inspected"))
                 )
+                .subcommand(
+                    Command::new("add")
+                        .about("add a DNS record to a zone")
+                        .arg(arg!(<zone> "Name of the DNS zone the record belongs to"))
+                        .arg(arg!(<name> "Name of the record relative to the zone, or @ for the zone itself"))
+                        .arg(arg!(<type> "Type of the record, such as A or MX"))
+                        .arg(arg!(<value> "Value of the record"))
+                        .arg(arg!(--ttl <SECONDS> "Time to live of the record").value_parser(clap::value_parser!(u64)))
+                        .arg(arg!(--prio <PRIORITY> "Priority of the record, for MX and SRV records").value_parser(clap::value_parser!(u64)))
+                )
                 .subcommand(
                     Command::new("set")
                         .about("update a DNS record")
-                        .arg(arg!(<zone_id> "ID of the DNS zone the record belongs to"))
+                        .arg(arg!(<zone> "Name of the DNS zone the record belongs to"))
+                        .arg(arg!(<name> "Name of the record relative to the zone, or @ for the zone itself"))
+                        .arg(arg!(<type> "Type of the record, such as A or MX"))
+                        .arg(arg!(<value> "New value of the record"))
+                        .arg(arg!(--old <VALUE> "Current value of the record, if there are several with the same name and type"))
+                        .arg(arg!(--ttl <SECONDS> "New time to live of the record").value_parser(clap::value_parser!(u64)))
+                        .arg(arg!(--prio <PRIORITY> "New priority of the record").value_parser(clap::value_parser!(u64)))
+                )
+                .subcommand(
+                    Command::new("delete")
+                        .about("remove a DNS record from a zone")
+                        .arg(arg!(<zone> "Name of the DNS zone the record belongs to"))
+                        .arg(arg!(<name> "Name of the record relative to the zone, or @ for the zone itself"))
+                        .arg(arg!(<type> "Type of the record, such as A or MX"))
+                        .arg(arg!([value] "Value of the record, if there are several with the same name and type"))
+                        .arg(arg!(--all "Remove all records that match instead of exactly one").action(ArgAction::SetTrue))
                 )
         )
         .get_matches();
 
-    let mut output_format = Format::HumanReadable;
-
-    let config_dir = dirs::config_dir().unwrap_or(std::env::current_dir().unwrap()).join("openprovider-cli");
-
-    let mut username = std::env::var("OPENPROVIDER_USERNAME").ok();
-    let mut password = std::env::var("OPENPROVIDER_PASSWORD").ok();
-    let mut token = std::env::var("OPENPROVIDER_TOKEN").ok();
-
-    let mut max_retries: u32 = 5;
-
-    let credentials_file = config_dir.join("credentials.toml");
-    match std::fs::read_to_string(&credentials_file).ok_not_found()? {
-        None => {},
-        Some(string) => {
-            let creds: Credentials = serde_json::from_str(&string)?;
-            username = Some(creds.username);
-            password = Some(creds.password);
-            token = creds.token;
-        },
+    let mut config = Config::load(Config::default_dir(), "OPENPROVIDER", Flags {
+        profile: matches.get_one::<String>("profile").cloned(),
+        provider: None,
+        max_retries: matches.get_one::<u32>("max-retries").copied(),
+        timeout: matches.get_one::<u64>("timeout").copied(),
+    })?;
+    if matches.get_one::<String>("profile").is_some() && !config.profile_exists() && !matches!(matches.subcommand(), Some(("config" | "login", _))) {
+        log::warn!("profile {} does not exist in {}", config.profile, config.dir().display());
     }
 
-    let settings_file = config_dir.join("config.toml");
-    match std::fs::read_to_string(settings_file).ok_not_found()? {
-        None => {},
-        Some(string) => {
-            let settings: Value = serde_json::from_str(&string)?;
-            match settings.get("max_retries") {
-                None => {},
-                Some(value) => { max_retries = value.as_u32_ok().unwrap() },
-            }
-        },
-    }
+    let output = Output::from_matches(&matches);
 
-    username = username.or(get_env_string("OPENPROVIDER_USERNAME"));
-    password = password.or(get_env_string("OPENPROVIDER_PASSWORD"));
-    token = token.or(get_env_string("OPENPROVIDER_TOKEN"));
-
-    if matches.get_flag("json") {
-        output_format = Format::JSON;
-    } else if matches.get_flag("human") {
-        output_format = Format::HumanReadable;
-    }
-
-    let mut client = Builder::new()
-        .max_retries(max_retries)
-        .token(token)
-        .build();
+    // Credentials may sit behind a passphrase and logging in takes a request, so both only happen
+    // for commands that talk to OpenProvider as a user.
+    let authenticate = match matches.subcommand() {
+        Some(("login" | "config", _)) => false,
+        Some(("snapshot", matches)) => matches!(matches.subcommand(), Some(("take" | "restore", _))),
+        _ => true,
+    };
+    let client = providers::openprovider(&config, authenticate).await.unwrap_print();
 
     match matches.subcommand() {
         Some(("login", matches)) => {
@@ -161,39 +359,162 @@ async fn main() -> Result<()> {
             let token = client.login(username, password).await.unwrap_print();
             println!("Authentication successful!");
             if save {
-                std::fs::create_dir_all(config_dir)?;
-                let writer = std::fs::File::create(&credentials_file)?;
-                serde_json::to_writer_pretty(writer, &Credentials {
-                    username: username.to_string(),
-                    password: password.to_string(),
-                    token: Some(token),
-                })?;
-                std::fs::set_permissions(&credentials_file, Permissions::from_mode(0o600))?;
-                println!("Credentials written to {:?}", credentials_file);
+                providers::save_openprovider_login(&mut config, username, password, &token, matches.get_flag("token-only"))?;
+                println!("Credentials of profile {} saved", config.profile);
             }
         },
+        Some(("config", matches)) => config.run(matches).unwrap_print(),
         Some(("zone", matches)) => match matches.subcommand() {
             Some(("list", _)) => {
-                eprintln!("{:#?}", client.list_zones().await.unwrap_print());
+                output.list(&client.list_zones().await.unwrap_print(), &ZONE_COLUMNS).unwrap_print();
             },
             Some(("info", matches)) => {
                 let name = matches.get_one::<String>("name").unwrap();
-                print(&client.get_zone(name).await.unwrap_print(), output_format);
+                output.item(&client.get_zone(name).await.unwrap_print(), &ZONE_COLUMNS).unwrap_print();
+            },
+            Some(("apply", matches)) => {
+                let path = matches.get_one::<String>("file").unwrap();
+                let zone = matches.get_one::<String>("zone").map(String::as_str);
+                let (zone, records) = apply::read_records_file(Path::new(path), None, zone).unwrap_print();
+                let desired = apply::desired_zone(&zone, records);
+                let mirror = Mirror::new(&desired, &client);
+                let plan = mirror.plan(&zone).await.unwrap_print();
+                run_plan(&mirror, &zone, plan, matches.get_flag("dry-run"), matches.get_flag("yes")).await;
+            },
+            Some(("export", matches)) => {
+                let name = matches.get_one::<String>("name").unwrap();
+                let format = *matches.get_one::<FileFormat>("format").unwrap();
+                let records = Registrar::list_records(&client, name).await.unwrap_print();
+                let text = formats::write(format, name, records).unwrap_print();
+                match matches.get_one::<String>("output") {
+                    Some(path) => std::fs::write(path, text)?,
+                    None => print!("{}", text),
+                }
             },
-            None => eprintln!("Please provide a subcommand."),
-            _ => eprintln!("Unrecognised subcommand. Please check your spelling."),
+            Some(("import", matches)) => {
+                let name = matches.get_one::<String>("name").unwrap();
+                let path = matches.get_one::<String>("file").unwrap();
+                let format = matches.get_one::<FileFormat>("format").copied();
+                let (_, records) = apply::read_records_file(Path::new(path), format, Some(name)).unwrap_print();
+                let desired = apply::desired_zone(name, records);
+                let mirror = Mirror::new(&desired, &client);
+                let mut plan = mirror.plan(name).await.unwrap_print();
+                if !matches.get_flag("replace") {
+                    plan.retain(|change| !matches!(change, Change::Delete { .. }));
+                }
+                run_plan(&mirror, name, plan, matches.get_flag("dry-run"), matches.get_flag("yes")).await;
+            },
+            Some(("migrate", matches)) => {
+                let name = matches.get_one::<String>("name").unwrap();
+                let dry_run = matches.get_flag("dry-run");
+                let source = providers::porkbun(&config).unwrap_print();
+                let mut migration = Migration::new(&source, &client)
+                    .verify(matches.get_flag("verify"))
+                    .dry_run(dry_run);
+                if matches.get_flag("switch-nameservers") {
+                    migration = migration.switch_name_servers(&source);
+                }
+                let report = migration.run(name).await.unwrap_print();
+                print_migration_report(&report, dry_run);
+                if matches.get_flag("switch-nameservers") && report.name_servers.is_none() {
+                    println!("Name servers were left alone because not all records were copied.");
+                }
+                if !report.is_complete() {
+                    std::process::exit(1);
+                }
+            },
+            _ => bail!("unrecognised subcommand"),
+        },
+        Some(("snapshot", matches)) => {
+            let data_dir = dirs::data_dir().unwrap_or(std::env::current_dir().unwrap()).join("openprovider-cli");
+            let store = SnapshotStore::new(data_dir.join("snapshots"));
+            match matches.subcommand() {
+                Some(("take", matches)) => {
+                    let name = matches.get_one::<String>("name").unwrap();
+                    let zone = client.get_zone(name).await.unwrap_print();
+                    let snapshot = Snapshot::take(&client, "openprovider", name).await.unwrap_print()
+                        .with_metadata(&zone)
+                        .unwrap_print();
+                    let info = store.save(&snapshot).unwrap_print();
+                    println!("Saved {} records as {}", snapshot.records.len(), info.id);
+                },
+                Some(("list", matches)) => {
+                    let name = matches.get_one::<String>("name").map(String::as_str);
+                    output.list(&store.list(name).unwrap_print(), &SNAPSHOT_COLUMNS).unwrap_print();
+                },
+                Some(("diff", matches)) => {
+                    let from = store.load(matches.get_one::<String>("from").unwrap()).unwrap_print();
+                    let to = store.load(matches.get_one::<String>("to").unwrap()).unwrap_print();
+                    for change in from.diff(&to) {
+                        print_change(&change);
+                    }
+                },
+                Some(("restore", matches)) => {
+                    let snapshot = store.load(matches.get_one::<String>("id").unwrap()).unwrap_print();
+                    let report = snapshot.restore(&client, matches.get_flag("dry-run")).await.unwrap_print();
+                    for change in &report.applied {
+                        print_change(change);
+                    }
+                    for (change, error) in &report.failed {
+                        println!("Failed: {} ({})", describe(change.record()), error);
+                    }
+                    if !report.failed.is_empty() || !report.unsupported.is_empty() {
+                        std::process::exit(1);
+                    }
+                },
+                _ => bail!("unrecognised subcommand"),
+            }
         },
         Some(("record", matches)) => match matches.subcommand() {
             Some(("list", matches)) => {
                 let name = matches.get_one::<String>("name").unwrap();
-                print(&client.list_records(name).await.unwrap_print(), output_format);
+                output.list(&client.list_records(name).await.unwrap_print(), &RECORD_COLUMNS).unwrap_print();
+            },
+            Some(("add", matches)) => {
+                let zone = matches.get_one::<String>("zone").unwrap();
+                let name = normalize_name(matches.get_one::<String>("name").unwrap(), zone);
+                let record = Record {
+                    creation_date: None,
+                    ip: None,
+                    modification_date: None,
+                    // New records at the zone apex have an empty name.
+                    name: if name == *zone { String::new() } else { name },
+                    prio: matches.get_one::<u64>("prio").copied(),
+                    ttl: matches.get_one::<u64>("ttl").copied().unwrap_or(DEFAULT_TTL),
+                    ty: matches.get_one::<String>("type").unwrap().parse().unwrap_print(),
+                    value: matches.get_one::<String>("value").unwrap().clone(),
+                };
+                client.create_record(zone, &record).await.unwrap_print();
+                println!("Added {}", describe_record(&record));
+            },
+            Some(("set", matches)) => {
+                let zone = matches.get_one::<String>("zone").unwrap();
+                let ty: RecordType = matches.get_one::<String>("type").unwrap().parse().unwrap_print();
+                let name = matches.get_one::<String>("name").unwrap();
+                let original = single_record(find_records(&client, zone, name, &ty, matches.get_one::<String>("old")).await);
+                let record = Record {
+                    value: matches.get_one::<String>("value").unwrap().clone(),
+                    ttl: matches.get_one::<u64>("ttl").copied().unwrap_or(original.ttl),
+                    prio: matches.get_one::<u64>("prio").copied().or(original.prio),
+                    ..original.clone()
+                };
+                client.set_record(zone, &original, &record).await.unwrap_print();
+                println!("Updated {}", describe_record(&record));
+            },
+            Some(("delete", matches)) => {
+                let zone = matches.get_one::<String>("zone").unwrap();
+                let ty: RecordType = matches.get_one::<String>("type").unwrap().parse().unwrap_print();
+                let name = matches.get_one::<String>("name").unwrap();
+                let records = find_records(&client, zone, name, &ty, matches.get_one::<String>("value")).await;
+                let records = if matches.get_flag("all") && !records.is_empty() { records } else { vec![single_record(records)] };
+                for record in records {
+                    client.delete_record(zone, &record).await.unwrap_print();
+                    println!("Deleted {}", describe_record(&record));
+                }
             },
-            Some(("set", _matches)) => unimplemented!(),
-            None => eprintln!("Please provide a subcommand."),
-            _ => eprintln!("Unrecognised subcommand. Please check your spelling."),
+            _ => bail!("unrecognised subcommand"),
         },
-        None => eprintln!("Please provide a command."),
-        _ => eprintln!("Unrecognised command. Please check your spelling."),
+        _ => bail!("unrecognised command"),
     }
 
     Ok(())
@@ -0,0 +1,214 @@
+
+use anyhow::{bail, Result};
+use clap::{arg, ArgAction, ArgGroup, ArgMatches, Command};
+use serde::Serialize;
+use serde_json::{Map, Value};
+
+use crate::formats::csv_field;
+
+/// The ways the CLI can print data on the standard output.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum OutputFormat {
+    /// Aligned columns for people to read.
+    #[default]
+    Table,
+    Json,
+    /// One JSON object per line.
+    Ndjson,
+    Csv,
+    Yaml,
+}
+
+/// Prints lists and single items in the format and with the columns the user asked for.
+#[derive(Clone, Debug, Default)]
+pub struct Output {
+    pub format: OutputFormat,
+    /// The fields to print, or `None` to print the default columns in tables and CSV and all
+    /// fields otherwise.
+    pub columns: Option<Vec<String>>,
+}
+
+fn cell(value: &Value) -> String {
+    match value {
+        Value::Null => String::new(),
+        Value::String(string) => string.clone(),
+        value => value.to_string(),
+    }
+}
+
+/// Lay out rows of cells in columns that are as wide as their widest cell.
+fn table(rows: &[Vec<String>]) -> String {
+    let mut widths: Vec<usize> = Vec::new();
+    for row in rows {
+        for (index, cell) in row.iter().enumerate() {
+            let width = cell.chars().count();
+            match widths.get_mut(index) {
+                Some(max) => *max = (*max).max(width),
+                None => widths.push(width),
+            }
+        }
+    }
+    let mut text = String::new();
+    for row in rows {
+        let mut line = String::new();
+        for (index, cell) in row.iter().enumerate() {
+            if index > 0 {
+                line.push_str("  ");
+            }
+            line.push_str(cell);
+            line.push_str(&" ".repeat(widths[index] - cell.chars().count()));
+        }
+        text.push_str(line.trim_end());
+        text.push('\n');
+    }
+    text
+}
+
+impl Output {
+
+    /// Add the flags that choose the output format and columns to a command.
+    pub fn args(command: Command) -> Command {
+        command
+            .arg(arg!(--json "Output data as JSON").action(ArgAction::SetTrue))
+            .arg(arg!(--ndjson "Output data as JSON, one object per line").action(ArgAction::SetTrue))
+            .arg(arg!(--csv "Output data as CSV").action(ArgAction::SetTrue))
+            .arg(arg!(--yaml "Output data as YAML").action(ArgAction::SetTrue))
+            .arg(arg!(--human "Output data as an aligned table (the default)").action(ArgAction::SetTrue))
+            .group(ArgGroup::new("output").args(["json", "ndjson", "csv", "yaml", "human"]))
+            .arg(arg!(--columns <LIST> "Only output these fields, separated by commas").value_delimiter(','))
+    }
+
+    /// Read the flags added by [`args`](Self::args).
+    pub fn from_matches(matches: &ArgMatches) -> Self {
+        let format = if matches.get_flag("json") {
+            OutputFormat::Json
+        } else if matches.get_flag("ndjson") {
+            OutputFormat::Ndjson
+        } else if matches.get_flag("csv") {
+            OutputFormat::Csv
+        } else if matches.get_flag("yaml") {
+            OutputFormat::Yaml
+        } else {
+            OutputFormat::Table
+        };
+        Self {
+            format,
+            columns: matches.get_many::<String>("columns").map(|columns| columns.cloned().collect()),
+        }
+    }
+
+    /// The columns to show: those the user asked for, or else the given defaults, or else every
+    /// field that occurs.
+    fn columns(&self, rows: &[Map<String, Value>], defaults: &[&str], all_by_default: bool) -> Result<Vec<String>> {
+        let mut available: Vec<String> = Vec::new();
+        for row in rows {
+            for key in row.keys() {
+                if !available.contains(key) {
+                    available.push(key.clone());
+                }
+            }
+        }
+        if let Some(columns) = &self.columns {
+            for column in columns {
+                // Without any rows there is nothing to check against.
+                if !rows.is_empty() && !available.contains(column) {
+                    bail!("unknown column {}; available columns are {}", column, available.join(", "));
+                }
+            }
+            return Ok(columns.clone());
+        }
+        if all_by_default || defaults.is_empty() {
+            // Put the default columns first, so that the most useful fields lead.
+            let mut columns: Vec<String> = defaults.iter().map(|column| column.to_string()).collect();
+            columns.extend(available.into_iter().filter(|column| !defaults.contains(&column.as_str())));
+            return Ok(columns);
+        }
+        Ok(defaults.iter().map(|column| column.to_string()).collect())
+    }
+
+    fn select(row: &Map<String, Value>, columns: &[String]) -> Map<String, Value> {
+        columns.iter()
+            .map(|column| (column.clone(), row.get(column).cloned().unwrap_or(Value::Null)))
+            .collect()
+    }
+
+    fn render(&self, rows: Vec<Map<String, Value>>, defaults: &[&str], single: bool) -> Result<String> {
+        // JSON and YAML keep every field unless the user picked some.
+        let structured = !matches!(self.format, OutputFormat::Table | OutputFormat::Csv);
+        let columns = self.columns(&rows, defaults, structured || single)?;
+        let selected = if structured && self.columns.is_none() {
+            rows
+        } else {
+            rows.iter().map(|row| Self::select(row, &columns)).collect()
+        };
+        let mut values: Vec<Value> = selected.into_iter().map(Value::Object).collect();
+        Ok(match self.format {
+            OutputFormat::Table if single => {
+                let Some(Value::Object(row)) = values.first() else {
+                    return Ok(String::new());
+                };
+                let rows: Vec<Vec<String>> = columns.iter()
+                    .map(|column| vec![column.clone(), row.get(column).map(cell).unwrap_or_default()])
+                    .collect();
+                table(&rows)
+            },
+            OutputFormat::Table => {
+                let mut rows = vec![columns.iter().map(|column| column.to_ascii_uppercase()).collect()];
+                for value in &values {
+                    rows.push(columns.iter().map(|column| value.get(column).map(cell).unwrap_or_default()).collect());
+                }
+                table(&rows)
+            },
+            OutputFormat::Csv => {
+                let mut text = columns.iter().map(|column| csv_field(column)).collect::<Vec<_>>().join(",") + "\n";
+                for value in &values {
+                    let fields: Vec<String> = columns.iter()
+                        .map(|column| csv_field(&value.get(column).map(cell).unwrap_or_default()))
+                        .collect();
+                    text.push_str(&fields.join(","));
+                    text.push('\n');
+                }
+                text
+            },
+            OutputFormat::Json if single => serde_json::to_string_pretty(&values.pop().unwrap_or(Value::Null))? + "\n",
+            OutputFormat::Json => serde_json::to_string_pretty(&values)? + "\n",
+            OutputFormat::Ndjson => values.iter().map(|value| value.to_string() + "\n").collect(),
+            OutputFormat::Yaml if single => serde_yaml_ng::to_string(&values.pop().unwrap_or(Value::Null))?,
+            OutputFormat::Yaml => serde_yaml_ng::to_string(&values)?,
+        })
+    }
+
+    /// Print a list of items, one row per item. Tables and CSV show the given columns unless the
+    /// user picked others.
+    pub fn list<T: Serialize>(&self, items: &[T], defaults: &[&str]) -> Result<()> {
+        print!("{}", self.list_to_string(items, defaults)?);
+        Ok(())
+    }
+
+    /// Format a list of items the way [`list`](Self::list) prints them.
+    pub fn list_to_string<T: Serialize>(&self, items: &[T], defaults: &[&str]) -> Result<String> {
+        let rows = items.iter()
+            .map(|item| match serde_json::to_value(item)? {
+                Value::Object(row) => Ok(row),
+                value => Ok(Map::from_iter([("value".to_string(), value)])),
+            })
+            .collect::<Result<Vec<_>>>()?;
+        self.render(rows, defaults, false)
+    }
+
+    /// Print a single item, which tables show as one field per line.
+    pub fn item<T: Serialize>(&self, item: &T, defaults: &[&str]) -> Result<()> {
+        print!("{}", self.item_to_string(item, defaults)?);
+        Ok(())
+    }
+
+    /// Format a single item the way [`item`](Self::item) prints it.
+    pub fn item_to_string<T: Serialize>(&self, item: &T, defaults: &[&str]) -> Result<String> {
+        let row = match serde_json::to_value(item)? {
+            Value::Object(row) => row,
+            value => Map::from_iter([("value".to_string(), value)]),
+        };
+        self.render(vec![row], defaults, true)
+    }
+
+}
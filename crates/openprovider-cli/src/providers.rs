@@ -0,0 +1,158 @@
+
+use std::time::Duration;
+
+use anyhow::{anyhow, Result};
+use registrar_common::Registrar;
+
+use crate::config::Config;
+
+/// A provider the command-line tools can manage.
+pub struct Provider {
+    pub name: &'static str,
+    /// The credentials and connection settings the provider takes, and whether they are secret.
+    pub fields: &'static [(&'static str, bool)],
+}
+
+pub const PROVIDERS: [Provider; 6] = [
+    Provider { name: "openprovider", fields: &[("username", false), ("password", true), ("token", true)] },
+    Provider { name: "porkbun", fields: &[("api_key", true), ("secret_api_key", true)] },
+    Provider { name: "cloudflare", fields: &[("token", true)] },
+    Provider { name: "hetzner", fields: &[("token", true)] },
+    Provider { name: "powerdns", fields: &[("base_url", false), ("server_id", false), ("api_key", true)] },
+    Provider { name: "rfc2136", fields: &[("server", false), ("zones", false), ("key_name", false), ("key_secret", true)] },
+];
+
+pub fn find_provider(name: &str) -> Result<&'static Provider> {
+    PROVIDERS.iter()
+        .find(|provider| provider.name == name)
+        .ok_or_else(|| {
+            let names: Vec<&str> = PROVIDERS.iter().map(|provider| provider.name).collect();
+            anyhow!("unknown provider {}; expected one of {}", name, names.join(", "))
+        })
+}
+
+fn optional(config: &Config, key: &str) -> Result<Option<String>> {
+    Ok(config.credential(key)?.value)
+}
+
+fn required(config: &Config, key: &str) -> Result<String> {
+    optional(config, key)?.ok_or_else(|| {
+        let variable = key.replace('.', "_").to_ascii_uppercase();
+        anyhow!("{} is not set; please set {} or use `config set {}`", key, variable, key)
+    })
+}
+
+fn timeout(config: &Config) -> Option<Duration> {
+    config.timeout.value.map(Duration::from_secs)
+}
+
+/// Build an OpenProvider client. Unless `authenticate` is false, the client uses the saved token
+/// or else logs in with the saved username and password.
+pub async fn openprovider(config: &Config, authenticate: bool) -> Result<openprovider::Client> {
+    let token = if authenticate { optional(config, "openprovider.token")? } else { None };
+    let mut builder = openprovider::Builder::new().token(token);
+    if let Some(max_retries) = config.max_retries.value {
+        builder = builder.max_retries(max_retries);
+    }
+    if let Some(timeout) = timeout(config) {
+        builder = builder.timeout(timeout);
+    }
+    let client = builder.build();
+    if authenticate && !client.has_token() {
+        if let Some(username) = optional(config, "openprovider.username")? {
+            if let Some(password) = optional(config, "openprovider.password")? {
+                client.set_token(client.login(&username, &password).await?);
+            }
+        }
+    }
+    Ok(client)
+}
+
+/// Keep the credentials of a successful login in the profile, leaving out the password if the
+/// profile or the caller says so.
+pub fn save_openprovider_login(config: &mut Config, username: &str, password: &str, token: &str, token_only: bool) -> Result<()> {
+    config.set("openprovider.username", Some(username))?;
+    if config.save_password() && !token_only {
+        config.set("openprovider.password", Some(password))?;
+    } else {
+        config.set("openprovider.password", None)?;
+    }
+    config.set("openprovider.token", Some(token))
+}
+
+pub fn porkbun(config: &Config) -> Result<porkbun::Client> {
+    let mut builder = porkbun::Builder::new()
+        .api_key(required(config, "porkbun.api_key")?)
+        .secret_api_key(required(config, "porkbun.secret_api_key")?);
+    if let Some(max_retries) = config.max_retries.value {
+        builder = builder.max_retries(max_retries);
+    }
+    if let Some(timeout) = timeout(config) {
+        builder = builder.timeout(timeout);
+    }
+    Ok(builder.build())
+}
+
+/// Build a client for the provider with the given name from the credentials in the configuration.
+///
+/// The `max_retries` setting applies to every provider with an HTTP API, which are all of them
+/// except RFC 2136.
+pub async fn connect(config: &Config, name: &str) -> Result<Box<dyn Registrar>> {
+    let max_retries = config.max_retries.value;
+    let timeout = timeout(config);
+    Ok(match find_provider(name)?.name {
+        "openprovider" => Box::new(openprovider(config, true).await?),
+        "porkbun" => Box::new(porkbun(config)?),
+        "cloudflare" => {
+            let mut builder = cloudflare::Builder::new().token(required(config, "cloudflare.token")?);
+            if let Some(max_retries) = max_retries {
+                builder = builder.max_retries(max_retries);
+            }
+            if let Some(timeout) = timeout {
+                builder = builder.timeout(timeout);
+            }
+            Box::new(builder.build())
+        },
+        "hetzner" => {
+            let mut builder = hetzner::Builder::new().token(required(config, "hetzner.token")?);
+            if let Some(max_retries) = max_retries {
+                builder = builder.max_retries(max_retries);
+            }
+            if let Some(timeout) = timeout {
+                builder = builder.timeout(timeout);
+            }
+            Box::new(builder.build())
+        },
+        "powerdns" => {
+            let mut builder = powerdns::Builder::new().api_key(required(config, "powerdns.api_key")?);
+            if let Some(max_retries) = max_retries {
+                builder = builder.max_retries(max_retries);
+            }
+            if let Some(base_url) = optional(config, "powerdns.base_url")? {
+                builder = builder.base_url(base_url);
+            }
+            if let Some(server_id) = optional(config, "powerdns.server_id")? {
+                builder = builder.server_id(server_id);
+            }
+            if let Some(timeout) = timeout {
+                builder = builder.timeout(timeout);
+            }
+            Box::new(builder.build())
+        },
+        _ => {
+            let mut builder = rfc2136::Builder::new().server(required(config, "rfc2136.server")?);
+            for zone in optional(config, "rfc2136.zones")?.unwrap_or_default().split(',') {
+                if !zone.trim().is_empty() {
+                    builder = builder.zone(zone.trim());
+                }
+            }
+            if let Some(key_name) = optional(config, "rfc2136.key_name")? {
+                builder = builder.key(rfc2136::Key::new(key_name, &required(config, "rfc2136.key_secret")?)?);
+            }
+            if let Some(timeout) = timeout {
+                builder = builder.timeout(timeout);
+            }
+            Box::new(builder.build())
+        },
+    })
+}
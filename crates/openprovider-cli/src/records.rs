@@ -0,0 +1,31 @@
+
+//! Record names and records as they are given on and shown on the command line.
+
+use registrar_common::DnsRecord;
+
+/// Turn a record name as given on the command line into one that is relative to the zone, with
+/// the empty string for the zone itself.
+///
+/// The name may be `@`, relative to the zone or fully qualified, with or without a trailing dot.
+/// The zone is recognised regardless of case, but the rest of the name keeps its case.
+pub fn relative_name(name: &str, zone: &str) -> String {
+    let name = name.trim_end_matches('.');
+    let zone = zone.trim_end_matches('.');
+    if name.is_empty() || name == "@" || name.eq_ignore_ascii_case(zone) {
+        return String::new();
+    }
+    let suffix = format!(".{}", zone.to_ascii_lowercase());
+    match name.to_ascii_lowercase().strip_suffix(&suffix) {
+        Some(relative) => name[..relative.len()].to_string(),
+        None => name.to_string(),
+    }
+}
+
+/// Show a record on one line, with `@` for the zone itself.
+pub fn describe(record: &DnsRecord) -> String {
+    let name = if record.name.is_empty() { "@" } else { &record.name };
+    match record.priority {
+        Some(priority) => format!("{} {} {} {}", name, record.ty, priority, record.content),
+        None => format!("{} {} {}", name, record.ty, record.content),
+    }
+}
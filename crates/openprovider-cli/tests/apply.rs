@@ -0,0 +1,83 @@
+use std::path::PathBuf;
+
+use registrar_common::{DnsRecord, DnsRecordType, MemoryRegistrar, Mirror};
+
+use openprovider_cli::apply::{desired_zone, read_records_file, run_plan, PlanOutcome};
+
+fn temp_file(name: &str, text: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("openprovider-cli-apply-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join(name);
+    std::fs::write(&path, text).unwrap();
+    path
+}
+
+const YAML: &str = "\
+zone: example.com.
+records:
+  - {name: www, type: CNAME, content: example.com.}
+  - name: ''
+    type: TXT
+    content: 12345
+";
+
+#[test]
+fn records_files_are_read_in_any_format() {
+    let expected = vec![
+        DnsRecord::new("www", DnsRecordType::CNAME, "example.com."),
+        DnsRecord::new("", DnsRecordType::TXT, "12345"),
+    ];
+    for name in ["records.yaml", "records.yml", "records"] {
+        let path = temp_file(name, YAML);
+        let (zone, records) = read_records_file(&path, None, None).unwrap();
+        assert_eq!(zone, "example.com", "{}", name);
+        assert_eq!(records, expected, "{}", name);
+    }
+
+    let path = temp_file("records.csv", "name,type,content\nwww,CNAME,example.com.\n");
+    assert!(read_records_file(&path, None, None).is_err());
+    let (zone, _) = read_records_file(&path, None, Some("example.org.")).unwrap();
+    assert_eq!(zone, "example.org");
+}
+
+#[tokio::test]
+async fn plans_are_applied_once_confirmed() {
+    let (zone, records) = read_records_file(&temp_file("plan.yaml", YAML), None, None).unwrap();
+    let desired = desired_zone(&zone, records);
+    let actual = MemoryRegistrar::new();
+    actual.add_record("example.com", DnsRecord::new("www", DnsRecordType::A, "93.184.216.34"));
+    let mirror = Mirror::new(&desired, &actual);
+
+    let outcome = run_plan(&mirror, &zone, mirror.plan(&zone).await.unwrap(), true, |_| panic!("dry runs do not ask")).await;
+    assert!(matches!(outcome, PlanOutcome::Drifted));
+    assert_eq!(outcome.exit_code(), 2);
+    assert_eq!(actual.records("example.com").len(), 1);
+
+    let outcome = run_plan(&mirror, &zone, mirror.plan(&zone).await.unwrap(), false, |plan| {
+        assert_eq!(plan.len(), 3);
+        false
+    }).await;
+    assert!(matches!(outcome, PlanOutcome::Declined));
+    assert_eq!(outcome.exit_code(), 1);
+    assert_eq!(actual.records("example.com").len(), 1);
+
+    let outcome = run_plan(&mirror, &zone, mirror.plan(&zone).await.unwrap(), false, |_| true).await;
+    assert!(matches!(&outcome, PlanOutcome::Applied(report) if report.applied.len() == 3));
+    assert_eq!(outcome.exit_code(), 0);
+    assert_eq!(actual.records("example.com"), desired.records("example.com"));
+
+    let outcome = run_plan(&mirror, &zone, mirror.plan(&zone).await.unwrap(), true, |_| panic!("nothing to ask")).await;
+    assert!(matches!(outcome, PlanOutcome::UpToDate));
+    assert_eq!(outcome.exit_code(), 0);
+}
+
+#[tokio::test]
+async fn refused_changes_fail_the_run() {
+    let desired = desired_zone("example.com", vec![DnsRecord::new("", DnsRecordType::TXT, "v=spf1 -all")]);
+    let actual = MemoryRegistrar::new().unsupported_type(DnsRecordType::TXT);
+    actual.add_zone("example.com");
+    let mirror = Mirror::new(&desired, &actual);
+    let outcome = run_plan(&mirror, "example.com", mirror.plan("example.com").await.unwrap(), false, |_| true).await;
+    assert!(matches!(&outcome, PlanOutcome::Applied(report) if report.unsupported.len() == 1));
+    assert_eq!(outcome.exit_code(), 1);
+}
@@ -0,0 +1,22 @@
+use std::process::Command;
+
+fn status(program: &str, args: &[&str]) -> Option<i32> {
+    Command::new(program)
+        .args(args)
+        .output()
+        .unwrap()
+        .status
+        .code()
+}
+
+#[test]
+fn commands_without_a_subcommand_fail() {
+    let registrar = env!("CARGO_BIN_EXE_registrar");
+    for args in [&[][..], &["zone"], &["record"], &["domain"], &["acme"], &["config"], &["porkbun"]] {
+        assert_eq!(status(registrar, args), Some(2), "registrar {:?}", args);
+    }
+    let openprovider = env!("CARGO_BIN_EXE_openprovider");
+    for args in [&[][..], &["zone"], &["snapshot"], &["record"], &["config"]] {
+        assert_eq!(status(openprovider, args), Some(2), "openprovider {:?}", args);
+    }
+}
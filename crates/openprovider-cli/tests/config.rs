@@ -0,0 +1,157 @@
+use std::path::PathBuf;
+
+use openprovider_cli::config::{Config, Flags, Source};
+
+fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("openprovider-cli-config-{}-{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+fn read_toml(path: PathBuf) -> toml::Table {
+    toml::from_str(&std::fs::read_to_string(path).unwrap()).unwrap()
+}
+
+#[test]
+fn settings_come_from_flags_then_environment_then_profile_then_default() {
+    // Every test uses its own prefix, so that they can change environment variables at the same
+    // time.
+    const PREFIX: &str = "CONFIGTEST_PRECEDENCE";
+    let dir = temp_dir("precedence");
+
+    let config = Config::load(dir.clone(), PREFIX, Flags::default()).unwrap();
+    assert_eq!((config.max_retries.value, config.max_retries.source), (Some(5), Source::Default));
+    assert_eq!((config.timeout.value, config.timeout.source), (None, Source::Default));
+
+    std::fs::write(dir.join("config.toml"), "[profiles.default]\nmax_retries = 3\ntimeout = 30\n").unwrap();
+    let config = Config::load(dir.clone(), PREFIX, Flags::default()).unwrap();
+    assert_eq!((config.max_retries.value, config.max_retries.source), (Some(3), Source::Profile));
+    assert_eq!((config.timeout.value, config.timeout.source), (Some(30), Source::Profile));
+
+    std::env::set_var(format!("{}_MAX_RETRIES", PREFIX), "4");
+    let config = Config::load(dir.clone(), PREFIX, Flags::default()).unwrap();
+    assert_eq!((config.max_retries.value, config.max_retries.source), (Some(4), Source::Env));
+    assert_eq!((config.timeout.value, config.timeout.source), (Some(30), Source::Profile));
+
+    let flags = Flags { max_retries: Some(2), ..Flags::default() };
+    let config = Config::load(dir, PREFIX, flags).unwrap();
+    assert_eq!((config.max_retries.value, config.max_retries.source), (Some(2), Source::Flag));
+    assert_eq!((config.timeout.value, config.timeout.source), (Some(30), Source::Profile));
+}
+
+#[test]
+fn profiles_are_selected_by_flag_then_environment_then_default_profile() {
+    const PREFIX: &str = "CONFIGTEST_PROFILES";
+    let dir = temp_dir("profiles");
+    std::fs::write(dir.join("config.toml"), "\
+default_profile = \"work\"
+
+[profiles.work]
+timeout = 10
+
+[profiles.home]
+timeout = 20
+
+[profiles.ci]
+timeout = 30
+").unwrap();
+
+    let config = Config::load(dir.clone(), PREFIX, Flags::default()).unwrap();
+    assert_eq!(config.profile, "work");
+    assert_eq!(config.timeout.value, Some(10));
+    assert_eq!(config.profiles(), vec!["ci", "home", "work"]);
+
+    std::env::set_var(format!("{}_PROFILE", PREFIX), "home");
+    let config = Config::load(dir.clone(), PREFIX, Flags::default()).unwrap();
+    assert_eq!(config.profile, "home");
+    assert_eq!(config.timeout.value, Some(20));
+
+    let flags = Flags { profile: Some("ci".to_string()), ..Flags::default() };
+    let config = Config::load(dir.clone(), PREFIX, flags).unwrap();
+    assert_eq!(config.profile, "ci");
+    assert_eq!(config.timeout.value, Some(30));
+
+    let flags = Flags { profile: Some("missing".to_string()), ..Flags::default() };
+    let config = Config::load(dir, PREFIX, flags).unwrap();
+    assert!(!config.profile_exists());
+    assert_eq!((config.timeout.value, config.timeout.source), (None, Source::Default));
+}
+
+#[test]
+fn old_json_credentials_are_read() {
+    let dir = temp_dir("legacy");
+    std::fs::write(
+        dir.join("credentials.toml"),
+        r#"{"username": "bob", "password": "123456789", "token": "abc"}"#,
+    ).unwrap();
+    let mut config = Config::load(dir.clone(), "CONFIGTEST_LEGACY", Flags::default()).unwrap();
+    assert!(config.profile_exists());
+    assert_eq!(config.get("openprovider.username").unwrap().as_deref(), Some("bob"));
+    assert_eq!(config.get("openprovider.password").unwrap().as_deref(), Some("123456789"));
+    assert_eq!(config.get("openprovider.token").unwrap().as_deref(), Some("abc"));
+
+    // Saving anything rewrites the file as TOML.
+    config.set("openprovider.token", Some("def")).unwrap();
+    let credentials = read_toml(dir.join("credentials.toml"));
+    assert_eq!(credentials["profiles"]["default"]["openprovider"]["username"].as_str(), Some("bob"));
+    assert_eq!(credentials["profiles"]["default"]["openprovider"]["token"].as_str(), Some("def"));
+}
+
+#[test]
+fn set_and_unset_write_valid_toml() {
+    const PREFIX: &str = "CONFIGTEST_SET";
+    let dir = temp_dir("set");
+    let flags = || Flags { profile: Some("work".to_string()), ..Flags::default() };
+    let mut config = Config::load(dir.clone(), PREFIX, flags()).unwrap();
+    config.set("default_profile", Some("work")).unwrap();
+    config.set("timeout", Some("30")).unwrap();
+    config.set("provider", Some("porkbun")).unwrap();
+    config.set("porkbun.api_key", Some("pk1_key")).unwrap();
+
+    let settings = read_toml(dir.join("config.toml"));
+    assert_eq!(settings["default_profile"].as_str(), Some("work"));
+    assert_eq!(settings["profiles"]["work"]["timeout"].as_integer(), Some(30));
+    assert_eq!(settings["profiles"]["work"]["provider"].as_str(), Some("porkbun"));
+    let credentials = read_toml(dir.join("credentials.toml"));
+    assert_eq!(credentials["profiles"]["work"]["porkbun"]["api_key"].as_str(), Some("pk1_key"));
+
+    let mut config = Config::load(dir.clone(), PREFIX, Flags::default()).unwrap();
+    assert_eq!(config.profile, "work");
+    assert_eq!((config.timeout.value, config.timeout.source), (Some(30), Source::Profile));
+    assert_eq!(config.provider.value.as_deref(), Some("porkbun"));
+
+    config.set("timeout", None).unwrap();
+    config.set("porkbun.api_key", None).unwrap();
+    let settings = read_toml(dir.join("config.toml"));
+    assert!(!settings["profiles"]["work"].as_table().unwrap().contains_key("timeout"));
+    let credentials = read_toml(dir.join("credentials.toml"));
+    assert!(!credentials["profiles"]["work"].as_table().unwrap().contains_key("porkbun"));
+
+    assert!(config.set("no_such_key", Some("1")).is_err());
+    assert!(config.set("provider", Some("no-such-provider")).is_err());
+}
+
+#[test]
+fn values_are_coerced_to_the_type_of_the_setting() {
+    let dir = temp_dir("coercion");
+    let mut config = Config::load(dir.clone(), "CONFIGTEST_COERCION", Flags::default()).unwrap();
+    config.set("max_retries", Some("3")).unwrap();
+    config.set("save_password", Some("false")).unwrap();
+    // Values that look like numbers or booleans stay strings where a string is expected.
+    config.set("credential_get_command", Some("true")).unwrap();
+    config.set("credential_set_command", Some("42")).unwrap();
+
+    let settings = read_toml(dir.join("config.toml"));
+    let profile = &settings["profiles"]["default"];
+    assert_eq!(profile["max_retries"].as_integer(), Some(3));
+    assert_eq!(profile["save_password"].as_bool(), Some(false));
+    assert_eq!(profile["credential_get_command"].as_str(), Some("true"));
+    assert_eq!(profile["credential_set_command"].as_str(), Some("42"));
+    assert!(!config.save_password());
+
+    assert!(config.set("timeout", Some("soon")).is_err());
+    assert!(config.set("max_retries", Some("-1")).is_err());
+    assert!(config.set("save_password", Some("maybe")).is_err());
+    assert!(config.set("credential_store", Some("safe")).is_err());
+}
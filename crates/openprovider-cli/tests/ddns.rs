@@ -0,0 +1,73 @@
+use registrar_common::{DnsRecord, DnsRecordType, MemoryRegistrar, Registrar};
+
+use openprovider_cli::ddns::{parse_interface, Addresses, Updater};
+
+fn addresses(ipv4: &str, ipv6: &str) -> Addresses {
+    Addresses {
+        ipv4: Some(ipv4.parse().unwrap()),
+        ipv6: Some(ipv6.parse().unwrap()),
+    }
+}
+
+#[tokio::test]
+async fn records_follow_the_address() {
+    let registrar = MemoryRegistrar::new();
+    registrar.add_zone("example.com");
+    registrar.add_record("example.com", DnsRecord::new("home", DnsRecordType::A, "192.0.2.1"));
+    registrar.add_record("example.com", DnsRecord::new("home", DnsRecordType::A, "192.0.2.2"));
+    registrar.add_record("example.com", DnsRecord::new("www", DnsRecordType::CNAME, "example.com"));
+
+    let mut updater = Updater::new("example.com", vec!["home".to_string()]);
+    let changed = updater.update(&registrar, addresses("192.0.2.3", "2001:db8::1")).await.unwrap();
+    assert_eq!(changed, vec![
+        DnsRecord::new("home", DnsRecordType::A, "192.0.2.3"),
+        DnsRecord::new("home", DnsRecordType::AAAA, "2001:db8::1"),
+    ]);
+    let mut records = registrar.list_records("example.com").await.unwrap();
+    records.sort_by(|a, b| a.ty.cmp(&b.ty));
+    assert_eq!(records, vec![
+        DnsRecord::new("home", DnsRecordType::A, "192.0.2.3"),
+        DnsRecord::new("home", DnsRecordType::AAAA, "2001:db8::1"),
+        DnsRecord::new("www", DnsRecordType::CNAME, "example.com"),
+    ]);
+
+    // Only an address that changed makes the updater call the API again.
+    let calls = registrar.call_count();
+    assert!(updater.update(&registrar, addresses("192.0.2.3", "2001:db8::1")).await.unwrap().is_empty());
+    assert_eq!(registrar.call_count(), calls);
+    let changed = updater.update(&registrar, addresses("192.0.2.3", "2001:db8::2")).await.unwrap();
+    assert_eq!(changed, vec![DnsRecord::new("home", DnsRecordType::AAAA, "2001:db8::2")]);
+}
+
+#[tokio::test]
+async fn the_cache_survives_a_restart() {
+    let path = std::env::temp_dir().join(format!("openprovider-cli-ddns-{}.json", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    let registrar = MemoryRegistrar::new();
+    registrar.add_zone("example.com");
+
+    let mut updater = Updater::new("example.com", vec![String::new()]).cache(&path);
+    updater.update(&registrar, addresses("192.0.2.1", "2001:db8::1")).await.unwrap();
+
+    let calls = registrar.call_count();
+    let mut restarted = Updater::new("example.com", vec![String::new()]).cache(&path);
+    assert!(restarted.update(&registrar, addresses("192.0.2.1", "2001:db8::1")).await.unwrap().is_empty());
+    assert_eq!(registrar.call_count(), calls);
+
+    // A cache written for other names does not count.
+    let mut other = Updater::new("example.com", vec!["home".to_string()]).cache(&path);
+    assert_eq!(other.update(&registrar, addresses("192.0.2.1", "2001:db8::1")).await.unwrap().len(), 2);
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn interfaces_prefer_stable_global_addresses() {
+    let output = r#"[{"ifname": "eth0", "addr_info": [
+        {"family": "inet", "local": "192.0.2.7", "prefixlen": 24, "scope": "global"},
+        {"family": "inet6", "local": "2001:db8::aaaa", "prefixlen": 64, "scope": "global", "temporary": true},
+        {"family": "inet6", "local": "2001:db8::1", "prefixlen": 64, "scope": "global"},
+        {"family": "inet6", "local": "fe80::1", "prefixlen": 64, "scope": "link"}
+    ]}]"#;
+    assert_eq!(parse_interface(output).unwrap(), addresses("192.0.2.7", "2001:db8::1"));
+    assert_eq!(parse_interface("[]").unwrap(), Addresses::default());
+}
@@ -0,0 +1,83 @@
+use std::path::Path;
+
+use registrar_common::{DnsRecord, DnsRecordType};
+
+use openprovider_cli::formats::{detect, read, write, FileFormat};
+
+/// Records with the characters and values that each format has to quote or escape somehow.
+fn records() -> Vec<DnsRecord> {
+    vec![
+        DnsRecord::new("", DnsRecordType::A, "93.184.216.34").ttl(3600),
+        DnsRecord::new("", DnsRecordType::MX, "mail.example.com.").priority(10),
+        DnsRecord::new("", DnsRecordType::TXT, "\"v=spf1 include:_spf.example.com -all\""),
+        DnsRecord::new("_dmarc", DnsRecordType::TXT, "v=DMARC1; p=none, rua=mailto:dmarc@example.com"),
+        DnsRecord::new("1", DnsRecordType::TXT, "12345"),
+        DnsRecord::new("yes", DnsRecordType::TXT, "null # not a comment: really"),
+        DnsRecord::new("*", DnsRecordType::CNAME, "example.com."),
+    ]
+}
+
+#[test]
+fn records_survive_a_round_trip_in_every_format() {
+    for format in [FileFormat::Bind, FileFormat::Json, FileFormat::Yaml, FileFormat::Csv, FileFormat::Toml] {
+        let text = write(format, "example.com", records()).unwrap();
+        let (zone, read_back) = read(format, &text, Some("example.com")).unwrap();
+        assert_eq!(read_back, records(), "{:?}:\n{}", format, text);
+        if !matches!(format, FileFormat::Csv) {
+            assert_eq!(zone.as_deref(), Some("example.com"), "{:?}", format);
+        }
+        assert_eq!(detect(Path::new("-"), &text), format, "{:?}", format);
+    }
+}
+
+#[test]
+fn yaml_is_read_in_any_style() {
+    let text = "\
+# Written by hand
+zone: example.com
+records:
+  - {name: '', type: A, content: 93.184.216.34, ttl: 300}
+  - name: 1
+    type: TXT
+    content: 12345
+  - name: long
+    type: TXT
+    content: >-
+      first half
+      second half
+  -
+    name: www
+    type: CNAME
+    content: \"example.com.\"
+";
+    let (zone, records) = read(FileFormat::Yaml, text, None).unwrap();
+    assert_eq!(zone.as_deref(), Some("example.com"));
+    assert_eq!(records, vec![
+        DnsRecord::new("", DnsRecordType::A, "93.184.216.34").ttl(300),
+        DnsRecord::new("1", DnsRecordType::TXT, "12345"),
+        DnsRecord::new("long", DnsRecordType::TXT, "first half second half"),
+        DnsRecord::new("www", DnsRecordType::CNAME, "example.com."),
+    ]);
+}
+
+#[test]
+fn csv_columns_can_be_reordered_and_left_out() {
+    let text = "Type,Name,Content\r\nA,,93.184.216.34\r\nTXT,,\"say \"\"hi\"\", twice\"\r\n\r\n";
+    let (zone, records) = read(FileFormat::Csv, text, None).unwrap();
+    assert_eq!(zone, None);
+    assert_eq!(records, vec![
+        DnsRecord::new("", DnsRecordType::A, "93.184.216.34"),
+        DnsRecord::new("", DnsRecordType::TXT, "say \"hi\", twice"),
+    ]);
+    assert!(read(FileFormat::Csv, "name,type\n", None).is_err());
+    assert!(read(FileFormat::Csv, "name,type,content,ttl\nwww,A,1.2.3.4,soon\n", None).is_err());
+}
+
+#[test]
+fn formats_are_detected_by_extension_first() {
+    assert_eq!(detect(Path::new("zone.yml"), "{}"), FileFormat::Yaml);
+    assert_eq!(detect(Path::new("zone.TOML"), ""), FileFormat::Toml);
+    assert_eq!(detect(Path::new("example.com.zone"), ""), FileFormat::Bind);
+    assert_eq!(detect(Path::new("records"), "records:\n  - name: www\n"), FileFormat::Yaml);
+    assert_eq!(detect(Path::new("records"), "$ORIGIN example.com.\n"), FileFormat::Bind);
+}
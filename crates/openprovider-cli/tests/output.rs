@@ -0,0 +1,94 @@
+use clap::Command;
+use serde::Serialize;
+use serde_json::Value;
+
+use openprovider_cli::output::{Output, OutputFormat};
+
+#[derive(Serialize)]
+struct Zone {
+    name: &'static str,
+    records: u32,
+    comment: &'static str,
+}
+
+fn zones() -> Vec<Zone> {
+    vec![
+        Zone { name: "example.com", records: 12, comment: "main site" },
+        Zone { name: "example.org", records: 3, comment: "say \"hi\", twice" },
+    ]
+}
+
+fn output(format: OutputFormat, columns: Option<&[&str]>) -> Output {
+    Output {
+        format,
+        columns: columns.map(|columns| columns.iter().map(|column| column.to_string()).collect()),
+    }
+}
+
+#[test]
+fn tables_align_their_columns() {
+    let text = output(OutputFormat::Table, None).list_to_string(&zones(), &["name", "records"]).unwrap();
+    assert_eq!(text, "\
+NAME         RECORDS
+example.com  12
+example.org  3
+");
+
+    let text = output(OutputFormat::Table, None).item_to_string(&zones()[0], &["name"]).unwrap();
+    assert_eq!(text, "\
+name     example.com
+comment  main site
+records  12
+");
+}
+
+#[test]
+fn columns_can_be_selected() {
+    let text = output(OutputFormat::Table, Some(&["comment", "name"])).list_to_string(&zones(), &["name"]).unwrap();
+    assert_eq!(text.lines().next(), Some("COMMENT          NAME"));
+
+    let text = output(OutputFormat::Json, Some(&["name"])).list_to_string(&zones(), &[]).unwrap();
+    let value: Value = serde_json::from_str(&text).unwrap();
+    assert_eq!(value, serde_json::json!([{ "name": "example.com" }, { "name": "example.org" }]));
+}
+
+#[test]
+fn unknown_columns_are_rejected() {
+    let error = output(OutputFormat::Table, Some(&["name", "owner"])).list_to_string(&zones(), &["name"]).unwrap_err();
+    assert_eq!(error.to_string(), "unknown column owner; available columns are comment, name, records");
+    assert!(output(OutputFormat::Csv, Some(&["owner"])).item_to_string(&zones()[0], &[]).is_err());
+}
+
+#[test]
+fn csv_fields_are_quoted_when_needed() {
+    let text = output(OutputFormat::Csv, Some(&["name", "comment"])).list_to_string(&zones(), &[]).unwrap();
+    assert_eq!(text, "\
+name,comment
+example.com,main site
+example.org,\"say \"\"hi\"\", twice\"
+");
+}
+
+#[test]
+fn ndjson_has_one_object_per_line() {
+    let text = output(OutputFormat::Ndjson, None).list_to_string(&zones(), &["name"]).unwrap();
+    let lines: Vec<&str> = text.lines().collect();
+    assert_eq!(lines.len(), 2);
+    for (line, zone) in lines.iter().zip(zones()) {
+        assert_eq!(serde_json::from_str::<Value>(line).unwrap(), serde_json::to_value(zone).unwrap());
+    }
+    assert!(text.ends_with('\n'));
+}
+
+#[test]
+fn flags_choose_the_format_and_columns() {
+    let command = Output::args(Command::new("list"));
+    let matches = command.clone().try_get_matches_from(["list", "--csv", "--columns", "name,records"]).unwrap();
+    let output = Output::from_matches(&matches);
+    assert_eq!(output.format, OutputFormat::Csv);
+    assert_eq!(output.columns, Some(vec!["name".to_string(), "records".to_string()]));
+
+    let matches = command.clone().try_get_matches_from(["list"]).unwrap();
+    assert_eq!(Output::from_matches(&matches).format, OutputFormat::Table);
+    assert!(command.try_get_matches_from(["list", "--json", "--yaml"]).is_err());
+}
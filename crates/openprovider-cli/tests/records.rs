@@ -0,0 +1,24 @@
+use registrar_common::{DnsRecord, DnsRecordType};
+
+use openprovider_cli::records::{describe, relative_name};
+
+#[test]
+fn names_are_made_relative_to_the_zone() {
+    for apex in ["", "@", "example.com", "example.com.", "Example.COM."] {
+        assert_eq!(relative_name(apex, "example.com"), "", "{:?}", apex);
+    }
+    assert_eq!(relative_name("@", "example.com."), "");
+    assert_eq!(relative_name("www", "example.com"), "www");
+    assert_eq!(relative_name("www.example.com.", "example.com"), "www");
+    assert_eq!(relative_name("www.example.com", "example.com."), "www");
+    assert_eq!(relative_name("Mail.EXAMPLE.com", "example.COM"), "Mail");
+    assert_eq!(relative_name("_dmarc.Sub.Example.com.", "example.com"), "_dmarc.Sub");
+    assert_eq!(relative_name("www.example.org.", "example.com"), "www.example.org");
+    assert_eq!(relative_name("notexample.com", "example.com"), "notexample.com");
+}
+
+#[test]
+fn records_are_described_on_one_line() {
+    assert_eq!(describe(&DnsRecord::new("", DnsRecordType::A, "93.184.216.34")), "@ A 93.184.216.34");
+    assert_eq!(describe(&DnsRecord::new("", DnsRecordType::MX, "mail.example.com.").priority(10)), "@ MX 10 mail.example.com.");
+}
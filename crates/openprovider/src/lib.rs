@@ -9,42 +9,70 @@
 //! yet to be implemented. You are invited to try out the API and contribute to the project [back
 //! on GitHub](https://github.com/samvv/openprovider-rs).
 
-use registrar_common::{Error, Result, ValueExt};
+#[cfg(feature = "mock")]
+pub mod mock;
+
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+use async_trait::async_trait;
+use registrar_common::{content_id, retry, Cassette, DEFAULT_MAX_RETRIES, DnsRecord, DnsRecordId, DnsRecordType, Error, RecordIdentity, Registrar, Result, Timeouts, ValueExt};
 use reqwest::Method;
 use serde::{Serialize, Deserialize};
 use serde_json::{Value, json};
 
-const DEFAULT_MAX_RETRIES: u32 = 5;
+const DEFAULT_BASE_URL: &str = "https://api.openprovider.eu/v1beta";
+
+/// The TTL that is used for new records when none was specified.
+pub const DEFAULT_TTL: u64 = 3600;
 
 struct Config {
+    base_url: String,
     token: Option<String>,
     max_retries: u32,
+    timeouts: Timeouts,
+    cassette: Option<Arc<Cassette>>,
 }
 
 /// Constructs an [API client](Client).
 ///
-/// Right now, this builder does not accept any options, but more may be added in the future.
-///
 /// ```no_run
-/// let mut client = openprovider::Builder::new().build();
+/// let client = openprovider::Builder::new().build();
 /// // use the client to make requests
 /// ```
 pub struct Builder {
     config: Config,
 }
 
+impl Default for Builder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Builder {
 
     /// Create a new API client builder object.
     pub fn new() -> Self {
         Self {
             config: Config {
+                base_url: DEFAULT_BASE_URL.to_string(),
                 token: None,
-                max_retries: DEFAULT_MAX_RETRIES
+                max_retries: DEFAULT_MAX_RETRIES,
+                timeouts: Timeouts::default(),
+                cassette: None,
             }
         }
     }
 
+    /// Send requests to another server than the official OpenProvider API.
+    ///
+    /// The URL should include the version of the API, e.g. `http://localhost:8080/v1beta`.
+    pub fn base_url<S: Into<String>>(mut self, base_url: S) -> Self {
+        self.config.base_url = base_url.into().trim_end_matches('/').to_string();
+        self
+    }
+
     /// Make sure the client to be built is configured to use this token.
     pub fn token(mut self, token: Option<String>) -> Self {
         self.config.token = token;
@@ -52,23 +80,60 @@ impl Builder {
     }
 
     /// Limit the amount of HTTP request retries to the given number.
+    ///
+    /// Only requests that failed in a way that is [transient](Error::is_transient) are retried.
     pub fn max_retries(mut self, max_retries: u32) -> Self {
         self.config.max_retries = max_retries;
         self
     }
 
-    /// Allow as many HTTP request retries as needed in the API client.
-    pub fn no_max_retries(mut self) -> Self {
-        self.config.max_retries = 0;
+    /// Give up on a request if no connection could be made within the given duration.
+    pub fn connect_timeout(mut self, timeout: Duration) -> Self {
+        self.config.timeouts.connect = Some(timeout);
+        self
+    }
+
+    /// Give up on a request if the server does not send any data within the given duration.
+    pub fn read_timeout(mut self, timeout: Duration) -> Self {
+        self.config.timeouts.read = Some(timeout);
+        self
+    }
+
+    /// Give up on a request if it did not complete within the given duration.
+    ///
+    /// Use [`Client::with_timeout`] to override this deadline for specific requests.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.config.timeouts.total = Some(timeout);
+        self
+    }
+
+    /// Record all requests to the given cassette or play them back from it.
+    ///
+    /// See [`Cassette`] for more information.
+    pub fn cassette(mut self, cassette: Arc<Cassette>) -> Self {
+        self.config.cassette = Some(cassette);
         self
     }
 
     /// Build the actual API client. This is a destructive operation.
     pub fn build(self) -> Client {
+        let mut builder = reqwest::Client::builder();
+        if let Some(timeout) = self.config.timeouts.connect {
+            builder = builder.connect_timeout(timeout);
+        }
+        if let Some(timeout) = self.config.timeouts.read {
+            builder = builder.read_timeout(timeout);
+        }
+        if let Some(timeout) = self.config.timeouts.total {
+            builder = builder.timeout(timeout);
+        }
         Client {
-            client: reqwest::Client::new(),
-            token: self.config.token,
+            client: builder.build().expect("failed to initialize the HTTP client"),
+            base_url: self.config.base_url,
+            token: Arc::new(RwLock::new(self.config.token)),
             max_retries: self.config.max_retries,
+            request_timeout: None,
+            cassette: self.config.cassette,
         }
     }
 
@@ -91,6 +156,18 @@ pub enum RecordType {
     SOA,
 }
 
+impl std::str::FromStr for RecordType {
+
+    type Err = Error;
+
+    /// Parse the name of a record type, ignoring case.
+    fn from_str(s: &str) -> Result<Self> {
+        serde_json::from_value(Value::String(s.to_ascii_uppercase()))
+            .map_err(|_| Error::Generic(format!("unknown record type {}", s)))
+    }
+
+}
+
 /// Represents a DNS record.
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Record {
@@ -142,6 +219,38 @@ pub struct Zone {
     pub ty: String,
 }
 
+/// Represents the name of a domain, split into its label and its extension.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct DomainName {
+    pub name: String,
+    pub extension: String,
+}
+
+impl std::fmt::Display for DomainName {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}.{}", self.name, self.extension)
+    }
+}
+
+/// Represents a name server that a [domain](Domain) is delegated to.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct NameServer {
+    pub name: String,
+    pub ip: Option<String>,
+    pub ip6: Option<String>,
+}
+
+/// Represents a domain that was registered through OpenProvider.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Domain {
+    pub id: u64,
+    pub domain: DomainName,
+    pub status: String,
+    pub expiration_date: Option<String>,
+    #[serde(default)]
+    pub name_servers: Vec<NameServer>,
+}
+
 const CODE_SUCCESS: u32 = 0;
 
 /// The error code the OpenProvider API returns whenever there is an authentication failure.
@@ -150,24 +259,38 @@ const CODE_AUTH_FAILED: u32 = 196;
 /// Communiates with the OpenProvider.nl API.
 ///
 /// ```no_run
-/// let mut client = openprovider::Client::default();
+/// let client = openprovider::Client::default();
 /// let token = client.login("bob", "123456789").await?;
 /// client.set_token(token);
 /// ```
 ///
+/// Clients are cheap to clone. All clones share the same connection pool and the same token, so
+/// you can hand out a copy to each task that needs one.
+///
+/// ```no_run
+/// let client = openprovider::Client::default();
+///
+/// let tasks: Vec<_> = ["example.com", "example.org"]
+///     .into_iter()
+///     .map(|name| {
+///         let client = client.clone();
+///         tokio::spawn(async move { client.list_records(name).await })
+///     })
+///     .collect();
+/// ```
+#[derive(Clone)]
 pub struct Client {
     client: reqwest::Client,
-    token: Option<String>,
+    base_url: String,
+    token: Arc<RwLock<Option<String>>>,
     max_retries: u32,
+    request_timeout: Option<Duration>,
+    cassette: Option<Arc<Cassette>>,
 }
 
 impl Default for Client {
     fn default() -> Self {
-        Self {
-            client: reqwest::Client::new(),
-            token: None,
-            max_retries: DEFAULT_MAX_RETRIES
-        }
+        Builder::new().build()
     }
 }
 
@@ -178,16 +301,16 @@ impl Client {
     /// Use [`set_token`](Self::set_token()) to assign the token to the client that should use it.
     ///
     /// ```no_run
-    /// let mut client = openprovider::Client::default();
+    /// let client = openprovider::Client::default();
     ///
     /// let token = client.login("bob", "123456789").await?;
     ///
     /// client.set_token(token);
     /// ```
-    pub async fn login<S1: AsRef<str>, S2: AsRef<str>>(&mut self, username: S1, password: S2) -> Result<String> {
+    pub async fn login<S1: AsRef<str>, S2: AsRef<str>>(&self, username: S1, password: S2) -> Result<String> {
         let res = self.request(
             Method::POST,
-            "https://api.openprovider.eu/v1beta/auth/login",
+            format!("{}/auth/login", self.base_url),
             Some(serde_json::json!({
                 "username": username.as_ref(),
                 "password": password.as_ref()
@@ -197,22 +320,23 @@ impl Client {
     }
 
     /// Get the current token used for authorization, if any.
-    pub fn get_token(&self) -> Option<&String> {
-        self.token.as_ref()
+    pub fn get_token(&self) -> Option<String> {
+        self.token.read().unwrap().clone()
     }
 
     /// Return `true` if a token is present and ready to be used for authorization; `false`
     /// otherwise.
     pub fn has_token(&self) -> bool {
-        self.token.is_some()
+        self.token.read().unwrap().is_some()
     }
 
-    //// Set the token that will be used to authenticate.
+    /// Set the token that will be used to authenticate.
     ///
     /// Use [`login`](Self::login()) to obtain a token from a combination of a username and password.
+    /// The token is shared with all clones of this client.
     ///
     /// ```no_run
-    /// let mut client = openprovider::Client::default();
+    /// let client = openprovider::Client::default();
     ///
     /// match std::env::var("OPENPROVIDER_TOKEN") {
     ///     Ok(token) => client.set_token(token),
@@ -220,26 +344,47 @@ impl Client {
     /// }
     ///
     /// ```
-    pub fn set_token<S: Into<String>>(&mut self, token: S) {
-        self.token = Some(token.into());
+    pub fn set_token<S: Into<String>>(&self, token: S) {
+        *self.token.write().unwrap() = Some(token.into());
+    }
+
+    /// Get a copy of this client whose requests must complete within the given duration.
+    ///
+    /// The copy shares its token with this client. This overrides the deadline set with [`Builder::timeout`]. When the deadline expires, the
+    /// request fails with [`Error::Http`] of kind [`HttpErrorKind::Timeout`](registrar_common::HttpErrorKind::Timeout).
+    ///
+    /// ```no_run
+    /// use std::time::Duration;
+    ///
+    /// let client = openprovider::Client::default();
+    ///
+    /// let zones = client
+    ///     .with_timeout(Duration::from_secs(5))
+    ///     .list_zones()
+    ///     .await?;
+    /// ```
+    pub fn with_timeout(&self, timeout: Duration) -> Self {
+        let mut client = self.clone();
+        client.request_timeout = Some(timeout);
+        client
     }
 
-    async fn request<U: AsRef<str>>(&mut self, method: Method, url: U, body: Option<Value>) -> Result<Value> {
+    async fn request<U: AsRef<str>>(&self, method: Method, url: U, body: Option<Value>) -> Result<Value> {
         let url_ref = url.as_ref();
-        log::info!("Starting request to {}", url_ref);
-        let mut builder = self.client
-            .request(method.clone(), url_ref)
-            .header("Accept", "*/*");
-        let body = body.clone();
-        if body.is_some() {
-            builder = builder.json(&body.unwrap());
-        }
-        if self.token.is_some() {
-            builder = builder.header("Authorization", format!("Bearer {}", self.token.clone().unwrap()));
-        }
-        let response: Value = builder
-            .send().await?
-            .json().await?;
+        let path = url_ref.strip_prefix(&self.base_url).unwrap_or(url_ref);
+        let response = match &self.cassette {
+            Some(cassette) if cassette.is_replaying() => {
+                log::info!("Replaying request to {}", url_ref);
+                cassette.find(method.as_str(), path, body.as_ref())?
+            },
+            _ => {
+                let response = retry(self.max_retries, || self.send(method.clone(), url_ref, body.as_ref())).await?;
+                if let Some(cassette) = &self.cassette {
+                    cassette.push(method.as_str(), path, body.as_ref(), &response);
+                }
+                response
+            },
+        };
         let code = response
             .get_ok("code")?
             .as_u32_ok()?;
@@ -256,10 +401,33 @@ impl Client {
         }
     }
 
+    async fn send(&self, method: Method, url_ref: &str, body: Option<&Value>) -> Result<Value> {
+        log::info!("Starting request to {}", url_ref);
+        let mut builder = self.client
+            .request(method.clone(), url_ref)
+            .header("Accept", "*/*");
+        if let Some(timeout) = self.request_timeout {
+            builder = builder.timeout(timeout);
+        }
+        if let Some(body) = body {
+            builder = builder.json(body);
+        }
+        if let Some(token) = self.get_token() {
+            builder = builder.header("Authorization", format!("Bearer {}", token));
+        }
+        let response = builder.send().await?;
+        // Errors of the API itself come with a JSON body, but those of proxies in front of it do not.
+        let error = Error::StatusCode(response.status().as_u16());
+        if error.is_transient() {
+            return Err(error);
+        }
+        Ok(response.json().await?)
+    }
+
     /// List all known DNS zones for this particular authenticated user.
     ///
     /// ```no_run
-    /// let mut client = openprovider::Client::default();
+    /// let client = openprovider::Client::default();
     ///
     /// // ...
     ///
@@ -269,10 +437,10 @@ impl Client {
     ///     .iter()
     ///     .filter(|z| !z.is_deleted);
     /// ```
-    pub async fn list_zones(&mut self) -> Result<Vec<Zone>> {
+    pub async fn list_zones(&self) -> Result<Vec<Zone>> {
         let response = self.request(
             Method::GET,
-            "https://api.openprovider.eu/v1beta/dns/zones",
+            format!("{}/dns/zones", self.base_url),
             Some(json!({}))
         ).await?;
         let zones: std::result::Result<Vec<Zone>, _> = response
@@ -284,10 +452,69 @@ impl Client {
         Ok(zones?)
     }
 
-    async fn get_zone_internal<S: AsRef<str>>(&mut self, name: S, with_records: bool) -> Result<Zone> {
+    /// List all domains that were registered by this particular authenticated user.
+    ///
+    /// ```no_run
+    /// let client = openprovider::Client::default();
+    ///
+    /// for domain in client.list_domains().await? {
+    ///     eprintln!("{} expires on {:?}", domain.domain, domain.expiration_date);
+    /// }
+    /// ```
+    pub async fn list_domains(&self) -> Result<Vec<Domain>> {
+        let response = self.request(
+            Method::GET,
+            format!("{}/domains", self.base_url),
+            None
+        ).await?;
+        let domains: std::result::Result<Vec<Domain>, _> = response
+            .get_ok("results")?
+            .as_array_ok()?
+            .iter()
+            .map(|x| serde_json::from_value::<Domain>(x.clone()))
+            .collect();
+        Ok(domains?)
+    }
+
+    /// Get more information about a specific domain.
+    pub async fn get_domain(&self, id: u64) -> Result<Domain> {
+        let response = self.request(
+            Method::GET,
+            format!("{}/domains/{}", self.base_url, id),
+            None
+        ).await?;
+        Ok(serde_json::from_value::<Domain>(response)?)
+    }
+
+    /// Delegate a domain to the given name servers.
+    ///
+    /// The glue addresses of the name servers only have to be given if they are within the domain
+    /// itself.
+    pub async fn update_name_servers(&self, id: u64, name_servers: &[NameServer]) -> Result<()> {
+        self.request(
+            Method::PUT,
+            format!("{}/domains/{}", self.base_url, id),
+            Some(json!({
+                "id": id,
+                "name_servers": name_servers,
+            }))
+        ).await?;
+        Ok(())
+    }
+
+    /// Look up a registered domain by its name.
+    async fn find_domain(&self, name: &str) -> Result<Domain> {
+        Client::list_domains(self)
+            .await?
+            .into_iter()
+            .find(|domain| domain.domain.to_string().eq_ignore_ascii_case(name.trim_end_matches('.')))
+            .ok_or_else(|| Error::ZoneNotFound(name.to_string()))
+    }
+
+    async fn get_zone_internal<S: AsRef<str>>(&self, name: S, with_records: bool) -> Result<Zone> {
         let response = self.request(
             Method::GET,
-            format!("https://api.openprovider.eu/v1beta/dns/zones/{}?with_records={}", name.as_ref(), if with_records { "true" } else { "false" }),
+            format!("{}/dns/zones/{}?with_records={}", self.base_url, name.as_ref(), if with_records { "true" } else { "false" }),
             None
         ).await?;
         Ok(serde_json::from_value::<Zone>(response)?)
@@ -303,7 +530,7 @@ impl Client {
     /// eprintln!("Zone created on {}", info.creation_date);
     /// eprintln!("Zone modified on {}", info.modification_date);
     /// ```
-    pub async fn get_zone<S: AsRef<str>>(&mut self, name: S) -> Result<Zone> {
+    pub async fn get_zone<S: AsRef<str>>(&self, name: S) -> Result<Zone> {
         self.get_zone_internal(name, false).await
     }
 
@@ -322,7 +549,7 @@ impl Client {
     ///     }
     /// }
     /// ```
-    pub async fn list_records<S: AsRef<str>>(&mut self, name: S) -> Result<Vec<Record>> {
+    pub async fn list_records<S: AsRef<str>>(&self, name: S) -> Result<Vec<Record>> {
         let name_ref = name.as_ref();
         let records = self.get_zone_internal(name_ref, true)
             .await?
@@ -369,11 +596,11 @@ impl Client {
     ///
     /// client.set_record("example.com", record, new_record)
     /// ```
-    pub async fn set_record<S: AsRef<str>>(&mut self, name: S, orig_record: &Record, new_record: &Record) -> Result<()> {
+    pub async fn set_record<S: AsRef<str>>(&self, name: S, orig_record: &Record, new_record: &Record) -> Result<()> {
         let name_ref = name.as_ref();
         self.request(
             Method::PUT,
-            format!("https://api.openprovider.eu/v1beta/dns/zones/{}", name_ref),
+            format!("{}/dns/zones/{}", self.base_url, name_ref),
             Some(serde_json::json!({
                 "name": name_ref,
                 "records":
@@ -391,5 +618,201 @@ impl Client {
         Ok(())
     }
 
+    /// Add a new DNS record to the given zone.
+    ///
+    /// ```no_run
+    /// use openprovider::{Record, RecordType};
+    ///
+    /// client.create_record("example.com", &Record {
+    ///     creation_date: None,
+    ///     ip: None,
+    ///     modification_date: None,
+    ///     name: "wiki".to_string(),
+    ///     prio: None,
+    ///     ttl: 3600,
+    ///     ty: RecordType::A,
+    ///     value: "93.184.216.34".to_string(),
+    /// }).await?;
+    /// ```
+    pub async fn create_record<S: AsRef<str>>(&self, name: S, record: &Record) -> Result<()> {
+        let name_ref = name.as_ref();
+        self.request(
+            Method::PUT,
+            format!("{}/dns/zones/{}", self.base_url, name_ref),
+            Some(serde_json::json!({
+                "name": name_ref,
+                "records": {
+                    "add": [ record ]
+                }
+            }))
+        ).await?;
+        Ok(())
+    }
+
+    /// Remove a DNS record from the given zone.
+    ///
+    /// Like with [`set_record`](Self::set_record), the record is identified by all of its
+    /// attributes, so it is best taken from the result of [`list_records`](Self::list_records).
+    pub async fn delete_record<S: AsRef<str>>(&self, name: S, record: &Record) -> Result<()> {
+        let name_ref = name.as_ref();
+        self.request(
+            Method::PUT,
+            format!("{}/dns/zones/{}", self.base_url, name_ref),
+            Some(serde_json::json!({
+                "name": name_ref,
+                "records": {
+                    "remove": [ record ]
+                }
+            }))
+        ).await?;
+        Ok(())
+    }
+
+}
+
+impl From<RecordType> for DnsRecordType {
+    fn from(ty: RecordType) -> Self {
+        match ty {
+            RecordType::A => DnsRecordType::A,
+            RecordType::AAAA => DnsRecordType::AAAA,
+            RecordType::CAA => DnsRecordType::CAA,
+            RecordType::CNAME => DnsRecordType::CNAME,
+            RecordType::MX => DnsRecordType::MX,
+            RecordType::SPF => DnsRecordType::SPF,
+            RecordType::SRV => DnsRecordType::SRV,
+            RecordType::TXT => DnsRecordType::TXT,
+            RecordType::NS => DnsRecordType::NS,
+            RecordType::TLSA => DnsRecordType::TLSA,
+            RecordType::SSHFP => DnsRecordType::SSHFP,
+            RecordType::SOA => DnsRecordType::SOA,
+        }
+    }
+}
+
+impl TryFrom<DnsRecordType> for RecordType {
+
+    type Error = Error;
+
+    fn try_from(ty: DnsRecordType) -> Result<Self> {
+        Ok(match ty {
+            DnsRecordType::A => RecordType::A,
+            DnsRecordType::AAAA => RecordType::AAAA,
+            DnsRecordType::CAA => RecordType::CAA,
+            DnsRecordType::CNAME => RecordType::CNAME,
+            DnsRecordType::MX => RecordType::MX,
+            DnsRecordType::SPF => RecordType::SPF,
+            DnsRecordType::SRV => RecordType::SRV,
+            DnsRecordType::TXT => RecordType::TXT,
+            DnsRecordType::NS => RecordType::NS,
+            DnsRecordType::TLSA => RecordType::TLSA,
+            DnsRecordType::SSHFP => RecordType::SSHFP,
+            DnsRecordType::SOA => RecordType::SOA,
+            other => return Err(Error::UnsupportedRecordType(other)),
+        })
+    }
+
+}
+
+fn to_common(record: Record, zone: &str) -> DnsRecord {
+    DnsRecord {
+        name: if record.name == zone { String::new() } else { record.name },
+        ty: record.ty.into(),
+        content: record.value,
+        ttl: u32::try_from(record.ttl).ok(),
+        priority: record.prio.and_then(|prio| u32::try_from(prio).ok()),
+    }
+}
+
+fn from_common(record: &DnsRecord) -> Result<Record> {
+    Ok(Record {
+        creation_date: None,
+        ip: None,
+        modification_date: None,
+        name: record.name.clone(),
+        prio: record.priority.map(u64::from),
+        ttl: record.ttl.map(u64::from).unwrap_or(DEFAULT_TTL),
+        ty: RecordType::try_from(record.ty.clone())?,
+        value: record.content.clone(),
+    })
+}
+
+impl Client {
+
+    /// Find the record of a zone whose [content identifier](content_id) is the given one.
+    async fn find_record(&self, zone: &str, id: &str) -> Result<Record> {
+        Client::list_records(self, zone)
+            .await?
+            .into_iter()
+            .find(|record| content_id(&to_common(record.clone(), zone)) == id)
+            .ok_or_else(|| Error::RecordNotFound(id.to_string()))
+    }
+
 }
 
+#[async_trait]
+impl Registrar for Client {
+
+    fn supports_type(&self, ty: &DnsRecordType) -> bool {
+        RecordType::try_from(ty.clone()).is_ok()
+    }
+
+    async fn list_zones(&self) -> Result<Vec<String>> {
+        Ok(Client::list_zones(self)
+            .await?
+            .into_iter()
+            .filter(|zone| !zone.is_deleted)
+            .map(|zone| zone.name)
+            .collect())
+    }
+
+    fn record_identity(&self) -> RecordIdentity {
+        RecordIdentity::Content
+    }
+
+    async fn list_records_with_ids(&self, zone: &str) -> Result<Vec<(DnsRecordId, DnsRecord)>> {
+        Ok(Client::list_records(self, zone)
+            .await?
+            .into_iter()
+            .map(|record| {
+                let record = to_common(record, zone);
+                (content_id(&record), record)
+            })
+            .collect())
+    }
+
+    /// OpenProvider does not assign identifiers to records, so the returned identifier is derived
+    /// from the contents of the new record.
+    async fn create_record(&self, zone: &str, record: &DnsRecord) -> Result<DnsRecordId> {
+        Client::create_record(self, zone, &from_common(record)?).await?;
+        Ok(content_id(record))
+    }
+
+    async fn update_record(&self, zone: &str, id: &str, record: &DnsRecord) -> Result<DnsRecordId> {
+        let original = self.find_record(zone, id).await?;
+        Client::set_record(self, zone, &original, &from_common(record)?).await?;
+        Ok(content_id(record))
+    }
+
+    async fn delete_record(&self, zone: &str, id: &str) -> Result<()> {
+        let original = self.find_record(zone, id).await?;
+        Client::delete_record(self, zone, &original).await
+    }
+
+    async fn delegated_name_servers(&self, domain: &str) -> Result<Vec<String>> {
+        Ok(self.find_domain(domain)
+            .await?
+            .name_servers
+            .into_iter()
+            .map(|name_server| name_server.name.trim_end_matches('.').to_string())
+            .collect())
+    }
+
+    async fn set_name_servers(&self, domain: &str, name_servers: &[String]) -> Result<()> {
+        let domain = self.find_domain(domain).await?;
+        let name_servers: Vec<NameServer> = name_servers.iter()
+            .map(|name| NameServer { name: name.clone(), ip: None, ip6: None })
+            .collect();
+        self.update_name_servers(domain.id, &name_servers).await
+    }
+
+}
@@ -0,0 +1,371 @@
+//! An in-process fake of the OpenProvider API, meant for testing.
+//!
+//! The fake keeps all of its state in memory and answers with the same `{code, desc, data}`
+//! envelope as the real API. Only a subset of the API is available: logging in, listing and
+//! updating DNS zones and listing domains.
+//!
+//! ```no_run
+//! use openprovider::mock::MockServer;
+//!
+//! let server = MockServer::start().await;
+//! server.add_user("bob", "123456789");
+//! server.add_zone("example.com");
+//!
+//! let client = openprovider::Builder::new()
+//!     .base_url(server.base_url())
+//!     .build();
+//!
+//! let token = client.login("bob", "123456789").await?;
+//! client.set_token(token);
+//!
+//! assert_eq!(client.list_zones().await?.len(), 1);
+//! ```
+
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::sync::{Arc, Mutex};
+
+use registrar_common::mock::{MockHttpServer, MockRequest, MockResponse};
+use reqwest::{Method, StatusCode};
+use serde_json::{json, Value};
+
+use crate::{Domain, DomainName, NameServer, Record, RecordType, Zone, CODE_AUTH_FAILED, CODE_SUCCESS};
+
+/// The error code the fake returns when the requested object does not exist.
+pub const CODE_NOT_FOUND: u32 = 320;
+
+/// The error code the fake returns when the request could not be understood.
+pub const CODE_BAD_REQUEST: u32 = 10;
+
+const TIMESTAMP: &str = "2024-01-01 00:00:00";
+
+#[derive(Default)]
+struct State {
+    users: HashMap<String, String>,
+    tokens: HashSet<String>,
+    zones: BTreeMap<String, Zone>,
+    domains: Vec<Domain>,
+    unavailable: usize,
+    errors: VecDeque<(u32, String)>,
+    requests: usize,
+    next_id: u64,
+}
+
+impl State {
+
+    fn next_id(&mut self) -> u64 {
+        self.next_id += 1;
+        self.next_id
+    }
+
+}
+
+/// A fake OpenProvider API server running on a random port on the loopback interface.
+///
+/// The server stops when this object is dropped.
+pub struct MockServer {
+    server: MockHttpServer,
+    state: Arc<Mutex<State>>,
+}
+
+impl MockServer {
+
+    /// Start a new server in the background of the current Tokio runtime.
+    pub async fn start() -> Self {
+        let state = Arc::new(Mutex::new(State::default()));
+        let handler_state = state.clone();
+        let server = MockHttpServer::start(move |request| handle(&mut handler_state.lock().unwrap(), request)).await;
+        Self { server, state }
+    }
+
+    /// The URL that should be passed to [`Builder::base_url`](crate::Builder::base_url) to talk to
+    /// this server.
+    pub fn base_url(&self) -> String {
+        format!("{}/v1beta", self.server.url())
+    }
+
+    /// Allow logging in with the given username and password.
+    pub fn add_user<S1: Into<String>, S2: Into<String>>(&self, username: S1, password: S2) {
+        self.state.lock().unwrap().users.insert(username.into(), password.into());
+    }
+
+    /// Create a token that is accepted by the server without having to log in.
+    pub fn issue_token(&self) -> String {
+        let mut state = self.state.lock().unwrap();
+        let token = format!("token-{}", state.next_id());
+        state.tokens.insert(token.clone());
+        token
+    }
+
+    /// Make all tokens that were handed out so far invalid, as if they had expired.
+    pub fn expire_tokens(&self) {
+        self.state.lock().unwrap().tokens.clear();
+    }
+
+    /// Create an empty DNS zone and a matching domain.
+    pub fn add_zone<S: Into<String>>(&self, name: S) {
+        let name = name.into();
+        let mut state = self.state.lock().unwrap();
+        let id = state.next_id();
+        let (label, extension) = name.split_once('.').unwrap_or((&name, ""));
+        state.domains.push(Domain {
+            id,
+            domain: DomainName {
+                name: label.to_string(),
+                extension: extension.to_string(),
+            },
+            status: "ACT".to_string(),
+            expiration_date: Some(TIMESTAMP.to_string()),
+            name_servers: Vec::new(),
+        });
+        state.zones.insert(name.clone(), Zone {
+            active: true,
+            creation_date: TIMESTAMP.to_string(),
+            dnskey: None,
+            id,
+            ip: "0.0.0.0".to_string(),
+            is_deleted: false,
+            is_shadow: false,
+            is_spamexperts_enabled: false,
+            modification_date: TIMESTAMP.to_string(),
+            name,
+            premium_dns: None,
+            provider: "openprovider".to_string(),
+            records: Some(Vec::new()),
+            reseller_id: 1,
+            ty: "master".to_string(),
+        });
+    }
+
+    /// Add a record to a zone that was created with [`add_zone`](Self::add_zone).
+    ///
+    /// The name of the record is relative to the zone, with an empty name referring to the zone
+    /// apex.
+    pub fn add_record<S: AsRef<str>>(&self, zone: S, name: &str, ty: RecordType, value: &str) {
+        let zone = zone.as_ref();
+        let mut state = self.state.lock().unwrap();
+        let zone_data = state.zones.get_mut(zone).expect("zone does not exist on mock server");
+        zone_data.records.get_or_insert_with(Vec::new).push(Record {
+            creation_date: Some(TIMESTAMP.to_string()),
+            ip: None,
+            modification_date: Some(TIMESTAMP.to_string()),
+            name: qualify(name, zone),
+            prio: None,
+            ttl: 3600,
+            ty,
+            value: value.to_string(),
+        });
+    }
+
+    /// Get the records of the given zone as they are currently stored on the server.
+    ///
+    /// The names of the records are fully qualified, just like the real API returns them.
+    pub fn records<S: AsRef<str>>(&self, zone: S) -> Vec<Record> {
+        self.state.lock().unwrap()
+            .zones
+            .get(zone.as_ref())
+            .and_then(|zone| zone.records.clone())
+            .unwrap_or_default()
+    }
+
+    /// Get the name servers the given domain is currently delegated to.
+    pub fn name_servers<S: AsRef<str>>(&self, domain: S) -> Vec<String> {
+        self.state.lock().unwrap()
+            .domains
+            .iter()
+            .find(|other| other.domain.to_string() == domain.as_ref())
+            .map(|domain| domain.name_servers.iter().map(|ns| ns.name.clone()).collect())
+            .unwrap_or_default()
+    }
+
+    /// Answer the next `count` requests with 503 Service Unavailable and a body that is not JSON,
+    /// like a proxy in front of an overloaded server would.
+    pub fn fail_unavailable(&self, count: usize) {
+        self.state.lock().unwrap().unavailable = count;
+    }
+
+    /// Make the next request fail with the given error code and description.
+    ///
+    /// Calling this multiple times queues up errors for the requests that follow.
+    pub fn push_error<S: Into<String>>(&self, code: u32, desc: S) {
+        self.state.lock().unwrap().errors.push_back((code, desc.into()));
+    }
+
+    /// The amount of requests the server has received so far.
+    pub fn request_count(&self) -> usize {
+        self.state.lock().unwrap().requests
+    }
+
+}
+
+fn qualify(name: &str, zone: &str) -> String {
+    if name.is_empty() || name == zone {
+        zone.to_string()
+    } else if name.ends_with(&format!(".{}", zone)) {
+        name.to_string()
+    } else {
+        format!("{}.{}", name, zone)
+    }
+}
+
+fn same_record(a: &Record, b: &Record, zone: &str) -> bool {
+    qualify(&a.name, zone) == qualify(&b.name, zone) && a.ty == b.ty && a.value == b.value
+}
+
+fn success(data: Value) -> MockResponse {
+    MockResponse::json(StatusCode::OK, json!({ "code": CODE_SUCCESS, "desc": "", "data": data }))
+}
+
+fn failure<S: Into<String>>(code: u32, desc: S) -> MockResponse {
+    let status = match code {
+        CODE_AUTH_FAILED => StatusCode::UNAUTHORIZED,
+        CODE_NOT_FOUND => StatusCode::NOT_FOUND,
+        _ => StatusCode::INTERNAL_SERVER_ERROR,
+    };
+    MockResponse::json(status, json!({ "code": code, "desc": desc.into() }))
+}
+
+fn handle(state: &mut State, request: MockRequest) -> MockResponse {
+
+    state.requests += 1;
+
+    if state.unavailable > 0 {
+        state.unavailable -= 1;
+        return MockResponse::text(StatusCode::SERVICE_UNAVAILABLE, "Service Unavailable");
+    }
+
+    if let Some((code, desc)) = state.errors.pop_front() {
+        return failure(code, desc);
+    }
+
+    let method = &request.method;
+    let body = request.json();
+    let path = match request.path.strip_prefix("/v1beta") {
+        Some(path) => path.trim_end_matches('/'),
+        None => return failure(CODE_NOT_FOUND, "Unknown API version"),
+    };
+
+    if *method == Method::POST && path == "/auth/login" {
+        let username = body.get("username").and_then(Value::as_str).unwrap_or("");
+        let password = body.get("password").and_then(Value::as_str).unwrap_or("");
+        if state.users.get(username).map(String::as_str) != Some(password) {
+            return failure(CODE_AUTH_FAILED, "Authentication/Authorization Failed");
+        }
+        let token = format!("token-{}", state.next_id());
+        state.tokens.insert(token.clone());
+        return success(json!({ "token": token, "reseller_id": 1 }));
+    }
+
+    if !request.bearer().is_some_and(|token| state.tokens.contains(token)) {
+        return failure(CODE_AUTH_FAILED, "Authentication/Authorization Failed");
+    }
+
+    let segments: Vec<&str> = path.split('/').skip(1).collect();
+
+    match (method.clone(), segments.as_slice()) {
+        (Method::GET, ["dns", "zones"]) => {
+            let zones: Vec<Value> = state.zones.values()
+                .map(|zone| {
+                    let mut zone = zone.clone();
+                    zone.records = None;
+                    serde_json::to_value(zone).unwrap()
+                })
+                .collect();
+            success(json!({ "results": zones, "total": zones.len() }))
+        },
+        (Method::GET, ["dns", "zones", name]) => match state.zones.get(*name) {
+            None => failure(CODE_NOT_FOUND, "Zone not found"),
+            Some(zone) => {
+                let mut zone = zone.clone();
+                if request.query_param("with_records") != Some("true") {
+                    zone.records = None;
+                }
+                success(serde_json::to_value(zone).unwrap())
+            },
+        },
+        (Method::PUT, ["dns", "zones", name]) => {
+            let name = name.to_string();
+            let Some(zone) = state.zones.get_mut(&name) else {
+                return failure(CODE_NOT_FOUND, "Zone not found");
+            };
+            let records = zone.records.get_or_insert_with(Vec::new);
+            let changes = body.get("records").cloned().unwrap_or(Value::Null);
+            let parse = |key: &str| -> std::result::Result<Vec<Value>, MockResponse> {
+                match changes.get(key) {
+                    None => Ok(Vec::new()),
+                    Some(Value::Array(values)) => Ok(values.clone()),
+                    Some(_) => Err(failure(CODE_BAD_REQUEST, format!("Field records.{} must be an array", key))),
+                }
+            };
+            let (remove, update, add) = match (parse("remove"), parse("update"), parse("add")) {
+                (Ok(remove), Ok(update), Ok(add)) => (remove, update, add),
+                (Err(error), _, _) | (_, Err(error), _) | (_, _, Err(error)) => return error,
+            };
+            for value in remove {
+                let Ok(record) = serde_json::from_value::<Record>(value) else {
+                    return failure(CODE_BAD_REQUEST, "Invalid record");
+                };
+                let count = records.len();
+                records.retain(|other| !same_record(other, &record, &name));
+                if records.len() == count {
+                    return failure(CODE_NOT_FOUND, "Record not found");
+                }
+            }
+            for value in update {
+                let (Some(original), Some(new)) = (
+                    value.get("original_record").and_then(|v| serde_json::from_value::<Record>(v.clone()).ok()),
+                    value.get("record").and_then(|v| serde_json::from_value::<Record>(v.clone()).ok()),
+                ) else {
+                    return failure(CODE_BAD_REQUEST, "Invalid record update");
+                };
+                let Some(record) = records.iter_mut().find(|other| same_record(other, &original, &name)) else {
+                    return failure(CODE_NOT_FOUND, "Record not found");
+                };
+                *record = Record {
+                    creation_date: record.creation_date.clone(),
+                    modification_date: Some(TIMESTAMP.to_string()),
+                    name: qualify(&new.name, &name),
+                    ..new
+                };
+            }
+            for value in add {
+                let Ok(record) = serde_json::from_value::<Record>(value) else {
+                    return failure(CODE_BAD_REQUEST, "Invalid record");
+                };
+                records.push(Record {
+                    creation_date: Some(TIMESTAMP.to_string()),
+                    modification_date: Some(TIMESTAMP.to_string()),
+                    name: qualify(&record.name, &name),
+                    ..record
+                });
+            }
+            success(json!({ "success": true }))
+        },
+        (Method::GET, ["domains"]) => {
+            let domains = serde_json::to_value(&state.domains).unwrap();
+            success(json!({ "results": domains, "total": state.domains.len() }))
+        },
+        (Method::GET, ["domains", id]) => {
+            let domain = id.parse::<u64>().ok()
+                .and_then(|id| state.domains.iter().find(|domain| domain.id == id));
+            match domain {
+                None => failure(CODE_NOT_FOUND, "Domain not found"),
+                Some(domain) => success(serde_json::to_value(domain).unwrap()),
+            }
+        },
+        (Method::PUT, ["domains", id]) => {
+            let domain = id.parse::<u64>().ok()
+                .and_then(|id| state.domains.iter_mut().find(|domain| domain.id == id));
+            let Some(domain) = domain else {
+                return failure(CODE_NOT_FOUND, "Domain not found");
+            };
+            if let Some(name_servers) = body.get("name_servers") {
+                match serde_json::from_value::<Vec<NameServer>>(name_servers.clone()) {
+                    Ok(name_servers) if !name_servers.is_empty() => domain.name_servers = name_servers,
+                    _ => return failure(CODE_BAD_REQUEST, "Invalid name servers"),
+                }
+            }
+            success(json!({ "status": domain.status }))
+        },
+        _ => failure(CODE_NOT_FOUND, format!("No route for {} {}", method, path)),
+    }
+}
@@ -0,0 +1,44 @@
+
+use std::sync::Arc;
+
+use openprovider::mock::MockServer;
+use openprovider::{Builder, RecordType};
+use registrar_common::Cassette;
+
+#[tokio::test]
+async fn recorded_requests_replay_offline() {
+    let path = std::env::temp_dir().join(format!("openprovider-cassette-{}.json", std::process::id()));
+
+    let server = MockServer::start().await;
+    server.add_user("bob", "123456789");
+    server.add_zone("example.com");
+    server.add_record("example.com", "wiki", RecordType::A, "93.184.216.34");
+
+    let cassette = Arc::new(Cassette::record(&path));
+    let client = Builder::new()
+        .base_url(server.base_url())
+        .cassette(cassette.clone())
+        .build();
+    let token = client.login("bob", "123456789").await.unwrap();
+    client.set_token(token);
+    let recorded = client.list_records("example.com").await.unwrap();
+    cassette.save().unwrap();
+    drop(server);
+
+    let contents = std::fs::read_to_string(&path).unwrap();
+    assert!(!contents.contains("123456789"));
+    assert!(!contents.contains("token-"));
+
+    let client = Builder::new()
+        .base_url("http://127.0.0.1:9/v1beta")
+        .cassette(Arc::new(Cassette::replay(&path).unwrap()))
+        .build();
+    let token = client.login("bob", "another password").await.unwrap();
+    client.set_token(token);
+    let replayed = client.list_records("example.com").await.unwrap();
+    assert_eq!(replayed.len(), recorded.len());
+    assert_eq!(replayed[0].value, "93.184.216.34");
+    assert!(client.list_zones().await.is_err());
+
+    std::fs::remove_file(path).unwrap();
+}
@@ -0,0 +1,224 @@
+
+use std::time::Duration;
+
+use openprovider::mock::MockServer;
+use openprovider::{Builder, Client, Record, RecordType};
+use registrar_common::{DnsRecord, DnsRecordType, Error, HttpErrorKind, Registrar};
+
+async fn setup() -> (MockServer, Client) {
+    let server = MockServer::start().await;
+    server.add_user("bob", "123456789");
+    server.add_zone("example.com");
+    server.add_record("example.com", "", RecordType::A, "93.184.216.34");
+    server.add_record("example.com", "wiki", RecordType::A, "93.184.216.35");
+    let client = Builder::new()
+        .base_url(server.base_url())
+        .build();
+    client.set_token(server.issue_token());
+    (server, client)
+}
+
+#[tokio::test]
+async fn login_returns_a_usable_token() {
+    let (server, _) = setup().await;
+    let client = Builder::new().base_url(server.base_url()).build();
+    let token = client.login("bob", "123456789").await.unwrap();
+    client.set_token(token);
+    assert_eq!(client.list_zones().await.unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn login_with_wrong_password_fails() {
+    let (server, _) = setup().await;
+    let client = Builder::new().base_url(server.base_url()).build();
+    let result = client.login("bob", "wrong").await;
+    assert!(matches!(result, Err(Error::AuthenticationFailed)));
+}
+
+#[tokio::test]
+async fn requests_without_token_fail() {
+    let (server, _) = setup().await;
+    let client = Builder::new().base_url(server.base_url()).build();
+    assert!(matches!(client.list_zones().await, Err(Error::AuthenticationFailed)));
+}
+
+#[tokio::test]
+async fn expired_tokens_are_rejected() {
+    let (server, client) = setup().await;
+    server.expire_tokens();
+    assert!(matches!(client.list_zones().await, Err(Error::AuthenticationFailed)));
+}
+
+#[tokio::test]
+async fn injected_errors_are_reported() {
+    let (server, client) = setup().await;
+    server.push_error(196, "Authentication/Authorization Failed");
+    server.push_error(399, "Something went wrong");
+    assert!(matches!(client.list_zones().await, Err(Error::AuthenticationFailed)));
+    assert!(matches!(client.list_zones().await, Err(Error::Api { code: Some(399), .. })));
+    assert!(client.list_zones().await.is_ok());
+}
+
+#[tokio::test]
+async fn unavailable_servers_are_retried() {
+    let (server, _) = setup().await;
+    let client = Builder::new()
+        .base_url(server.base_url())
+        .max_retries(2)
+        .build();
+    client.set_token(server.issue_token());
+
+    server.fail_unavailable(2);
+    assert_eq!(client.list_zones().await.unwrap().len(), 1);
+    assert_eq!(server.request_count(), 3);
+
+    server.fail_unavailable(3);
+    assert!(matches!(client.list_zones().await, Err(Error::StatusCode(503))));
+    assert_eq!(server.request_count(), 6);
+}
+
+#[tokio::test]
+async fn get_zone_returns_metadata() {
+    let (_server, client) = setup().await;
+    let zone = client.get_zone("example.com").await.unwrap();
+    assert_eq!(zone.name, "example.com");
+    assert!(zone.records.is_none());
+    assert!(matches!(client.get_zone("example.org").await, Err(Error::Api { .. })));
+}
+
+#[tokio::test]
+async fn list_records_strips_zone_name() {
+    let (_server, client) = setup().await;
+    let mut names: Vec<String> = client.list_records("example.com").await.unwrap()
+        .into_iter()
+        .map(|record| record.name)
+        .collect();
+    names.sort();
+    assert_eq!(names, vec!["example.com", "wiki"]);
+}
+
+#[tokio::test]
+async fn set_record_updates_value() {
+    let (server, client) = setup().await;
+    let record = client.list_records("example.com").await.unwrap()
+        .into_iter()
+        .find(|record| record.name == "wiki")
+        .unwrap();
+    let mut new_record = record.clone();
+    new_record.value = "10.0.0.1".to_string();
+    client.set_record("example.com", &record, &new_record).await.unwrap();
+    let stored = server.records("example.com");
+    assert!(stored.iter().any(|r| r.name == "wiki.example.com" && r.value == "10.0.0.1"));
+    assert!(!stored.iter().any(|r| r.value == "93.184.216.35"));
+}
+
+#[tokio::test]
+async fn create_record_adds_record() {
+    let (server, client) = setup().await;
+    client.create_record("example.com", &Record {
+        creation_date: None,
+        ip: None,
+        modification_date: None,
+        name: "mail".to_string(),
+        prio: Some(10),
+        ttl: 900,
+        ty: RecordType::MX,
+        value: "mx.example.com".to_string(),
+    }).await.unwrap();
+    let stored = server.records("example.com");
+    assert!(stored.iter().any(|r| r.name == "mail.example.com" && r.ty == RecordType::MX && r.prio == Some(10)));
+}
+
+#[test]
+fn record_types_are_parsed_ignoring_case() {
+    assert_eq!("aaaa".parse::<RecordType>().unwrap(), RecordType::AAAA);
+    assert_eq!("MX".parse::<RecordType>().unwrap(), RecordType::MX);
+    assert!(matches!("ALIAS".parse::<RecordType>(), Err(Error::Generic(_))));
+}
+
+#[tokio::test]
+async fn list_domains_returns_registered_domains() {
+    let (_server, client) = setup().await;
+    let domains = client.list_domains().await.unwrap();
+    assert_eq!(domains.len(), 1);
+    assert_eq!(domains[0].domain.to_string(), "example.com");
+    let domain = client.get_domain(domains[0].id).await.unwrap();
+    assert_eq!(domain.status, "ACT");
+}
+
+#[tokio::test]
+async fn name_servers_can_be_changed() {
+    let (server, client) = setup().await;
+    let name_servers = vec!["ns1.openprovider.nl".to_string(), "ns2.openprovider.be".to_string()];
+    Registrar::set_name_servers(&client, "example.com", &name_servers).await.unwrap();
+    assert_eq!(server.name_servers("example.com"), name_servers);
+    assert_eq!(Registrar::delegated_name_servers(&client, "example.com.").await.unwrap(), name_servers);
+    let result = Registrar::set_name_servers(&client, "example.org", &name_servers).await;
+    assert!(matches!(result, Err(Error::ZoneNotFound(_))));
+    let result = Registrar::delegated_name_servers(&client, "example.org").await;
+    assert!(matches!(result, Err(Error::ZoneNotFound(_))));
+}
+
+#[tokio::test]
+async fn registrar_adapter_uses_relative_names() {
+    let (server, client) = setup().await;
+    let registrar: &dyn Registrar = &client;
+    assert_eq!(registrar.list_zones().await.unwrap(), vec!["example.com"]);
+    let records = registrar.list_records("example.com").await.unwrap();
+    assert!(records.iter().any(|r| r.name.is_empty() && r.content == "93.184.216.34"));
+    registrar.create_record("example.com", &DnsRecord {
+        name: "_verify".to_string(),
+        ty: DnsRecordType::TXT,
+        content: "token=123".to_string(),
+        ttl: None,
+        priority: None,
+    }).await.unwrap();
+    assert!(server.records("example.com").iter().any(|r| r.name == "_verify.example.com" && r.ttl == 3600));
+    let result = registrar.create_record("example.com", &DnsRecord {
+        name: "".to_string(),
+        ty: DnsRecordType::ALIAS,
+        content: "example.net".to_string(),
+        ttl: None,
+        priority: None,
+    }).await;
+    assert!(matches!(result, Err(Error::UnsupportedRecordType(DnsRecordType::ALIAS))));
+}
+
+#[tokio::test]
+async fn registrar_adapter_addresses_records_by_content() {
+    let (server, client) = setup().await;
+    let registrar: &dyn Registrar = &client;
+    let records = registrar.list_records_with_ids("example.com").await.unwrap();
+    let (id, mut record) = records.into_iter().find(|(_, record)| record.name == "wiki").unwrap();
+
+    record.content = "93.184.216.36".to_string();
+    let new_id = registrar.update_record("example.com", &id, &record).await.unwrap();
+    assert_ne!(new_id, id);
+    assert!(server.records("example.com").iter().any(|r| r.name == "wiki.example.com" && r.value == "93.184.216.36"));
+    assert!(matches!(registrar.update_record("example.com", &id, &record).await, Err(Error::RecordNotFound(_))));
+
+    registrar.delete_record("example.com", &new_id).await.unwrap();
+    assert_eq!(server.records("example.com").len(), 1);
+}
+
+#[tokio::test]
+async fn hanging_server_times_out() {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap();
+    let _server = tokio::spawn(async move {
+        let mut connections = Vec::new();
+        loop {
+            let (stream, _) = listener.accept().await.unwrap();
+            connections.push(stream);
+        }
+    });
+    let client = Builder::new()
+        .base_url(format!("http://{}/v1beta", address))
+        .timeout(Duration::from_secs(30))
+        .build();
+    let result = client
+        .with_timeout(Duration::from_millis(100))
+        .list_zones()
+        .await;
+    assert!(matches!(result, Err(Error::Http { kind: HttpErrorKind::Timeout, .. })));
+}
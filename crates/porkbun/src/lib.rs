@@ -1,80 +1,74 @@
 
-mod json;
+#[cfg(feature = "mock")]
+pub mod mock;
 
-use json::ValueExt;
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use registrar_common::{retry, Cassette, DEFAULT_MAX_RETRIES, DnsRecordId, Error, Registrar, Result, Timeouts, ValueExt};
 use reqwest::Method;
 use serde::{Deserialize, Serialize};
-use serde_json::{error::Category, Value};
+use serde_json::{json, Value};
 
 const BASE_URL: &str = "https://api.porkbun.com/api/json/v3";
 
-#[derive(Debug)]
-pub enum Error {
-    AuthenticationFailed,
-    /// This is due to an incompatibility with the underlying HTTP library and will be fixed in the
-    /// future.
-    Io(std::io::Error),
-    /// Data sent or received from OpenProvider did not meet the schema of this library.
-    Json(String),
-    /// Any other error stored as a human-readable error message.
-    Other(String),
-}
-
-
-impl std::fmt::Display for Error {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        match self {
-            Self::AuthenticationFailed => write!(f, "OpenProvider did not accept the current authentication"),
-            Self::Io(error) => write!(f, "input/output error: {}", error),
-            Self::Json(message) => f.write_str(message),
-            Self::Other(message) => f.write_str(message),
-        }
-    }
-}
-
-impl std::error::Error for Error {}
-
-impl From<serde_json::Error> for Error {
-    fn from(error: serde_json::Error) -> Self {
-        match error.classify() {
-            Category::Io => Error::Io(error.into()),
-            Category::Eof | Category::Data | Category::Syntax => Error::Json(error.to_string()),
-        }
-    }
-}
-
-impl From<json::Error> for Error {
-    fn from(error: json::Error) -> Self {
-        Error::Json(error.to_string())
-    }
-}
-
-impl From<reqwest::Error> for Error {
-    fn from(error: reqwest::Error) -> Self {
-        Error::Other(error.to_string())
-    }
-}
-
-pub type Result<T> = std::result::Result<T, Error>;
+/// The amount of domains Porkbun returns at most for each call to `domain/listAll`.
+const DOMAINS_PAGE_SIZE: usize = 1000;
 
+#[derive(Clone)]
 pub struct Client {
     client: reqwest::Client,
+    base_url: String,
     keys: Option<(String, String)>,
+    max_retries: u32,
+    request_timeout: Option<Duration>,
+    cassette: Option<Arc<Cassette>>,
 }
 
 pub struct Builder {
     max_retries: u32,
+    base_url: String,
     api_key: Option<String>,
     secret_api_key: Option<String>,
+    timeouts: Timeouts,
+    cassette: Option<Arc<Cassette>>,
+}
+
+impl Default for Builder {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Builder {
 
+    pub fn new() -> Self {
+        Self {
+            max_retries: DEFAULT_MAX_RETRIES,
+            base_url: BASE_URL.to_string(),
+            api_key: None,
+            secret_api_key: None,
+            timeouts: Timeouts::default(),
+            cassette: None,
+        }
+    }
+
+    /// Retry a request at most this many times if it failed in a way that is
+    /// [transient](Error::is_transient).
     pub fn max_retries(mut self, retries: u32) -> Self {
         self.max_retries = retries;
         self
     }
 
+    /// Send requests to another server than the official Porkbun API.
+    ///
+    /// The URL should include the version of the API, e.g. `http://localhost:8080/api/json/v3`.
+    pub fn base_url<S: Into<String>>(mut self, base_url: S) -> Self {
+        self.base_url = base_url.into().trim_end_matches('/').to_string();
+        self
+    }
+
     pub fn api_key<S: Into<String>>(mut self, key: S) -> Self {
         self.api_key = Some(key.into());
         self
@@ -85,80 +79,403 @@ impl Builder {
         self
     }
 
+    /// Give up on a request if no connection could be made within the given duration.
+    pub fn connect_timeout(mut self, timeout: Duration) -> Self {
+        self.timeouts.connect = Some(timeout);
+        self
+    }
+
+    /// Give up on a request if the server does not send any data within the given duration.
+    pub fn read_timeout(mut self, timeout: Duration) -> Self {
+        self.timeouts.read = Some(timeout);
+        self
+    }
+
+    /// Give up on a request if it did not complete within the given duration.
+    ///
+    /// Use [`Client::with_timeout`] to override this deadline for specific requests.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeouts.total = Some(timeout);
+        self
+    }
+
+    /// Record all requests to the given cassette or play them back from it.
+    ///
+    /// See [`Cassette`] for more information.
+    pub fn cassette(mut self, cassette: Arc<Cassette>) -> Self {
+        self.cassette = Some(cassette);
+        self
+    }
+
     pub fn build(self) -> Client {
+        let mut builder = reqwest::Client::builder();
+        if let Some(timeout) = self.timeouts.connect {
+            builder = builder.connect_timeout(timeout);
+        }
+        if let Some(timeout) = self.timeouts.read {
+            builder = builder.read_timeout(timeout);
+        }
+        if let Some(timeout) = self.timeouts.total {
+            builder = builder.timeout(timeout);
+        }
         Client {
-            client: reqwest::Client::new(),
+            client: builder.build().expect("failed to initialize the HTTP client"),
+            base_url: self.base_url,
+            max_retries: self.max_retries,
+            request_timeout: None,
+            cassette: self.cassette,
             keys: Some((self.api_key.expect("API key must be set"), self.secret_api_key.expect("secret API key must be set"))),
         }
     }
 
 }
 
-#[derive(Serialize, Deserialize)]
-enum DnsRecordType {
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DnsRecordType {
     A, MX, CNAME, ALIAS, TXT, NS, AAAA, SRV, TLSA, CAA, HTTPS, SVCB
 }
 
-#[derive(Serialize, Deserialize)]
-struct DnsRecord {
-    #[serde(rename = "secretapikey")]
-    secret_api_key: String,
-    #[serde(rename = "apikey")]
-    api_key: String,
-    name: String,
+/// The attributes of a DNS record that is about to be created or edited.
+///
+/// The name is the subdomain of the record, with an empty name referring to the domain itself.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct DnsRecord {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub ty: DnsRecordType,
+    pub content: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ttl: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub prio: Option<u32>,
+}
+
+/// A DNS record as it is returned by Porkbun.
+///
+/// The name is fully qualified. Porkbun encodes the TTL and priority as strings.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Record {
+    pub id: String,
+    pub name: String,
     #[serde(rename = "type")]
-    ty: DnsRecordType,
-    content: String,
-    ttl: Option<u32>,
-    prio: Option<u32>,
+    pub ty: DnsRecordType,
+    pub content: String,
+    pub ttl: String,
+    pub prio: Option<String>,
+    pub notes: Option<String>,
+}
+
+/// A domain that was registered through Porkbun.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Domain {
+    pub domain: String,
+    pub status: String,
+    pub tld: String,
+    pub create_date: Option<String>,
+    pub expire_date: Option<String>,
 }
 
-#[derive(Serialize, Deserialize)]
-#[serde(tag = "status")]
-enum CreateDnsRecordResponse {
-    #[serde(rename = "ERROR")]
-    Error {
-        message: String,
-    },
-    #[serde(rename = "SUCCESS")]
-    Success {
-        id: String,
+/// Porkbun returns identifiers as numbers in some places and as strings in others.
+fn id_to_string(value: &Value) -> Result<String> {
+    match value {
+        Value::Number(number) => Ok(number.to_string()),
+        _ => Ok(value.as_str_ok()?.to_owned()),
     }
 }
 
 impl Client {
 
-    async fn request<U: AsRef<str>>(&mut self, method: Method, url: U, mut body: Value) -> Result<Value> {
-        let url = format!("{}{}", BASE_URL, url.as_ref());
+    /// Get a copy of this client whose requests must complete within the given duration.
+    ///
+    /// This overrides the deadline set with [`Builder::timeout`].
+    pub fn with_timeout(&self, timeout: Duration) -> Self {
+        let mut client = self.clone();
+        client.request_timeout = Some(timeout);
+        client
+    }
+
+    async fn request<U: AsRef<str>>(&self, method: Method, url: U, body: Value) -> Result<Value> {
+        let path = url.as_ref();
+        let response = match &self.cassette {
+            Some(cassette) if cassette.is_replaying() => {
+                log::info!("Replaying request to {}", path);
+                cassette.find(method.as_str(), path, Some(&body))?
+            },
+            _ => {
+                let response = retry(self.max_retries, || self.send(method.clone(), path, body.clone())).await?;
+                if let Some(cassette) = &self.cassette {
+                    cassette.push(method.as_str(), path, Some(&body), &response);
+                }
+                response
+            },
+        };
+        if response.get_ok("status")?.as_str_ok()? != "SUCCESS" {
+            let message = response.get_ok("message")?.as_str_ok()?.to_owned();
+            if message.starts_with("Invalid API key") {
+                return Err(Error::AuthenticationFailed);
+            }
+            Err(Error::Api {
+                code: None,
+                message,
+            })
+        } else {
+            Ok(response)
+        }
+    }
+
+    async fn send(&self, method: Method, path: &str, mut body: Value) -> Result<Value> {
+        let url = format!("{}{}", self.base_url, path);
         log::info!("Starting request to {}", url);
         let mut builder = self.client
-            .request(method.clone(), url)
+            .request(method, url)
             .header("Accept", "*/*");
+        if let Some(timeout) = self.request_timeout {
+            builder = builder.timeout(timeout);
+        }
         let obj = body.as_object_mut().unwrap();
         if let Some((key, secret_key)) = &self.keys {
             obj.insert("apikey".to_owned(), key.clone().into());
             obj.insert("secretapikey".to_owned(), secret_key.clone().into());
         }
         builder = builder.json(&body);
-        let response: Value = builder
-            .send().await?
-            .json().await?;
-        if response.get_ok("status")?.as_str_ok()? != "SUCCESS" {
-            Err(Error::Other(response.get_ok("message")?.as_str_ok()?.to_owned()))
-        } else {
-            Ok(response)
+        let response = builder.send().await?;
+        // Errors of the API itself come with a JSON body, but those of proxies in front of it do not.
+        let error = Error::StatusCode(response.status().as_u16());
+        if error.is_transient() {
+            return Err(error);
+        }
+        Ok(response.json().await?)
+    }
+
+    /// Check whether the API keys are accepted.
+    ///
+    /// Returns the public IP address the request originated from.
+    pub async fn ping(&self) -> Result<String> {
+        Ok(self.request(
+            Method::POST,
+            "/ping",
+            json!({})
+        ).await?.get_ok("yourIp")?.as_str_ok()?.to_owned())
+    }
+
+    /// List all domains in the account.
+    pub async fn list_domains(&self) -> Result<Vec<Domain>> {
+        let mut domains = Vec::new();
+        loop {
+            let response = self.request(
+                Method::POST,
+                "/domain/listAll",
+                json!({ "start": domains.len().to_string() })
+            ).await?;
+            let page = response
+                .get_ok("domains")?
+                .as_array_ok()?
+                .iter()
+                .map(|x| serde_json::from_value::<Domain>(x.clone()))
+                .collect::<std::result::Result<Vec<_>, _>>()?;
+            let count = page.len();
+            domains.extend(page);
+            if count < DOMAINS_PAGE_SIZE {
+                break;
+            }
         }
+        Ok(domains)
+    }
+
+    /// Get the name servers a domain is delegated to.
+    pub async fn get_name_servers<S: AsRef<str>>(&self, domain: S) -> Result<Vec<String>> {
+        let response = self.request(
+            Method::POST,
+            format!("/domain/getNs/{}", domain.as_ref()),
+            json!({})
+        ).await?;
+        Ok(serde_json::from_value(response.get_ok("ns")?.clone())?)
+    }
+
+    /// Delegate a domain to the given name servers.
+    pub async fn update_name_servers<S: AsRef<str>>(&self, domain: S, name_servers: &[String]) -> Result<()> {
+        self.request(
+            Method::POST,
+            format!("/domain/updateNs/{}", domain.as_ref()),
+            json!({ "ns": name_servers })
+        ).await?;
+        Ok(())
+    }
+
+    /// List all DNS records of a domain.
+    pub async fn list_dns_records<S: AsRef<str>>(&self, domain: S) -> Result<Vec<Record>> {
+        let response = self.request(
+            Method::POST,
+            format!("/dns/retrieve/{}", domain.as_ref()),
+            json!({})
+        ).await?;
+        let records: std::result::Result<Vec<Record>, _> = response
+            .get_ok("records")?
+            .as_array_ok()?
+            .iter()
+            .map(|x| serde_json::from_value::<Record>(x.clone()))
+            .collect();
+        Ok(records?)
     }
 
     /// Create a DNS record.
     ///
     /// Returns the ID of the newly created record.
-    pub async fn create_dns_record(&mut self, record: &DnsRecord) -> Result<String> {
-        Ok(self.request(
+    pub async fn create_dns_record<S: AsRef<str>>(&self, domain: S, record: &DnsRecord) -> Result<String> {
+        let response = self.request(
+            Method::POST,
+            format!("/dns/create/{}", domain.as_ref()),
+            serde_json::to_value(record)?
+        ).await?;
+        id_to_string(response.get_ok("id")?)
+    }
+
+    /// Replace the attributes of the DNS record with the given ID.
+    pub async fn edit_dns_record<S1: AsRef<str>, S2: AsRef<str>>(&self, domain: S1, id: S2, record: &DnsRecord) -> Result<()> {
+        self.request(
             Method::POST,
-            "/dns/edit/{name}/{id}",
+            format!("/dns/edit/{}/{}", domain.as_ref(), id.as_ref()),
             serde_json::to_value(record)?
-        ).await?.get_ok("id")?.as_str_ok()?.to_owned())
+        ).await?;
+        Ok(())
+    }
+
+    /// Delete the DNS record with the given ID.
+    pub async fn delete_dns_record<S1: AsRef<str>, S2: AsRef<str>>(&self, domain: S1, id: S2) -> Result<()> {
+        self.request(
+            Method::POST,
+            format!("/dns/delete/{}/{}", domain.as_ref(), id.as_ref()),
+            json!({})
+        ).await?;
+        Ok(())
+    }
+
+}
+
+impl From<DnsRecordType> for registrar_common::DnsRecordType {
+    fn from(ty: DnsRecordType) -> Self {
+        match ty {
+            DnsRecordType::A => registrar_common::DnsRecordType::A,
+            DnsRecordType::MX => registrar_common::DnsRecordType::MX,
+            DnsRecordType::CNAME => registrar_common::DnsRecordType::CNAME,
+            DnsRecordType::ALIAS => registrar_common::DnsRecordType::ALIAS,
+            DnsRecordType::TXT => registrar_common::DnsRecordType::TXT,
+            DnsRecordType::NS => registrar_common::DnsRecordType::NS,
+            DnsRecordType::AAAA => registrar_common::DnsRecordType::AAAA,
+            DnsRecordType::SRV => registrar_common::DnsRecordType::SRV,
+            DnsRecordType::TLSA => registrar_common::DnsRecordType::TLSA,
+            DnsRecordType::CAA => registrar_common::DnsRecordType::CAA,
+            DnsRecordType::HTTPS => registrar_common::DnsRecordType::HTTPS,
+            DnsRecordType::SVCB => registrar_common::DnsRecordType::SVCB,
+        }
+    }
+}
+
+impl TryFrom<registrar_common::DnsRecordType> for DnsRecordType {
+
+    type Error = Error;
+
+    fn try_from(ty: registrar_common::DnsRecordType) -> Result<Self> {
+        use registrar_common::DnsRecordType as Common;
+        Ok(match ty {
+            Common::A => DnsRecordType::A,
+            Common::MX => DnsRecordType::MX,
+            Common::CNAME => DnsRecordType::CNAME,
+            Common::ALIAS => DnsRecordType::ALIAS,
+            Common::TXT => DnsRecordType::TXT,
+            Common::NS => DnsRecordType::NS,
+            Common::AAAA => DnsRecordType::AAAA,
+            Common::SRV => DnsRecordType::SRV,
+            Common::TLSA => DnsRecordType::TLSA,
+            Common::CAA => DnsRecordType::CAA,
+            Common::HTTPS => DnsRecordType::HTTPS,
+            Common::SVCB => DnsRecordType::SVCB,
+            other => return Err(Error::UnsupportedRecordType(other)),
+        })
+    }
+
+}
+
+impl TryFrom<&registrar_common::DnsRecord> for DnsRecord {
+
+    type Error = Error;
+
+    fn try_from(record: &registrar_common::DnsRecord) -> Result<Self> {
+        Ok(DnsRecord {
+            name: record.name.clone(),
+            ty: DnsRecordType::try_from(record.ty.clone())?,
+            content: record.content.clone(),
+            ttl: record.ttl,
+            prio: record.priority,
+        })
+    }
+
+}
+
+/// Strip the domain from a fully qualified record name, using the empty string for the domain
+/// itself.
+fn relative_name(name: &str, domain: &str) -> String {
+    if name == domain {
+        String::new()
+    } else {
+        name.strip_suffix(domain)
+            .and_then(|name| name.strip_suffix('.'))
+            .unwrap_or(name)
+            .to_string()
+    }
+}
+
+#[async_trait]
+impl Registrar for Client {
+
+    fn supports_type(&self, ty: &registrar_common::DnsRecordType) -> bool {
+        DnsRecordType::try_from(ty.clone()).is_ok()
+    }
+
+    async fn list_zones(&self) -> Result<Vec<String>> {
+        Ok(self.list_domains()
+            .await?
+            .into_iter()
+            .map(|domain| domain.domain)
+            .collect())
+    }
+
+    async fn list_records_with_ids(&self, zone: &str) -> Result<Vec<(DnsRecordId, registrar_common::DnsRecord)>> {
+        Ok(self.list_dns_records(zone)
+            .await?
+            .into_iter()
+            .map(|record| (record.id, registrar_common::DnsRecord {
+                name: relative_name(&record.name, zone),
+                ty: record.ty.into(),
+                content: record.content,
+                ttl: record.ttl.parse().ok(),
+                priority: record.prio
+                    .and_then(|prio| prio.parse().ok())
+                    .filter(|prio| *prio != 0),
+            }))
+            .collect())
+    }
+
+    async fn create_record(&self, zone: &str, record: &registrar_common::DnsRecord) -> Result<DnsRecordId> {
+        self.create_dns_record(zone, &DnsRecord::try_from(record)?).await
+    }
+
+    async fn update_record(&self, zone: &str, id: &str, record: &registrar_common::DnsRecord) -> Result<DnsRecordId> {
+        self.edit_dns_record(zone, id, &DnsRecord::try_from(record)?).await?;
+        Ok(id.to_string())
+    }
+
+    async fn delete_record(&self, zone: &str, id: &str) -> Result<()> {
+        self.delete_dns_record(zone, id).await
+    }
+
+    async fn delegated_name_servers(&self, domain: &str) -> Result<Vec<String>> {
+        self.get_name_servers(domain).await
+    }
+
+    async fn set_name_servers(&self, domain: &str, name_servers: &[String]) -> Result<()> {
+        self.update_name_servers(domain, name_servers).await
     }
 
 }
@@ -0,0 +1,315 @@
+//! An in-process fake of the Porkbun API, meant for testing.
+//!
+//! The fake keeps all of its state in memory, checks the API keys that are sent in the body of
+//! each request and answers with the same `{status, ...}` objects as the real API.
+//!
+//! ```no_run
+//! use porkbun::mock::MockServer;
+//!
+//! let server = MockServer::start("pk1_key", "sk1_secret").await;
+//! server.add_domain("example.com");
+//!
+//! let client = porkbun::Builder::new()
+//!     .base_url(server.base_url())
+//!     .api_key("pk1_key")
+//!     .secret_api_key("sk1_secret")
+//!     .build();
+//!
+//! assert_eq!(client.list_domains().await?.len(), 1);
+//! ```
+
+use std::collections::{BTreeMap, VecDeque};
+use std::sync::{Arc, Mutex};
+
+use registrar_common::mock::{MockHttpServer, MockRequest, MockResponse};
+use reqwest::{Method, StatusCode};
+use serde_json::{json, Value};
+
+use crate::{DnsRecordType, Domain, Record};
+
+const DEFAULT_TTL: &str = "600";
+
+const TIMESTAMP: &str = "2024-01-01 00:00:00";
+
+struct DomainState {
+    domain: Domain,
+    records: Vec<Record>,
+    name_servers: Vec<String>,
+}
+
+struct State {
+    api_key: String,
+    secret_api_key: String,
+    ip: String,
+    domains: BTreeMap<String, DomainState>,
+    unavailable: usize,
+    errors: VecDeque<String>,
+    requests: usize,
+    next_id: u64,
+}
+
+/// A fake Porkbun API server running on a random port on the loopback interface.
+///
+/// The server stops when this object is dropped.
+pub struct MockServer {
+    server: MockHttpServer,
+    state: Arc<Mutex<State>>,
+}
+
+impl MockServer {
+
+    /// Start a new server in the background of the current Tokio runtime that only accepts the
+    /// given pair of API keys.
+    pub async fn start<S1: Into<String>, S2: Into<String>>(api_key: S1, secret_api_key: S2) -> Self {
+        let state = Arc::new(Mutex::new(State {
+            api_key: api_key.into(),
+            secret_api_key: secret_api_key.into(),
+            ip: "127.0.0.1".to_string(),
+            domains: BTreeMap::new(),
+            unavailable: 0,
+            errors: VecDeque::new(),
+            requests: 0,
+            next_id: 0,
+        }));
+        let handler_state = state.clone();
+        let server = MockHttpServer::start(move |request| handle(&mut handler_state.lock().unwrap(), request)).await;
+        Self { server, state }
+    }
+
+    /// The URL that should be passed to [`Builder::base_url`](crate::Builder::base_url) to talk to
+    /// this server.
+    pub fn base_url(&self) -> String {
+        format!("{}/api/json/v3", self.server.url())
+    }
+
+    /// Set the IP address that is reported back by the `ping` endpoint.
+    pub fn set_ip<S: Into<String>>(&self, ip: S) {
+        self.state.lock().unwrap().ip = ip.into();
+    }
+
+    /// Register a domain without any DNS records.
+    pub fn add_domain<S: Into<String>>(&self, name: S) {
+        let name = name.into();
+        let tld = name.rsplit('.').next().unwrap_or("").to_string();
+        self.state.lock().unwrap().domains.insert(name.clone(), DomainState {
+            domain: Domain {
+                domain: name,
+                status: "ACTIVE".to_string(),
+                tld,
+                create_date: Some(TIMESTAMP.to_string()),
+                expire_date: Some(TIMESTAMP.to_string()),
+            },
+            records: Vec::new(),
+            name_servers: Vec::new(),
+        });
+    }
+
+    /// Add a record to a domain that was registered with [`add_domain`](Self::add_domain).
+    ///
+    /// The name is the subdomain of the record. Returns the ID of the new record.
+    pub fn add_record<S: AsRef<str>>(&self, domain: S, name: &str, ty: DnsRecordType, content: &str) -> String {
+        let domain = domain.as_ref();
+        let mut state = self.state.lock().unwrap();
+        state.next_id += 1;
+        let id = state.next_id.to_string();
+        let domain_state = state.domains.get_mut(domain).expect("domain does not exist on mock server");
+        domain_state.records.push(Record {
+            id: id.clone(),
+            name: qualify(name, domain),
+            ty,
+            content: content.to_string(),
+            ttl: DEFAULT_TTL.to_string(),
+            prio: None,
+            notes: None,
+        });
+        id
+    }
+
+    /// Get the DNS records of the given domain as they are currently stored on the server.
+    pub fn records<S: AsRef<str>>(&self, domain: S) -> Vec<Record> {
+        self.state.lock().unwrap()
+            .domains
+            .get(domain.as_ref())
+            .map(|domain| domain.records.clone())
+            .unwrap_or_default()
+    }
+
+    /// Get the name servers the given domain is delegated to.
+    pub fn name_servers<S: AsRef<str>>(&self, domain: S) -> Vec<String> {
+        self.state.lock().unwrap()
+            .domains
+            .get(domain.as_ref())
+            .map(|domain| domain.name_servers.clone())
+            .unwrap_or_default()
+    }
+
+    /// Answer the next `count` requests with 503 Service Unavailable and a body that is not JSON,
+    /// like a proxy in front of an overloaded server would.
+    pub fn fail_unavailable(&self, count: usize) {
+        self.state.lock().unwrap().unavailable = count;
+    }
+
+    /// Make the next request fail with the given message.
+    ///
+    /// Calling this multiple times queues up errors for the requests that follow.
+    pub fn push_error<S: Into<String>>(&self, message: S) {
+        self.state.lock().unwrap().errors.push_back(message.into());
+    }
+
+    /// The amount of requests the server has received so far.
+    pub fn request_count(&self) -> usize {
+        self.state.lock().unwrap().requests
+    }
+
+}
+
+fn qualify(name: &str, domain: &str) -> String {
+    if name.is_empty() {
+        domain.to_string()
+    } else {
+        format!("{}.{}", name, domain)
+    }
+}
+
+fn optional_string(value: Option<&Value>) -> Option<String> {
+    match value {
+        Some(Value::String(string)) => Some(string.clone()),
+        Some(Value::Number(number)) => Some(number.to_string()),
+        _ => None,
+    }
+}
+
+fn success(mut data: Value) -> MockResponse {
+    data.as_object_mut().unwrap().insert("status".to_string(), "SUCCESS".into());
+    MockResponse::json(StatusCode::OK, data)
+}
+
+fn failure<S: Into<String>>(message: S) -> MockResponse {
+    MockResponse::json(StatusCode::BAD_REQUEST, json!({ "status": "ERROR", "message": message.into() }))
+}
+
+/// Parse the fields of a record from the body of a create or edit request.
+fn parse_record(body: &Value, id: String, domain: &str) -> Option<Record> {
+    let ty = serde_json::from_value::<DnsRecordType>(body.get("type")?.clone()).ok()?;
+    Some(Record {
+        id,
+        name: qualify(body.get("name").and_then(Value::as_str).unwrap_or(""), domain),
+        ty,
+        content: body.get("content")?.as_str()?.to_string(),
+        ttl: optional_string(body.get("ttl")).unwrap_or_else(|| DEFAULT_TTL.to_string()),
+        prio: optional_string(body.get("prio")),
+        notes: optional_string(body.get("notes")),
+    })
+}
+
+fn handle(state: &mut State, request: MockRequest) -> MockResponse {
+
+    state.requests += 1;
+
+    if state.unavailable > 0 {
+        state.unavailable -= 1;
+        return MockResponse::text(StatusCode::SERVICE_UNAVAILABLE, "Service Unavailable");
+    }
+
+    if let Some(message) = state.errors.pop_front() {
+        return failure(message);
+    }
+
+    if request.method != Method::POST {
+        return failure("Only POST requests are supported.");
+    }
+
+    let body = request.json();
+    let Some(path) = request.path.strip_prefix("/api/json/v3") else {
+        return failure("Unknown API version.");
+    };
+
+    let api_key = body.get("apikey").and_then(Value::as_str);
+    let secret_api_key = body.get("secretapikey").and_then(Value::as_str);
+    if api_key != Some(state.api_key.as_str()) || secret_api_key != Some(state.secret_api_key.as_str()) {
+        return failure("Invalid API key. (002)");
+    }
+
+    let segments: Vec<&str> = path.trim_end_matches('/').split('/').skip(1).collect();
+
+    match segments.as_slice() {
+        ["ping"] => success(json!({ "yourIp": state.ip })),
+        ["domain", "listAll"] => {
+            let start = optional_string(body.get("start"))
+                .and_then(|start| start.parse::<usize>().ok())
+                .unwrap_or(0);
+            let domains: Vec<&Domain> = state.domains.values()
+                .map(|domain| &domain.domain)
+                .skip(start)
+                .take(crate::DOMAINS_PAGE_SIZE)
+                .collect();
+            success(json!({ "domains": domains }))
+        },
+        ["domain", "getNs", domain] => match state.domains.get(*domain) {
+            Some(domain) => success(json!({ "ns": domain.name_servers })),
+            None => failure("Invalid domain."),
+        },
+        ["domain", "updateNs", domain] => {
+            let Some(domain) = state.domains.get_mut(*domain) else {
+                return failure("Invalid domain.");
+            };
+            let Some(name_servers) = body.get("ns").and_then(Value::as_array) else {
+                return failure("Name servers are required.");
+            };
+            domain.name_servers = name_servers.iter()
+                .filter_map(|ns| ns.as_str().map(str::to_string))
+                .collect();
+            success(json!({}))
+        },
+        ["dns", "retrieve", domain] | ["dns", "retrieve", domain, _] => {
+            let Some(domain_state) = state.domains.get(*domain) else {
+                return failure("Invalid domain.");
+            };
+            let records: Vec<&Record> = match segments.get(3) {
+                None => domain_state.records.iter().collect(),
+                Some(id) => domain_state.records.iter().filter(|record| record.id == *id).collect(),
+            };
+            success(json!({ "records": records }))
+        },
+        ["dns", "create", domain] => {
+            let domain = domain.to_string();
+            if !state.domains.contains_key(&domain) {
+                return failure("Invalid domain.");
+            }
+            state.next_id += 1;
+            let id = state.next_id;
+            let Some(record) = parse_record(&body, id.to_string(), &domain) else {
+                return failure("Invalid record.");
+            };
+            state.domains.get_mut(&domain).unwrap().records.push(record);
+            success(json!({ "id": id }))
+        },
+        ["dns", "edit", domain, id] => {
+            let Some(domain_state) = state.domains.get_mut(*domain) else {
+                return failure("Invalid domain.");
+            };
+            let Some(record) = parse_record(&body, id.to_string(), domain) else {
+                return failure("Invalid record.");
+            };
+            match domain_state.records.iter_mut().find(|other| other.id == *id) {
+                None => failure("Invalid record ID."),
+                Some(existing) => {
+                    *existing = record;
+                    success(json!({}))
+                },
+            }
+        },
+        ["dns", "delete", domain, id] => {
+            let Some(domain_state) = state.domains.get_mut(*domain) else {
+                return failure("Invalid domain.");
+            };
+            let count = domain_state.records.len();
+            domain_state.records.retain(|record| record.id != *id);
+            if domain_state.records.len() == count {
+                return failure("Invalid record ID.");
+            }
+            success(json!({}))
+        },
+        _ => failure(format!("No route for {}.", path)),
+    }
+}
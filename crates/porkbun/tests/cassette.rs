@@ -0,0 +1,41 @@
+
+use std::sync::Arc;
+
+use porkbun::mock::MockServer;
+use porkbun::{Builder, DnsRecordType};
+use registrar_common::Cassette;
+
+#[tokio::test]
+async fn recorded_requests_replay_offline() {
+    let path = std::env::temp_dir().join(format!("porkbun-cassette-{}.json", std::process::id()));
+
+    let server = MockServer::start("pk1_key", "sk1_secret").await;
+    server.add_domain("example.com");
+    server.add_record("example.com", "wiki", DnsRecordType::A, "93.184.216.34");
+
+    let cassette = Arc::new(Cassette::record(&path));
+    let client = Builder::new()
+        .base_url(server.base_url())
+        .api_key("pk1_key")
+        .secret_api_key("sk1_secret")
+        .cassette(cassette.clone())
+        .build();
+    let recorded = client.list_dns_records("example.com").await.unwrap();
+    cassette.save().unwrap();
+    drop(server);
+
+    let contents = std::fs::read_to_string(&path).unwrap();
+    assert!(!contents.contains("sk1_secret"));
+
+    let client = Builder::new()
+        .base_url("http://127.0.0.1:9/api/json/v3")
+        .api_key("other_key")
+        .secret_api_key("other_secret")
+        .cassette(Arc::new(Cassette::replay(&path).unwrap()))
+        .build();
+    let replayed = client.list_dns_records("example.com").await.unwrap();
+    assert_eq!(replayed.len(), recorded.len());
+    assert_eq!(replayed[0].id, recorded[0].id);
+
+    std::fs::remove_file(path).unwrap();
+}
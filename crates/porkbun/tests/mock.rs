@@ -0,0 +1,151 @@
+
+use porkbun::mock::MockServer;
+use porkbun::{Builder, Client, DnsRecord, DnsRecordType};
+use registrar_common::{Error, Registrar};
+
+async fn setup() -> (MockServer, Client) {
+    let server = MockServer::start("pk1_key", "sk1_secret").await;
+    server.add_domain("example.com");
+    server.add_record("example.com", "", DnsRecordType::A, "93.184.216.34");
+    server.add_record("example.com", "wiki", DnsRecordType::A, "93.184.216.35");
+    let client = Builder::new()
+        .base_url(server.base_url())
+        .api_key("pk1_key")
+        .secret_api_key("sk1_secret")
+        .build();
+    (server, client)
+}
+
+#[tokio::test]
+async fn ping_reports_ip() {
+    let (server, client) = setup().await;
+    server.set_ip("203.0.113.7");
+    assert_eq!(client.ping().await.unwrap(), "203.0.113.7");
+}
+
+#[tokio::test]
+async fn wrong_keys_are_rejected() {
+    let (server, _) = setup().await;
+    let client = Builder::new()
+        .base_url(server.base_url())
+        .api_key("pk1_key")
+        .secret_api_key("wrong")
+        .build();
+    assert!(matches!(client.ping().await, Err(Error::AuthenticationFailed)));
+}
+
+#[tokio::test]
+async fn injected_errors_are_reported() {
+    let (server, client) = setup().await;
+    server.push_error("Rate limit exceeded.");
+    assert!(matches!(client.ping().await, Err(Error::Api { code: None, .. })));
+    assert!(client.ping().await.is_ok());
+}
+
+#[tokio::test]
+async fn unavailable_servers_are_retried() {
+    let (server, _) = setup().await;
+    let client = Builder::new()
+        .base_url(server.base_url())
+        .api_key("pk1_key")
+        .secret_api_key("sk1_secret")
+        .max_retries(1)
+        .build();
+
+    server.fail_unavailable(1);
+    assert!(client.ping().await.is_ok());
+    assert_eq!(server.request_count(), 2);
+
+    server.fail_unavailable(2);
+    assert!(matches!(client.ping().await, Err(Error::StatusCode(503))));
+    assert_eq!(server.request_count(), 4);
+}
+
+#[tokio::test]
+async fn records_can_be_created_edited_and_deleted() {
+    let (server, client) = setup().await;
+    let id = client.create_dns_record("example.com", &DnsRecord {
+        name: "mail".to_string(),
+        ty: DnsRecordType::MX,
+        content: "mx.example.com".to_string(),
+        ttl: Some(3600),
+        prio: Some(10),
+    }).await.unwrap();
+    let record = client.list_dns_records("example.com").await.unwrap()
+        .into_iter()
+        .find(|record| record.id == id)
+        .unwrap();
+    assert_eq!(record.name, "mail.example.com");
+    assert_eq!(record.ttl, "3600");
+    assert_eq!(record.prio.as_deref(), Some("10"));
+
+    client.edit_dns_record("example.com", &id, &DnsRecord {
+        name: "mail".to_string(),
+        ty: DnsRecordType::MX,
+        content: "mx2.example.com".to_string(),
+        ttl: None,
+        prio: Some(20),
+    }).await.unwrap();
+    assert!(server.records("example.com").iter().any(|r| r.id == id && r.content == "mx2.example.com"));
+
+    client.delete_dns_record("example.com", &id).await.unwrap();
+    assert!(!server.records("example.com").iter().any(|r| r.id == id));
+    assert!(client.delete_dns_record("example.com", &id).await.is_err());
+}
+
+#[tokio::test]
+async fn domains_and_name_servers() {
+    let (server, client) = setup().await;
+    let domains = client.list_domains().await.unwrap();
+    assert_eq!(domains.len(), 1);
+    assert_eq!(domains[0].domain, "example.com");
+    let name_servers = vec!["ns1.example.net".to_string(), "ns2.example.net".to_string()];
+    client.update_name_servers("example.com", &name_servers).await.unwrap();
+    assert_eq!(server.name_servers("example.com"), name_servers);
+
+    assert_eq!(client.get_name_servers("example.com").await.unwrap(), name_servers);
+
+    let name_servers = vec!["ns1.openprovider.nl".to_string()];
+    Registrar::set_name_servers(&client, "example.com", &name_servers).await.unwrap();
+    assert_eq!(server.name_servers("example.com"), name_servers);
+    assert_eq!(Registrar::delegated_name_servers(&client, "example.com").await.unwrap(), name_servers);
+    assert!(client.get_name_servers("example.org").await.is_err());
+}
+
+#[tokio::test]
+async fn registrar_adapter_uses_relative_names() {
+    let (server, client) = setup().await;
+    let registrar: &dyn Registrar = &client;
+    assert_eq!(registrar.list_zones().await.unwrap(), vec!["example.com"]);
+    let mut names: Vec<String> = registrar.list_records("example.com").await.unwrap()
+        .into_iter()
+        .map(|record| record.name)
+        .collect();
+    names.sort();
+    assert_eq!(names, vec!["", "wiki"]);
+    let id = registrar.create_record("example.com", &registrar_common::DnsRecord {
+        name: "_verify".to_string(),
+        ty: registrar_common::DnsRecordType::TXT,
+        content: "token=123".to_string(),
+        ttl: None,
+        priority: None,
+    }).await.unwrap();
+    assert!(server.records("example.com").iter().any(|r| r.id == id && r.name == "_verify.example.com"));
+}
+
+#[tokio::test]
+async fn registrar_adapter_addresses_records_by_native_id() {
+    let (server, client) = setup().await;
+    let wiki_id = server.records("example.com").into_iter().find(|r| r.name == "wiki.example.com").unwrap().id;
+    let registrar: &dyn Registrar = &client;
+    let records = registrar.list_records_with_ids("example.com").await.unwrap();
+    let (id, mut record) = records.into_iter().find(|(_, record)| record.name == "wiki").unwrap();
+    assert_eq!(id, wiki_id);
+
+    record.content = "93.184.216.36".to_string();
+    assert_eq!(registrar.update_record("example.com", &id, &record).await.unwrap(), id);
+    assert!(server.records("example.com").iter().any(|r| r.id == id && r.content == "93.184.216.36"));
+
+    registrar.delete_record("example.com", &id).await.unwrap();
+    assert_eq!(server.records("example.com").len(), 1);
+}
@@ -0,0 +1,784 @@
+//!
+//! The [PowerDNS Authoritative Server](https://www.powerdns.com/powerdns-authoritative-server)
+//! comes with an HTTP API to manage the zones it serves.
+//!
+//! This crate implements the part of that API that deals with zones and their RRsets. It
+//! authenticates using the API key that is configured in `pdns.conf`, which is sent in the
+//! `X-API-Key` header.
+//!
+//! ```no_run
+//! let client = powerdns::Builder::new()
+//!     .base_url("http://ns1.example.com:8081/api/v1")
+//!     .api_key("my-api-key")
+//!     .build();
+//!
+//! for rrset in client.get_zone("example.com").await?.rrsets {
+//!     eprintln!("{} {:?} {:?}", rrset.name, rrset.ty, rrset.records);
+//! }
+//! ```
+
+#[cfg(feature = "mock")]
+pub mod mock;
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use registrar_common::{retry, content_id, Cassette, DEFAULT_MAX_RETRIES, DnsRecord, DnsRecordId, DnsRecordType, Error, RecordIdentity, Registrar, Result, Timeouts, ValueExt};
+use reqwest::Method;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+const DEFAULT_BASE_URL: &str = "http://localhost:8081/api/v1";
+
+const DEFAULT_SERVER_ID: &str = "localhost";
+
+/// The TTL of records that are created without one in an RRset that does not exist yet.
+const DEFAULT_TTL: u32 = 3600;
+
+/// Constructs an [API client](Client).
+///
+/// ```no_run
+/// let client = powerdns::Builder::new()
+///     .api_key("my-api-key")
+///     .build();
+/// ```
+pub struct Builder {
+    base_url: String,
+    server_id: String,
+    api_key: Option<String>,
+    max_retries: u32,
+    timeouts: Timeouts,
+    cassette: Option<Arc<Cassette>>,
+}
+
+impl Default for Builder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Builder {
+
+    /// Create a new API client builder object that talks to a server on the local machine.
+    pub fn new() -> Self {
+        Self {
+            base_url: DEFAULT_BASE_URL.to_string(),
+            server_id: DEFAULT_SERVER_ID.to_string(),
+            api_key: None,
+            max_retries: DEFAULT_MAX_RETRIES,
+            timeouts: Timeouts::default(),
+            cassette: None,
+        }
+    }
+
+    /// The URL of the API, including its version, e.g. `http://ns1.example.com:8081/api/v1`.
+    pub fn base_url<S: Into<String>>(mut self, base_url: S) -> Self {
+        self.base_url = base_url.into().trim_end_matches('/').to_string();
+        self
+    }
+
+    /// The identifier of the server whose zones are managed. PowerDNS only knows `localhost`, but
+    /// proxies in front of several servers may use other identifiers.
+    pub fn server_id<S: Into<String>>(mut self, server_id: S) -> Self {
+        self.server_id = server_id.into();
+        self
+    }
+
+    /// Authenticate using the given API key.
+    pub fn api_key<S: Into<String>>(mut self, api_key: S) -> Self {
+        self.api_key = Some(api_key.into());
+        self
+    }
+
+    /// Retry a request at most this many times if it failed in a way that is
+    /// [transient](Error::is_transient).
+    pub fn max_retries(mut self, max_retries: u32) -> Self {
+        self.max_retries = max_retries;
+        self
+    }
+
+    /// Give up on a request if no connection could be made within the given duration.
+    pub fn connect_timeout(mut self, timeout: Duration) -> Self {
+        self.timeouts.connect = Some(timeout);
+        self
+    }
+
+    /// Give up on a request if the server does not send any data within the given duration.
+    pub fn read_timeout(mut self, timeout: Duration) -> Self {
+        self.timeouts.read = Some(timeout);
+        self
+    }
+
+    /// Give up on a request if it did not complete within the given duration.
+    ///
+    /// Use [`Client::with_timeout`] to override this deadline for specific requests.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeouts.total = Some(timeout);
+        self
+    }
+
+    /// Record all requests to the given cassette or play them back from it.
+    ///
+    /// See [`Cassette`] for more information.
+    pub fn cassette(mut self, cassette: Arc<Cassette>) -> Self {
+        self.cassette = Some(cassette);
+        self
+    }
+
+    /// Build the actual API client. This is a destructive operation.
+    pub fn build(self) -> Client {
+        let mut builder = reqwest::Client::builder();
+        if let Some(timeout) = self.timeouts.connect {
+            builder = builder.connect_timeout(timeout);
+        }
+        if let Some(timeout) = self.timeouts.read {
+            builder = builder.read_timeout(timeout);
+        }
+        if let Some(timeout) = self.timeouts.total {
+            builder = builder.timeout(timeout);
+        }
+        Client {
+            client: builder.build().expect("failed to initialize the HTTP client"),
+            base_url: self.base_url,
+            server_id: self.server_id,
+            api_key: self.api_key,
+            max_retries: self.max_retries,
+            request_timeout: None,
+            cassette: self.cassette,
+        }
+    }
+
+}
+
+/// Represents a DNS record type that is supported by PowerDNS.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum RecordType {
+    A,
+    AAAA,
+    AFSDB,
+    ALIAS,
+    CAA,
+    CERT,
+    CNAME,
+    DHCID,
+    DNAME,
+    DNSKEY,
+    DS,
+    HINFO,
+    HTTPS,
+    LOC,
+    MX,
+    NAPTR,
+    NS,
+    NSEC,
+    NSEC3,
+    NSEC3PARAM,
+    PTR,
+    RP,
+    RRSIG,
+    SMIMEA,
+    SOA,
+    SPF,
+    SRV,
+    SSHFP,
+    SVCB,
+    TLSA,
+    TXT,
+    URI,
+    /// Any type this crate does not know about.
+    #[serde(other)]
+    Other,
+}
+
+/// The content of a single record within an [`Rrset`].
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RrsetRecord {
+    /// The data of the record in zone file format, with fully qualified names and quoted strings,
+    /// e.g. `10 mail.example.com.` or `"v=spf1 -all"`.
+    pub content: String,
+    #[serde(default)]
+    pub disabled: bool,
+}
+
+/// A comment that is attached to an [`Rrset`].
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Comment {
+    pub content: String,
+    #[serde(default)]
+    pub account: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub modified_at: Option<u64>,
+}
+
+/// All records with the same name and type, which PowerDNS manages as a single unit.
+///
+/// The name is fully qualified and ends with a dot.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Rrset {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub ty: RecordType,
+    pub ttl: u32,
+    pub records: Vec<RrsetRecord>,
+    #[serde(default)]
+    pub comments: Vec<Comment>,
+}
+
+/// Whether an [`RrsetChange`] replaces an RRset or deletes it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum ChangeType {
+    Replace,
+    Delete,
+}
+
+/// A change to a single RRset that is sent as part of a PATCH request.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RrsetChange {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub ty: RecordType,
+    pub changetype: ChangeType,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ttl: Option<u32>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub records: Vec<RrsetRecord>,
+    /// Replaces the comments of the RRset if given.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub comments: Option<Vec<Comment>>,
+}
+
+impl RrsetChange {
+
+    /// Replace the RRset with the name and type of the given one.
+    pub fn replace(rrset: &Rrset) -> Self {
+        Self {
+            name: rrset.name.clone(),
+            ty: rrset.ty.clone(),
+            changetype: ChangeType::Replace,
+            ttl: Some(rrset.ttl),
+            records: rrset.records.clone(),
+            comments: Some(rrset.comments.clone()),
+        }
+    }
+
+    /// Delete the RRset with the given name and type.
+    pub fn delete<S: AsRef<str>>(name: S, ty: RecordType) -> Self {
+        Self {
+            name: absolute(name.as_ref()),
+            ty,
+            changetype: ChangeType::Delete,
+            ttl: None,
+            records: Vec::new(),
+            comments: None,
+        }
+    }
+
+}
+
+/// Represents a zone that is served by PowerDNS.
+///
+/// The RRsets are only filled in by [`Client::get_zone`].
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Zone {
+    pub id: String,
+    pub name: String,
+    /// `Native`, `Master` or `Slave`.
+    pub kind: String,
+    #[serde(default)]
+    pub serial: u32,
+    #[serde(default)]
+    pub dnssec: bool,
+    #[serde(default)]
+    pub rrsets: Vec<Rrset>,
+}
+
+/// The attributes of a zone that is about to be created.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct NewZone {
+    pub name: String,
+    /// `Native`, `Master` or `Slave`.
+    pub kind: String,
+    /// The name servers that are put into the NS RRset at the zone apex.
+    pub nameservers: Vec<String>,
+}
+
+/// Communicates with the PowerDNS Authoritative HTTP API.
+///
+/// Clients are cheap to clone. All clones share the same connection pool.
+#[derive(Clone)]
+pub struct Client {
+    client: reqwest::Client,
+    base_url: String,
+    server_id: String,
+    api_key: Option<String>,
+    max_retries: u32,
+    request_timeout: Option<Duration>,
+    cassette: Option<Arc<Cassette>>,
+}
+
+/// Make a name fully qualified by adding the trailing dot.
+fn absolute(name: &str) -> String {
+    if name.ends_with('.') {
+        name.to_string()
+    } else {
+        format!("{}.", name)
+    }
+}
+
+impl Client {
+
+    /// Get a copy of this client whose requests must complete within the given duration.
+    ///
+    /// This overrides the deadline set with [`Builder::timeout`].
+    pub fn with_timeout(&self, timeout: Duration) -> Self {
+        let mut client = self.clone();
+        client.request_timeout = Some(timeout);
+        client
+    }
+
+    /// Perform a request and return the body of the response, or `null` if there is none.
+    async fn request<P: AsRef<str>>(&self, method: Method, path: P, body: Option<Value>) -> Result<Value> {
+        let path = format!("/servers/{}{}", self.server_id, path.as_ref());
+        let response = match &self.cassette {
+            Some(cassette) if cassette.is_replaying() => {
+                log::info!("Replaying request to {}", path);
+                cassette.find(method.as_str(), &path, body.as_ref())?
+            },
+            _ => {
+                let response = retry(self.max_retries, || self.send(method.clone(), &path, body.as_ref())).await?;
+                if let Some(cassette) = &self.cassette {
+                    cassette.push(method.as_str(), &path, body.as_ref(), &response);
+                }
+                response
+            },
+        };
+        let status = response.get_ok("status")?.as_u32_ok()?;
+        let body = response.get_ok("body")?;
+        if (200..300).contains(&status) {
+            return Ok(body.clone());
+        }
+        if status == 401 || status == 403 {
+            return Err(Error::AuthenticationFailed);
+        }
+        let message = body.get("error")
+            .and_then(Value::as_str)
+            .or_else(|| body.as_str())
+            .filter(|message| !message.is_empty())
+            .unwrap_or("unknown error");
+        Err(Error::Api {
+            code: Some(status),
+            message: message.to_string(),
+        })
+    }
+
+    /// Send a request to the API and wrap the response in a `{status, body}` object.
+    async fn send(&self, method: Method, path: &str, body: Option<&Value>) -> Result<Value> {
+        let url = format!("{}{}", self.base_url, path);
+        log::info!("Starting request to {}", url);
+        let mut builder = self.client
+            .request(method, url)
+            .header("Accept", "application/json");
+        if let Some(timeout) = self.request_timeout {
+            builder = builder.timeout(timeout);
+        }
+        if let Some(body) = body {
+            builder = builder.json(body);
+        }
+        if let Some(api_key) = &self.api_key {
+            builder = builder.header("X-API-Key", api_key);
+        }
+        let response = builder.send().await?;
+        let status = response.status().as_u16();
+        // These are left to `retry` rather than reported as errors of the API.
+        let error = Error::StatusCode(status);
+        if error.is_transient() {
+            return Err(error);
+        }
+        let text = response.text().await?;
+        let body = if text.is_empty() {
+            Value::Null
+        } else {
+            serde_json::from_str(&text).unwrap_or(Value::String(text))
+        };
+        Ok(json!({ "status": status, "body": body }))
+    }
+
+    /// List all zones on the server, without their RRsets.
+    pub async fn list_zones(&self) -> Result<Vec<Zone>> {
+        let response = self.request(Method::GET, "/zones", None).await?;
+        Ok(serde_json::from_value(response)?)
+    }
+
+    /// Get a zone together with all of its RRsets.
+    pub async fn get_zone<S: AsRef<str>>(&self, zone: S) -> Result<Zone> {
+        let response = self.request(
+            Method::GET,
+            format!("/zones/{}", absolute(zone.as_ref())),
+            None
+        ).await?;
+        Ok(serde_json::from_value(response)?)
+    }
+
+    /// Create a new zone.
+    pub async fn create_zone(&self, zone: &NewZone) -> Result<Zone> {
+        let zone = NewZone {
+            name: absolute(&zone.name),
+            kind: zone.kind.clone(),
+            nameservers: zone.nameservers.iter().map(|name| absolute(name)).collect(),
+        };
+        let response = self.request(
+            Method::POST,
+            "/zones",
+            Some(serde_json::to_value(&zone)?)
+        ).await?;
+        Ok(serde_json::from_value(response)?)
+    }
+
+    /// Delete a zone and all of its records.
+    pub async fn delete_zone<S: AsRef<str>>(&self, zone: S) -> Result<()> {
+        self.request(
+            Method::DELETE,
+            format!("/zones/{}", absolute(zone.as_ref())),
+            None
+        ).await?;
+        Ok(())
+    }
+
+    /// Apply changes to the RRsets of a zone.
+    ///
+    /// PowerDNS applies either all changes or none of them.
+    pub async fn patch_rrsets<S: AsRef<str>>(&self, zone: S, changes: &[RrsetChange]) -> Result<()> {
+        self.request(
+            Method::PATCH,
+            format!("/zones/{}", absolute(zone.as_ref())),
+            Some(json!({ "rrsets": changes }))
+        ).await?;
+        Ok(())
+    }
+
+}
+
+/// Get the record type of the shared model that belongs to a PowerDNS record type.
+fn common_type(ty: &RecordType) -> Option<DnsRecordType> {
+    Some(match ty {
+        RecordType::A => DnsRecordType::A,
+        RecordType::AAAA => DnsRecordType::AAAA,
+        RecordType::AFSDB => DnsRecordType::AFSDB,
+        RecordType::ALIAS => DnsRecordType::ALIAS,
+        RecordType::CAA => DnsRecordType::CAA,
+        RecordType::CERT => DnsRecordType::CERT,
+        RecordType::CNAME => DnsRecordType::CNAME,
+        RecordType::DHCID => DnsRecordType::DHCID,
+        RecordType::DNAME => DnsRecordType::DNAME,
+        RecordType::DNSKEY => DnsRecordType::DNSKEY,
+        RecordType::DS => DnsRecordType::DS,
+        RecordType::HINFO => DnsRecordType::HINFO,
+        RecordType::HTTPS => DnsRecordType::HTTPS,
+        RecordType::LOC => DnsRecordType::LOC,
+        RecordType::MX => DnsRecordType::MX,
+        RecordType::NAPTR => DnsRecordType::NAPTR,
+        RecordType::NS => DnsRecordType::NS,
+        RecordType::NSEC => DnsRecordType::NSEC,
+        RecordType::NSEC3 => DnsRecordType::NSEC3,
+        RecordType::NSEC3PARAM => DnsRecordType::NSEC3PARAM,
+        RecordType::PTR => DnsRecordType::PTR,
+        RecordType::RP => DnsRecordType::RP,
+        RecordType::RRSIG => DnsRecordType::RRSIG,
+        RecordType::SMIMEA => DnsRecordType::SMIMEA,
+        RecordType::SOA => DnsRecordType::SOA,
+        RecordType::SPF => DnsRecordType::SPF,
+        RecordType::SRV => DnsRecordType::SRV,
+        RecordType::SSHFP => DnsRecordType::SSHFP,
+        RecordType::SVCB => DnsRecordType::SVCB,
+        RecordType::TLSA => DnsRecordType::TLSA,
+        RecordType::TXT => DnsRecordType::TXT,
+        RecordType::URI => DnsRecordType::URI,
+        RecordType::Other => return None,
+    })
+}
+
+impl TryFrom<DnsRecordType> for RecordType {
+
+    type Error = Error;
+
+    fn try_from(ty: DnsRecordType) -> Result<Self> {
+        Ok(match ty {
+            DnsRecordType::A => RecordType::A,
+            DnsRecordType::AAAA => RecordType::AAAA,
+            DnsRecordType::AFSDB => RecordType::AFSDB,
+            DnsRecordType::ALIAS => RecordType::ALIAS,
+            DnsRecordType::CAA => RecordType::CAA,
+            DnsRecordType::CERT => RecordType::CERT,
+            DnsRecordType::CNAME => RecordType::CNAME,
+            DnsRecordType::DHCID => RecordType::DHCID,
+            DnsRecordType::DNAME => RecordType::DNAME,
+            DnsRecordType::DNSKEY => RecordType::DNSKEY,
+            DnsRecordType::DS => RecordType::DS,
+            DnsRecordType::HINFO => RecordType::HINFO,
+            DnsRecordType::HTTPS => RecordType::HTTPS,
+            DnsRecordType::LOC => RecordType::LOC,
+            DnsRecordType::MX => RecordType::MX,
+            DnsRecordType::NAPTR => RecordType::NAPTR,
+            DnsRecordType::NS => RecordType::NS,
+            DnsRecordType::NSEC => RecordType::NSEC,
+            DnsRecordType::NSEC3 => RecordType::NSEC3,
+            DnsRecordType::NSEC3PARAM => RecordType::NSEC3PARAM,
+            DnsRecordType::PTR => RecordType::PTR,
+            DnsRecordType::RP => RecordType::RP,
+            DnsRecordType::RRSIG => RecordType::RRSIG,
+            DnsRecordType::SMIMEA => RecordType::SMIMEA,
+            DnsRecordType::SOA => RecordType::SOA,
+            DnsRecordType::SPF => RecordType::SPF,
+            DnsRecordType::SRV => RecordType::SRV,
+            DnsRecordType::SSHFP => RecordType::SSHFP,
+            DnsRecordType::SVCB => RecordType::SVCB,
+            DnsRecordType::TLSA => RecordType::TLSA,
+            DnsRecordType::TXT => RecordType::TXT,
+            DnsRecordType::URI => RecordType::URI,
+        })
+    }
+
+}
+
+/// Turn the strings of a TXT record into a single string, e.g. `"v=spf1 " "-all"` into
+/// `v=spf1 -all`.
+fn unquote(content: &str) -> String {
+    if !content.starts_with('"') {
+        return content.to_string();
+    }
+    let mut text = String::new();
+    let mut chars = content.chars();
+    let mut quoted = false;
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => quoted = !quoted,
+            '\\' if quoted => {
+                if let Some(escaped) = chars.next() {
+                    text.push(escaped);
+                }
+            },
+            _ if quoted => text.push(c),
+            _ => {},
+        }
+    }
+    text
+}
+
+/// Turn a string into the strings of a TXT record, splitting it up if it is too long.
+fn quote(text: &str) -> String {
+    if text.starts_with('"') {
+        return text.to_string();
+    }
+    let mut strings = Vec::new();
+    let mut current = String::new();
+    for c in text.chars() {
+        if current.len() + c.len_utf8() > 255 {
+            strings.push(std::mem::take(&mut current));
+        }
+        current.push(c);
+    }
+    strings.push(current);
+    strings.iter()
+        .map(|string| format!("\"{}\"", string.replace('\\', "\\\\").replace('"', "\\\"")))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Convert a record from the zone file format of PowerDNS to the shared model, which puts the
+/// priority in a separate field and leaves out the trailing dot of names.
+fn to_common(ty: &DnsRecordType, content: &str) -> (Option<u32>, String) {
+    let strip = |name: &str| name.strip_suffix('.').unwrap_or(name).to_string();
+    match ty {
+        DnsRecordType::TXT | DnsRecordType::SPF => (None, unquote(content)),
+        DnsRecordType::CNAME | DnsRecordType::NS | DnsRecordType::PTR | DnsRecordType::DNAME | DnsRecordType::ALIAS => {
+            (None, strip(content))
+        },
+        DnsRecordType::MX | DnsRecordType::SRV => match content.split_once(' ') {
+            Some((priority, rest)) => match priority.parse() {
+                Ok(priority) => (Some(priority), strip(rest)),
+                Err(_) => (None, content.to_string()),
+            },
+            None => (None, content.to_string()),
+        },
+        _ => (None, content.to_string()),
+    }
+}
+
+/// The reverse of [`to_common`].
+fn from_common(record: &DnsRecord) -> String {
+    match record.ty {
+        DnsRecordType::TXT | DnsRecordType::SPF => quote(&record.content),
+        DnsRecordType::CNAME | DnsRecordType::NS | DnsRecordType::PTR | DnsRecordType::DNAME | DnsRecordType::ALIAS => {
+            absolute(&record.content)
+        },
+        DnsRecordType::MX | DnsRecordType::SRV => {
+            // The target is the last field of both MX and SRV records.
+            let content = match record.content.rsplit_once(' ') {
+                Some((rest, target)) => format!("{} {}", rest, absolute(target)),
+                None => absolute(&record.content),
+            };
+            format!("{} {}", record.priority.unwrap_or(0), content)
+        },
+        _ => record.content.clone(),
+    }
+}
+
+fn relative_name(name: &str, zone: &str) -> String {
+    let name = name.strip_suffix('.').unwrap_or(name);
+    if name == zone {
+        String::new()
+    } else {
+        name.strip_suffix(zone)
+            .and_then(|name| name.strip_suffix('.'))
+            .unwrap_or(name)
+            .to_string()
+    }
+}
+
+/// Get the records of an RRset in the shared model, together with their index in the RRset.
+/// Disabled records are left out.
+fn flatten(rrset: &Rrset, zone: &str) -> Vec<(usize, DnsRecord)> {
+    let Some(ty) = common_type(&rrset.ty) else {
+        log::debug!("Skipping RRset {} of unsupported type", rrset.name);
+        return Vec::new();
+    };
+    rrset.records.iter()
+        .enumerate()
+        .filter(|(_, record)| !record.disabled)
+        .map(|(i, record)| {
+            let (priority, content) = to_common(&ty, &record.content);
+            (i, DnsRecord {
+                name: relative_name(&rrset.name, zone),
+                ty: ty.clone(),
+                content,
+                ttl: Some(rrset.ttl),
+                priority,
+            })
+        })
+        .collect()
+}
+
+/// Add a record to the RRset with the same name and type, creating it if it does not exist yet,
+/// and return the index of that RRset.
+///
+/// The TTL of the RRset is changed to the one of the record if it has one.
+fn insert(rrsets: &mut Vec<Rrset>, zone: &str, record: &DnsRecord) -> Result<usize> {
+    let ty = RecordType::try_from(record.ty.clone())?;
+    let name = if record.name.is_empty() { absolute(zone) } else { format!("{}.{}.", record.name, zone) };
+    let index = match rrsets.iter().position(|rrset| rrset.name.eq_ignore_ascii_case(&name) && rrset.ty == ty) {
+        Some(index) => index,
+        None => {
+            rrsets.push(Rrset {
+                name,
+                ty,
+                ttl: DEFAULT_TTL,
+                records: Vec::new(),
+                comments: Vec::new(),
+            });
+            rrsets.len() - 1
+        },
+    };
+    let rrset = &mut rrsets[index];
+    if let Some(ttl) = record.ttl {
+        rrset.ttl = ttl;
+    }
+    let content = from_common(record);
+    if !rrset.records.iter().any(|existing| existing.content == content) {
+        rrset.records.push(RrsetRecord { content, disabled: false });
+    }
+    Ok(index)
+}
+
+/// The change that brings an RRset on the server in line with the given one, deleting it if it
+/// has no records left.
+fn change_for(rrset: &Rrset) -> RrsetChange {
+    if rrset.records.is_empty() {
+        RrsetChange::delete(&rrset.name, rrset.ty.clone())
+    } else {
+        RrsetChange::replace(rrset)
+    }
+}
+
+impl Client {
+
+    /// Get the RRsets of a zone and remove the record whose [content identifier](content_id) is
+    /// the given one from them. Returns the RRsets and the index of the one that was changed.
+    async fn remove_record(&self, zone: &str, id: &str) -> Result<(Vec<Rrset>, usize)> {
+        let mut rrsets = self.get_zone(zone).await?.rrsets;
+        for (i, rrset) in rrsets.iter_mut().enumerate() {
+            let found = flatten(rrset, zone)
+                .into_iter()
+                .find(|(_, record)| content_id(record) == id);
+            if let Some((j, _)) = found {
+                rrset.records.remove(j);
+                return Ok((rrsets, i));
+            }
+        }
+        Err(Error::RecordNotFound(id.to_string()))
+    }
+
+}
+
+#[async_trait]
+impl Registrar for Client {
+
+    fn record_identity(&self) -> RecordIdentity {
+        RecordIdentity::Content
+    }
+
+    async fn list_zones(&self) -> Result<Vec<String>> {
+        Ok(Client::list_zones(self)
+            .await?
+            .into_iter()
+            .map(|zone| zone.name.trim_end_matches('.').to_string())
+            .collect())
+    }
+
+    /// Every record of an RRset becomes a record of its own. Disabled records are left out.
+    async fn list_records_with_ids(&self, zone: &str) -> Result<Vec<(DnsRecordId, DnsRecord)>> {
+        let zone = zone.trim_end_matches('.');
+        Ok(self.get_zone(zone)
+            .await?
+            .rrsets
+            .iter()
+            .flat_map(|rrset| flatten(rrset, zone))
+            .map(|(_, record)| (content_id(&record), record))
+            .collect())
+    }
+
+    /// PowerDNS does not assign identifiers to records, so the returned identifier is derived from
+    /// the contents of the new record.
+    ///
+    /// The record is added to the RRset with the same name and type, whose TTL is changed to the
+    /// one of the record if it has one.
+    async fn create_record(&self, zone: &str, record: &DnsRecord) -> Result<DnsRecordId> {
+        let zone = zone.trim_end_matches('.');
+        let mut rrsets = self.get_zone(zone).await?.rrsets;
+        let index = insert(&mut rrsets, zone, record)?;
+        self.patch_rrsets(zone, &[change_for(&rrsets[index])]).await?;
+        Ok(content_id(record))
+    }
+
+    /// The record is moved to another RRset if its name or type changes. Both RRsets are changed
+    /// in the same request.
+    async fn update_record(&self, zone: &str, id: &str, record: &DnsRecord) -> Result<DnsRecordId> {
+        let zone = zone.trim_end_matches('.');
+        let (mut rrsets, old) = self.remove_record(zone, id).await?;
+        let new = insert(&mut rrsets, zone, record)?;
+        let mut changes = vec![change_for(&rrsets[new])];
+        if old != new {
+            changes.insert(0, change_for(&rrsets[old]));
+        }
+        self.patch_rrsets(zone, &changes).await?;
+        Ok(content_id(record))
+    }
+
+    async fn delete_record(&self, zone: &str, id: &str) -> Result<()> {
+        let zone = zone.trim_end_matches('.');
+        let (rrsets, old) = self.remove_record(zone, id).await?;
+        self.patch_rrsets(zone, &[change_for(&rrsets[old])]).await
+    }
+
+}
@@ -0,0 +1,323 @@
+//! An in-process fake of the PowerDNS Authoritative HTTP API, meant for testing.
+//!
+//! The fake keeps all of its state in memory, checks the `X-API-Key` header of each request and
+//! answers with the same objects and status codes as the real server. Like PowerDNS, it insists on
+//! fully qualified names and quoted TXT records, and increases the serial of a zone whenever its
+//! RRsets change.
+//!
+//! ```no_run
+//! use powerdns::mock::MockServer;
+//! use powerdns::RecordType;
+//!
+//! let server = MockServer::start("my-api-key").await;
+//! server.add_zone("example.com");
+//! server.add_rrset("example.com", "www", RecordType::A, 300, &["192.0.2.1"]);
+//!
+//! let client = powerdns::Builder::new()
+//!     .base_url(server.base_url())
+//!     .api_key("my-api-key")
+//!     .build();
+//!
+//! assert_eq!(client.get_zone("example.com").await?.rrsets.len(), 3);
+//! ```
+
+use std::collections::{BTreeMap, VecDeque};
+use std::sync::{Arc, Mutex};
+
+use registrar_common::mock::{MockHttpServer, MockRequest, MockResponse};
+use reqwest::{Method, StatusCode};
+use serde_json::json;
+
+use crate::{absolute, ChangeType, NewZone, Rrset, RrsetChange, RrsetRecord, RecordType, Zone};
+
+const SERVER_ID: &str = "localhost";
+
+const DEFAULT_TTL: u32 = 3600;
+
+/// The record types whose content ends with a name, which has to be fully qualified.
+const NAME_TYPES: [RecordType; 7] = [
+    RecordType::ALIAS,
+    RecordType::CNAME,
+    RecordType::DNAME,
+    RecordType::MX,
+    RecordType::NS,
+    RecordType::PTR,
+    RecordType::SRV,
+];
+
+struct State {
+    api_key: String,
+    zones: BTreeMap<String, Zone>,
+    errors: VecDeque<(StatusCode, String)>,
+    requests: usize,
+}
+
+/// A fake PowerDNS server running on a random port on the loopback interface.
+///
+/// The server stops when this object is dropped.
+pub struct MockServer {
+    server: MockHttpServer,
+    state: Arc<Mutex<State>>,
+}
+
+impl MockServer {
+
+    /// Start a new server in the background of the current Tokio runtime that only accepts the
+    /// given API key.
+    pub async fn start<S: Into<String>>(api_key: S) -> Self {
+        let state = Arc::new(Mutex::new(State {
+            api_key: api_key.into(),
+            zones: BTreeMap::new(),
+            errors: VecDeque::new(),
+            requests: 0,
+        }));
+        let handler_state = state.clone();
+        let server = MockHttpServer::start(move |request| handle(&mut handler_state.lock().unwrap(), request)).await;
+        Self { server, state }
+    }
+
+    /// The URL that should be passed to [`Builder::base_url`](crate::Builder::base_url) to talk to
+    /// this server.
+    pub fn base_url(&self) -> String {
+        format!("{}/api/v1", self.server.url())
+    }
+
+    /// Create a zone that only contains SOA and NS records.
+    pub fn add_zone<S: AsRef<str>>(&self, name: S) {
+        let zone = new_zone(&NewZone {
+            name: absolute(name.as_ref()),
+            kind: "Native".to_string(),
+            nameservers: vec![format!("ns1.{}", absolute(name.as_ref()))],
+        });
+        self.state.lock().unwrap().zones.insert(zone.id.clone(), zone);
+    }
+
+    /// Add an RRset to a zone that was created with [`add_zone`](Self::add_zone), replacing the
+    /// one with the same name and type.
+    ///
+    /// The name is relative to the zone, with an empty name referring to the zone apex. The
+    /// contents are in the format PowerDNS uses, e.g. `10 mail.example.com.` for an MX record.
+    pub fn add_rrset(&self, zone: &str, name: &str, ty: RecordType, ttl: u32, contents: &[&str]) {
+        let mut state = self.state.lock().unwrap();
+        let zone = state.zones.get_mut(&absolute(zone)).expect("zone does not exist on mock server");
+        let name = if name.is_empty() { zone.name.clone() } else { format!("{}.{}", name, zone.name) };
+        zone.rrsets.retain(|rrset| rrset.name != name || rrset.ty != ty);
+        zone.rrsets.push(Rrset {
+            name,
+            ty,
+            ttl,
+            records: contents.iter()
+                .map(|content| RrsetRecord { content: content.to_string(), disabled: false })
+                .collect(),
+            comments: Vec::new(),
+        });
+    }
+
+    /// Get the RRsets of the given zone as they are currently stored on the server.
+    pub fn rrsets(&self, zone: &str) -> Vec<Rrset> {
+        self.state.lock().unwrap()
+            .zones
+            .get(&absolute(zone))
+            .map(|zone| zone.rrsets.clone())
+            .unwrap_or_default()
+    }
+
+    /// The serial number of the given zone.
+    pub fn serial(&self, zone: &str) -> u32 {
+        self.state.lock().unwrap()
+            .zones
+            .get(&absolute(zone))
+            .map(|zone| zone.serial)
+            .unwrap_or_default()
+    }
+
+    /// Make the next request fail with the given HTTP status and message.
+    ///
+    /// Calling this multiple times queues up errors for the requests that follow.
+    pub fn push_error<S: Into<String>>(&self, status: StatusCode, message: S) {
+        self.state.lock().unwrap().errors.push_back((status, message.into()));
+    }
+
+    /// The amount of requests the server has received so far.
+    pub fn request_count(&self) -> usize {
+        self.state.lock().unwrap().requests
+    }
+
+}
+
+fn failure<S: Into<String>>(status: StatusCode, message: S) -> MockResponse {
+    MockResponse::json(status, json!({ "error": message.into() }))
+}
+
+fn no_content() -> MockResponse {
+    MockResponse::text(StatusCode::NO_CONTENT, "")
+}
+
+fn soa_content(zone: &str, serial: u32) -> String {
+    format!("ns1.{} hostmaster.{} {} 10800 3600 604800 3600", zone, zone, serial)
+}
+
+fn new_zone(zone: &NewZone) -> Zone {
+    let serial = 1;
+    Zone {
+        id: zone.name.clone(),
+        name: zone.name.clone(),
+        kind: zone.kind.clone(),
+        serial,
+        dnssec: false,
+        rrsets: vec![
+            Rrset {
+                name: zone.name.clone(),
+                ty: RecordType::SOA,
+                ttl: DEFAULT_TTL,
+                records: vec![RrsetRecord { content: soa_content(&zone.name, serial), disabled: false }],
+                comments: Vec::new(),
+            },
+            Rrset {
+                name: zone.name.clone(),
+                ty: RecordType::NS,
+                ttl: DEFAULT_TTL,
+                records: zone.nameservers.iter()
+                    .map(|name| RrsetRecord { content: name.clone(), disabled: false })
+                    .collect(),
+                comments: Vec::new(),
+            },
+        ],
+    }
+}
+
+/// Check a change the way PowerDNS does before it touches the zone.
+fn validate(zone: &Zone, change: &RrsetChange) -> Result<(), String> {
+    if !change.name.ends_with('.') {
+        return Err(format!("RRset {} IN {:?}: Name is not canonical", change.name, change.ty));
+    }
+    if change.name != zone.name && !change.name.ends_with(&format!(".{}", zone.name)) {
+        return Err(format!("RRset {} IN {:?}: Name is out of zone", change.name, change.ty));
+    }
+    if change.ty == RecordType::Other {
+        return Err(format!("RRset {}: unknown record type", change.name));
+    }
+    if change.changetype == ChangeType::Delete {
+        return Ok(());
+    }
+    if change.ttl.is_none() {
+        return Err(format!("RRset {} IN {:?}: no TTL given", change.name, change.ty));
+    }
+    for record in &change.records {
+        let content = record.content.trim();
+        let valid = match change.ty {
+            RecordType::TXT | RecordType::SPF => content.starts_with('"') && content.ends_with('"'),
+            ref ty if NAME_TYPES.contains(ty) => content.ends_with('.'),
+            RecordType::A => content.parse::<std::net::Ipv4Addr>().is_ok(),
+            RecordType::AAAA => content.parse::<std::net::Ipv6Addr>().is_ok(),
+            _ => !content.is_empty(),
+        };
+        if !valid {
+            return Err(format!("Record {}/{:?} '{}': Parsing record content failed", change.name, change.ty, record.content));
+        }
+    }
+    if change.ty == RecordType::CNAME {
+        let conflict = zone.rrsets.iter().any(|rrset| rrset.name == change.name && rrset.ty != RecordType::CNAME);
+        if conflict {
+            return Err(format!("RRset {} IN CNAME: Conflicts with pre-existing RRset", change.name));
+        }
+    }
+    Ok(())
+}
+
+/// Apply all changes to a zone or none of them.
+fn patch(zone: &mut Zone, changes: Vec<RrsetChange>) -> Result<(), String> {
+    let mut rrsets = zone.rrsets.clone();
+    for change in changes {
+        validate(zone, &change)?;
+        rrsets.retain(|rrset| rrset.name != change.name || rrset.ty != change.ty);
+        if change.changetype == ChangeType::Replace && !change.records.is_empty() {
+            rrsets.push(Rrset {
+                name: change.name,
+                ty: change.ty,
+                ttl: change.ttl.unwrap_or(DEFAULT_TTL),
+                records: change.records,
+                comments: change.comments.unwrap_or_default(),
+            });
+        }
+    }
+    if rrsets != zone.rrsets {
+        zone.rrsets = rrsets;
+        zone.serial += 1;
+        let (name, serial) = (zone.name.clone(), zone.serial);
+        if let Some(soa) = zone.rrsets.iter_mut().find(|rrset| rrset.ty == RecordType::SOA) {
+            soa.records = vec![RrsetRecord { content: soa_content(&name, serial), disabled: false }];
+        }
+    }
+    Ok(())
+}
+
+fn handle(state: &mut State, request: MockRequest) -> MockResponse {
+
+    state.requests += 1;
+
+    if let Some((status, message)) = state.errors.pop_front() {
+        return failure(status, message);
+    }
+
+    let Some(path) = request.path.strip_prefix("/api/v1") else {
+        return failure(StatusCode::NOT_FOUND, "Not Found");
+    };
+
+    if request.header("x-api-key") != Some(state.api_key.as_str()) {
+        return MockResponse::text(StatusCode::UNAUTHORIZED, "Unauthorized");
+    }
+
+    let path = path.trim_end_matches('/').to_string();
+    let segments: Vec<&str> = path.split('/').skip(1).collect();
+
+    match (request.method.clone(), segments.as_slice()) {
+        (Method::GET, ["servers", SERVER_ID, "zones"]) => {
+            let zones: Vec<Zone> = state.zones.values()
+                .map(|zone| Zone { rrsets: Vec::new(), ..zone.clone() })
+                .collect();
+            MockResponse::json(StatusCode::OK, json!(zones))
+        },
+        (Method::POST, ["servers", SERVER_ID, "zones"]) => {
+            let zone: NewZone = match serde_json::from_value(request.json()) {
+                Ok(zone) => zone,
+                Err(error) => return failure(StatusCode::BAD_REQUEST, format!("invalid zone: {}", error)),
+            };
+            if !zone.name.ends_with('.') {
+                return failure(StatusCode::UNPROCESSABLE_ENTITY, format!("DNS Name '{}' is not canonical", zone.name));
+            }
+            if state.zones.contains_key(&zone.name) {
+                return failure(StatusCode::CONFLICT, format!("Domain '{}' already exists", zone.name));
+            }
+            let zone = new_zone(&zone);
+            state.zones.insert(zone.id.clone(), zone.clone());
+            MockResponse::json(StatusCode::CREATED, json!(zone))
+        },
+        (Method::GET, ["servers", SERVER_ID, "zones", id]) => match state.zones.get(*id) {
+            None => failure(StatusCode::NOT_FOUND, format!("Could not find domain '{}'", id)),
+            Some(zone) => MockResponse::json(StatusCode::OK, json!(zone)),
+        },
+        (Method::PATCH, ["servers", SERVER_ID, "zones", id]) => {
+            let Some(zone) = state.zones.get_mut(*id) else {
+                return failure(StatusCode::NOT_FOUND, format!("Could not find domain '{}'", id));
+            };
+            let changes = request.json()
+                .get("rrsets")
+                .cloned()
+                .map(serde_json::from_value::<Vec<RrsetChange>>);
+            match changes {
+                Some(Ok(changes)) => match patch(zone, changes) {
+                    Ok(()) => no_content(),
+                    Err(message) => failure(StatusCode::UNPROCESSABLE_ENTITY, message),
+                },
+                Some(Err(error)) => failure(StatusCode::BAD_REQUEST, format!("invalid RRsets: {}", error)),
+                None => failure(StatusCode::BAD_REQUEST, "No rrsets given"),
+            }
+        },
+        (Method::DELETE, ["servers", SERVER_ID, "zones", id]) => match state.zones.remove(*id) {
+            None => failure(StatusCode::NOT_FOUND, format!("Could not find domain '{}'", id)),
+            Some(_) => no_content(),
+        },
+        (method, _) => failure(StatusCode::NOT_FOUND, format!("no route for {} {}", method, path)),
+    }
+}
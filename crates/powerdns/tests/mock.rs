@@ -0,0 +1,170 @@
+use powerdns::mock::MockServer;
+use powerdns::{Builder, Client, NewZone, RecordType, RrsetChange};
+use registrar_common::{DnsRecord, DnsRecordType, Error, Registrar};
+use reqwest::StatusCode;
+
+async fn setup() -> (MockServer, Client) {
+    let server = MockServer::start("key").await;
+    server.add_zone("example.com");
+    server.add_rrset("example.com", "", RecordType::A, 300, &["93.184.216.34"]);
+    server.add_rrset("example.com", "", RecordType::MX, 3600, &["10 mx1.example.com.", "20 mx2.example.com."]);
+    server.add_rrset("example.com", "", RecordType::TXT, 300, &["\"v=spf1 \" \"-all\""]);
+    let client = Builder::new()
+        .base_url(server.base_url())
+        .api_key("key")
+        .build();
+    (server, client)
+}
+
+fn record(name: &str, ty: DnsRecordType, content: &str, priority: Option<u32>) -> DnsRecord {
+    DnsRecord {
+        name: name.to_string(),
+        ty,
+        content: content.to_string(),
+        ttl: Some(300),
+        priority,
+    }
+}
+
+#[tokio::test]
+async fn wrong_api_key_is_rejected() {
+    let (server, _) = setup().await;
+    let client = Builder::new()
+        .base_url(server.base_url())
+        .api_key("wrong")
+        .build();
+    assert!(matches!(client.list_zones().await, Err(Error::AuthenticationFailed)));
+}
+
+#[tokio::test]
+async fn zones_can_be_created_and_deleted() {
+    let (server, client) = setup().await;
+    let zone = client.create_zone(&NewZone {
+        name: "example.org".to_string(),
+        kind: "Native".to_string(),
+        nameservers: vec!["ns1.example.org".to_string()],
+    }).await.unwrap();
+    assert_eq!(zone.name, "example.org.");
+    assert_eq!(server.rrsets("example.org").len(), 2);
+    assert_eq!(Registrar::list_zones(&client).await.unwrap(), vec!["example.com", "example.org"]);
+
+    client.delete_zone("example.org").await.unwrap();
+    assert!(matches!(client.get_zone("example.org").await, Err(Error::Api { code: Some(404), .. })));
+}
+
+#[tokio::test]
+async fn rrsets_are_flattened_into_records() {
+    let (_server, client) = setup().await;
+    let records = Registrar::list_records(&client, "example.com").await.unwrap();
+    let mx: Vec<&DnsRecord> = records.iter().filter(|record| record.ty == DnsRecordType::MX).collect();
+    assert_eq!(mx.len(), 2);
+    assert_eq!(mx[0].name, "");
+    assert_eq!(mx[0].content, "mx1.example.com");
+    assert_eq!(mx[0].priority, Some(10));
+    let txt = records.iter().find(|record| record.ty == DnsRecordType::TXT).unwrap();
+    assert_eq!(txt.content, "v=spf1 -all");
+    let ns = records.iter().find(|record| record.ty == DnsRecordType::NS).unwrap();
+    assert_eq!(ns.content, "ns1.example.com");
+}
+
+#[tokio::test]
+async fn created_records_are_added_to_their_rrset() {
+    let (server, client) = setup().await;
+    let serial = server.serial("example.com");
+    Registrar::create_record(&client, "example.com", &record("", DnsRecordType::MX, "mx3.example.com", Some(30)))
+        .await
+        .unwrap();
+    Registrar::create_record(&client, "example.com", &record("www", DnsRecordType::CNAME, "example.com", None))
+        .await
+        .unwrap();
+    Registrar::create_record(&client, "example.com", &record("_dmarc", DnsRecordType::TXT, "v=DMARC1; p=\"none\"", None))
+        .await
+        .unwrap();
+    assert_eq!(server.serial("example.com"), serial + 3);
+
+    let rrsets = server.rrsets("example.com");
+    let mx = rrsets.iter().find(|rrset| rrset.ty == RecordType::MX).unwrap();
+    assert_eq!(mx.records.len(), 3);
+    assert_eq!(mx.records[2].content, "30 mx3.example.com.");
+    assert_eq!(mx.ttl, 300);
+    let www = rrsets.iter().find(|rrset| rrset.name == "www.example.com.").unwrap();
+    assert_eq!(www.records[0].content, "example.com.");
+    let dmarc = rrsets.iter().find(|rrset| rrset.name == "_dmarc.example.com.").unwrap();
+    assert_eq!(dmarc.records[0].content, "\"v=DMARC1; p=\\\"none\\\"\"");
+
+    let records = Registrar::list_records(&client, "example.com").await.unwrap();
+    assert!(records.iter().any(|record| record.name == "_dmarc" && record.content == "v=DMARC1; p=\"none\""));
+}
+
+#[tokio::test]
+async fn long_txt_records_are_split_into_strings() {
+    let (server, client) = setup().await;
+    let value = "k".repeat(300);
+    Registrar::create_record(&client, "example.com", &record("dkim", DnsRecordType::TXT, &value, None))
+        .await
+        .unwrap();
+    let rrset = server.rrsets("example.com").into_iter().find(|rrset| rrset.name == "dkim.example.com.").unwrap();
+    assert_eq!(rrset.records[0].content, format!("\"{}\" \"{}\"", "k".repeat(255), "k".repeat(45)));
+    let records = Registrar::list_records(&client, "example.com").await.unwrap();
+    assert!(records.iter().any(|record| record.name == "dkim" && record.content == value));
+}
+
+#[tokio::test]
+async fn invalid_patches_change_nothing() {
+    let (server, client) = setup().await;
+    let serial = server.serial("example.com");
+    let changes = [
+        RrsetChange::delete("example.com", RecordType::A),
+        RrsetChange::delete("www.example.org", RecordType::A),
+    ];
+    let result = client.patch_rrsets("example.com", &changes).await;
+    assert!(matches!(result, Err(Error::Api { code: Some(422), .. })));
+    assert_eq!(server.serial("example.com"), serial);
+    assert_eq!(server.rrsets("example.com").len(), 5);
+
+    client.patch_rrsets("example.com", &changes[..1]).await.unwrap();
+    assert_eq!(server.rrsets("example.com").len(), 4);
+}
+
+#[tokio::test]
+async fn server_errors_are_reported() {
+    let (server, client) = setup().await;
+    server.push_error(StatusCode::INTERNAL_SERVER_ERROR, "Backend error");
+    match client.get_zone("example.com").await {
+        Err(Error::Api { code, message }) => {
+            assert_eq!(code, Some(500));
+            assert_eq!(message, "Backend error");
+        },
+        other => panic!("unexpected result: {:?}", other.map(|zone| zone.name)),
+    }
+    assert_eq!(server.request_count(), 1);
+}
+
+#[tokio::test]
+async fn unavailable_servers_are_retried() {
+    let (server, client) = setup().await;
+    server.push_error(StatusCode::SERVICE_UNAVAILABLE, "Backend busy");
+    assert!(client.get_zone("example.com").await.is_ok());
+    assert_eq!(server.request_count(), 2);
+}
+
+#[tokio::test]
+async fn records_are_moved_between_rrsets() {
+    let (server, client) = setup().await;
+    let (id, mut mx) = Registrar::list_records_with_ids(&client, "example.com").await.unwrap()
+        .into_iter()
+        .find(|(_, record)| record.content == "mx2.example.com")
+        .unwrap();
+
+    mx.name = "backup".to_string();
+    let new_id = Registrar::update_record(&client, "example.com", &id, &mx).await.unwrap();
+    let rrsets = server.rrsets("example.com");
+    let apex = rrsets.iter().find(|rrset| rrset.ty == RecordType::MX && rrset.name == "example.com.").unwrap();
+    assert_eq!(apex.records.len(), 1);
+    let backup = rrsets.iter().find(|rrset| rrset.name == "backup.example.com.").unwrap();
+    assert_eq!(backup.records[0].content, "20 mx2.example.com.");
+    assert!(matches!(Registrar::delete_record(&client, "example.com", &id).await, Err(Error::RecordNotFound(_))));
+
+    Registrar::delete_record(&client, "example.com", &new_id).await.unwrap();
+    assert!(server.rrsets("example.com").iter().all(|rrset| rrset.name != "backup.example.com."));
+}
@@ -0,0 +1,338 @@
+
+use std::net::{Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::time::{Duration, Instant};
+
+use ring::digest::{digest, SHA256};
+use ring::rand::{SecureRandom, SystemRandom};
+use tokio::net::UdpSocket;
+
+use crate::{DnsRecord, DnsRecordType, Error, Registrar, Result};
+
+const TYPE_TXT: u16 = 16;
+
+const CLASS_IN: u16 = 1;
+
+/// The label under which ACME servers look for the TXT record of a DNS-01 challenge.
+pub const ACME_CHALLENGE_LABEL: &str = "_acme-challenge";
+
+/// Compute the value of the TXT record for a DNS-01 challenge from the key authorization, which is
+/// the token followed by a dot and the thumbprint of the account key.
+///
+/// Most ACME clients hand this value to hooks directly, but some only give the key authorization.
+pub fn dns01_value(key_authorization: &str) -> String {
+    base64url(digest(&SHA256, key_authorization.as_bytes()).as_ref())
+}
+
+fn base64url(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_";
+    let mut text = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let bits = chunk.iter().enumerate().fold(0u32, |bits, (i, byte)| bits | (*byte as u32) << (16 - 8 * i));
+        for i in 0..=chunk.len() {
+            text.push(ALPHABET[(bits >> (18 - 6 * i) & 0x3f) as usize] as char);
+        }
+    }
+    text
+}
+
+/// Turn the domain a certificate is for into the domain that is validated, so that `*.example.com`,
+/// `example.com.` and `_acme-challenge.example.com` all become `example.com`.
+fn challenged_domain(domain: &str) -> String {
+    let domain = domain.trim_end_matches('.').to_ascii_lowercase();
+    let domain = domain.strip_prefix("*.").unwrap_or(&domain);
+    let prefix = format!("{}.", ACME_CHALLENGE_LABEL);
+    domain.strip_prefix(&prefix).unwrap_or(domain).to_string()
+}
+
+/// Some services return TXT records with the quotes of the zone file syntax around them.
+fn unquote(content: &str) -> &str {
+    content.trim_matches('"')
+}
+
+fn query(id: u16, name: &str) -> Vec<u8> {
+    let mut buf = Vec::with_capacity(64);
+    buf.extend_from_slice(&id.to_be_bytes());
+    // A plain query without recursion, since only the answer of the server itself counts.
+    buf.extend_from_slice(&[0, 0, 0, 1, 0, 0, 0, 0, 0, 0]);
+    for label in name.split('.').filter(|label| !label.is_empty()) {
+        buf.push(label.len() as u8);
+        buf.extend_from_slice(label.as_bytes());
+    }
+    buf.push(0);
+    buf.extend_from_slice(&TYPE_TXT.to_be_bytes());
+    buf.extend_from_slice(&CLASS_IN.to_be_bytes());
+    buf
+}
+
+fn malformed() -> Error {
+    Error::Generic("malformed DNS response".to_string())
+}
+
+fn skip_name(buf: &[u8], mut pos: usize) -> Result<usize> {
+    loop {
+        let length = *buf.get(pos).ok_or_else(malformed)? as usize;
+        match length {
+            0 => return Ok(pos + 1),
+            // A compression pointer always ends the name.
+            length if length & 0xc0 == 0xc0 => return Ok(pos + 2),
+            length => pos += 1 + length,
+        }
+    }
+}
+
+fn read_u16(buf: &[u8], pos: usize) -> Result<u16> {
+    buf.get(pos..pos + 2).map(|bytes| u16::from_be_bytes([bytes[0], bytes[1]])).ok_or_else(malformed)
+}
+
+/// Read the TXT records in the answer section of a response to [`query`].
+fn parse_txt(buf: &[u8], id: u16) -> Result<Vec<String>> {
+    if read_u16(buf, 0)? != id {
+        return Err(Error::Generic("DNS response does not belong to the query".to_string()));
+    }
+    let flags = read_u16(buf, 2)?;
+    if flags & 0x0200 != 0 {
+        return Err(Error::Generic("DNS response was truncated".to_string()));
+    }
+    match flags & 0xf {
+        // A name without records is not an error here; the record is just not there yet.
+        0 | 3 => {},
+        rcode => return Err(Error::Generic(format!("name server answered with response code {}", rcode))),
+    }
+    let mut pos = 12;
+    for _ in 0..read_u16(buf, 4)? {
+        pos = skip_name(buf, pos)? + 4;
+    }
+    let mut texts = Vec::new();
+    for _ in 0..read_u16(buf, 6)? {
+        pos = skip_name(buf, pos)?;
+        let ty = read_u16(buf, pos)?;
+        let length = read_u16(buf, pos + 8)? as usize;
+        pos += 10;
+        let rdata = buf.get(pos..pos + length).ok_or_else(malformed)?;
+        pos += length;
+        // Answers can also hold the CNAME records that were followed to get to the TXT records.
+        if ty != TYPE_TXT {
+            continue;
+        }
+        let mut text = Vec::new();
+        let mut offset = 0;
+        while offset < rdata.len() {
+            let length = rdata[offset] as usize;
+            text.extend_from_slice(rdata.get(offset + 1..offset + 1 + length).ok_or_else(malformed)?);
+            offset += 1 + length;
+        }
+        texts.push(String::from_utf8_lossy(&text).into_owned());
+    }
+    Ok(texts)
+}
+
+/// Ask a single name server for the TXT records of a name.
+async fn lookup_txt(server: SocketAddr, name: &str, timeout: Duration) -> Result<Vec<String>> {
+    let local: SocketAddr = match server {
+        SocketAddr::V4(_) => (Ipv4Addr::UNSPECIFIED, 0).into(),
+        SocketAddr::V6(_) => (Ipv6Addr::UNSPECIFIED, 0).into(),
+    };
+    let socket = UdpSocket::bind(local).await?;
+    socket.connect(server).await?;
+    let id = rand_id();
+    socket.send(&query(id, name)).await?;
+    let mut buf = vec![0; 4096];
+    let length = tokio::time::timeout(timeout, socket.recv(&mut buf))
+        .await
+        .map_err(|_| Error::Generic(format!("name server {} did not answer", server)))??;
+    parse_txt(&buf[..length], id)
+}
+
+fn rand_id() -> u16 {
+    let mut id = [0; 2];
+    // Failing to get random bytes only makes the response easier to spoof, so it is not fatal.
+    let _ = SystemRandom::new().fill(&mut id);
+    u16::from_ne_bytes(id)
+}
+
+/// Creates and removes the TXT records that prove control over a domain to an ACME server such as
+/// Let's Encrypt, using any [`Registrar`].
+///
+/// After creating a record, [`present`](Self::present) waits until every authoritative name server
+/// of the zone serves it, because the ACME server may ask any of them. The name servers are the
+/// NS records at the apex of the zone, unless others are given.
+///
+/// ```no_run
+/// use registrar_common::{dns01_value, AcmeChallenge};
+///
+/// let challenge = AcmeChallenge::new(&openprovider_client).ttl(60);
+/// let value = dns01_value(&key_authorization);
+/// challenge.present("*.example.com", &value).await?;
+/// // ... let the ACME server validate the challenge ...
+/// challenge.cleanup("*.example.com", &value).await?;
+/// ```
+pub struct AcmeChallenge<'a> {
+    registrar: &'a dyn Registrar,
+    ttl: Option<u32>,
+    wait: bool,
+    timeout: Duration,
+    interval: Duration,
+    name_servers: Option<Vec<SocketAddr>>,
+    port: u16,
+}
+
+impl<'a> AcmeChallenge<'a> {
+
+    pub fn new(registrar: &'a dyn Registrar) -> Self {
+        Self {
+            registrar,
+            ttl: None,
+            wait: true,
+            timeout: Duration::from_secs(300),
+            interval: Duration::from_secs(5),
+            name_servers: None,
+            port: 53,
+        }
+    }
+
+    /// Give the TXT records this time to live instead of the default of the service.
+    pub fn ttl(mut self, ttl: u32) -> Self {
+        self.ttl = Some(ttl);
+        self
+    }
+
+    /// Whether [`present`](Self::present) waits for the name servers to serve the record. Enabled
+    /// by default.
+    pub fn wait(mut self, enable: bool) -> Self {
+        self.wait = enable;
+        self
+    }
+
+    /// Give up waiting for the name servers after this long. Defaults to five minutes.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Ask the name servers again after this long. Defaults to five seconds.
+    pub fn interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    /// Check these name servers instead of the ones in the NS records of the zone.
+    pub fn name_servers(mut self, servers: Vec<SocketAddr>) -> Self {
+        self.name_servers = Some(servers);
+        self
+    }
+
+    /// Ask the name servers of the zone on this port. Defaults to 53.
+    pub fn port(mut self, port: u16) -> Self {
+        self.port = port;
+        self
+    }
+
+    /// Find the zone that holds the challenge of a domain, and the name of the record relative to
+    /// that zone.
+    pub async fn locate(&self, domain: &str) -> Result<(String, String)> {
+        let domain = challenged_domain(domain);
+        let zone = self.registrar.list_zones()
+            .await?
+            .into_iter()
+            .map(|zone| zone.trim_end_matches('.').to_ascii_lowercase())
+            .filter(|zone| domain == *zone || domain.ends_with(&format!(".{}", zone)))
+            .max_by_key(|zone| zone.len())
+            .ok_or_else(|| Error::ZoneNotFound(domain.clone()))?;
+        let name = match domain.strip_suffix(&zone).and_then(|name| name.strip_suffix('.')) {
+            Some(name) => format!("{}.{}", ACME_CHALLENGE_LABEL, name),
+            None => ACME_CHALLENGE_LABEL.to_string(),
+        };
+        Ok((zone, name))
+    }
+
+    /// Create the TXT record for the challenge of a domain, unless it exists already, and wait for
+    /// it to be served.
+    ///
+    /// Other values for the same domain are left alone, since a certificate for both a domain and
+    /// its wildcard needs two of them at the same time.
+    pub async fn present(&self, domain: &str, value: &str) -> Result<()> {
+        let (zone, name) = self.locate(domain).await?;
+        let exists = self.registrar.list_records(&zone)
+            .await?
+            .iter()
+            .any(|record| record.ty == DnsRecordType::TXT && record.name.eq_ignore_ascii_case(&name) && unquote(&record.content) == value);
+        if !exists {
+            self.registrar.create_record(&zone, &DnsRecord {
+                name: name.clone(),
+                ty: DnsRecordType::TXT,
+                content: value.to_string(),
+                ttl: self.ttl,
+                priority: None,
+            }).await?;
+        }
+        if self.wait {
+            self.wait_for(&zone, &format!("{}.{}", name, zone), value).await?;
+        }
+        Ok(())
+    }
+
+    /// Remove the TXT record for the challenge of a domain with the given value, if it exists.
+    pub async fn cleanup(&self, domain: &str, value: &str) -> Result<()> {
+        let (zone, name) = self.locate(domain).await?;
+        for (id, record) in self.registrar.list_records_with_ids(&zone).await? {
+            if record.ty == DnsRecordType::TXT && record.name.eq_ignore_ascii_case(&name) && unquote(&record.content) == value {
+                self.registrar.delete_record(&zone, &id).await?;
+            }
+        }
+        Ok(())
+    }
+
+    /// The addresses of the name servers to check, grouped by name server.
+    async fn servers(&self, zone: &str) -> Result<Vec<(String, Vec<SocketAddr>)>> {
+        if let Some(servers) = &self.name_servers {
+            return Ok(servers.iter().map(|server| (server.to_string(), vec![*server])).collect());
+        }
+        let mut servers = Vec::new();
+        for name in self.registrar.name_servers(zone).await? {
+            let addresses = tokio::net::lookup_host((name.as_str(), self.port)).await?.collect();
+            servers.push((name, addresses));
+        }
+        if servers.is_empty() {
+            return Err(Error::Generic(format!("no name servers are known for {}", zone)));
+        }
+        Ok(servers)
+    }
+
+    /// Wait until every name server of the zone serves a TXT record with the given value. A name
+    /// server counts once any of its addresses does.
+    pub async fn wait_for(&self, zone: &str, name: &str, value: &str) -> Result<()> {
+        let mut pending = self.servers(zone).await?;
+        let deadline = Instant::now() + self.timeout;
+        let per_query = self.interval.clamp(Duration::from_secs(1), Duration::from_secs(5));
+        loop {
+            let mut waiting = Vec::new();
+            for (server, addresses) in pending {
+                let mut seen = false;
+                for address in &addresses {
+                    match lookup_txt(*address, name, per_query).await {
+                        Ok(texts) if texts.iter().any(|text| text == value) => {
+                            seen = true;
+                            break;
+                        },
+                        Ok(_) => {},
+                        Err(error) => log::debug!("could not ask {} ({}) for {}: {}", server, address, name, error),
+                    }
+                }
+                if !seen {
+                    waiting.push((server, addresses));
+                }
+            }
+            if waiting.is_empty() {
+                return Ok(());
+            }
+            if Instant::now() + self.interval > deadline {
+                let names: Vec<String> = waiting.into_iter().map(|(server, _)| server).collect();
+                return Err(Error::Generic(format!("{} is not served by {} yet", name, names.join(", "))));
+            }
+            log::info!("waiting for {} to serve {}", waiting.len(), name);
+            tokio::time::sleep(self.interval).await;
+            pending = waiting;
+        }
+    }
+
+}
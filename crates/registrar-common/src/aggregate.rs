@@ -0,0 +1,234 @@
+
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+
+use async_trait::async_trait;
+
+use crate::{DnsRecord, DnsRecordId, DnsRecordType, Error, RecordIdentity, Registrar, Result};
+
+/// A zone together with the name of the provider that hosts it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ZoneOrigin {
+    pub zone: String,
+    pub provider: String,
+}
+
+/// The zones of all providers of an [`Aggregate`].
+#[derive(Debug, Default)]
+pub struct Inventory {
+    /// All zones that could be listed, in the order in which the providers were added.
+    pub zones: Vec<ZoneOrigin>,
+    /// Providers whose zones could not be listed, together with the reason.
+    pub failed: Vec<(String, Error)>,
+}
+
+/// Presents several named [`Registrar`]s as if they were one.
+///
+/// Record operations are routed to the provider that hosts the zone. Which provider that is, is
+/// found out by listing the zones of all providers, after which the answer is cached. If more than
+/// one provider claims a zone, the one that was added first wins. Changes to the delegation of a
+/// domain go to the provider where the domain is registered instead.
+///
+/// ```no_run
+/// use registrar_common::Aggregate;
+///
+/// let aggregate = Aggregate::new()
+///     .provider("openprovider", openprovider_client)
+///     .provider("cloudflare", cloudflare_client);
+///
+/// for origin in aggregate.inventory().await.zones {
+///     eprintln!("{} is hosted by {}", origin.zone, origin.provider);
+/// }
+/// ```
+#[derive(Default)]
+pub struct Aggregate {
+    providers: Vec<(String, Arc<dyn Registrar>)>,
+    owners: RwLock<HashMap<String, String>>,
+}
+
+fn normalize(zone: &str) -> String {
+    zone.trim_end_matches('.').to_ascii_lowercase()
+}
+
+impl Aggregate {
+
+    /// Create an aggregate without any providers.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a provider under the given name. Adding a provider with a name that is already taken
+    /// replaces the earlier one.
+    pub fn provider<S: Into<String>, R: Registrar + 'static>(self, name: S, registrar: R) -> Self {
+        self.shared_provider(name, Arc::new(registrar))
+    }
+
+    /// Like [`provider`](Self::provider), for a registrar that is also used elsewhere.
+    pub fn shared_provider<S: Into<String>>(mut self, name: S, registrar: Arc<dyn Registrar>) -> Self {
+        let name = name.into();
+        match self.providers.iter_mut().find(|(other, _)| *other == name) {
+            Some((_, existing)) => *existing = registrar,
+            None => self.providers.push((name, registrar)),
+        }
+        self.owners.get_mut().unwrap().clear();
+        self
+    }
+
+    /// The names of all providers, in the order in which they were added.
+    pub fn provider_names(&self) -> Vec<&str> {
+        self.providers.iter().map(|(name, _)| name.as_str()).collect()
+    }
+
+    /// Get the provider with the given name.
+    pub fn get(&self, name: &str) -> Option<&dyn Registrar> {
+        self.providers.iter()
+            .find(|(other, _)| other == name)
+            .map(|(_, registrar)| registrar.as_ref())
+    }
+
+    /// List the zones of all providers at the same time.
+    ///
+    /// A provider that fails does not keep the zones of the others from being listed.
+    pub async fn inventory(&self) -> Inventory {
+        let listings = futures::future::join_all(
+            self.providers.iter().map(|(_, registrar)| registrar.list_zones())
+        ).await;
+        let mut inventory = Inventory::default();
+        let mut owners = HashMap::new();
+        for ((provider, _), listing) in self.providers.iter().zip(listings) {
+            match listing {
+                Ok(zones) => for zone in zones {
+                    let key = normalize(&zone);
+                    match owners.get(&key) {
+                        Some(owner) => log::warn!("zone {} is hosted by both {} and {}; using {}", zone, owner, provider, owner),
+                        None => {
+                            owners.insert(key, provider.clone());
+                        },
+                    }
+                    inventory.zones.push(ZoneOrigin { zone, provider: provider.clone() });
+                },
+                Err(error) => {
+                    log::warn!("failed to list zones of {}: {}", provider, error);
+                    inventory.failed.push((provider.clone(), error));
+                },
+            }
+        }
+        // Keep what is known about providers that failed this time.
+        let mut cache = self.owners.write().unwrap();
+        cache.retain(|_, owner| inventory.failed.iter().any(|(failed, _)| failed == owner));
+        cache.extend(owners);
+        inventory
+    }
+
+    /// Find out which provider hosts the given zone.
+    pub async fn owner(&self, zone: &str) -> Result<String> {
+        let key = normalize(zone);
+        if let Some(owner) = self.owners.read().unwrap().get(&key) {
+            return Ok(owner.clone());
+        }
+        let inventory = self.inventory().await;
+        if let Some(owner) = self.owners.read().unwrap().get(&key) {
+            return Ok(owner.clone());
+        }
+        // The zone might be hosted by a provider that could not be reached.
+        match inventory.failed.into_iter().next() {
+            Some((_, error)) => Err(error),
+            None => Err(Error::ZoneNotFound(zone.to_string())),
+        }
+    }
+
+    async fn route(&self, zone: &str) -> Result<&dyn Registrar> {
+        let owner = self.owner(zone).await?;
+        self.get(&owner).ok_or(Error::ZoneNotFound(zone.to_string()))
+    }
+
+}
+
+#[async_trait]
+impl Registrar for Aggregate {
+
+    /// Identifiers of records are only guaranteed to survive an update if they do so for all
+    /// providers.
+    fn record_identity(&self) -> RecordIdentity {
+        if self.providers.iter().all(|(_, registrar)| registrar.record_identity() == RecordIdentity::Native) {
+            RecordIdentity::Native
+        } else {
+            RecordIdentity::Content
+        }
+    }
+
+    /// Without a zone to route by, a type only counts as supported if every provider accepts it.
+    fn supports_type(&self, ty: &DnsRecordType) -> bool {
+        self.providers.iter().all(|(_, registrar)| registrar.supports_type(ty))
+    }
+
+    /// Unlike [`inventory`](Aggregate::inventory), this fails if any of the providers fails, and
+    /// zones that are hosted by more than one provider are only listed once.
+    async fn list_zones(&self) -> Result<Vec<String>> {
+        let inventory = self.inventory().await;
+        if let Some((_, error)) = inventory.failed.into_iter().next() {
+            return Err(error);
+        }
+        let mut zones: Vec<String> = Vec::new();
+        for origin in inventory.zones {
+            if !zones.iter().any(|zone| normalize(zone) == normalize(&origin.zone)) {
+                zones.push(origin.zone);
+            }
+        }
+        Ok(zones)
+    }
+
+    async fn list_records_with_ids(&self, zone: &str) -> Result<Vec<(DnsRecordId, DnsRecord)>> {
+        self.route(zone).await?.list_records_with_ids(zone).await
+    }
+
+    async fn create_record(&self, zone: &str, record: &DnsRecord) -> Result<DnsRecordId> {
+        self.route(zone).await?.create_record(zone, record).await
+    }
+
+    async fn update_record(&self, zone: &str, id: &str, record: &DnsRecord) -> Result<DnsRecordId> {
+        self.route(zone).await?.update_record(zone, id, record).await
+    }
+
+    async fn delete_record(&self, zone: &str, id: &str) -> Result<()> {
+        self.route(zone).await?.delete_record(zone, id).await
+    }
+
+    async fn name_servers(&self, zone: &str) -> Result<Vec<String>> {
+        self.route(zone).await?.name_servers(zone).await
+    }
+
+    /// Like [`set_name_servers`](Self::set_name_servers), this asks each provider in turn until
+    /// one of them knows the domain.
+    async fn delegated_name_servers(&self, domain: &str) -> Result<Vec<String>> {
+        let mut failure = None;
+        for (_, registrar) in &self.providers {
+            match registrar.delegated_name_servers(domain).await {
+                Ok(name_servers) => return Ok(name_servers),
+                Err(Error::Unsupported(_) | Error::ZoneNotFound(_)) => {},
+                Err(error) => {
+                    failure.get_or_insert(error);
+                },
+            }
+        }
+        Err(failure.unwrap_or_else(|| Error::ZoneNotFound(domain.to_string())))
+    }
+
+    /// The delegation of a domain is changed at the registrar where it is registered, which need
+    /// not be the provider that hosts its zone. Each provider is tried in turn until one of them
+    /// accepts the change.
+    async fn set_name_servers(&self, domain: &str, name_servers: &[String]) -> Result<()> {
+        let mut failure = None;
+        for (_, registrar) in &self.providers {
+            match registrar.set_name_servers(domain, name_servers).await {
+                Ok(()) => return Ok(()),
+                Err(Error::Unsupported(_) | Error::ZoneNotFound(_)) => {},
+                Err(error) => {
+                    failure.get_or_insert(error);
+                },
+            }
+        }
+        Err(failure.unwrap_or_else(|| Error::ZoneNotFound(domain.to_string())))
+    }
+
+}
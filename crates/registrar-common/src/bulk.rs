@@ -0,0 +1,150 @@
+
+use std::future::Future;
+
+use futures::StreamExt;
+
+use crate::{Error, Registrar, Result};
+
+const DEFAULT_CONCURRENCY: usize = 8;
+
+/// The outcome of running an operation against many DNS zones with [`Bulk`].
+#[derive(Debug)]
+pub struct BulkReport<T> {
+    /// Zones for which the operation completed, together with the value it produced.
+    pub succeeded: Vec<(String, T)>,
+    /// Zones for which the operation returned an error.
+    pub failed: Vec<(String, Error)>,
+    /// Zones for which the operation decided there was nothing to do.
+    pub skipped: Vec<String>,
+}
+
+impl<T> Default for BulkReport<T> {
+    fn default() -> Self {
+        Self {
+            succeeded: Vec::new(),
+            failed: Vec::new(),
+            skipped: Vec::new(),
+        }
+    }
+}
+
+impl<T> BulkReport<T> {
+
+    /// Return `true` if the operation did not fail for any of the zones.
+    pub fn is_success(&self) -> bool {
+        self.failed.is_empty()
+    }
+
+    /// The total amount of zones that were visited.
+    pub fn len(&self) -> usize {
+        self.succeeded.len() + self.failed.len() + self.skipped.len()
+    }
+
+    /// Return `true` if no zones were visited at all.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+}
+
+/// Runs an operation against many DNS zones of a [`Registrar`] at the same time.
+///
+/// The operation receives the registrar and the name of a zone. It returns `Ok(Some(value))` when
+/// the zone was processed, `Ok(None)` when the zone should be reported as skipped and `Err(error)`
+/// when processing the zone failed. A failure does not stop the other zones from being processed.
+///
+/// ```no_run
+/// use registrar_common::{Bulk, DnsRecord, DnsRecordType};
+///
+/// let record = DnsRecord {
+///     name: "_verify".to_string(),
+///     ty: DnsRecordType::TXT,
+///     content: "token=123".to_string(),
+///     ttl: None,
+///     priority: None,
+/// };
+/// let record = &record;
+///
+/// let report = Bulk::new(&client)
+///     .concurrency(16)
+///     .run_all(|registrar, zone| async move {
+///         registrar.create_record(&zone, record).await.map(Some)
+///     })
+///     .await?;
+///
+/// for (zone, error) in &report.failed {
+///     eprintln!("Could not add record to {}: {}", zone, error);
+/// }
+/// ```
+pub struct Bulk<'r, R: ?Sized> {
+    registrar: &'r R,
+    concurrency: usize,
+}
+
+impl<'r, R: Registrar + ?Sized> Bulk<'r, R> {
+
+    /// Prepare to run operations against zones of the given registrar.
+    pub fn new(registrar: &'r R) -> Self {
+        Self {
+            registrar,
+            concurrency: DEFAULT_CONCURRENCY,
+        }
+    }
+
+    /// Limit the amount of zones that are processed at the same time.
+    ///
+    /// A value of zero is treated as one.
+    pub fn concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = concurrency.max(1);
+        self
+    }
+
+    /// Run the operation against each of the given zones.
+    ///
+    /// Zones are processed in no particular order, so the entries in the report may be in a
+    /// different order than the one in which they were given.
+    pub async fn run<I, S, F, Fut, T>(&self, zones: I, operation: F) -> BulkReport<T>
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+        F: Fn(&'r R, String) -> Fut,
+        Fut: Future<Output = Result<Option<T>>>,
+    {
+        let registrar = self.registrar;
+        let operation = &operation;
+        let mut outcomes = futures::stream::iter(zones)
+            .map(|zone| {
+                let zone = zone.into();
+                async move {
+                    let outcome = operation(registrar, zone.clone()).await;
+                    (zone, outcome)
+                }
+            })
+            .buffer_unordered(self.concurrency);
+        let mut report = BulkReport::default();
+        while let Some((zone, outcome)) = outcomes.next().await {
+            match outcome {
+                Ok(Some(value)) => report.succeeded.push((zone, value)),
+                Ok(None) => report.skipped.push(zone),
+                Err(error) => {
+                    log::warn!("operation failed for zone {}: {}", zone, error);
+                    report.failed.push((zone, error));
+                },
+            }
+        }
+        report
+    }
+
+    /// Run the operation against every zone the registrar manages.
+    ///
+    /// This only fails if the list of zones could not be retrieved.
+    pub async fn run_all<F, Fut, T>(&self, operation: F) -> Result<BulkReport<T>>
+    where
+        F: Fn(&'r R, String) -> Fut,
+        Fut: Future<Output = Result<Option<T>>>,
+    {
+        let zones = self.registrar.list_zones().await?;
+        Ok(self.run(zones, operation).await)
+    }
+
+}
@@ -0,0 +1,212 @@
+
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::{Error, Result};
+
+/// Keys of JSON objects whose values are never written to a cassette.
+pub const REDACTED_KEYS: &[&str] = &[ "apikey", "secretapikey", "password", "token" ];
+
+const REDACTED: &str = "REDACTED";
+
+/// Whether a [`Cassette`] is capturing real traffic or playing it back.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CassetteMode {
+    Record,
+    Replay,
+}
+
+/// A single request together with the response the API gave to it.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Interaction {
+    pub method: String,
+    /// The path and query of the request, relative to the base URL of the API.
+    pub path: String,
+    pub body: Value,
+    pub response: Value,
+}
+
+#[derive(Default, Serialize, Deserialize)]
+struct CassetteFile {
+    interactions: Vec<Interaction>,
+}
+
+struct CassetteState {
+    interactions: Vec<Interaction>,
+    used: Vec<bool>,
+    dirty: bool,
+}
+
+/// Records HTTP interactions with an API to a JSON file and plays them back later on.
+///
+/// Pass a cassette to the builder of an API client. In [record mode](CassetteMode::Record), the
+/// client talks to the API as usual and every request is appended to the cassette. In [replay
+/// mode](CassetteMode::Replay), the client does not touch the network at all. Instead, requests
+/// are matched on their method, path and body against the ones on the cassette, and the recorded
+/// response is returned.
+///
+/// Credentials such as passwords, tokens and API keys are [redacted](REDACTED_KEYS) before
+/// anything is written to disk.
+///
+/// ```no_run
+/// use std::sync::Arc;
+/// use registrar_common::Cassette;
+///
+/// let cassette = Arc::new(Cassette::record("tests/cassettes/list_zones.json"));
+/// let client = openprovider::Builder::new()
+///     .cassette(cassette.clone())
+///     .build();
+///
+/// // ... make some requests ...
+///
+/// cassette.save()?;
+/// ```
+pub struct Cassette {
+    path: PathBuf,
+    mode: CassetteMode,
+    state: Mutex<CassetteState>,
+}
+
+/// Replace the values of all keys in [`REDACTED_KEYS`] with a placeholder.
+pub fn redact(value: &Value) -> Value {
+    match value {
+        Value::Object(map) => Value::Object(
+            map.iter()
+                .map(|(key, value)| {
+                    let value = if REDACTED_KEYS.contains(&key.as_str()) {
+                        Value::String(REDACTED.to_string())
+                    } else {
+                        redact(value)
+                    };
+                    (key.clone(), value)
+                })
+                .collect()
+        ),
+        Value::Array(values) => Value::Array(values.iter().map(redact).collect()),
+        _ => value.clone(),
+    }
+}
+
+/// Bring a request body in a form that is suitable for comparison.
+fn normalize(body: Option<&Value>) -> Value {
+    match body {
+        None => Value::Null,
+        Some(Value::Object(map)) if map.is_empty() => Value::Null,
+        Some(value) => redact(value),
+    }
+}
+
+impl Cassette {
+
+    /// Create an empty cassette that will be written to the given path.
+    pub fn record<P: Into<PathBuf>>(path: P) -> Self {
+        Self {
+            path: path.into(),
+            mode: CassetteMode::Record,
+            state: Mutex::new(CassetteState {
+                interactions: Vec::new(),
+                used: Vec::new(),
+                dirty: false,
+            }),
+        }
+    }
+
+    /// Load a cassette that was recorded earlier so that it can be played back.
+    pub fn replay<P: Into<PathBuf>>(path: P) -> Result<Self> {
+        let path = path.into();
+        let file: CassetteFile = serde_json::from_str(&std::fs::read_to_string(&path)?)?;
+        let used = vec![false; file.interactions.len()];
+        Ok(Self {
+            path,
+            mode: CassetteMode::Replay,
+            state: Mutex::new(CassetteState {
+                interactions: file.interactions,
+                used,
+                dirty: false,
+            }),
+        })
+    }
+
+    pub fn mode(&self) -> CassetteMode {
+        self.mode
+    }
+
+    pub fn is_replaying(&self) -> bool {
+        self.mode == CassetteMode::Replay
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Get a copy of all interactions that are on this cassette.
+    pub fn interactions(&self) -> Vec<Interaction> {
+        self.state.lock().unwrap().interactions.clone()
+    }
+
+    /// Append an interaction to the cassette.
+    ///
+    /// Both the request body and the response are redacted.
+    pub fn push(&self, method: &str, path: &str, body: Option<&Value>, response: &Value) {
+        let mut state = self.state.lock().unwrap();
+        state.interactions.push(Interaction {
+            method: method.to_string(),
+            path: path.to_string(),
+            body: normalize(body),
+            response: redact(response),
+        });
+        state.used.push(false);
+        state.dirty = true;
+    }
+
+    /// Find the response to a request.
+    ///
+    /// Each recorded interaction is only used once, so that requests that are made multiple times
+    /// receive their responses in the order they were recorded.
+    pub fn find(&self, method: &str, path: &str, body: Option<&Value>) -> Result<Value> {
+        let body = normalize(body);
+        let mut state = self.state.lock().unwrap();
+        let state = &mut *state;
+        let index = state.interactions.iter()
+            .zip(state.used.iter())
+            .position(|(interaction, used)| {
+                !used
+                    && interaction.method == method
+                    && interaction.path == path
+                    && interaction.body == body
+            })
+            .ok_or_else(|| Error::Generic(format!("no interaction on cassette {} matches {} {}", self.path.display(), method, path)))?;
+        state.used[index] = true;
+        Ok(state.interactions[index].response.clone())
+    }
+
+    /// Write all recorded interactions to disk.
+    ///
+    /// This is also done automatically when a recording cassette is dropped, but errors are only
+    /// logged in that case.
+    pub fn save(&self) -> Result<()> {
+        let mut state = self.state.lock().unwrap();
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let file = CassetteFile { interactions: state.interactions.clone() };
+        std::fs::write(&self.path, serde_json::to_string_pretty(&file)?)?;
+        state.dirty = false;
+        Ok(())
+    }
+
+}
+
+impl Drop for Cassette {
+    fn drop(&mut self) {
+        let dirty = self.state.lock().map(|state| state.dirty).unwrap_or(false);
+        if self.mode == CassetteMode::Record && dirty {
+            if let Err(error) = self.save() {
+                log::error!("failed to save cassette {}: {}", self.path.display(), error);
+            }
+        }
+    }
+}
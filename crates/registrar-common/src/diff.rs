@@ -0,0 +1,72 @@
+
+use crate::{DnsRecord, DnsRecordId};
+
+/// Check whether two records are the same, ignoring their TTL and differences in how providers
+/// write names.
+pub fn same_record(a: &DnsRecord, b: &DnsRecord) -> bool {
+    a.name.eq_ignore_ascii_case(&b.name)
+        && a.ty == b.ty
+        && a.content.trim_end_matches('.') == b.content.trim_end_matches('.')
+        && a.priority == b.priority
+}
+
+/// A single step that brings the records of a zone closer to what they should be.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Change {
+    /// Add a record that is missing.
+    Create(DnsRecord),
+    /// Give a record that is present another TTL.
+    Update {
+        id: DnsRecordId,
+        from: DnsRecord,
+        to: DnsRecord,
+    },
+    /// Remove a record that should not be there.
+    Delete {
+        id: DnsRecordId,
+        record: DnsRecord,
+    },
+}
+
+impl Change {
+
+    /// The record as it will be after the change, or as it was before it was deleted.
+    pub fn record(&self) -> &DnsRecord {
+        match self {
+            Change::Create(record) => record,
+            Change::Update { to, .. } => to,
+            Change::Delete { record, .. } => record,
+        }
+    }
+
+}
+
+/// Work out which changes turn the `actual` records of a zone into the `desired` ones.
+///
+/// Records are matched with [`same_record`], so only the TTL of a record is ever updated. Any
+/// other difference results in the old record being deleted and the new one being created.
+/// Desired records without a TTL match whatever TTL the actual record has.
+///
+/// Deletions come first, so that a record which may only exist once, such as a CNAME, can be
+/// replaced.
+pub fn diff(desired: &[DnsRecord], actual: &[(DnsRecordId, DnsRecord)]) -> Vec<Change> {
+    let mut unmatched: Vec<&(DnsRecordId, DnsRecord)> = actual.iter().collect();
+    let mut creates = Vec::new();
+    let mut updates = Vec::new();
+    for record in desired {
+        match unmatched.iter().position(|(_, other)| same_record(other, record)) {
+            Some(index) => {
+                let (id, other) = unmatched.remove(index);
+                if record.ttl.is_some() && record.ttl != other.ttl {
+                    updates.push(Change::Update { id: id.clone(), from: other.clone(), to: record.clone() });
+                }
+            },
+            None => creates.push(Change::Create(record.clone())),
+        }
+    }
+    unmatched.into_iter()
+        .map(|(id, record)| Change::Delete { id: id.clone(), record: record.clone() })
+        .chain(updates)
+        .chain(creates)
+        .collect()
+}
@@ -2,29 +2,7 @@
 use http::{header::{InvalidHeaderName, InvalidHeaderValue, MaxSizeReached}, status::InvalidStatusCode, uri::InvalidUri};
 use serde_json::{Number, Value};
 
-use crate::{NumberType, ValueType};
-
-// #[derive(Clone, Debug)]
-// pub enum HttpErrorKind {
-//     /// Error is from a type Builder
-//     Builder,
-//     /// The request or response body has issues
-//     Body,
-//     /// Unparseable response status
-//     Status,
-//     /// Related to decoding the response's body
-//     Decode,
-//     /// Broken redirect policy
-//     Redirect,
-//     /// Connection issues
-//     Connect,
-//     /// HTTP request issues
-//     Request,
-//     /// Related to a timeout
-//     Timeout,
-//     /// Other kinds that aren't directly handled by this library
-//     Unknown,
-// }
+use crate::{DnsRecordId, DnsRecordType, NumberType, ValueType};
 
 #[derive(Debug)]
 pub enum HttpErrorKind {
@@ -36,6 +14,19 @@ pub enum HttpErrorKind {
     Closed,
     IncCmpleteMessage,
     BodyWriteAborted,
+    /// Error is from a type Builder
+    Builder,
+    /// The request or response body has issues
+    Body,
+    /// Related to decoding the response's body
+    Decode,
+    /// Broken redirect policy
+    Redirect,
+    /// Connection issues
+    Connect,
+    /// HTTP request issues
+    Request,
+    /// Connecting, reading or the request as a whole took longer than allowed
     Timeout,
     Unknown,
 }
@@ -121,6 +112,18 @@ pub enum Error {
     /// and converting to/from structs.
     OtherJson(serde_json::Error),
 
+    /// The service does not support records of the given type.
+    UnsupportedRecordType(DnsRecordType),
+
+    /// There is no record with the given identifier in the zone.
+    RecordNotFound(DnsRecordId),
+
+    /// The zone does not exist, or none of the services that were asked hosts it.
+    ZoneNotFound(String),
+
+    /// The service does not support the described operation.
+    Unsupported(String),
+
     /// Any other error stored as a human-readable error message.
     Generic(String),
 }
@@ -133,6 +136,7 @@ impl std::fmt::Display for Error {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             Self::AuthenticationFailed => write!(f, "failed to authenticate with API"),
+            Self::MissingPath => write!(f, "no path was given for the request"),
             Self::Http { message, .. } => write!(f, "{}", message),
             Self::Api { code, message } => {
                 write!(f, "API returned with error: {}", message)?;
@@ -141,14 +145,24 @@ impl std::fmt::Display for Error {
                 }
                 Ok(())
             },
+            Self::StatusCode(status) => write!(f, "server responded with HTTP status {}", status),
+            Self::ParseStatusCode => write!(f, "invalid HTTP status code"),
+            Self::Method => write!(f, "invalid HTTP method"),
+            Self::HeaderName => write!(f, "invalid HTTP header name"),
+            Self::HeaderValue => write!(f, "invalid HTTP header value"),
+            Self::MaxSizeReached => write!(f, "too many HTTP headers"),
+            Self::InvalidUri(message) => write!(f, "invalid URI: {}", message),
             Self::Io(error) => write!(f, "input/output error: {}", error),
             Self::KeyMissing(key) => write!(f, "key '{}' is missing in JSON-value", key),
             Self::WrongType(_value, expected) => write!(f, "expected {} but got another JSON-value", expected),
             Self::WrongNumberType(_value, expected) => write!(f, "expected {} but got another type of number", expected),
             Self::IndexOutOfBounds(index) => write!(f, "index {} is out of bounds in JSON-array", index),
             Self::OtherJson(error) => write!(f, "{}", error),
+            Self::UnsupportedRecordType(ty) => write!(f, "records of type {} are not supported", ty),
+            Self::RecordNotFound(id) => write!(f, "record {} does not exist", id),
+            Self::ZoneNotFound(zone) => write!(f, "zone {} does not exist", zone),
+            Self::Unsupported(operation) => write!(f, "{} is not supported", operation),
             Self::Generic(message) => write!(f, "{}", message),
-            _ => todo!(),
         }
     }
 
@@ -231,29 +245,38 @@ impl From<hyper::Error> for Error {
 
 }
 
-// impl From<reqwest::Error> for Error {
-
-//     fn from(error: reqwest::Error) -> Self {
-//         let kind = if error.is_body() {
-//             HttpErrorKind::Body
-//         } else if error.is_request() {
-//             HttpErrorKind::Request
-//         } else if error.is_status() {
-//             HttpErrorKind::Status
-//         } else if error.is_decode() {
-//             HttpErrorKind::Decode
-//         } else if error.is_builder() {
-//             HttpErrorKind::Builder
-//         } else if error.is_timeout() {
-//             HttpErrorKind::Timeout
-//         } else if error.is_connect() {
-//             HttpErrorKind::Connect
-//         } else if error.is_redirect() {
-//             HttpErrorKind::Redirect
-//         } else {
-//             HttpErrorKind::Unknown
-//         };
-//         Error::Http { kind, message: error.to_string() }
-//     }
-
-// }
+impl From<reqwest::Error> for Error {
+
+    fn from(error: reqwest::Error) -> Self {
+        let kind = if error.is_timeout() {
+            HttpErrorKind::Timeout
+        } else if error.is_body() {
+            HttpErrorKind::Body
+        } else if error.is_decode() {
+            HttpErrorKind::Decode
+        } else if error.is_builder() {
+            HttpErrorKind::Builder
+        } else if error.is_connect() {
+            HttpErrorKind::Connect
+        } else if error.is_redirect() {
+            HttpErrorKind::Redirect
+        } else if error.is_request() {
+            HttpErrorKind::Request
+        } else {
+            HttpErrorKind::Unknown
+        };
+        Error::Http { kind, message: error.to_string() }
+    }
+
+}
+
+impl From<tokio::time::error::Elapsed> for Error {
+
+    fn from(_: tokio::time::error::Elapsed) -> Self {
+        Error::Http {
+            kind: HttpErrorKind::Timeout,
+            message: "operation timed out".to_owned(),
+        }
+    }
+
+}
@@ -0,0 +1,34 @@
+
+use crate::{DnsRecord, DnsRecordId};
+
+/// How a [`Registrar`](crate::Registrar) addresses individual records.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RecordIdentity {
+    /// The service assigns an identifier to each record, which stays the same when the record is
+    /// updated.
+    Native,
+    /// The service has no identifiers of its own, so they are derived from the contents of records
+    /// using [`content_id`]. Updating a record changes its identifier.
+    Content,
+}
+
+const FNV_OFFSET_BASIS: u64 = 0xcbf29ce484222325;
+const FNV_PRIME: u64 = 0x100000001b3;
+
+/// Derive an identifier from the name, type, content and priority of a record.
+///
+/// The identifier is the same between runs and on every machine. The TTL is not part of it, since
+/// some services fill in a default when a record is created without one.
+pub fn content_id(record: &DnsRecord) -> DnsRecordId {
+    let priority = record.priority.map(|priority| priority.to_string()).unwrap_or_default();
+    let fields = [record.name.as_str(), &record.ty.to_string(), &record.content, &priority];
+    let mut hash = FNV_OFFSET_BASIS;
+    for field in fields {
+        // The separator keeps fields from running into each other.
+        for byte in field.bytes().chain([0]) {
+            hash ^= byte as u64;
+            hash = hash.wrapping_mul(FNV_PRIME);
+        }
+    }
+    format!("{:016x}", hash)
+}
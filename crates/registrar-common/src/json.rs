@@ -80,7 +80,7 @@ impl IndexExt for str {
 
 }
 
-impl<'a, T> IndexExt for &'a T
+impl<T> IndexExt for &T
 where
     T: ?Sized + IndexExt,
 {
@@ -170,7 +170,7 @@ impl ValueExt for serde_json::Value {
 
     fn as_u32_ok(&self) -> Result<u32> {
         match self {
-            Value::Number(num) if num.is_u64() && num.as_u64().unwrap() < (std::u32::MAX as u64) => Ok(num.as_u64().unwrap() as u32),
+            Value::Number(num) if num.is_u64() && num.as_u64().unwrap() < (u32::MAX as u64) => Ok(num.as_u64().unwrap() as u32),
             Value::Number(num) if num.is_u64() => Err(Error::WrongNumberType(num.clone(), NumberType::U32)),
             Value::Number(num) => Err(Error::WrongNumberType(num.clone(), NumberType::U64)),
             _ => Err(Error::WrongType(self.clone(), ValueType::Number))
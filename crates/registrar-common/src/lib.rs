@@ -2,7 +2,27 @@
 mod io_result_ext;
 mod json;
 mod error;
-
+mod bulk;
+mod memory;
+mod cassette;
+mod identity;
+mod aggregate;
+mod migrate;
+mod diff;
+mod mirror;
+mod snapshot;
+mod zonefile;
+mod secret_store;
+mod acme;
+mod retry;
+
+#[cfg(feature = "mock")]
+pub mod mock;
+
+use std::future::Future;
+use std::time::Duration;
+
+use async_trait::async_trait;
 use http::{Method, Request};
 use http_body_util::{BodyExt, Empty};
 use hyper::body::Bytes;
@@ -10,13 +30,31 @@ use hyper_util::rt::TokioIo;
 pub use io_result_ext::*;
 pub use json::*;
 pub use error::*;
+pub use bulk::*;
+pub use memory::*;
+pub use cassette::*;
+pub use identity::*;
+pub use aggregate::*;
+pub use migrate::*;
+pub use diff::*;
+pub use mirror::*;
+pub use snapshot::*;
+pub use zonefile::*;
+pub use secret_store::*;
+pub use acme::*;
+pub use retry::*;
+use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use tokio::net::TcpStream;
 
-const DEFAULT_MAX_RETRIES: u32 = 5;
+/// How often clients send a request again when it failed in a [transient](Error::is_transient)
+/// way, unless they are told otherwise.
+pub const DEFAULT_MAX_RETRIES: u32 = 5;
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
 pub enum DnsRecordType {
+    /// IPv4 host address
+    A,
     /// IPv6 host address
     AAAA,
     /// Auto resolved alias
@@ -69,17 +107,79 @@ pub enum DnsRecordType {
     RP,
     /// Transport Layer Security Authentication
     TLSA,
+    /// Sender Policy Framework
+    SPF,
+    /// SSH key fingerprint
+    SSHFP,
+    /// General purpose service binding
+    SVCB,
+    /// S/MIME certificate association
+    SMIMEA,
+    /// Uniform Resource Identifier
+    URI,
 }
 
-#[derive(Clone, Debug)]
+impl std::fmt::Display for DnsRecordType {
+
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self)
+    }
+
+}
+
+impl std::str::FromStr for DnsRecordType {
+
+    type Err = Error;
+
+    /// Parse the name of a record type, ignoring case.
+    fn from_str(s: &str) -> Result<Self> {
+        serde_json::from_value(Value::String(s.to_ascii_uppercase()))
+            .map_err(|_| Error::Generic(format!("unknown record type {}", s)))
+    }
+
+}
+
+/// A DNS record as understood by every [`Registrar`].
+///
+/// The name is relative to the zone the record belongs to. The empty string refers to the zone
+/// apex.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub struct DnsRecord {
     pub name: String,
+    #[serde(rename = "type", alias = "ty")]
     pub ty: DnsRecordType,
     pub content: String,
     pub ttl: Option<u32>,
     pub priority: Option<u32>,
 }
 
+impl DnsRecord {
+
+    /// Create a record without a priority that uses the default TTL of the service.
+    pub fn new<S1: Into<String>, S2: Into<String>>(name: S1, ty: DnsRecordType, content: S2) -> Self {
+        Self {
+            name: name.into(),
+            ty,
+            content: content.into(),
+            ttl: None,
+            priority: None,
+        }
+    }
+
+    /// Give the record its own time to live.
+    pub fn ttl(mut self, ttl: u32) -> Self {
+        self.ttl = Some(ttl);
+        self
+    }
+
+    /// Give the record a priority, as MX and SRV records have.
+    pub fn priority(mut self, priority: u32) -> Self {
+        self.priority = Some(priority);
+        self
+    }
+
+}
+
 /// Represents the category of all computations that may fail in this library.
 pub type Result<T> = std::result::Result<T, Error>;
 
@@ -110,6 +210,27 @@ impl PageHint {
 pub struct ApiClient {
     base_url: String,
     max_retries: u32,
+    timeouts: Timeouts,
+}
+
+/// The different deadlines that can be imposed on a single HTTP request.
+///
+/// A value of `None` means that no deadline is enforced for that phase of the request.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Timeouts {
+    /// How long establishing the connection with the server may take.
+    pub connect: Option<Duration>,
+    /// How long we may wait on the server to send us (more of) the response.
+    pub read: Option<Duration>,
+    /// How long the request may take from start to finish.
+    pub total: Option<Duration>,
+}
+
+async fn with_timeout<F: Future>(timeout: Option<Duration>, future: F) -> Result<F::Output> {
+    match timeout {
+        None => Ok(future.await),
+        Some(duration) => Ok(tokio::time::timeout(duration, future).await?),
+    }
 }
 
 impl ApiClient {
@@ -118,18 +239,43 @@ impl ApiClient {
         Self {
             base_url: base_url.into(),
             max_retries: DEFAULT_MAX_RETRIES,
+            timeouts: Timeouts::default(),
         }
     }
 
+    /// Limit the amount of times a request is retried when it failed in a
+    /// [transient](Error::is_transient) way.
     pub fn max_retries(mut self, max_retries: u32) -> Self {
         self.max_retries = max_retries;
         self
     }
 
+    /// Abort a request when the connection could not be established within the given duration.
+    pub fn connect_timeout(mut self, timeout: Duration) -> Self {
+        self.timeouts.connect = Some(timeout);
+        self
+    }
+
+    /// Abort a request when the server did not send anything back within the given duration.
+    pub fn read_timeout(mut self, timeout: Duration) -> Self {
+        self.timeouts.read = Some(timeout);
+        self
+    }
+
+    /// Abort a request when it did not complete within the given duration.
+    ///
+    /// Individual requests can override this value using [`ApiRequest::timeout`].
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeouts.total = Some(timeout);
+        self
+    }
+
     pub fn get<S: AsRef<str>>(&self, path: S) -> ApiRequest {
         ApiRequest::new(
             format!("{}{}", self.base_url, path.as_ref()),
-            Method::GET
+            Method::GET,
+            self.max_retries,
+            self.timeouts,
         )
     }
 
@@ -139,19 +285,41 @@ pub struct ApiRequest {
     url: String,
     method: Method,
     bearer: Option<String>,
+    max_retries: u32,
+    timeouts: Timeouts,
 }
 
 impl ApiRequest {
 
-    fn new(url: String, method: Method) -> Self {
+    fn new(url: String, method: Method, max_retries: u32, timeouts: Timeouts) -> Self {
         Self {
             url,
             method,
             bearer: None,
+            max_retries,
+            timeouts,
         }
     }
 
-    pub async fn send<S: AsRef<str>>(&self) -> Result<Value> {
+    /// Authorize this request using the given bearer token.
+    pub fn bearer<S: Into<String>>(mut self, token: S) -> Self {
+        self.bearer = Some(token.into());
+        self
+    }
+
+    /// Override the overall deadline of the [client](ApiClient) for this request only.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeouts.total = Some(timeout);
+        self
+    }
+
+    pub async fn send(&self) -> Result<Value> {
+        retry(self.max_retries, || async {
+            with_timeout(self.timeouts.total, self.send_without_deadline()).await?
+        }).await
+    }
+
+    async fn send_without_deadline(&self) -> Result<Value> {
 
         log::info!("{} {}", self.method, self.url);
 
@@ -166,45 +334,121 @@ impl ApiRequest {
             Some(port) => port.as_u16(),
         };
 
-        let address = format!("{}://{}:{}", url.scheme_str().unwrap(), url.host().unwrap(), port);
+        let address = format!("{}:{}", url.host().unwrap(), port);
 
-        eprintln!("{}", address);
-
-        let stream = TcpStream::connect(address).await?;
+        let stream = with_timeout(self.timeouts.connect, TcpStream::connect(address)).await??;
         let io = TokioIo::new(stream);
 
         // Create the Hyper client
-        let (mut sender, conn) = hyper::client::conn::http1::handshake(io).await?;
+        let (mut sender, conn) = with_timeout(self.timeouts.connect, hyper::client::conn::http1::handshake(io)).await??;
 
-        // Wait for the connection to be established
-        conn.await?;
+        // Drive the connection in the background while we are using it
+        tokio::spawn(async move {
+            if let Err(error) = conn.await {
+                log::warn!("connection failed: {}", error);
+            }
+        });
 
         // Create an HTTP request with an empty body and a HOST header
-        let req = Request::builder()
+        let mut builder = Request::builder()
             .uri(&url)
             .method(self.method.clone())
-            .header(hyper::header::HOST, url.authority().ok_or(Error::InvalidUri("authority is missing".to_owned()))?.as_str())
-            .body(Empty::<Bytes>::new())?;
+            .header(hyper::header::HOST, url.authority().ok_or(Error::InvalidUri("authority is missing".to_owned()))?.as_str());
+        if let Some(token) = &self.bearer {
+            builder = builder.header(hyper::header::AUTHORIZATION, format!("Bearer {}", token));
+        }
+        let req = builder.body(Empty::<Bytes>::new())?;
 
         // Await the response...
-        let res = sender.send_request(req).await?;
+        let res = with_timeout(self.timeouts.read, sender.send_request(req)).await??;
 
         let status = res.status().as_u16();
-        if status < 200 || status > 300 {
+        if !(200..300).contains(&status) {
             return Err(Error::StatusCode(status));
         }
 
-        let data = res.into_body().collect().await?.to_bytes();
+        let data = with_timeout(self.timeouts.read, res.into_body().collect()).await??.to_bytes();
         Ok(serde_json::from_slice(&data)?)
     }
 
 }
 
-pub trait Registrar {
+/// A service that hosts DNS zones, such as a domain registrar or a DNS provider.
+///
+/// Implementations are expected to be cheap to share between tasks, which is why all methods take
+/// `&self`.
+#[async_trait]
+pub trait Registrar: Send + Sync {
+
+    /// List the names of all DNS zones that are managed by this service.
+    async fn list_zones(&self) -> Result<Vec<String>>;
+
+    /// How this service addresses individual records.
+    ///
+    /// Generic code uses this to find out whether the identifier of a record survives an update.
+    fn record_identity(&self) -> RecordIdentity {
+        RecordIdentity::Native
+    }
 
-    fn list_records(&mut self, hint: &PageHint) -> Result<Vec<DnsRecord>>;
+    /// Whether this service accepts records of the given type.
+    ///
+    /// Generic code uses this to find out what would happen to a record without creating it.
+    fn supports_type(&self, _ty: &DnsRecordType) -> bool {
+        true
+    }
 
-    fn create_record<S: AsRef<str>>(&mut self, name: S, record: &DnsRecord) -> Result<DnsRecordId>;
+    /// List all records of the given DNS zone.
+    async fn list_records(&self, zone: &str) -> Result<Vec<DnsRecord>> {
+        Ok(self.list_records_with_ids(zone)
+            .await?
+            .into_iter()
+            .map(|(_, record)| record)
+            .collect())
+    }
+
+    /// List all records of the given DNS zone together with the identifiers that
+    /// [`update_record`](Self::update_record) and [`delete_record`](Self::delete_record) accept.
+    async fn list_records_with_ids(&self, zone: &str) -> Result<Vec<(DnsRecordId, DnsRecord)>>;
+
+    /// Add a new record to the given DNS zone.
+    async fn create_record(&self, zone: &str, record: &DnsRecord) -> Result<DnsRecordId>;
+
+    /// Replace the record with the given identifier.
+    ///
+    /// Returns the identifier of the updated record, which is only different from the given one
+    /// if the [identity](Self::record_identity) of records is derived from their contents.
+    async fn update_record(&self, zone: &str, id: &str, record: &DnsRecord) -> Result<DnsRecordId>;
+
+    /// Delete the record with the given identifier.
+    async fn delete_record(&self, zone: &str, id: &str) -> Result<()>;
+
+    /// The name servers that serve the given zone.
+    ///
+    /// By default, these are taken from the NS records at the zone apex.
+    async fn name_servers(&self, zone: &str) -> Result<Vec<String>> {
+        Ok(self.list_records(zone)
+            .await?
+            .into_iter()
+            .filter(|record| record.name.is_empty() && record.ty == DnsRecordType::NS)
+            .map(|record| record.content.trim_end_matches('.').to_string())
+            .collect())
+    }
+
+    /// The name servers a domain that was registered through this service is delegated to.
+    ///
+    /// Unlike [`name_servers`](Self::name_servers), this asks the registry rather than the zone,
+    /// so the two differ while a delegation change is underway. Services that only host zones do
+    /// not support this.
+    async fn delegated_name_servers(&self, domain: &str) -> Result<Vec<String>> {
+        Err(Error::Unsupported(format!("looking up the name servers of {}", domain)))
+    }
+
+    /// Delegate a domain that was registered through this service to the given name servers.
+    ///
+    /// Services that only host zones do not support this.
+    async fn set_name_servers(&self, domain: &str, _name_servers: &[String]) -> Result<()> {
+        Err(Error::Unsupported(format!("changing the name servers of {}", domain)))
+    }
 
 }
 
@@ -0,0 +1,235 @@
+
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use async_trait::async_trait;
+
+use crate::{DnsRecord, DnsRecordId, DnsRecordType, Error, Registrar, Result};
+
+#[derive(Default)]
+struct MemoryState {
+    zones: BTreeMap<String, Vec<(DnsRecordId, DnsRecord)>>,
+    delegations: BTreeMap<String, Vec<String>>,
+    fail_authentication: bool,
+    calls: u64,
+    next_id: u64,
+}
+
+/// A [`Registrar`] that keeps all of its zones and records in memory.
+///
+/// This is meant to test code that is written against the [`Registrar`] trait without having to
+/// talk to a real service. Faults can be injected to see how that code copes with a misbehaving
+/// service.
+///
+/// Clones share the same zones and records.
+///
+/// ```no_run
+/// use std::time::Duration;
+/// use registrar_common::MemoryRegistrar;
+///
+/// let registrar = MemoryRegistrar::new()
+///     .latency(Duration::from_millis(10))
+///     .fail_every(3);
+/// registrar.add_zone("example.com");
+/// ```
+#[derive(Clone, Default)]
+pub struct MemoryRegistrar {
+    state: Arc<Mutex<MemoryState>>,
+    latency: Option<Duration>,
+    fail_every: Option<u64>,
+    unsupported: Vec<DnsRecordType>,
+    dns_only: bool,
+}
+
+impl MemoryRegistrar {
+
+    /// Create a registrar without any zones.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Delay every operation by the given duration.
+    pub fn latency(mut self, latency: Duration) -> Self {
+        self.latency = Some(latency);
+        self
+    }
+
+    /// Make every `n`th operation fail with an [API error](Error::Api).
+    ///
+    /// A value of zero disables this fault.
+    pub fn fail_every(mut self, n: u64) -> Self {
+        self.fail_every = if n == 0 { None } else { Some(n) };
+        self
+    }
+
+    /// Refuse records of the given type with [`Error::UnsupportedRecordType`], like a service that
+    /// does not know about them.
+    pub fn unsupported_type(mut self, ty: DnsRecordType) -> Self {
+        self.unsupported.push(ty);
+        self
+    }
+
+    /// Act like a service that only hosts DNS, which refuses to change the delegation of domains
+    /// with [`Error::Unsupported`].
+    pub fn dns_only(mut self, enable: bool) -> Self {
+        self.dns_only = enable;
+        self
+    }
+
+    /// Make all operations fail with [`Error::AuthenticationFailed`] until this is turned off
+    /// again.
+    pub fn fail_authentication(&self, enable: bool) {
+        self.state.lock().unwrap().fail_authentication = enable;
+    }
+
+    /// Create an empty zone. Does nothing if the zone already exists.
+    pub fn add_zone<S: Into<String>>(&self, name: S) {
+        self.state.lock().unwrap().zones.entry(name.into()).or_default();
+    }
+
+    /// Add a record to a zone, bypassing any faults that were configured.
+    ///
+    /// The zone is created if it does not exist yet.
+    pub fn add_record<S: Into<String>>(&self, zone: S, record: DnsRecord) -> DnsRecordId {
+        let mut state = self.state.lock().unwrap();
+        state.next_id += 1;
+        let id = state.next_id.to_string();
+        state.zones.entry(zone.into()).or_default().push((id.clone(), record));
+        id
+    }
+
+    /// Get the records that are currently stored in the given zone, bypassing any faults that were
+    /// configured.
+    pub fn records<S: AsRef<str>>(&self, zone: S) -> Vec<DnsRecord> {
+        self.state.lock().unwrap()
+            .zones
+            .get(zone.as_ref())
+            .map(|records| records.iter().map(|(_, record)| record.clone()).collect())
+            .unwrap_or_default()
+    }
+
+    /// The name servers the given domain was delegated to with
+    /// [`set_name_servers`](Registrar::set_name_servers), if any.
+    pub fn delegation<S: AsRef<str>>(&self, domain: S) -> Option<Vec<String>> {
+        self.state.lock().unwrap().delegations.get(domain.as_ref()).cloned()
+    }
+
+    /// The amount of operations that were performed through the [`Registrar`] trait so far.
+    pub fn call_count(&self) -> u64 {
+        self.state.lock().unwrap().calls
+    }
+
+    fn check_type(&self, record: &DnsRecord) -> Result<()> {
+        if self.unsupported.contains(&record.ty) {
+            return Err(Error::UnsupportedRecordType(record.ty.clone()));
+        }
+        Ok(())
+    }
+
+    /// Simulate the latency and faults of a single call.
+    async fn call(&self) -> Result<()> {
+        if let Some(latency) = self.latency {
+            tokio::time::sleep(latency).await;
+        }
+        let mut state = self.state.lock().unwrap();
+        state.calls += 1;
+        if state.fail_authentication {
+            return Err(Error::AuthenticationFailed);
+        }
+        if let Some(n) = self.fail_every {
+            if state.calls.is_multiple_of(n) {
+                return Err(Error::Api {
+                    code: None,
+                    message: format!("injected failure on call {}", state.calls),
+                });
+            }
+        }
+        Ok(())
+    }
+
+}
+
+#[async_trait]
+impl Registrar for MemoryRegistrar {
+
+    fn supports_type(&self, ty: &DnsRecordType) -> bool {
+        !self.unsupported.contains(ty)
+    }
+
+    async fn list_zones(&self) -> Result<Vec<String>> {
+        self.call().await?;
+        Ok(self.state.lock().unwrap().zones.keys().cloned().collect())
+    }
+
+    async fn list_records_with_ids(&self, zone: &str) -> Result<Vec<(DnsRecordId, DnsRecord)>> {
+        self.call().await?;
+        let state = self.state.lock().unwrap();
+        let records = state.zones.get(zone).ok_or_else(|| Error::ZoneNotFound(zone.to_string()))?;
+        Ok(records.clone())
+    }
+
+    async fn create_record(&self, zone: &str, record: &DnsRecord) -> Result<DnsRecordId> {
+        self.call().await?;
+        self.check_type(record)?;
+        let mut state = self.state.lock().unwrap();
+        state.next_id += 1;
+        let id = state.next_id.to_string();
+        let records = state.zones.get_mut(zone).ok_or_else(|| Error::ZoneNotFound(zone.to_string()))?;
+        records.push((id.clone(), record.clone()));
+        Ok(id)
+    }
+
+    async fn update_record(&self, zone: &str, id: &str, record: &DnsRecord) -> Result<DnsRecordId> {
+        self.call().await?;
+        self.check_type(record)?;
+        let mut state = self.state.lock().unwrap();
+        let records = state.zones.get_mut(zone).ok_or_else(|| Error::ZoneNotFound(zone.to_string()))?;
+        let (_, stored) = records.iter_mut()
+            .find(|(other, _)| other == id)
+            .ok_or_else(|| Error::RecordNotFound(id.to_string()))?;
+        *stored = record.clone();
+        Ok(id.to_string())
+    }
+
+    async fn delete_record(&self, zone: &str, id: &str) -> Result<()> {
+        self.call().await?;
+        let mut state = self.state.lock().unwrap();
+        let records = state.zones.get_mut(zone).ok_or_else(|| Error::ZoneNotFound(zone.to_string()))?;
+        let count = records.len();
+        records.retain(|(other, _)| other != id);
+        if records.len() == count {
+            return Err(Error::RecordNotFound(id.to_string()));
+        }
+        Ok(())
+    }
+
+    /// Every zone counts as a registered domain, unless the registrar is [DNS only](Self::dns_only).
+    /// Domains that were never delegated have no name servers.
+    async fn delegated_name_servers(&self, domain: &str) -> Result<Vec<String>> {
+        self.call().await?;
+        if self.dns_only {
+            return Err(Error::Unsupported(format!("looking up the name servers of {}", domain)));
+        }
+        let state = self.state.lock().unwrap();
+        if !state.zones.contains_key(domain) {
+            return Err(Error::ZoneNotFound(domain.to_string()));
+        }
+        Ok(state.delegations.get(domain).cloned().unwrap_or_default())
+    }
+
+    /// Every zone counts as a registered domain, unless the registrar is [DNS only](Self::dns_only).
+    async fn set_name_servers(&self, domain: &str, name_servers: &[String]) -> Result<()> {
+        self.call().await?;
+        if self.dns_only {
+            return Err(Error::Unsupported(format!("changing the name servers of {}", domain)));
+        }
+        let mut state = self.state.lock().unwrap();
+        if !state.zones.contains_key(domain) {
+            return Err(Error::ZoneNotFound(domain.to_string()));
+        }
+        state.delegations.insert(domain.to_string(), name_servers.to_vec());
+        Ok(())
+    }
+
+}
@@ -0,0 +1,207 @@
+
+use crate::{same_record, DnsRecord, DnsRecordType, Error, Registrar, Result};
+
+/// Types of records that the destination of a migration generates on its own.
+const MANAGED_TYPES: [DnsRecordType; 6] = [
+    DnsRecordType::SOA,
+    DnsRecordType::DNSKEY,
+    DnsRecordType::NSEC,
+    DnsRecordType::NSEC3,
+    DnsRecordType::NSEC3PARAM,
+    DnsRecordType::RRSIG,
+];
+
+/// Record types that are copied as another type if the destination does not support them.
+const CONVERSIONS: [(DnsRecordType, DnsRecordType); 1] = [
+    // SPF records have been deprecated in favour of TXT records with the same content.
+    (DnsRecordType::SPF, DnsRecordType::TXT),
+];
+
+/// Return `true` for records that the destination of a migration or a mirror generates on its
+/// own, such as the SOA record and the NS records at the zone apex.
+pub(crate) fn is_managed(record: &DnsRecord) -> bool {
+    (record.name.is_empty() && record.ty == DnsRecordType::NS) || MANAGED_TYPES.contains(&record.ty)
+}
+
+/// What happened to the records of a zone during a [`Migration`].
+#[derive(Debug, Default)]
+pub struct MigrationReport {
+    /// Records that were added to the destination.
+    pub copied: Vec<DnsRecord>,
+    /// Records that were added to the destination as another type, which is given.
+    pub converted: Vec<(DnsRecord, DnsRecordType)>,
+    /// Records that the destination already had.
+    pub existing: Vec<DnsRecord>,
+    /// Records that were left out because the destination manages them itself, such as the SOA
+    /// record and the NS records at the zone apex.
+    pub skipped: Vec<DnsRecord>,
+    /// Records of a type the destination does not support.
+    pub unsupported: Vec<DnsRecord>,
+    /// Records that the destination refused for another reason.
+    pub failed: Vec<(DnsRecord, Error)>,
+    /// Records that were added but could not be found when the destination was listed again.
+    pub missing: Vec<DnsRecord>,
+    /// The name servers the domain was delegated to after the records were copied.
+    pub name_servers: Option<Vec<String>>,
+}
+
+impl MigrationReport {
+
+    /// Return `true` if every record that should have been copied is present at the destination.
+    pub fn is_complete(&self) -> bool {
+        self.unsupported.is_empty() && self.failed.is_empty() && self.missing.is_empty()
+    }
+
+}
+
+/// Copies all records of a zone from one [`Registrar`] to another.
+///
+/// Records are converted to the shared model on the way, so the zone can move between any two
+/// services. Records the destination does not support are reported instead of failing the whole
+/// migration. Nothing is removed from the source or the destination, so a migration can be run
+/// again after fixing whatever went wrong.
+///
+/// ```no_run
+/// use registrar_common::Migration;
+///
+/// let report = Migration::new(&porkbun_client, &openprovider_client)
+///     .verify(true)
+///     .switch_name_servers(&porkbun_client)
+///     .run("example.com")
+///     .await?;
+///
+/// for record in &report.unsupported {
+///     eprintln!("Could not copy {} {} {}", record.name, record.ty, record.content);
+/// }
+/// ```
+pub struct Migration<'a> {
+    source: &'a dyn Registrar,
+    destination: &'a dyn Registrar,
+    verify: bool,
+    dry_run: bool,
+    registrar: Option<&'a dyn Registrar>,
+}
+
+impl<'a> Migration<'a> {
+
+    pub fn new(source: &'a dyn Registrar, destination: &'a dyn Registrar) -> Self {
+        Self {
+            source,
+            destination,
+            verify: false,
+            dry_run: false,
+            registrar: None,
+        }
+    }
+
+    /// List the records of the destination again after copying, to check that they all arrived.
+    pub fn verify(mut self, enable: bool) -> Self {
+        self.verify = enable;
+        self
+    }
+
+    /// Only report what would be copied without changing anything. Which records the destination
+    /// would refuse or convert is predicted with [`Registrar::supports_type`].
+    pub fn dry_run(mut self, enable: bool) -> Self {
+        self.dry_run = enable;
+        self
+    }
+
+    /// Delegate the domain to the name servers of the destination once all records were copied,
+    /// using the given registrar, which is usually the source.
+    ///
+    /// The name servers are left alone if the [report](MigrationReport::is_complete) shows that
+    /// records are missing at the destination.
+    pub fn switch_name_servers(mut self, registrar: &'a dyn Registrar) -> Self {
+        self.registrar = Some(registrar);
+        self
+    }
+
+    /// Copy the records of the given zone, which has to exist at both sides already.
+    pub async fn run(&self, zone: &str) -> Result<MigrationReport> {
+
+        let mut report = MigrationReport::default();
+
+        let existing = self.destination.list_records(zone).await?;
+
+        for record in self.source.list_records(zone).await? {
+            if is_managed(&record) {
+                report.skipped.push(record);
+                continue;
+            }
+            if existing.iter().any(|other| same_record(other, &record)) {
+                report.existing.push(record);
+                continue;
+            }
+            if self.dry_run {
+                if self.destination.supports_type(&record.ty) {
+                    report.copied.push(record);
+                    continue;
+                }
+                match CONVERSIONS.iter().find(|(from, _)| *from == record.ty) {
+                    Some((_, target)) if self.destination.supports_type(target) => {
+                        let converted = DnsRecord { ty: target.clone(), ..record.clone() };
+                        if existing.iter().any(|other| same_record(other, &converted)) {
+                            report.existing.push(record);
+                        } else {
+                            report.converted.push((record, target.clone()));
+                        }
+                    },
+                    _ => report.unsupported.push(record),
+                }
+                continue;
+            }
+            match self.destination.create_record(zone, &record).await {
+                Ok(_) => report.copied.push(record),
+                Err(Error::UnsupportedRecordType(ty)) => {
+                    let Some((_, target)) = CONVERSIONS.iter().find(|(from, _)| *from == ty) else {
+                        report.unsupported.push(record);
+                        continue;
+                    };
+                    let converted = DnsRecord { ty: target.clone(), ..record.clone() };
+                    if existing.iter().any(|other| same_record(other, &converted)) {
+                        report.existing.push(record);
+                        continue;
+                    }
+                    match self.destination.create_record(zone, &converted).await {
+                        Ok(_) => report.converted.push((record, target.clone())),
+                        Err(Error::UnsupportedRecordType(_)) => report.unsupported.push(record),
+                        Err(error) => report.failed.push((record, error)),
+                    }
+                },
+                Err(error) => {
+                    log::warn!("failed to copy {} {} record of {}: {}", record.name, record.ty, zone, error);
+                    report.failed.push((record, error));
+                },
+            }
+        }
+
+        if self.verify && !self.dry_run {
+            let present = self.destination.list_records(zone).await?;
+            let expected = report.copied.iter()
+                .cloned()
+                .chain(report.converted.iter().map(|(record, ty)| DnsRecord { ty: ty.clone(), ..record.clone() }));
+            report.missing = expected
+                .filter(|record| !present.iter().any(|other| same_record(other, record)))
+                .collect();
+        }
+
+        if let Some(registrar) = self.registrar {
+            if !report.is_complete() {
+                log::warn!("not switching name servers of {} because not all records were copied", zone);
+            } else {
+                let name_servers = self.destination.name_servers(zone).await?;
+                if name_servers.is_empty() {
+                    return Err(Error::Generic(format!("the destination did not report any name servers for {}", zone)));
+                }
+                if !self.dry_run {
+                    registrar.set_name_servers(zone, &name_servers).await?;
+                }
+                report.name_servers = Some(name_servers);
+            }
+        }
+
+        Ok(report)
+    }
+
+}
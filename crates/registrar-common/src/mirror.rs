@@ -0,0 +1,186 @@
+
+use std::time::Duration;
+
+use crate::migrate::is_managed;
+use crate::{diff, Change, DnsRecord, DnsRecordType, Error, Registrar, Result};
+
+type Filter<'a> = Box<dyn Fn(&DnsRecord) -> bool + Send + Sync + 'a>;
+
+/// What a [`Mirror`] did to the secondary copy of a zone in a single round.
+#[derive(Debug, Default)]
+pub struct MirrorReport {
+    /// Changes that were applied to the secondary, or that would have been during a dry run.
+    pub applied: Vec<Change>,
+    /// Changes involving a record type the secondary does not support.
+    pub unsupported: Vec<Change>,
+    /// Changes that the secondary refused for another reason.
+    pub failed: Vec<(Change, Error)>,
+    /// The amount of records on both sides that were left alone because of an ignore rule.
+    pub ignored: usize,
+}
+
+impl MirrorReport {
+
+    /// Return `true` if the secondary was already identical to the primary.
+    pub fn is_unchanged(&self) -> bool {
+        self.applied.is_empty() && self.unsupported.is_empty() && self.failed.is_empty()
+    }
+
+}
+
+/// Keeps the copy of a zone at a secondary [`Registrar`] identical to the one at a primary.
+///
+/// Each round lists the records on both sides, works out the [differences](diff) and applies them
+/// to the secondary. Records that match an ignore rule are never touched on either side. By
+/// default these are the records a service manages on its own, like the SOA record, the NS
+/// records at the zone apex and DNSSEC records.
+///
+/// ```no_run
+/// use std::time::Duration;
+/// use registrar_common::{DnsRecordType, Mirror};
+///
+/// let mirror = Mirror::new(&openprovider_client, &cloudflare_client)
+///     .ignore_name("_acme-challenge");
+///
+/// // Keeps going until the future is dropped.
+/// mirror.run("example.com", Duration::from_secs(300)).await;
+/// ```
+pub struct Mirror<'a> {
+    primary: &'a dyn Registrar,
+    secondary: &'a dyn Registrar,
+    dry_run: bool,
+    ignore_managed: bool,
+    ignored_types: Vec<DnsRecordType>,
+    ignored_names: Vec<String>,
+    filters: Vec<Filter<'a>>,
+}
+
+impl<'a> Mirror<'a> {
+
+    pub fn new(primary: &'a dyn Registrar, secondary: &'a dyn Registrar) -> Self {
+        Self {
+            primary,
+            secondary,
+            dry_run: false,
+            ignore_managed: true,
+            ignored_types: Vec::new(),
+            ignored_names: Vec::new(),
+            filters: Vec::new(),
+        }
+    }
+
+    /// Only report what would change without changing anything.
+    pub fn dry_run(mut self, enable: bool) -> Self {
+        self.dry_run = enable;
+        self
+    }
+
+    /// Whether to leave the records alone that services manage on their own. Enabled by default.
+    pub fn ignore_managed(mut self, enable: bool) -> Self {
+        self.ignore_managed = enable;
+        self
+    }
+
+    /// Leave all records of the given type alone.
+    pub fn ignore_type(mut self, ty: DnsRecordType) -> Self {
+        self.ignored_types.push(ty);
+        self
+    }
+
+    /// Leave all records with the given name alone. The name is relative to the zone, with an
+    /// empty name referring to the zone apex.
+    pub fn ignore_name<S: Into<String>>(mut self, name: S) -> Self {
+        self.ignored_names.push(name.into());
+        self
+    }
+
+    /// Leave all records alone for which the given function returns `true`.
+    pub fn ignore_if<F: Fn(&DnsRecord) -> bool + Send + Sync + 'a>(mut self, filter: F) -> Self {
+        self.filters.push(Box::new(filter));
+        self
+    }
+
+    fn is_ignored(&self, record: &DnsRecord) -> bool {
+        (self.ignore_managed && is_managed(record))
+            || self.ignored_types.contains(&record.ty)
+            || self.ignored_names.iter().any(|name| name.eq_ignore_ascii_case(&record.name))
+            || self.filters.iter().any(|filter| filter(record))
+    }
+
+    async fn compare(&self, zone: &str) -> Result<(Vec<Change>, usize)> {
+        let (desired, actual) = futures::try_join!(
+            self.primary.list_records(zone),
+            self.secondary.list_records_with_ids(zone),
+        )?;
+        let desired: Vec<DnsRecord> = desired.into_iter()
+            .filter(|record| !self.is_ignored(record))
+            .collect();
+        let total = actual.len();
+        let actual: Vec<_> = actual.into_iter()
+            .filter(|(_, record)| !self.is_ignored(record))
+            .collect();
+        Ok((diff(&desired, &actual), total - actual.len()))
+    }
+
+    /// Work out which changes would bring the secondary copy of the given zone up to date, without
+    /// applying them.
+    pub async fn plan(&self, zone: &str) -> Result<Vec<Change>> {
+        Ok(self.compare(zone).await?.0)
+    }
+
+    /// Apply changes that were worked out with [`plan`](Self::plan) to the secondary.
+    pub async fn apply(&self, zone: &str, changes: Vec<Change>) -> MirrorReport {
+        let mut report = MirrorReport::default();
+        for change in changes {
+            if self.dry_run {
+                report.applied.push(change);
+                continue;
+            }
+            let result = match &change {
+                Change::Create(record) => self.secondary.create_record(zone, record).await.map(|_| ()),
+                Change::Update { id, to, .. } => self.secondary.update_record(zone, id, to).await.map(|_| ()),
+                Change::Delete { id, .. } => self.secondary.delete_record(zone, id).await,
+            };
+            match result {
+                Ok(()) => report.applied.push(change),
+                Err(Error::UnsupportedRecordType(_)) => report.unsupported.push(change),
+                Err(error) => {
+                    let record = change.record();
+                    log::warn!("failed to mirror {} {} record of {}: {}", record.name, record.ty, zone, error);
+                    report.failed.push((change, error));
+                },
+            }
+        }
+        report
+    }
+
+    /// Bring the secondary copy of the given zone up to date once.
+    pub async fn sync(&self, zone: &str) -> Result<MirrorReport> {
+        let (changes, ignored) = self.compare(zone).await?;
+        let mut report = self.apply(zone, changes).await;
+        report.ignored = ignored;
+        Ok(report)
+    }
+
+    /// Bring the secondary copy of the given zone up to date every `interval`, forever.
+    ///
+    /// Rounds that fail are logged and retried at the next interval, so that an outage of either
+    /// side does not stop the mirror. Drop the future to stop it.
+    pub async fn run(&self, zone: &str, interval: Duration) {
+        loop {
+            match self.sync(zone).await {
+                Ok(report) if report.is_unchanged() => log::debug!("{} is in sync", zone),
+                Ok(report) => log::info!(
+                    "mirrored {}: {} applied, {} unsupported, {} failed",
+                    zone,
+                    report.applied.len(),
+                    report.unsupported.len(),
+                    report.failed.len(),
+                ),
+                Err(error) => log::warn!("failed to mirror {}: {}", zone, error),
+            }
+            tokio::time::sleep(interval).await;
+        }
+    }
+
+}
@@ -0,0 +1,176 @@
+//! Building blocks for in-process fakes of HTTP APIs, meant for testing.
+//!
+//! Each provider crate builds its own fake on top of [`MockHttpServer`] by supplying a function
+//! that turns a [`MockRequest`] into a [`MockResponse`].
+
+use std::convert::Infallible;
+use std::net::SocketAddr;
+use std::sync::Arc;
+
+use http::{HeaderMap, Method, StatusCode};
+use http_body_util::{BodyExt, Full};
+use hyper::body::{Bytes, Incoming};
+use hyper::service::service_fn;
+use hyper::{Request, Response};
+use hyper_util::rt::TokioIo;
+use serde_json::Value;
+use tokio::net::TcpListener;
+use tokio::task::JoinHandle;
+
+/// A request that was received by a [`MockHttpServer`].
+pub struct MockRequest {
+    pub method: Method,
+    pub path: String,
+    pub query: String,
+    pub headers: HeaderMap,
+    pub body: Bytes,
+}
+
+impl MockRequest {
+
+    /// Get the value of a header if it is present and valid UTF-8.
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers.get(name).and_then(|value| value.to_str().ok())
+    }
+
+    /// Get the token of the `Authorization: Bearer` header, if any.
+    pub fn bearer(&self) -> Option<&str> {
+        self.header("authorization").and_then(|value| value.strip_prefix("Bearer "))
+    }
+
+    /// Get the value of a parameter in the query string.
+    ///
+    /// Values are not percent-decoded.
+    pub fn query_param(&self, key: &str) -> Option<&str> {
+        self.query.split('&')
+            .filter_map(|pair| pair.split_once('='))
+            .find(|(name, _)| *name == key)
+            .map(|(_, value)| value)
+    }
+
+    /// Parse the body as JSON, returning `null` if it is empty or not valid JSON.
+    pub fn json(&self) -> Value {
+        serde_json::from_slice(&self.body).unwrap_or(Value::Null)
+    }
+
+    /// Get the body as text, replacing invalid UTF-8.
+    pub fn text(&self) -> String {
+        String::from_utf8_lossy(&self.body).into_owned()
+    }
+
+    /// Split the path into its segments, ignoring leading and trailing slashes.
+    pub fn segments(&self) -> Vec<&str> {
+        self.path.split('/').filter(|segment| !segment.is_empty()).collect()
+    }
+
+}
+
+/// A response that is sent back by a [`MockHttpServer`].
+pub struct MockResponse {
+    pub status: StatusCode,
+    pub content_type: &'static str,
+    pub body: Bytes,
+}
+
+impl MockResponse {
+
+    pub fn json(status: StatusCode, value: Value) -> Self {
+        Self {
+            status,
+            content_type: "application/json",
+            body: Bytes::from(value.to_string()),
+        }
+    }
+
+    pub fn text<S: Into<String>>(status: StatusCode, text: S) -> Self {
+        Self {
+            status,
+            content_type: "text/plain",
+            body: Bytes::from(text.into()),
+        }
+    }
+
+}
+
+/// An HTTP server running on a random port on the loopback interface.
+///
+/// The server stops when this object is dropped.
+pub struct MockHttpServer {
+    address: SocketAddr,
+    task: JoinHandle<()>,
+}
+
+impl Drop for MockHttpServer {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+async fn serve<H>(handler: Arc<H>, request: Request<Incoming>) -> std::result::Result<Response<Full<Bytes>>, Infallible>
+where
+    H: Fn(MockRequest) -> MockResponse,
+{
+    let (parts, body) = request.into_parts();
+    let body = match body.collect().await {
+        Ok(body) => body.to_bytes(),
+        Err(_) => Bytes::new(),
+    };
+    let response = handler(MockRequest {
+        method: parts.method,
+        path: parts.uri.path().to_string(),
+        query: parts.uri.query().unwrap_or("").to_string(),
+        headers: parts.headers,
+        body,
+    });
+    Ok(Response::builder()
+        .status(response.status)
+        .header(hyper::header::CONTENT_TYPE, response.content_type)
+        .body(Full::new(response.body))
+        .unwrap())
+}
+
+impl MockHttpServer {
+
+    /// Start a new server in the background of the current Tokio runtime that answers every
+    /// request using the given function.
+    pub async fn start<H>(handler: H) -> Self
+    where
+        H: Fn(MockRequest) -> MockResponse + Send + Sync + 'static,
+    {
+        let listener = TcpListener::bind("127.0.0.1:0").await
+            .expect("failed to bind mock server to a local port");
+        let address = listener.local_addr().unwrap();
+        let handler = Arc::new(handler);
+        let task = tokio::spawn(async move {
+            loop {
+                let (stream, _) = match listener.accept().await {
+                    Ok(connection) => connection,
+                    Err(error) => {
+                        log::warn!("mock server failed to accept a connection: {}", error);
+                        continue;
+                    },
+                };
+                let handler = handler.clone();
+                tokio::spawn(async move {
+                    let service = service_fn(move |request| serve(handler.clone(), request));
+                    if let Err(error) = hyper::server::conn::http1::Builder::new()
+                        .serve_connection(TokioIo::new(stream), service)
+                        .await {
+                        log::warn!("mock server connection failed: {}", error);
+                    }
+                });
+            }
+        });
+        Self { address, task }
+    }
+
+    pub fn address(&self) -> SocketAddr {
+        self.address
+    }
+
+    /// The URL of the server without a trailing slash, e.g. `http://127.0.0.1:1234`.
+    pub fn url(&self) -> String {
+        format!("http://{}", self.address)
+    }
+
+}
@@ -0,0 +1,53 @@
+
+use std::future::Future;
+use std::time::Duration;
+
+use crate::{Error, HttpErrorKind, Result};
+
+/// How long to wait before the first retry. The delay doubles with every retry after that.
+const FIRST_DELAY: Duration = Duration::from_millis(200);
+
+/// The longest delay between two attempts.
+const MAX_DELAY: Duration = Duration::from_secs(30);
+
+impl Error {
+
+    /// Whether the request that failed with this error can safely be sent again, because the
+    /// server never got to act on it.
+    ///
+    /// This covers failures to connect and the HTTP statuses 429 (Too Many Requests) and 503
+    /// (Service Unavailable). Timeouts, 502 (Bad Gateway) and 504 (Gateway Timeout) do not count,
+    /// since the server behind the gateway may have made the change all the same.
+    pub fn is_transient(&self) -> bool {
+        match self {
+            Error::Http { kind: HttpErrorKind::Connect, .. } => true,
+            Error::StatusCode(status) => matches!(status, 429 | 503),
+            _ => false,
+        }
+    }
+
+}
+
+/// Run an operation until it succeeds or fails with an error that is not
+/// [transient](Error::is_transient), waiting longer after each failed attempt.
+///
+/// The operation is retried at most `max_retries` times, after which the last error is returned.
+pub async fn retry<T, F, Fut>(max_retries: u32, mut operation: F) -> Result<T>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T>>,
+{
+    let mut retries = 0;
+    let mut delay = FIRST_DELAY;
+    loop {
+        match operation().await {
+            Err(error) if error.is_transient() && retries < max_retries => {
+                retries += 1;
+                log::warn!("{}; retrying in {:?}", error, delay);
+                tokio::time::sleep(delay).await;
+                delay = (delay * 2).min(MAX_DELAY);
+            },
+            result => return result,
+        }
+    }
+}
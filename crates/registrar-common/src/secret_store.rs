@@ -0,0 +1,352 @@
+
+use std::collections::BTreeMap;
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::num::NonZeroU32;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output, Stdio};
+
+use ring::aead::{Aad, LessSafeKey, Nonce, UnboundKey, CHACHA20_POLY1305, NONCE_LEN};
+use ring::pbkdf2;
+use ring::rand::{SecureRandom, SystemRandom};
+
+use crate::{Error, IOResultExt, Result};
+
+/// A place to keep passwords, tokens and API keys, so that they do not have to be written to
+/// configuration files in plain text.
+///
+/// Keys consist of ASCII letters, digits and the characters `.`, `_`, `-` and `/`.
+pub trait SecretStore {
+
+    /// Look up a secret, returning `None` if the store does not have it.
+    fn get(&self, key: &str) -> Result<Option<String>>;
+
+    /// Store a secret, replacing any previous one with the same key.
+    fn set(&self, key: &str, secret: &str) -> Result<()>;
+
+    /// Remove a secret. Removing a secret that does not exist is not an error.
+    fn delete(&self, key: &str) -> Result<()>;
+
+}
+
+fn check_key(key: &str) -> Result<()> {
+    let valid = !key.is_empty() && key.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '_' | '-' | '/'));
+    if valid {
+        Ok(())
+    } else {
+        Err(Error::Generic(format!("invalid secret key {:?}", key)))
+    }
+}
+
+/// Create or truncate a file that only the current user may read, for storing secrets in.
+///
+/// Files that already existed are made private as well.
+#[cfg(unix)]
+pub fn create_private(path: &Path) -> std::io::Result<File> {
+    use std::fs::Permissions;
+    use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+
+    let file = OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .mode(0o600)
+        .open(path)?;
+    // The mode only applies to new files.
+    file.set_permissions(Permissions::from_mode(0o600))?;
+    Ok(file)
+}
+
+/// Create or truncate a file, which is left to the permissions of the directory it is in.
+#[cfg(not(unix))]
+pub fn create_private(path: &Path) -> std::io::Result<File> {
+    OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .open(path)
+}
+
+/// Run a command, passing the given input on its standard input.
+fn run(command: &mut Command, input: Option<&str>) -> std::io::Result<Output> {
+    let mut child = command
+        .stdin(if input.is_some() { Stdio::piped() } else { Stdio::null() })
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+    if let (Some(input), Some(mut stdin)) = (input, child.stdin.take()) {
+        stdin.write_all(input.as_bytes())?;
+    }
+    child.wait_with_output()
+}
+
+fn check_status(output: &Output, program: &str) -> Result<()> {
+    if output.status.success() {
+        return Ok(());
+    }
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    Err(Error::Generic(format!("{} failed: {}", program, stderr.trim())))
+}
+
+/// Keeps secrets in the keyring of the desktop, through the Secret Service API that GNOME Keyring
+/// and KWallet provide over D-Bus.
+///
+/// The keyring is reached with `secret-tool`, which comes with libsecret.
+pub struct KeyringStore {
+    service: String,
+}
+
+impl KeyringStore {
+
+    /// Keep secrets under the given service name, which is shown in the keyring application.
+    pub fn new<S: Into<String>>(service: S) -> Self {
+        Self { service: service.into() }
+    }
+
+    fn secret_tool(&self, action: &str, key: &str) -> Command {
+        let mut command = Command::new("secret-tool");
+        command.arg(action);
+        if action == "store" {
+            command.arg(format!("--label={} {}", self.service, key));
+        }
+        command.args(["service", &self.service, "key", key]);
+        command
+    }
+
+    fn run(&self, action: &str, key: &str, input: Option<&str>) -> Result<Output> {
+        check_key(key)?;
+        match run(&mut self.secret_tool(action, key), input).ok_not_found()? {
+            Some(output) => Ok(output),
+            None => Err(Error::Unsupported("the keyring without secret-tool installed".to_string())),
+        }
+    }
+
+}
+
+impl SecretStore for KeyringStore {
+
+    fn get(&self, key: &str) -> Result<Option<String>> {
+        let output = self.run("lookup", key, None)?;
+        // secret-tool fails without a message when there is no such secret.
+        if !output.status.success() && output.stderr.is_empty() {
+            return Ok(None);
+        }
+        check_status(&output, "secret-tool")?;
+        Ok(Some(String::from_utf8_lossy(&output.stdout).into_owned()))
+    }
+
+    fn set(&self, key: &str, secret: &str) -> Result<()> {
+        check_status(&self.run("store", key, Some(secret))?, "secret-tool")
+    }
+
+    fn delete(&self, key: &str) -> Result<()> {
+        let output = self.run("clear", key, None)?;
+        if !output.status.success() && output.stderr.is_empty() {
+            return Ok(());
+        }
+        check_status(&output, "secret-tool")
+    }
+
+}
+
+/// Keeps secrets in a password manager or any other program that can be run from the shell, such
+/// as `pass`, `op` or `age`.
+///
+/// Commands are run with `sh -c`, after replacing `{key}` with the key of the secret. The secret
+/// is the first line of the output of the command that looks it up, and is passed on the standard
+/// input of the command that stores it. A command that fails to look up a secret is taken to mean
+/// that there is no such secret.
+///
+/// ```no_run
+/// let store = CommandStore::new("pass show openprovider/{key}")
+///     .set_command("pass insert --multiline --force openprovider/{key}")
+///     .delete_command("pass rm --force openprovider/{key}");
+/// ```
+pub struct CommandStore {
+    get: String,
+    set: Option<String>,
+    delete: Option<String>,
+}
+
+impl CommandStore {
+
+    pub fn new<S: Into<String>>(get: S) -> Self {
+        Self {
+            get: get.into(),
+            set: None,
+            delete: None,
+        }
+    }
+
+    /// The command that stores a secret. Without one, the store is read-only.
+    pub fn set_command<S: Into<String>>(mut self, command: S) -> Self {
+        self.set = Some(command.into());
+        self
+    }
+
+    /// The command that removes a secret.
+    pub fn delete_command<S: Into<String>>(mut self, command: S) -> Self {
+        self.delete = Some(command.into());
+        self
+    }
+
+    fn run(&self, command: &str, key: &str, input: Option<&str>) -> Result<Output> {
+        check_key(key)?;
+        // Keys cannot contain characters that mean something to the shell.
+        let command = command.replace("{key}", key);
+        Ok(run(Command::new("sh").arg("-c").arg(&command), input)?)
+    }
+
+}
+
+impl SecretStore for CommandStore {
+
+    fn get(&self, key: &str) -> Result<Option<String>> {
+        let output = self.run(&self.get, key, None)?;
+        if !output.status.success() {
+            log::debug!("looking up secret {} failed: {}", key, String::from_utf8_lossy(&output.stderr).trim());
+            return Ok(None);
+        }
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        Ok(stdout.lines().next().filter(|line| !line.is_empty()).map(str::to_string))
+    }
+
+    fn set(&self, key: &str, secret: &str) -> Result<()> {
+        let Some(command) = &self.set else {
+            return Err(Error::Unsupported("storing secrets without a command to do so".to_string()));
+        };
+        check_status(&self.run(command, key, Some(&format!("{}\n", secret)))?, command)
+    }
+
+    fn delete(&self, key: &str) -> Result<()> {
+        let Some(command) = &self.delete else {
+            return Err(Error::Unsupported("removing secrets without a command to do so".to_string()));
+        };
+        check_status(&self.run(command, key, None)?, command)
+    }
+
+}
+
+const MAGIC: &[u8; 8] = b"RGSECv1\n";
+const SALT_LEN: usize = 16;
+const HEADER_LEN: usize = MAGIC.len() + 4 + SALT_LEN + NONCE_LEN;
+
+/// The number of rounds of PBKDF2 that new files are encrypted with, as recommended by OWASP for
+/// HMAC-SHA256.
+pub const DEFAULT_ITERATIONS: u32 = 600_000;
+
+/// Keeps secrets in a file that is encrypted with a passphrase.
+///
+/// The key is derived from the passphrase with PBKDF2-HMAC-SHA256 and a random salt, and the
+/// secrets are encrypted with ChaCha20-Poly1305. The whole file is written again with a new salt
+/// and nonce whenever a secret changes, and is only readable by its owner.
+pub struct EncryptedFileStore {
+    path: PathBuf,
+    passphrase: String,
+    iterations: u32,
+}
+
+impl EncryptedFileStore {
+
+    pub fn new<P: Into<PathBuf>, S: Into<String>>(path: P, passphrase: S) -> Self {
+        Self {
+            path: path.into(),
+            passphrase: passphrase.into(),
+            iterations: DEFAULT_ITERATIONS,
+        }
+    }
+
+    /// The number of rounds of PBKDF2 to use when writing the file. Files remember the number they
+    /// were written with, so this does not affect reading.
+    pub fn iterations(mut self, iterations: u32) -> Self {
+        self.iterations = iterations.max(1);
+        self
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    fn key(&self, salt: &[u8], iterations: u32) -> LessSafeKey {
+        let mut key = [0; 32];
+        pbkdf2::derive(pbkdf2::PBKDF2_HMAC_SHA256, NonZeroU32::new(iterations).unwrap(), salt, self.passphrase.as_bytes(), &mut key);
+        LessSafeKey::new(UnboundKey::new(&CHACHA20_POLY1305, &key).unwrap())
+    }
+
+    fn read(&self) -> Result<BTreeMap<String, String>> {
+        let Some(data) = std::fs::read(&self.path).ok_not_found()? else {
+            return Ok(BTreeMap::new());
+        };
+        let invalid = || Error::Generic(format!("{} is not a file of encrypted secrets", self.path.display()));
+        if data.len() < HEADER_LEN || !data.starts_with(MAGIC) {
+            return Err(invalid());
+        }
+        let (header, ciphertext) = data.split_at(HEADER_LEN);
+        let iterations = u32::from_be_bytes(header[MAGIC.len()..MAGIC.len() + 4].try_into().unwrap());
+        if iterations == 0 {
+            return Err(invalid());
+        }
+        let salt = &header[MAGIC.len() + 4..MAGIC.len() + 4 + SALT_LEN];
+        let nonce = Nonce::try_assume_unique_for_key(&header[HEADER_LEN - NONCE_LEN..]).unwrap();
+        let mut plaintext = ciphertext.to_vec();
+        let plaintext = self.key(salt, iterations)
+            .open_in_place(nonce, Aad::from(header), &mut plaintext)
+            .map_err(|_| Error::Generic(format!("wrong passphrase for {}, or the file is damaged", self.path.display())))?;
+        Ok(serde_json::from_slice(plaintext)?)
+    }
+
+    fn write(&self, secrets: &BTreeMap<String, String>) -> Result<()> {
+        let random = SystemRandom::new();
+        let mut header = Vec::with_capacity(HEADER_LEN);
+        header.extend_from_slice(MAGIC);
+        header.extend_from_slice(&self.iterations.to_be_bytes());
+        let mut salt = [0; SALT_LEN];
+        let mut nonce = [0; NONCE_LEN];
+        random.fill(&mut salt).and_then(|_| random.fill(&mut nonce))
+            .map_err(|_| Error::Generic("failed to generate random numbers".to_string()))?;
+        header.extend_from_slice(&salt);
+        header.extend_from_slice(&nonce);
+        let mut data = serde_json::to_vec(secrets)?;
+        self.key(&salt, self.iterations)
+            .seal_in_place_append_tag(Nonce::assume_unique_for_key(nonce), Aad::from(&header), &mut data)
+            .map_err(|_| Error::Generic("failed to encrypt secrets".to_string()))?;
+        header.extend_from_slice(&data);
+
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        // Write a new file next to the old one, so that a failure cannot leave a damaged file.
+        let temporary = self.path.with_extension("tmp");
+        let mut file = create_private(&temporary)?;
+        file.write_all(&header)?;
+        file.sync_all()?;
+        std::fs::rename(&temporary, &self.path)?;
+        Ok(())
+    }
+
+}
+
+impl SecretStore for EncryptedFileStore {
+
+    fn get(&self, key: &str) -> Result<Option<String>> {
+        check_key(key)?;
+        Ok(self.read()?.remove(key))
+    }
+
+    fn set(&self, key: &str, secret: &str) -> Result<()> {
+        check_key(key)?;
+        let mut secrets = self.read()?;
+        secrets.insert(key.to_string(), secret.to_string());
+        self.write(&secrets)
+    }
+
+    fn delete(&self, key: &str) -> Result<()> {
+        check_key(key)?;
+        let mut secrets = self.read()?;
+        if secrets.remove(key).is_some() {
+            self.write(&secrets)?;
+        }
+        Ok(())
+    }
+
+}
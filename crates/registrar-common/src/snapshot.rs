@@ -0,0 +1,239 @@
+
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::{content_id, diff, Change, DnsRecord, Error, IOResultExt, MemoryRegistrar, Mirror, MirrorReport, Registrar, Result};
+
+/// The version of the file format that snapshots are written in.
+pub const SNAPSHOT_VERSION: u32 = 1;
+
+/// A copy of all records of a zone at a certain point in time.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Snapshot {
+    /// The [version](SNAPSHOT_VERSION) of the format this snapshot was written in.
+    pub version: u32,
+    pub zone: String,
+    /// The name of the provider the records were taken from.
+    pub provider: String,
+    /// When the snapshot was taken, in milliseconds since the Unix epoch.
+    pub timestamp: u64,
+    /// Anything the provider knows about the zone apart from its records, such as the zone
+    /// information that OpenProvider keeps.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<Value>,
+    pub records: Vec<DnsRecord>,
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+impl Snapshot {
+
+    /// List all records of the given zone.
+    pub async fn take<S: Into<String>>(registrar: &dyn Registrar, provider: S, zone: &str) -> Result<Self> {
+        Ok(Self {
+            version: SNAPSHOT_VERSION,
+            zone: zone.to_string(),
+            provider: provider.into(),
+            timestamp: now(),
+            metadata: None,
+            records: registrar.list_records(zone).await?,
+        })
+    }
+
+    /// Store provider-specific information about the zone along with its records.
+    pub fn with_metadata<T: Serialize>(mut self, metadata: &T) -> Result<Self> {
+        self.metadata = Some(serde_json::to_value(metadata)?);
+        Ok(self)
+    }
+
+    /// The changes that turn the records of this snapshot into those of the given one.
+    pub fn diff(&self, other: &Snapshot) -> Vec<Change> {
+        let actual: Vec<_> = self.records.iter()
+            .map(|record| (content_id(record), record.clone()))
+            .collect();
+        diff(&other.records, &actual)
+    }
+
+    /// Make the records of the zone at the given registrar identical to the ones in this
+    /// snapshot again.
+    ///
+    /// Records that were added after the snapshot was taken are deleted. Records a service manages
+    /// on its own, like the SOA record and the NS records at the zone apex, are left alone.
+    pub async fn restore(&self, registrar: &dyn Registrar, dry_run: bool) -> Result<MirrorReport> {
+        let source = MemoryRegistrar::new();
+        source.add_zone(self.zone.clone());
+        for record in &self.records {
+            source.add_record(self.zone.clone(), record.clone());
+        }
+        let mirror = Mirror::new(&source, registrar).dry_run(dry_run);
+        mirror.sync(&self.zone).await
+    }
+
+}
+
+/// Describes a snapshot in a [`SnapshotStore`] without loading it.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SnapshotInfo {
+    /// Identifies the snapshot within its store.
+    pub id: String,
+    pub zone: String,
+    pub provider: String,
+    /// When the snapshot was taken, in milliseconds since the Unix epoch.
+    pub timestamp: u64,
+}
+
+/// Keeps [snapshots](Snapshot) as JSON files in a directory, one subdirectory per zone.
+///
+/// ```no_run
+/// use registrar_common::{Snapshot, SnapshotStore};
+///
+/// let store = SnapshotStore::new("/var/backups/dns");
+/// store.save(&Snapshot::take(&client, "openprovider", "example.com").await?)?;
+///
+/// // After something went wrong ...
+/// let snapshot = store.latest("example.com")?.unwrap();
+/// snapshot.restore(&client, false).await?;
+/// ```
+pub struct SnapshotStore {
+    root: PathBuf,
+}
+
+fn check_name(name: &str) -> Result<()> {
+    if name.is_empty() || name.starts_with('.') || name.contains(['/', '\\']) {
+        return Err(Error::Generic(format!("{:?} cannot be used as part of a snapshot name", name)));
+    }
+    Ok(())
+}
+
+impl SnapshotStore {
+
+    /// Use the given directory, which is created when the first snapshot is saved.
+    pub fn new<P: Into<PathBuf>>(root: P) -> Self {
+        Self { root: root.into() }
+    }
+
+    /// The directory the snapshots are kept in.
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Write a snapshot to disk. Existing snapshots are never overwritten.
+    pub fn save(&self, snapshot: &Snapshot) -> Result<SnapshotInfo> {
+        check_name(&snapshot.zone)?;
+        check_name(&snapshot.provider)?;
+        let dir = self.root.join(&snapshot.zone);
+        std::fs::create_dir_all(&dir)?;
+        let mut timestamp = snapshot.timestamp;
+        loop {
+            let file_name = format!("{}-{}.json", timestamp, snapshot.provider);
+            let file = std::fs::OpenOptions::new()
+                .write(true)
+                .create_new(true)
+                .open(dir.join(&file_name))
+                .ok_already_exists()?;
+            if let Some(file) = file {
+                let contents = serde_json::to_string_pretty(&Snapshot { timestamp, ..snapshot.clone() })?;
+                std::io::Write::write_all(&mut &file, contents.as_bytes())?;
+                return Ok(SnapshotInfo {
+                    id: format!("{}/{}", snapshot.zone, file_name.trim_end_matches(".json")),
+                    zone: snapshot.zone.clone(),
+                    provider: snapshot.provider.clone(),
+                    timestamp,
+                });
+            }
+            // Another snapshot of the same zone was taken in the same millisecond.
+            timestamp += 1;
+        }
+    }
+
+    /// List the snapshots of the given zone, or of all zones, from oldest to newest.
+    pub fn list(&self, zone: Option<&str>) -> Result<Vec<SnapshotInfo>> {
+        let zones = match zone {
+            Some(zone) => {
+                check_name(zone)?;
+                vec![zone.to_string()]
+            },
+            None => match std::fs::read_dir(&self.root).ok_not_found()? {
+                None => Vec::new(),
+                Some(entries) => entries
+                    .filter_map(|entry| entry.ok())
+                    .filter(|entry| entry.path().is_dir())
+                    .filter_map(|entry| entry.file_name().into_string().ok())
+                    .collect(),
+            },
+        };
+        let mut snapshots = Vec::new();
+        for zone in zones {
+            let Some(entries) = std::fs::read_dir(self.root.join(&zone)).ok_not_found()? else {
+                continue;
+            };
+            for entry in entries {
+                let file_name = entry?.file_name();
+                let Some(name) = file_name.to_str().and_then(|name| name.strip_suffix(".json")) else {
+                    continue;
+                };
+                let Some((timestamp, provider)) = name.split_once('-') else {
+                    continue;
+                };
+                let Ok(timestamp) = timestamp.parse() else {
+                    continue;
+                };
+                snapshots.push(SnapshotInfo {
+                    id: format!("{}/{}", zone, name),
+                    zone: zone.clone(),
+                    provider: provider.to_string(),
+                    timestamp,
+                });
+            }
+        }
+        snapshots.sort_by(|a, b| (a.timestamp, &a.zone).cmp(&(b.timestamp, &b.zone)));
+        Ok(snapshots)
+    }
+
+    /// Read the snapshot with the given [identifier](SnapshotInfo::id).
+    pub fn load(&self, id: &str) -> Result<Snapshot> {
+        let Some((zone, name)) = id.split_once('/') else {
+            return Err(Error::Generic(format!("invalid snapshot identifier {:?}", id)));
+        };
+        check_name(zone)?;
+        check_name(name)?;
+        let contents = std::fs::read_to_string(self.root.join(zone).join(format!("{}.json", name)))?;
+        let snapshot: Snapshot = serde_json::from_str(&contents)?;
+        if snapshot.version > SNAPSHOT_VERSION {
+            return Err(Error::Generic(format!(
+                "snapshot {} was written in version {} of the format, which is newer than this library",
+                id,
+                snapshot.version,
+            )));
+        }
+        Ok(snapshot)
+    }
+
+    /// Read the most recent snapshot of the given zone, if there is one.
+    pub fn latest(&self, zone: &str) -> Result<Option<Snapshot>> {
+        match self.list(Some(zone))?.last() {
+            Some(info) => Ok(Some(self.load(&info.id)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Delete the snapshot with the given [identifier](SnapshotInfo::id).
+    pub fn remove(&self, id: &str) -> Result<()> {
+        let Some((zone, name)) = id.split_once('/') else {
+            return Err(Error::Generic(format!("invalid snapshot identifier {:?}", id)));
+        };
+        check_name(zone)?;
+        check_name(name)?;
+        std::fs::remove_file(self.root.join(zone).join(format!("{}.json", name)))?;
+        Ok(())
+    }
+
+}
@@ -0,0 +1,309 @@
+
+use crate::{DnsRecord, DnsRecordType, Error, Result};
+
+/// The records read from a zone file by [`parse_zone_file`].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ZoneFile {
+    /// The origin the file ended with, which is usually the name of the zone.
+    pub origin: Option<String>,
+    pub records: Vec<DnsRecord>,
+}
+
+#[derive(Debug)]
+struct Token {
+    text: String,
+    quoted: bool,
+}
+
+/// A line of a zone file after comments were removed and parentheses were joined.
+struct Line {
+    number: usize,
+    /// Whether the line started with whitespace, meaning that the owner of the previous record is
+    /// reused.
+    indented: bool,
+    tokens: Vec<Token>,
+}
+
+fn syntax_error<S: AsRef<str>>(line: usize, message: S) -> Error {
+    Error::Generic(format!("line {} of zone file: {}", line, message.as_ref()))
+}
+
+fn tokenize(text: &str) -> Result<Vec<Line>> {
+    let mut lines = Vec::new();
+    let mut current: Option<Line> = None;
+    let mut depth = 0;
+    for (index, raw) in text.lines().enumerate() {
+        let number = index + 1;
+        let line = current.get_or_insert_with(|| Line {
+            number,
+            indented: raw.starts_with([' ', '\t']),
+            tokens: Vec::new(),
+        });
+        let mut chars = raw.chars().peekable();
+        while let Some(c) = chars.next() {
+            match c {
+                ';' => break,
+                '(' => depth += 1,
+                ')' if depth == 0 => return Err(syntax_error(number, "unbalanced parentheses")),
+                ')' => depth -= 1,
+                '"' => {
+                    let mut text = String::new();
+                    loop {
+                        match chars.next() {
+                            None => return Err(syntax_error(number, "unterminated string")),
+                            Some('"') => break,
+                            Some('\\') => match chars.next() {
+                                Some(digit) if digit.is_ascii_digit() => {
+                                    let mut code = digit.to_digit(10).unwrap();
+                                    for _ in 0..2 {
+                                        match chars.next().and_then(|c| c.to_digit(10)) {
+                                            Some(digit) => code = code * 10 + digit,
+                                            None => return Err(syntax_error(number, "invalid escape sequence")),
+                                        }
+                                    }
+                                    text.push(char::from_u32(code).unwrap_or(char::REPLACEMENT_CHARACTER));
+                                },
+                                Some(c) => text.push(c),
+                                None => return Err(syntax_error(number, "unterminated string")),
+                            },
+                            Some(c) => text.push(c),
+                        }
+                    }
+                    line.tokens.push(Token { text, quoted: true });
+                },
+                c if c.is_whitespace() => {},
+                c => {
+                    let mut text = c.to_string();
+                    while let Some(&c) = chars.peek() {
+                        if c.is_whitespace() || matches!(c, ';' | '(' | ')' | '"') {
+                            break;
+                        }
+                        text.push(c);
+                        chars.next();
+                    }
+                    line.tokens.push(Token { text, quoted: false });
+                },
+            }
+        }
+        if depth == 0 {
+            let line = current.take().unwrap();
+            if !line.tokens.is_empty() {
+                lines.push(line);
+            }
+        }
+    }
+    if let Some(line) = current {
+        return Err(syntax_error(line.number, "unbalanced parentheses"));
+    }
+    Ok(lines)
+}
+
+fn parse_ttl(text: &str) -> Option<u32> {
+    if let Ok(seconds) = text.parse() {
+        return Some(seconds);
+    }
+    // BIND also accepts durations such as 1h30m.
+    let mut total: u32 = 0;
+    let mut value: u32 = 0;
+    let mut has_digits = false;
+    for c in text.chars() {
+        if let Some(digit) = c.to_digit(10) {
+            value = value.checked_mul(10)?.checked_add(digit)?;
+            has_digits = true;
+            continue;
+        }
+        let unit = match c.to_ascii_lowercase() {
+            's' => 1,
+            'm' => 60,
+            'h' => 3600,
+            'd' => 86400,
+            'w' => 604800,
+            _ => return None,
+        };
+        if !has_digits {
+            return None;
+        }
+        total = total.checked_add(value.checked_mul(unit)?)?;
+        value = 0;
+        has_digits = false;
+    }
+    if has_digits {
+        return None;
+    }
+    Some(total)
+}
+
+fn is_class(text: &str) -> bool {
+    matches!(text.to_ascii_uppercase().as_str(), "IN" | "CH" | "HS" | "CS")
+}
+
+/// Turn a domain name from a zone file into an absolute name without the trailing dot.
+fn absolute(name: &str, origin: Option<&str>, line: usize) -> Result<String> {
+    if name == "@" {
+        return origin.map(str::to_string).ok_or_else(|| syntax_error(line, "@ used without an origin"));
+    }
+    if let Some(name) = name.strip_suffix('.') {
+        return Ok(name.to_string());
+    }
+    match origin {
+        Some(origin) => Ok(format!("{}.{}", name, origin)),
+        None => Err(syntax_error(line, format!("relative name {} used without an origin", name))),
+    }
+}
+
+/// Turn an absolute name into one that is relative to the origin, with the empty string referring
+/// to the origin itself.
+fn relative(name: &str, origin: &str) -> Option<String> {
+    if name.eq_ignore_ascii_case(origin) {
+        return Some(String::new());
+    }
+    let suffix = format!(".{}", origin.to_ascii_lowercase());
+    name.to_ascii_lowercase()
+        .strip_suffix(&suffix)
+        .map(|relative| name[..relative.len()].to_string())
+}
+
+/// Read the records from a zone file in the format of RFC 1035.
+///
+/// Names of records are made relative to the origin, which is either given or set with an
+/// `$ORIGIN` directive in the file. Records outside of the origin are rejected. The content of
+/// records is converted to the form a [`DnsRecord`] uses: strings of TXT records are joined
+/// without quotes, host names are absolute without a trailing dot and the priority of MX and SRV
+/// records is split off.
+///
+/// `$INCLUDE` directives are not supported.
+pub fn parse_zone_file(text: &str, origin: Option<&str>) -> Result<ZoneFile> {
+
+    let mut origin = origin.map(|origin| origin.trim_end_matches('.').to_string());
+    let mut default_ttl: Option<u32> = None;
+    let mut previous_owner: Option<String> = None;
+    let mut records = Vec::new();
+
+    for line in tokenize(text)? {
+
+        let number = line.number;
+        let mut tokens = line.tokens.into_iter().peekable();
+
+        let first = tokens.peek().unwrap();
+        if !first.quoted && first.text.starts_with('$') {
+            let directive = tokens.next().unwrap().text.to_ascii_uppercase();
+            let argument = tokens.next().map(|token| token.text);
+            match (directive.as_str(), argument) {
+                ("$ORIGIN", Some(name)) => origin = Some(absolute(&name, origin.as_deref(), number)?),
+                ("$TTL", Some(ttl)) => default_ttl = Some(parse_ttl(&ttl).ok_or_else(|| syntax_error(number, "invalid TTL"))?),
+                ("$INCLUDE", _) => return Err(syntax_error(number, "$INCLUDE is not supported")),
+                (directive, _) => return Err(syntax_error(number, format!("invalid {} directive", directive))),
+            }
+            continue;
+        }
+
+        let owner = if line.indented {
+            previous_owner.clone().ok_or_else(|| syntax_error(number, "record without an owner"))?
+        } else {
+            absolute(&tokens.next().unwrap().text, origin.as_deref(), number)?
+        };
+        previous_owner = Some(owner.clone());
+
+        let mut ttl = default_ttl;
+        let ty = loop {
+            let Some(token) = tokens.next() else {
+                return Err(syntax_error(number, "record without a type"));
+            };
+            if let Some(seconds) = parse_ttl(&token.text) {
+                ttl = Some(seconds);
+            } else if !is_class(&token.text) {
+                break token.text.parse::<DnsRecordType>()
+                    .map_err(|_| syntax_error(number, format!("unknown record type {}", token.text)))?;
+            }
+        };
+
+        let rdata: Vec<Token> = tokens.collect();
+        if rdata.is_empty() {
+            return Err(syntax_error(number, "record without data"));
+        }
+        let host = |token: &Token| absolute(&token.text, origin.as_deref(), number);
+        let number_at = |index: usize| -> Result<u32> {
+            rdata.get(index)
+                .and_then(|token| token.text.parse().ok())
+                .ok_or_else(|| syntax_error(number, format!("invalid {} record", ty)))
+        };
+        let (priority, content) = match ty {
+            DnsRecordType::TXT | DnsRecordType::SPF => {
+                (None, rdata.iter().map(|token| token.text.as_str()).collect())
+            },
+            DnsRecordType::CNAME | DnsRecordType::NS | DnsRecordType::PTR | DnsRecordType::DNAME | DnsRecordType::ALIAS => {
+                (None, host(&rdata[0])?)
+            },
+            DnsRecordType::MX if rdata.len() == 2 => (Some(number_at(0)?), host(&rdata[1])?),
+            DnsRecordType::SRV if rdata.len() == 4 => {
+                let (weight, port) = (number_at(1)?, number_at(2)?);
+                (Some(number_at(0)?), format!("{} {} {}", weight, port, host(&rdata[3])?))
+            },
+            DnsRecordType::MX | DnsRecordType::SRV => return Err(syntax_error(number, format!("invalid {} record", ty))),
+            _ => {
+                let parts: Vec<String> = rdata.iter()
+                    .map(|token| if token.quoted { format!("\"{}\"", token.text) } else { token.text.clone() })
+                    .collect();
+                (None, parts.join(" "))
+            },
+        };
+
+        let Some(origin) = &origin else {
+            return Err(syntax_error(number, "record without an origin"));
+        };
+        let name = relative(&owner, origin)
+            .ok_or_else(|| syntax_error(number, format!("{} is outside of {}", owner, origin)))?;
+
+        records.push(DnsRecord { name, ty, content, ttl, priority });
+    }
+
+    Ok(ZoneFile { origin, records })
+}
+
+fn quote(text: &str) -> String {
+    // Strings in zone files are limited to 255 bytes, so longer ones are split.
+    let mut strings = vec![String::new()];
+    let mut length = 0;
+    for c in text.chars() {
+        let escaped = match c {
+            '"' | '\\' => format!("\\{}", c),
+            c if c.is_control() => format!("\\{:03}", c as u32),
+            c => c.to_string(),
+        };
+        if length + c.len_utf8() > 255 {
+            strings.push(String::new());
+            length = 0;
+        }
+        length += c.len_utf8();
+        strings.last_mut().unwrap().push_str(&escaped);
+    }
+    strings.iter()
+        .map(|string| format!("\"{}\"", string))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Write records in the format of RFC 1035, so that [`parse_zone_file`] reads them back the same.
+pub fn write_zone_file(origin: &str, records: &[DnsRecord]) -> String {
+    let origin = origin.trim_end_matches('.');
+    let mut text = format!("$ORIGIN {}.\n", origin);
+    for record in records {
+        let name = if record.name.is_empty() { "@" } else { &record.name };
+        let ttl = record.ttl.map(|ttl| format!("{}\t", ttl)).unwrap_or_default();
+        let priority = record.priority.map(|priority| format!("{} ", priority)).unwrap_or_default();
+        let content = match record.ty {
+            DnsRecordType::TXT | DnsRecordType::SPF => quote(&record.content),
+            DnsRecordType::CNAME | DnsRecordType::NS | DnsRecordType::PTR | DnsRecordType::DNAME | DnsRecordType::ALIAS | DnsRecordType::MX => {
+                format!("{}.", record.content)
+            },
+            // The target is the last field of an SRV record.
+            DnsRecordType::SRV => match record.content.rsplit_once(' ') {
+                Some((rest, target)) => format!("{} {}.", rest, target),
+                None => record.content.clone(),
+            },
+            _ => record.content.clone(),
+        };
+        text.push_str(&format!("{}\t{}IN\t{}\t{}{}\n", name, ttl, record.ty, priority, content));
+    }
+    text
+}
@@ -0,0 +1,102 @@
+use std::net::SocketAddr;
+use std::time::Duration;
+
+use registrar_common::{dns01_value, AcmeChallenge, DnsRecord, DnsRecordType, Error, MemoryRegistrar, Registrar};
+use tokio::net::UdpSocket;
+
+/// Answer every TXT query with the TXT records of the given zone at the queried name, like an
+/// authoritative name server of that zone would.
+async fn name_server(registrar: MemoryRegistrar, zone: &'static str) -> SocketAddr {
+    let socket = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+    let address = socket.local_addr().unwrap();
+    tokio::spawn(async move {
+        let mut buf = [0; 512];
+        loop {
+            let (length, peer) = socket.recv_from(&mut buf).await.unwrap();
+            let query = &buf[..length];
+            let mut labels = Vec::new();
+            let mut pos = 12;
+            while query[pos] != 0 {
+                let length = query[pos] as usize;
+                labels.push(String::from_utf8_lossy(&query[pos + 1..pos + 1 + length]).into_owned());
+                pos += 1 + length;
+            }
+            let name = labels.join(".");
+            let relative = name.strip_suffix(zone).unwrap_or(&name).trim_end_matches('.').to_string();
+            let answers: Vec<String> = registrar.records(zone).into_iter()
+                .filter(|record| record.ty == DnsRecordType::TXT && record.name == relative)
+                .map(|record| record.content)
+                .collect();
+            let mut response = query[..2].to_vec();
+            response.extend_from_slice(&[0x84, 0, 0, 1, 0, answers.len() as u8, 0, 0, 0, 0]);
+            response.extend_from_slice(&query[12..pos + 5]);
+            for answer in answers {
+                response.extend_from_slice(&[0xc0, 12, 0, 16, 0, 1, 0, 0, 0, 60, 0, answer.len() as u8 + 1, answer.len() as u8]);
+                response.extend_from_slice(answer.as_bytes());
+            }
+            socket.send_to(&response, peer).await.unwrap();
+        }
+    });
+    address
+}
+
+#[test]
+fn values_are_derived_from_the_key_authorization() {
+    assert_eq!(
+        dns01_value("evaGxfADs6pSRb2LAv9IZf17Dt3juxGJ-PCt92wr-oA.nP1qzpXGymHBrUEepNY9HCsQk7K8KhOypzEt62jcerQ"),
+        "NGwKoXBgCT8JhEa0bK7AwfSqHyu_ZWeugV07fLGIVq0"
+    );
+}
+
+#[tokio::test]
+async fn records_go_into_the_closest_zone() {
+    let registrar = MemoryRegistrar::new();
+    registrar.add_zone("example.com");
+    registrar.add_zone("dev.example.com");
+    let challenge = AcmeChallenge::new(&registrar);
+    assert_eq!(challenge.locate("*.example.com").await.unwrap(), ("example.com".to_string(), "_acme-challenge".to_string()));
+    assert_eq!(challenge.locate("_acme-challenge.www.example.com.").await.unwrap(), ("example.com".to_string(), "_acme-challenge.www".to_string()));
+    assert_eq!(challenge.locate("api.dev.example.com").await.unwrap(), ("dev.example.com".to_string(), "_acme-challenge.api".to_string()));
+    assert!(matches!(challenge.locate("example.org").await, Err(Error::ZoneNotFound(_))));
+}
+
+#[tokio::test]
+async fn challenges_are_presented_and_cleaned_up() {
+    let registrar = MemoryRegistrar::new();
+    registrar.add_zone("example.com");
+    registrar.add_record("example.com", DnsRecord::new("", DnsRecordType::TXT, "v=spf1 -all"));
+    let server = name_server(registrar.clone(), "example.com").await;
+    let challenge = AcmeChallenge::new(&registrar)
+        .name_servers(vec![server])
+        .interval(Duration::from_millis(10));
+
+    // A certificate for a domain and its wildcard needs both values at once.
+    challenge.present("example.com", "first").await.unwrap();
+    challenge.present("*.example.com", "second").await.unwrap();
+    challenge.present("*.example.com", "second").await.unwrap();
+    assert_eq!(registrar.records("example.com"), vec![
+        DnsRecord::new("", DnsRecordType::TXT, "v=spf1 -all"),
+        DnsRecord::new("_acme-challenge", DnsRecordType::TXT, "first"),
+        DnsRecord::new("_acme-challenge", DnsRecordType::TXT, "second"),
+    ]);
+
+    challenge.cleanup("example.com", "first").await.unwrap();
+    challenge.cleanup("example.com", "missing").await.unwrap();
+    assert_eq!(registrar.list_records("example.com").await.unwrap(), vec![
+        DnsRecord::new("", DnsRecordType::TXT, "v=spf1 -all"),
+        DnsRecord::new("_acme-challenge", DnsRecordType::TXT, "second"),
+    ]);
+}
+
+#[tokio::test]
+async fn waiting_gives_up_after_the_timeout() {
+    let registrar = MemoryRegistrar::new();
+    registrar.add_zone("example.com");
+    // This name server hosts another zone, so it never serves the record.
+    let server = name_server(MemoryRegistrar::new(), "example.com").await;
+    let challenge = AcmeChallenge::new(&registrar)
+        .name_servers(vec![server])
+        .interval(Duration::from_millis(10))
+        .timeout(Duration::from_millis(50));
+    assert!(matches!(challenge.present("example.com", "value").await, Err(Error::Generic(_))));
+}
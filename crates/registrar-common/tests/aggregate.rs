@@ -0,0 +1,95 @@
+use std::sync::Arc;
+
+use registrar_common::{Aggregate, DnsRecord, DnsRecordType, Error, MemoryRegistrar, Registrar, ZoneOrigin};
+
+fn setup() -> (MemoryRegistrar, MemoryRegistrar, Aggregate) {
+    let first = MemoryRegistrar::new();
+    first.add_zone("example.com");
+    first.add_zone("example.org");
+    let second = MemoryRegistrar::new();
+    second.add_zone("example.net");
+    let aggregate = Aggregate::new()
+        .provider("first", first.clone())
+        .shared_provider("second", Arc::new(second.clone()));
+    (first, second, aggregate)
+}
+
+#[tokio::test]
+async fn inventory_lists_zones_with_their_origin() {
+    let (_, _, aggregate) = setup();
+    assert_eq!(aggregate.provider_names(), vec!["first", "second"]);
+    let inventory = aggregate.inventory().await;
+    assert!(inventory.failed.is_empty());
+    assert_eq!(inventory.zones, vec![
+        ZoneOrigin { zone: "example.com".to_string(), provider: "first".to_string() },
+        ZoneOrigin { zone: "example.org".to_string(), provider: "first".to_string() },
+        ZoneOrigin { zone: "example.net".to_string(), provider: "second".to_string() },
+    ]);
+    assert_eq!(aggregate.owner("EXAMPLE.net.").await.unwrap(), "second");
+    assert!(matches!(aggregate.owner("example.io").await, Err(Error::ZoneNotFound(_))));
+}
+
+#[tokio::test]
+async fn record_operations_are_routed_to_the_owner() {
+    let (first, second, aggregate) = setup();
+    let id = aggregate.create_record("example.net", &DnsRecord::new("a", DnsRecordType::TXT, "b")).await.unwrap();
+    assert_eq!(second.records("example.net"), vec![DnsRecord::new("a", DnsRecordType::TXT, "b")]);
+    assert!(first.records("example.com").is_empty());
+
+    aggregate.update_record("example.net", &id, &DnsRecord::new("a", DnsRecordType::TXT, "c")).await.unwrap();
+    assert_eq!(aggregate.list_records("example.net").await.unwrap(), vec![DnsRecord::new("a", DnsRecordType::TXT, "c")]);
+    aggregate.delete_record("example.net", &id).await.unwrap();
+    assert!(second.records("example.net").is_empty());
+}
+
+#[tokio::test]
+async fn owners_are_cached() {
+    let (first, _, aggregate) = setup();
+    aggregate.list_records("example.com").await.unwrap();
+    aggregate.list_records("example.org").await.unwrap();
+    // One call to list the zones and one call for each listing of records.
+    assert_eq!(first.call_count(), 3);
+}
+
+#[tokio::test]
+async fn failing_providers_do_not_hide_the_others() {
+    let (_, second, aggregate) = setup();
+    second.fail_authentication(true);
+    let inventory = aggregate.inventory().await;
+    assert_eq!(inventory.zones.len(), 2);
+    assert_eq!(inventory.failed.len(), 1);
+    assert_eq!(inventory.failed[0].0, "second");
+    assert!(matches!(aggregate.list_zones().await, Err(Error::AuthenticationFailed)));
+    assert!(matches!(aggregate.owner("example.net").await, Err(Error::AuthenticationFailed)));
+    assert!(aggregate.list_records("example.com").await.is_ok());
+}
+
+#[tokio::test]
+async fn the_first_provider_wins_shared_zones() {
+    let (_, second, aggregate) = setup();
+    second.add_zone("example.com");
+    assert_eq!(aggregate.list_zones().await.unwrap(), vec!["example.com", "example.org", "example.net"]);
+    assert_eq!(aggregate.owner("example.com").await.unwrap(), "first");
+}
+
+#[tokio::test]
+async fn delegation_goes_to_the_registrar_of_the_domain() {
+    // The zone is hosted by a DNS service, while the domain is registered elsewhere.
+    let dns = MemoryRegistrar::new().dns_only(true);
+    dns.add_record("example.com", DnsRecord::new("", DnsRecordType::NS, "ns1.dns.example"));
+    let registrar = MemoryRegistrar::new();
+    registrar.add_zone("example.com");
+    let aggregate = Aggregate::new()
+        .provider("dns", dns.clone())
+        .provider("registrar", registrar.clone());
+    let servers = vec!["ns1.example.net".to_string()];
+    aggregate.set_name_servers("example.com", &servers).await.unwrap();
+    assert_eq!(registrar.delegation("example.com"), Some(servers.clone()));
+    assert_eq!(dns.delegation("example.com"), None);
+    assert!(matches!(aggregate.set_name_servers("example.io", &servers).await, Err(Error::ZoneNotFound(_))));
+
+    // The registry and the zone can tell different stories while the delegation changes.
+    assert_eq!(aggregate.delegated_name_servers("example.com").await.unwrap(), servers);
+    assert_eq!(aggregate.name_servers("example.com").await.unwrap(), vec!["ns1.dns.example".to_string()]);
+    assert!(matches!(aggregate.delegated_name_servers("example.io").await, Err(Error::ZoneNotFound(_))));
+}
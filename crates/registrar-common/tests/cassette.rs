@@ -0,0 +1,52 @@
+
+use registrar_common::{Cassette, CassetteMode, Error};
+use serde_json::json;
+
+fn temp_path(name: &str) -> std::path::PathBuf {
+    std::env::temp_dir().join(format!("registrar-common-{}-{}.json", name, std::process::id()))
+}
+
+#[test]
+fn secrets_are_redacted() {
+    let cassette = Cassette::record(temp_path("redacted"));
+    cassette.push(
+        "POST",
+        "/auth/login",
+        Some(&json!({ "username": "bob", "password": "123456789" })),
+        &json!({ "code": 0, "data": { "token": "abc" } }),
+    );
+    let interaction = &cassette.interactions()[0];
+    assert_eq!(interaction.body, json!({ "username": "bob", "password": "REDACTED" }));
+    assert_eq!(interaction.response, json!({ "code": 0, "data": { "token": "REDACTED" } }));
+}
+
+#[test]
+fn saved_cassettes_can_be_replayed() {
+    let path = temp_path("replay");
+    let cassette = Cassette::record(&path);
+    assert_eq!(cassette.mode(), CassetteMode::Record);
+    cassette.push("GET", "/dns/zones", None, &json!({ "n": 1 }));
+    cassette.push("GET", "/dns/zones", None, &json!({ "n": 2 }));
+    cassette.push("POST", "/ping", Some(&json!({ "apikey": "a" })), &json!({ "n": 3 }));
+    cassette.save().unwrap();
+
+    let cassette = Cassette::replay(&path).unwrap();
+    assert!(cassette.is_replaying());
+    assert_eq!(cassette.find("GET", "/dns/zones", Some(&json!({}))).unwrap(), json!({ "n": 1 }));
+    assert_eq!(cassette.find("GET", "/dns/zones", None).unwrap(), json!({ "n": 2 }));
+    assert!(matches!(cassette.find("GET", "/dns/zones", None), Err(Error::Generic(_))));
+    assert_eq!(cassette.find("POST", "/ping", Some(&json!({ "apikey": "b" }))).unwrap(), json!({ "n": 3 }));
+    assert!(cassette.find("POST", "/other", None).is_err());
+    std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn recording_cassettes_are_saved_when_dropped() {
+    let path = temp_path("drop");
+    {
+        let cassette = Cassette::record(&path);
+        cassette.push("GET", "/domains", None, &json!({}));
+    }
+    assert_eq!(Cassette::replay(&path).unwrap().interactions().len(), 1);
+    std::fs::remove_file(path).unwrap();
+}
@@ -0,0 +1,37 @@
+use registrar_common::{Error, HttpErrorKind};
+
+#[test]
+fn every_error_can_be_displayed() {
+    let errors = [
+        Error::AuthenticationFailed,
+        Error::MissingPath,
+        Error::Api { code: Some(196), message: "Authentication failed".to_string() },
+        Error::Http { kind: HttpErrorKind::Timeout, message: "operation timed out".to_string() },
+        Error::StatusCode(503),
+        Error::ParseStatusCode,
+        Error::Method,
+        Error::HeaderName,
+        Error::HeaderValue,
+        Error::MaxSizeReached,
+        Error::InvalidUri("authority is missing".to_string()),
+        Error::IndexOutOfBounds(3),
+        Error::KeyMissing("data".to_string()),
+        Error::Unsupported("transfers".to_string()),
+        Error::Generic("something broke".to_string()),
+    ];
+    for error in errors {
+        assert!(!error.to_string().is_empty(), "{:?}", error);
+    }
+    assert_eq!(Error::StatusCode(503).to_string(), "server responded with HTTP status 503");
+}
+
+#[test]
+fn only_requests_the_server_did_not_act_on_are_transient() {
+    assert!(Error::Http { kind: HttpErrorKind::Connect, message: "connection refused".to_string() }.is_transient());
+    assert!(Error::StatusCode(429).is_transient());
+    assert!(Error::StatusCode(503).is_transient());
+    assert!(!Error::Http { kind: HttpErrorKind::Timeout, message: "operation timed out".to_string() }.is_transient());
+    assert!(!Error::StatusCode(502).is_transient());
+    assert!(!Error::StatusCode(504).is_transient());
+    assert!(!Error::StatusCode(500).is_transient());
+}
@@ -0,0 +1,28 @@
+use registrar_common::{content_id, DnsRecord, DnsRecordType};
+
+fn mx(content: &str, ttl: Option<u32>, priority: Option<u32>) -> DnsRecord {
+    DnsRecord {
+        name: String::new(),
+        ty: DnsRecordType::MX,
+        content: content.to_string(),
+        ttl,
+        priority,
+    }
+}
+
+#[test]
+fn content_ids_only_depend_on_identifying_fields() {
+    let id = content_id(&mx("mail.example.com", None, Some(10)));
+    assert_eq!(id.len(), 16);
+    assert_eq!(id, content_id(&mx("mail.example.com", Some(300), Some(10))));
+    assert_ne!(id, content_id(&mx("mail.example.com", None, Some(20))));
+    assert_ne!(id, content_id(&mx("mail.example.org", None, Some(10))));
+    assert_ne!(id, content_id(&DnsRecord { ty: DnsRecordType::CNAME, ..mx("mail.example.com", None, Some(10)) }));
+}
+
+#[test]
+fn fields_do_not_run_into_each_other() {
+    let a = DnsRecord { name: "ab".to_string(), ..mx("c", None, None) };
+    let b = DnsRecord { name: "a".to_string(), ..mx("bc", None, None) };
+    assert_ne!(content_id(&a), content_id(&b));
+}
@@ -0,0 +1,113 @@
+
+use std::time::Duration;
+
+use registrar_common::{Bulk, DnsRecord, DnsRecordType, Error, MemoryRegistrar, Registrar};
+
+#[tokio::test]
+async fn records_are_stored_per_zone() {
+    let registrar = MemoryRegistrar::new();
+    registrar.add_zone("example.com");
+    registrar.add_zone("example.org");
+    registrar.create_record("example.com", &DnsRecord::new("", DnsRecordType::TXT, "hello")).await.unwrap();
+    assert_eq!(registrar.list_zones().await.unwrap(), vec!["example.com", "example.org"]);
+    assert_eq!(registrar.list_records("example.com").await.unwrap(), vec![DnsRecord::new("", DnsRecordType::TXT, "hello")]);
+    assert!(registrar.list_records("example.org").await.unwrap().is_empty());
+    assert!(matches!(registrar.list_records("example.net").await, Err(Error::ZoneNotFound(_))));
+}
+
+#[tokio::test]
+async fn clones_share_state() {
+    let registrar = MemoryRegistrar::new();
+    let clone = registrar.clone();
+    clone.add_record("example.com", DnsRecord::new("a", DnsRecordType::TXT, "b"));
+    assert_eq!(registrar.records("example.com"), vec![DnsRecord::new("a", DnsRecordType::TXT, "b")]);
+}
+
+#[tokio::test]
+async fn every_nth_call_fails() {
+    let registrar = MemoryRegistrar::new().fail_every(3);
+    registrar.add_zone("example.com");
+    assert!(registrar.list_zones().await.is_ok());
+    assert!(registrar.list_zones().await.is_ok());
+    assert!(matches!(registrar.list_zones().await, Err(Error::Api { .. })));
+    assert!(registrar.list_zones().await.is_ok());
+    assert_eq!(registrar.call_count(), 4);
+}
+
+#[tokio::test]
+async fn authentication_failures_can_be_toggled() {
+    let registrar = MemoryRegistrar::new();
+    registrar.fail_authentication(true);
+    assert!(matches!(registrar.list_zones().await, Err(Error::AuthenticationFailed)));
+    registrar.fail_authentication(false);
+    assert!(registrar.list_zones().await.is_ok());
+}
+
+#[tokio::test(start_paused = true)]
+async fn latency_delays_calls() {
+    let registrar = MemoryRegistrar::new().latency(Duration::from_secs(2));
+    let start = tokio::time::Instant::now();
+    registrar.list_zones().await.unwrap();
+    assert!(start.elapsed() >= Duration::from_secs(2));
+}
+
+#[tokio::test]
+async fn bulk_reports_outcome_per_zone() {
+    let registrar = MemoryRegistrar::new().fail_every(4);
+    for zone in ["a.com", "b.com", "c.com", "d.com", "e.com"] {
+        registrar.add_zone(zone);
+    }
+    let record = DnsRecord::new("_verify", DnsRecordType::TXT, "token");
+    let record = &record;
+    let report = Bulk::new(&registrar)
+        .concurrency(2)
+        .run_all(|registrar, zone| async move {
+            if zone == "e.com" {
+                return Ok(None);
+            }
+            registrar.create_record(&zone, record).await.map(Some)
+        })
+        .await
+        .unwrap();
+    assert_eq!(report.len(), 5);
+    assert_eq!(report.skipped, vec!["e.com"]);
+    assert_eq!(report.failed.len(), 1);
+    assert_eq!(report.succeeded.len(), 3);
+    assert!(!report.is_success());
+    let (failed_zone, _) = &report.failed[0];
+    assert!(registrar.records(failed_zone).is_empty());
+}
+
+#[tokio::test(start_paused = true)]
+async fn bulk_limits_concurrency() {
+    let registrar = MemoryRegistrar::new().latency(Duration::from_secs(1));
+    for i in 0..8 {
+        registrar.add_zone(format!("zone{}.com", i));
+    }
+    let start = tokio::time::Instant::now();
+    let report = Bulk::new(&registrar)
+        .concurrency(4)
+        .run((0..8).map(|i| format!("zone{}.com", i)), |registrar, zone| async move {
+            registrar.list_records(&zone).await.map(Some)
+        })
+        .await;
+    assert_eq!(report.succeeded.len(), 8);
+    let elapsed = start.elapsed();
+    assert!(elapsed >= Duration::from_secs(2) && elapsed < Duration::from_secs(3));
+}
+
+#[tokio::test]
+async fn records_can_be_updated_and_deleted_by_id() {
+    let registrar = MemoryRegistrar::new();
+    registrar.add_zone("example.com");
+    let id = registrar.create_record("example.com", &DnsRecord::new("a", DnsRecordType::TXT, "b")).await.unwrap();
+    registrar.add_record("example.com", DnsRecord::new("c", DnsRecordType::TXT, "d"));
+    let updated = registrar.update_record("example.com", &id, &DnsRecord::new("a", DnsRecordType::TXT, "c")).await.unwrap();
+    assert_eq!(updated, id);
+    let records = registrar.list_records_with_ids("example.com").await.unwrap();
+    assert_eq!(records[0], (id.clone(), DnsRecord::new("a", DnsRecordType::TXT, "c")));
+
+    registrar.delete_record("example.com", &id).await.unwrap();
+    assert_eq!(registrar.records("example.com"), vec![DnsRecord::new("c", DnsRecordType::TXT, "d")]);
+    assert!(matches!(registrar.delete_record("example.com", &id).await, Err(Error::RecordNotFound(_))));
+}
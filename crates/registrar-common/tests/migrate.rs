@@ -0,0 +1,88 @@
+use registrar_common::{DnsRecord, DnsRecordType, MemoryRegistrar, Migration};
+
+fn setup() -> (MemoryRegistrar, MemoryRegistrar) {
+    let source = MemoryRegistrar::new();
+    source.add_record("example.com", DnsRecord::new("", DnsRecordType::SOA, "ns1.porkbun.com admin.example.com 1 3600 600 604800 300"));
+    source.add_record("example.com", DnsRecord::new("", DnsRecordType::NS, "ns1.porkbun.com"));
+    source.add_record("example.com", DnsRecord::new("", DnsRecordType::A, "93.184.216.34"));
+    source.add_record("example.com", DnsRecord::new("www", DnsRecordType::CNAME, "example.com."));
+    source.add_record("example.com", DnsRecord::new("", DnsRecordType::SPF, "v=spf1 -all"));
+    source.add_record("example.com", DnsRecord::new("host", DnsRecordType::SSHFP, "1 1 0123456789abcdef"));
+    let destination = MemoryRegistrar::new()
+        .unsupported_type(DnsRecordType::SPF)
+        .unsupported_type(DnsRecordType::SSHFP);
+    destination.add_record("example.com", DnsRecord::new("", DnsRecordType::NS, "ns1.openprovider.nl"));
+    destination.add_record("example.com", DnsRecord::new("WWW", DnsRecordType::CNAME, "example.com"));
+    (source, destination)
+}
+
+#[tokio::test]
+async fn records_are_copied_and_converted() {
+    let (source, destination) = setup();
+    let report = Migration::new(&source, &destination)
+        .verify(true)
+        .run("example.com")
+        .await
+        .unwrap();
+
+    assert_eq!(report.copied, vec![DnsRecord::new("", DnsRecordType::A, "93.184.216.34")]);
+    assert_eq!(report.converted, vec![(DnsRecord::new("", DnsRecordType::SPF, "v=spf1 -all"), DnsRecordType::TXT)]);
+    assert_eq!(report.existing.len(), 1);
+    assert_eq!(report.skipped.len(), 2);
+    assert_eq!(report.unsupported, vec![DnsRecord::new("host", DnsRecordType::SSHFP, "1 1 0123456789abcdef")]);
+    assert!(report.failed.is_empty());
+    assert!(report.missing.is_empty());
+    assert!(!report.is_complete());
+
+    let records = destination.records("example.com");
+    assert_eq!(records.len(), 4);
+    assert!(records.contains(&DnsRecord::new("", DnsRecordType::TXT, "v=spf1 -all")));
+
+    // Running the migration again does not duplicate anything.
+    let report = Migration::new(&source, &destination).run("example.com").await.unwrap();
+    assert!(report.copied.is_empty() && report.converted.is_empty());
+    assert_eq!(destination.records("example.com").len(), 4);
+}
+
+#[tokio::test]
+async fn dry_runs_change_nothing() {
+    let (source, destination) = setup();
+    let report = Migration::new(&source, &destination)
+        .dry_run(true)
+        .run("example.com")
+        .await
+        .unwrap();
+    // The report predicts what the destination would do with each record.
+    assert_eq!(report.copied, vec![DnsRecord::new("", DnsRecordType::A, "93.184.216.34")]);
+    assert_eq!(report.converted, vec![(DnsRecord::new("", DnsRecordType::SPF, "v=spf1 -all"), DnsRecordType::TXT)]);
+    assert_eq!(report.unsupported, vec![DnsRecord::new("host", DnsRecordType::SSHFP, "1 1 0123456789abcdef")]);
+    assert_eq!(report.existing.len(), 1);
+    assert!(!report.is_complete());
+    assert_eq!(destination.records("example.com").len(), 2);
+}
+
+#[tokio::test]
+async fn name_servers_are_only_switched_after_complete_migrations() {
+    let (source, destination) = setup();
+    let report = Migration::new(&source, &destination)
+        .switch_name_servers(&source)
+        .run("example.com")
+        .await
+        .unwrap();
+    assert_eq!(report.name_servers, None);
+    assert_eq!(source.delegation("example.com"), None);
+
+    let destination = MemoryRegistrar::new();
+    destination.add_record("example.com", DnsRecord::new("", DnsRecordType::NS, "ns1.openprovider.nl."));
+    destination.add_record("example.com", DnsRecord::new("", DnsRecordType::NS, "ns2.openprovider.be."));
+    let report = Migration::new(&source, &destination)
+        .verify(true)
+        .switch_name_servers(&source)
+        .run("example.com")
+        .await
+        .unwrap();
+    assert!(report.is_complete());
+    let expected = vec!["ns1.openprovider.nl".to_string(), "ns2.openprovider.be".to_string()];
+    assert_eq!(report.name_servers.as_ref(), Some(&expected));
+    assert_eq!(source.delegation("example.com"), Some(expected));
+}
@@ -0,0 +1,88 @@
+use std::time::Duration;
+
+use registrar_common::{diff, Change, DnsRecord, DnsRecordType, MemoryRegistrar, Mirror};
+
+fn setup() -> (MemoryRegistrar, MemoryRegistrar) {
+    let primary = MemoryRegistrar::new();
+    primary.add_record("example.com", DnsRecord::new("", DnsRecordType::NS, "ns1.openprovider.nl").ttl(3600));
+    primary.add_record("example.com", DnsRecord::new("", DnsRecordType::A, "93.184.216.34").ttl(300));
+    primary.add_record("example.com", DnsRecord::new("www", DnsRecordType::CNAME, "example.com").ttl(600));
+    primary.add_record("example.com", DnsRecord::new("mail", DnsRecordType::A, "93.184.216.35").ttl(300));
+    let secondary = MemoryRegistrar::new();
+    secondary.add_record("example.com", DnsRecord::new("", DnsRecordType::NS, "ns1.cloudflare.com").ttl(3600));
+    secondary.add_record("example.com", DnsRecord::new("", DnsRecordType::A, "93.184.216.34").ttl(300));
+    secondary.add_record("example.com", DnsRecord::new("WWW", DnsRecordType::CNAME, "example.com.").ttl(3600));
+    secondary.add_record("example.com", DnsRecord::new("old", DnsRecordType::A, "93.184.216.36").ttl(300));
+    secondary.add_record("example.com", DnsRecord::new("_acme-challenge", DnsRecordType::TXT, "token").ttl(60));
+    (primary, secondary)
+}
+
+#[test]
+fn diff_deletes_before_creating() {
+    let desired = [DnsRecord::new("www", DnsRecordType::A, "93.184.216.34").ttl(300)];
+    let actual = [("1".to_string(), DnsRecord::new("www", DnsRecordType::CNAME, "example.com").ttl(300))];
+    assert_eq!(diff(&desired, &actual), vec![
+        Change::Delete { id: "1".to_string(), record: actual[0].1.clone() },
+        Change::Create(desired[0].clone()),
+    ]);
+    assert!(diff(&desired, &[("2".to_string(), desired[0].clone())]).is_empty());
+}
+
+#[tokio::test]
+async fn secondary_is_made_identical() {
+    let (primary, secondary) = setup();
+    let mirror = Mirror::new(&primary, &secondary).ignore_name("_acme-challenge");
+
+    let report = mirror.sync("example.com").await.unwrap();
+    assert_eq!(report.ignored, 2);
+    assert_eq!(report.applied.len(), 3);
+    assert!(report.failed.is_empty());
+
+    let records = secondary.records("example.com");
+    assert_eq!(records.len(), 5);
+    assert!(records.contains(&DnsRecord::new("", DnsRecordType::NS, "ns1.cloudflare.com").ttl(3600)));
+    assert!(records.contains(&DnsRecord::new("www", DnsRecordType::CNAME, "example.com").ttl(600)));
+    assert!(records.contains(&DnsRecord::new("mail", DnsRecordType::A, "93.184.216.35").ttl(300)));
+    assert!(records.contains(&DnsRecord::new("_acme-challenge", DnsRecordType::TXT, "token").ttl(60)));
+    assert!(!records.iter().any(|record| record.name == "old"));
+
+    assert!(mirror.sync("example.com").await.unwrap().is_unchanged());
+}
+
+#[tokio::test]
+async fn dry_runs_change_nothing() {
+    let (primary, secondary) = setup();
+    let report = Mirror::new(&primary, &secondary)
+        .dry_run(true)
+        .ignore_if(|record| record.name.starts_with('_'))
+        .sync("example.com")
+        .await
+        .unwrap();
+    assert_eq!(report.applied.len(), 3);
+    assert_eq!(secondary.records("example.com").len(), 5);
+}
+
+#[tokio::test]
+async fn unsupported_records_are_reported() {
+    let (primary, _) = setup();
+    primary.add_record("example.com", DnsRecord::new("host", DnsRecordType::SSHFP, "1 1 0123456789abcdef").ttl(300));
+    let secondary = MemoryRegistrar::new().unsupported_type(DnsRecordType::SSHFP);
+    secondary.add_zone("example.com");
+    let report = Mirror::new(&primary, &secondary).sync("example.com").await.unwrap();
+    assert_eq!(report.applied.len(), 3);
+    assert_eq!(report.unsupported, vec![Change::Create(DnsRecord::new("host", DnsRecordType::SSHFP, "1 1 0123456789abcdef").ttl(300))]);
+}
+
+#[tokio::test(start_paused = true)]
+async fn mirrors_keep_going_after_failures() {
+    let (primary, secondary) = setup();
+    primary.fail_authentication(true);
+    let mirror = Mirror::new(&primary, &secondary).ignore_name("_acme-challenge");
+    let _ = tokio::time::timeout(Duration::from_secs(5), mirror.run("example.com", Duration::from_secs(2))).await;
+    assert_eq!(primary.call_count(), 3);
+    assert!(secondary.records("example.com").iter().any(|record| record.name == "old"));
+
+    primary.fail_authentication(false);
+    let _ = tokio::time::timeout(Duration::from_secs(1), mirror.run("example.com", Duration::from_secs(2))).await;
+    assert!(!secondary.records("example.com").iter().any(|record| record.name == "old"));
+}
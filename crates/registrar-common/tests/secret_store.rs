@@ -0,0 +1,74 @@
+#[cfg(unix)]
+use std::os::unix::fs::PermissionsExt;
+
+use registrar_common::{CommandStore, EncryptedFileStore, Error, SecretStore};
+
+fn temp_dir(name: &str) -> std::path::PathBuf {
+    let dir = std::env::temp_dir().join(format!("registrar-common-secrets-{}-{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    dir
+}
+
+#[test]
+fn encrypted_files_keep_secrets() {
+    let path = temp_dir("encrypted").join("secrets.enc");
+    let store = EncryptedFileStore::new(&path, "correct horse").iterations(1000);
+    assert_eq!(store.get("work/openprovider.token").unwrap(), None);
+
+    store.set("work/openprovider.token", "abc").unwrap();
+    store.set("work/porkbun.api_key", "pk1_xyz").unwrap();
+    store.delete("work/porkbun.api_key").unwrap();
+    store.delete("missing").unwrap();
+    #[cfg(unix)]
+    assert_eq!(std::fs::metadata(&path).unwrap().permissions().mode() & 0o777, 0o600);
+    assert!(!String::from_utf8_lossy(&std::fs::read(&path).unwrap()).contains("abc"));
+
+    let reopened = EncryptedFileStore::new(&path, "correct horse");
+    assert_eq!(reopened.get("work/openprovider.token").unwrap().as_deref(), Some("abc"));
+    assert_eq!(reopened.get("work/porkbun.api_key").unwrap(), None);
+
+    let wrong = EncryptedFileStore::new(&path, "battery staple");
+    assert!(matches!(wrong.get("work/openprovider.token"), Err(Error::Generic(_))));
+}
+
+#[cfg(unix)]
+#[test]
+fn existing_files_are_made_private() {
+    let dir = temp_dir("private");
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("credentials.toml");
+    std::fs::write(&path, "token = \"abc\"").unwrap();
+    std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o644)).unwrap();
+    registrar_common::create_private(&path).unwrap();
+    assert_eq!(std::fs::metadata(&path).unwrap().permissions().mode() & 0o777, 0o600);
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), "");
+}
+
+// Commands are run by `sh`, and these use `cat` and `rm`.
+#[cfg(unix)]
+#[test]
+fn commands_keep_secrets() {
+    let dir = temp_dir("command");
+    std::fs::create_dir_all(&dir).unwrap();
+    let dir = dir.display();
+    let store = CommandStore::new(format!("cat {}/{{key}}", dir))
+        .set_command(format!("cat > {}/{{key}}", dir))
+        .delete_command(format!("rm -f {}/{{key}}", dir));
+    assert_eq!(store.get("token").unwrap(), None);
+    store.set("token", "abc").unwrap();
+    assert_eq!(store.get("token").unwrap().as_deref(), Some("abc"));
+    store.delete("token").unwrap();
+    assert_eq!(store.get("token").unwrap(), None);
+
+    let read_only = CommandStore::new("echo secret");
+    assert_eq!(read_only.get("token").unwrap().as_deref(), Some("secret"));
+    assert!(matches!(read_only.set("token", "abc"), Err(Error::Unsupported(_))));
+}
+
+#[test]
+fn keys_cannot_reach_the_shell() {
+    let store = CommandStore::new("echo {key}");
+    for key in ["", "a b", "$(id)", "a;b", "a'b"] {
+        assert!(matches!(store.get(key), Err(Error::Generic(_))), "accepted {:?}", key);
+    }
+}
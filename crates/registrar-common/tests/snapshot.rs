@@ -0,0 +1,67 @@
+use registrar_common::{Change, DnsRecord, DnsRecordType, Error, MemoryRegistrar, Snapshot, SnapshotStore};
+use serde_json::json;
+
+fn temp_dir(name: &str) -> std::path::PathBuf {
+    std::env::temp_dir().join(format!("registrar-common-snapshots-{}-{}", name, std::process::id()))
+}
+
+fn setup() -> MemoryRegistrar {
+    let registrar = MemoryRegistrar::new();
+    registrar.add_record("example.com", DnsRecord::new("", DnsRecordType::A, "93.184.216.34").ttl(300));
+    registrar.add_record("example.com", DnsRecord::new("www", DnsRecordType::A, "93.184.216.34").ttl(300));
+    registrar
+}
+
+#[tokio::test]
+async fn snapshots_are_saved_and_listed() {
+    let registrar = setup();
+    let store = SnapshotStore::new(temp_dir("list"));
+    assert!(store.list(None).unwrap().is_empty());
+
+    let snapshot = Snapshot::take(&registrar, "memory", "example.com").await.unwrap()
+        .with_metadata(&json!({ "id": 1 }))
+        .unwrap();
+    let first = store.save(&snapshot).unwrap();
+    let second = store.save(&snapshot).unwrap();
+    assert_eq!(second.timestamp, first.timestamp + 1);
+    assert_eq!(store.list(Some("example.com")).unwrap(), vec![first.clone(), second.clone()]);
+    assert_eq!(store.list(Some("example.org")).unwrap(), vec![]);
+
+    let loaded = store.load(&first.id).unwrap();
+    assert_eq!(loaded.provider, "memory");
+    assert_eq!(loaded.records, snapshot.records);
+    assert_eq!(loaded.metadata, Some(json!({ "id": 1 })));
+    assert_eq!(store.latest("example.com").unwrap().unwrap().timestamp, second.timestamp);
+
+    store.remove(&second.id).unwrap();
+    assert_eq!(store.list(None).unwrap(), vec![first]);
+    assert!(matches!(store.load("../etc/passwd"), Err(Error::Generic(_))));
+
+    std::fs::remove_dir_all(store.root()).unwrap();
+}
+
+#[tokio::test]
+async fn snapshots_can_be_compared() {
+    let registrar = setup();
+    let before = Snapshot::take(&registrar, "memory", "example.com").await.unwrap();
+    registrar.add_record("example.com", DnsRecord::new("mail", DnsRecordType::A, "93.184.216.35").ttl(300));
+    let after = Snapshot::take(&registrar, "memory", "example.com").await.unwrap();
+    assert_eq!(before.diff(&after), vec![Change::Create(DnsRecord::new("mail", DnsRecordType::A, "93.184.216.35").ttl(300))]);
+    assert!(matches!(after.diff(&before)[..], [Change::Delete { .. }]));
+}
+
+#[tokio::test]
+async fn snapshots_can_be_restored() {
+    let registrar = setup();
+    let snapshot = Snapshot::take(&registrar, "memory", "example.com").await.unwrap();
+
+    // A bad script wipes the zone and adds something else.
+    let wiped = MemoryRegistrar::new();
+    wiped.add_record("example.com", DnsRecord::new("junk", DnsRecordType::A, "10.0.0.1").ttl(300));
+    let report = snapshot.restore(&wiped, true).await.unwrap();
+    assert_eq!(report.applied.len(), 3);
+    assert_eq!(wiped.records("example.com"), vec![DnsRecord::new("junk", DnsRecordType::A, "10.0.0.1").ttl(300)]);
+
+    snapshot.restore(&wiped, false).await.unwrap();
+    assert_eq!(wiped.records("example.com"), snapshot.records);
+}
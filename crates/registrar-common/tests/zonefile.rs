@@ -0,0 +1,71 @@
+use registrar_common::{parse_zone_file, write_zone_file, DnsRecord, DnsRecordType, Error};
+
+const ZONE: &str = r#"
+$ORIGIN example.com.
+$TTL 1h
+@   IN  SOA ns1.example.com. hostmaster.example.com. (
+            2024010101 ; serial
+            7200 3600 1209600 300 )
+    IN  NS  ns1
+    300 IN  A   93.184.216.34
+www     CNAME   @
+mail.example.com. IN 600 MX 10 mx1.example.net.
+_sip._tcp   SRV 10 60 5060 sip
+@   TXT "v=spf1 " "include:_spf.example.net -all" ; split in two
+@   CAA 0 issue "letsencrypt.org"
+"#;
+
+#[test]
+fn zone_files_are_parsed() {
+    let zone = parse_zone_file(ZONE, None).unwrap();
+    assert_eq!(zone.origin.as_deref(), Some("example.com"));
+    assert_eq!(zone.records, vec![
+        DnsRecord::new("", DnsRecordType::SOA, "ns1.example.com. hostmaster.example.com. 2024010101 7200 3600 1209600 300").ttl(3600),
+        DnsRecord::new("", DnsRecordType::NS, "ns1.example.com").ttl(3600),
+        DnsRecord::new("", DnsRecordType::A, "93.184.216.34").ttl(300),
+        DnsRecord::new("www", DnsRecordType::CNAME, "example.com").ttl(3600),
+        DnsRecord::new("mail", DnsRecordType::MX, "mx1.example.net").ttl(600).priority(10),
+        DnsRecord::new("_sip._tcp", DnsRecordType::SRV, "60 5060 sip.example.com").ttl(3600).priority(10),
+        DnsRecord::new("", DnsRecordType::TXT, "v=spf1 include:_spf.example.net -all").ttl(3600),
+        DnsRecord::new("", DnsRecordType::CAA, "0 issue \"letsencrypt.org\"").ttl(3600),
+    ]);
+}
+
+#[test]
+fn origin_can_be_given() {
+    let zone = parse_zone_file("www A 93.184.216.34\n", Some("example.org.")).unwrap();
+    assert_eq!(zone.records[0].name, "www");
+    assert_eq!(zone.records[0].ttl, None);
+}
+
+#[test]
+fn invalid_zone_files_are_rejected() {
+    let cases = [
+        "www A 93.184.216.34\n",
+        "$ORIGIN example.com.\nwww.example.org. A 93.184.216.34\n",
+        "$ORIGIN example.com.\nwww 300 IN\n",
+        "$ORIGIN example.com.\nwww TXT \"unterminated\n",
+        "$ORIGIN example.com.\n@ SOA ( ns1 hostmaster 1 2 3 4 5\n",
+        "$INCLUDE other.zone\n",
+    ];
+    for case in cases {
+        assert!(matches!(parse_zone_file(case, None), Err(Error::Generic(_))), "accepted {:?}", case);
+    }
+}
+
+#[test]
+fn written_zone_files_are_read_back_the_same() {
+    let records = vec![
+        DnsRecord::new("", DnsRecordType::A, "93.184.216.34").ttl(300),
+        DnsRecord::new("www", DnsRecordType::CNAME, "example.com").ttl(3600),
+        DnsRecord::new("mail", DnsRecordType::MX, "mx1.example.net").ttl(600).priority(10),
+        DnsRecord::new("_sip._tcp", DnsRecordType::SRV, "60 5060 sip.example.com").ttl(3600).priority(10),
+        DnsRecord::new("dkim", DnsRecordType::TXT, format!("say \"hi\" \\ {}", "k".repeat(300))).ttl(300),
+        DnsRecord::new("", DnsRecordType::CAA, "0 issue \"letsencrypt.org\""),
+    ];
+    let text = write_zone_file("example.com.", &records);
+    assert!(text.starts_with("$ORIGIN example.com.\n@\t300\tIN\tA\t93.184.216.34\n"));
+    let zone = parse_zone_file(&text, None).unwrap();
+    assert_eq!(zone.origin.as_deref(), Some("example.com"));
+    assert_eq!(zone.records, records);
+}
@@ -0,0 +1,524 @@
+//!
+//! Manage DNS zones that are hosted on an authoritative server such as BIND, Knot or PowerDNS,
+//! without going through an HTTP API.
+//!
+//! Records are changed with dynamic updates as described in RFC 2136 and listed with zone
+//! transfers (AXFR). All messages are sent over TCP and can be signed with a [TSIG key](Key), so
+//! that the server can check that they are allowed.
+//!
+//! ```no_run
+//! let client = rfc2136::Builder::new()
+//!     .server("ns1.example.com:53")
+//!     .key(rfc2136::Key::new("update-key", "c2VjcmV0IGtleSBmb3IgdGVzdGluZw==")?)
+//!     .zone("example.com")
+//!     .build();
+//!
+//! for record in client.transfer("example.com").await? {
+//!     eprintln!("{} {} {} {}", record.name, record.ttl, record.ty, record.data);
+//! }
+//! ```
+
+#[cfg(feature = "mock")]
+pub mod mock;
+mod rdata;
+mod tsig;
+mod wire;
+
+pub use tsig::{Key, HMAC_SHA256};
+
+use std::net::{IpAddr, SocketAddr};
+use std::time::Duration;
+
+use async_trait::async_trait;
+use registrar_common::{content_id, DnsRecord, DnsRecordId, DnsRecordType, Error, RecordIdentity, Registrar, Result, Timeouts};
+use ring::rand::{SecureRandom, SystemRandom};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+
+use wire::{Message, Question, ResourceRecord};
+
+/// The port authoritative servers listen on unless configured otherwise.
+const DEFAULT_PORT: u16 = 53;
+
+/// The TTL of records that are created without one.
+const DEFAULT_TTL: u32 = 3600;
+
+/// Constructs a [client](Client).
+///
+/// ```no_run
+/// let client = rfc2136::Builder::new()
+///     .server("192.0.2.53")
+///     .zone("example.com")
+///     .build();
+/// ```
+pub struct Builder {
+    server: String,
+    key: Option<Key>,
+    zones: Vec<String>,
+    default_ttl: u32,
+    timeouts: Timeouts,
+}
+
+impl Default for Builder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Builder {
+
+    /// Create a new client builder object that talks to a server on the local machine.
+    pub fn new() -> Self {
+        Self {
+            server: format!("127.0.0.1:{}", DEFAULT_PORT),
+            key: None,
+            zones: Vec::new(),
+            default_ttl: DEFAULT_TTL,
+            timeouts: Timeouts::default(),
+        }
+    }
+
+    /// The address of the authoritative server, e.g. `ns1.example.com` or `192.0.2.53:5353`.
+    ///
+    /// Port 53 is used if no port is given.
+    pub fn server<S: Into<String>>(mut self, server: S) -> Self {
+        let server = server.into();
+        self.server = match server.parse::<IpAddr>() {
+            Ok(ip) => SocketAddr::new(ip, DEFAULT_PORT).to_string(),
+            Err(_) if server.parse::<SocketAddr>().is_ok() => server,
+            Err(_) if server.rsplit_once(':').is_some_and(|(_, port)| port.parse::<u16>().is_ok()) => server,
+            Err(_) => format!("{}:{}", server, DEFAULT_PORT),
+        };
+        self
+    }
+
+    /// Sign all messages with the given key.
+    pub fn key(mut self, key: Key) -> Self {
+        self.key = Some(key);
+        self
+    }
+
+    /// Manage the given zone.
+    ///
+    /// DNS offers no way to ask a server for the zones it hosts, so all zones have to be listed
+    /// here to be reported by [`Registrar::list_zones`].
+    pub fn zone<S: Into<String>>(mut self, zone: S) -> Self {
+        self.zones.push(normalize(&zone.into()));
+        self
+    }
+
+    /// The TTL of records that are created through [`Registrar::create_record`] without one.
+    pub fn default_ttl(mut self, ttl: u32) -> Self {
+        self.default_ttl = ttl;
+        self
+    }
+
+    /// Give up if no connection could be made within the given duration.
+    pub fn connect_timeout(mut self, timeout: Duration) -> Self {
+        self.timeouts.connect = Some(timeout);
+        self
+    }
+
+    /// Give up if the server does not answer within the given duration.
+    pub fn read_timeout(mut self, timeout: Duration) -> Self {
+        self.timeouts.read = Some(timeout);
+        self
+    }
+
+    /// Give up on an update or transfer if it did not complete within the given duration.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeouts.total = Some(timeout);
+        self
+    }
+
+    /// Build the actual client. This is a destructive operation.
+    pub fn build(self) -> Client {
+        Client {
+            server: self.server,
+            key: self.key,
+            zones: self.zones,
+            default_ttl: self.default_ttl,
+            timeouts: self.timeouts,
+        }
+    }
+
+}
+
+/// A resource record as it is stored on the server.
+///
+/// The name is fully qualified, without the trailing dot. The data is in the presentation format
+/// of the zone file, e.g. `10 mail.example.com` for an MX record.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Record {
+    pub name: String,
+    pub ty: DnsRecordType,
+    pub ttl: u32,
+    pub data: String,
+}
+
+/// A single change that is part of a dynamic update.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Change {
+    /// Add a record, or change the TTL of the records with the same name and type if the record
+    /// exists already.
+    Add(Record),
+    /// Delete a single record. Its TTL is ignored.
+    Delete(Record),
+    /// Delete all records with the given name and type.
+    DeleteRrset {
+        name: String,
+        ty: DnsRecordType,
+    },
+    /// Delete all records with the given name.
+    DeleteName(String),
+}
+
+/// Talks to an authoritative DNS server.
+///
+/// Every update or transfer opens a new TCP connection, so clients are cheap to clone.
+#[derive(Clone)]
+pub struct Client {
+    server: String,
+    key: Option<Key>,
+    zones: Vec<String>,
+    default_ttl: u32,
+    timeouts: Timeouts,
+}
+
+/// Remove the trailing dot from a name and bring it in lowercase.
+fn normalize(name: &str) -> String {
+    name.trim_end_matches('.').to_ascii_lowercase()
+}
+
+fn random_id() -> u16 {
+    let mut bytes = [0; 2];
+    SystemRandom::new().fill(&mut bytes).expect("failed to generate a random message ID");
+    u16::from_be_bytes(bytes)
+}
+
+async fn write_message(stream: &mut TcpStream, message: &[u8]) -> Result<()> {
+    let mut frame = Vec::with_capacity(message.len() + 2);
+    frame.extend_from_slice(&(message.len() as u16).to_be_bytes());
+    frame.extend_from_slice(message);
+    stream.write_all(&frame).await?;
+    Ok(())
+}
+
+async fn read_message(stream: &mut TcpStream, timeout: Option<Duration>) -> Result<Vec<u8>> {
+    let read = async {
+        let length = stream.read_u16().await? as usize;
+        let mut buf = vec![0; length];
+        stream.read_exact(&mut buf).await?;
+        Ok::<_, Error>(buf)
+    };
+    match timeout {
+        Some(timeout) => tokio::time::timeout(timeout, read).await?,
+        None => read.await,
+    }
+}
+
+impl Client {
+
+    /// Get a copy of this client whose updates and transfers must complete within the given
+    /// duration.
+    pub fn with_timeout(&self, timeout: Duration) -> Self {
+        let mut client = self.clone();
+        client.timeouts.total = Some(timeout);
+        client
+    }
+
+    /// The zones that were configured with [`Builder::zone`].
+    pub fn zones(&self) -> &[String] {
+        &self.zones
+    }
+
+    async fn connect(&self) -> Result<TcpStream> {
+        log::info!("Connecting to {}", self.server);
+        let connect = TcpStream::connect(&self.server);
+        Ok(match self.timeouts.connect {
+            Some(timeout) => tokio::time::timeout(timeout, connect).await??,
+            None => connect.await?,
+        })
+    }
+
+    /// Encode a request and sign it if a key was configured, returning the MAC of the request.
+    fn encode_request(&self, request: &Message) -> Result<(Vec<u8>, Option<Vec<u8>>)> {
+        let mut buf = request.encode()?;
+        let mac = match &self.key {
+            Some(key) => Some(key.sign(&mut buf, None, false, 0)?),
+            None => None,
+        };
+        Ok((buf, mac))
+    }
+
+    /// Decode a response and check its signature and response code.
+    ///
+    /// Returns the MAC of the response, which is needed to verify the message after it.
+    fn check_response(&self, id: u16, buf: &[u8], prior_mac: Option<&[u8]>, timers_only: bool) -> Result<(Message, Option<Vec<u8>>)> {
+        let response = Message::decode(buf)?;
+        if response.id != id || !response.response {
+            return Err(wire::malformed("response does not belong to the request"));
+        }
+        if response.rcode == wire::RCODE_NOTAUTH {
+            return Err(Error::AuthenticationFailed);
+        }
+        let mac = match &self.key {
+            Some(key) => match key.verify(buf, &response, prior_mac, timers_only) {
+                Ok(mac) => Some(mac),
+                Err(error) => {
+                    log::warn!("Response from {} failed TSIG verification: {:?}", self.server, error);
+                    return Err(Error::AuthenticationFailed);
+                },
+            },
+            None => None,
+        };
+        if response.rcode != wire::RCODE_NOERROR {
+            return Err(Error::Api {
+                code: Some(response.rcode as u32),
+                message: wire::rcode_name(response.rcode).to_string(),
+            });
+        }
+        Ok((response, mac))
+    }
+
+    async fn with_deadline<T, F: std::future::Future<Output = Result<T>>>(&self, future: F) -> Result<T> {
+        match self.timeouts.total {
+            Some(timeout) => tokio::time::timeout(timeout, future).await?,
+            None => future.await,
+        }
+    }
+
+    /// Download all records of a zone with a zone transfer (AXFR).
+    ///
+    /// The SOA record is included once. Records of types this crate does not understand are
+    /// skipped.
+    pub async fn transfer<S: AsRef<str>>(&self, zone: S) -> Result<Vec<Record>> {
+        let zone = normalize(zone.as_ref());
+        self.with_deadline(self.do_transfer(&zone)).await
+    }
+
+    async fn do_transfer(&self, zone: &str) -> Result<Vec<Record>> {
+        let id = random_id();
+        let (request, request_mac) = self.encode_request(&Message {
+            id,
+            opcode: wire::OPCODE_QUERY,
+            questions: vec![Question { name: zone.to_string(), ty: wire::TYPE_AXFR, class: wire::CLASS_IN }],
+            ..Message::default()
+        })?;
+        let mut stream = self.connect().await?;
+        write_message(&mut stream, &request).await?;
+
+        let mut records = Vec::new();
+        let mut soa_count = 0;
+        let mut prior_mac = request_mac;
+        let mut first = true;
+        while soa_count < 2 {
+            let buf = read_message(&mut stream, self.timeouts.read).await?;
+            let (response, mac) = self.check_response(id, &buf, prior_mac.as_deref(), !first)?;
+            if response.answers.is_empty() {
+                return Err(wire::malformed("zone transfer ended early"));
+            }
+            prior_mac = mac;
+            for record in response.answers {
+                if record.ty == wire::TYPE_SOA {
+                    soa_count += 1;
+                    if soa_count == 2 {
+                        break;
+                    }
+                } else if soa_count == 0 {
+                    return Err(wire::malformed("zone transfer does not start with SOA record"));
+                }
+                let Some(ty) = rdata::record_type(record.ty) else {
+                    log::debug!("Skipping record of unsupported type {} at {}", record.ty, record.name);
+                    continue;
+                };
+                records.push(Record {
+                    name: normalize(&record.name),
+                    ty,
+                    ttl: record.ttl,
+                    data: rdata::from_wire(record.ty, &record.rdata)?,
+                });
+            }
+            first = false;
+        }
+        Ok(records)
+    }
+
+    /// Apply a list of changes to a zone as a single dynamic update.
+    ///
+    /// The server applies either all changes or none of them. The names of the records are fully
+    /// qualified.
+    pub async fn update<S: AsRef<str>>(&self, zone: S, changes: &[Change]) -> Result<()> {
+        let zone = normalize(zone.as_ref());
+        let mut updates = Vec::with_capacity(changes.len());
+        for change in changes {
+            updates.push(match change {
+                Change::Add(record) | Change::Delete(record) => {
+                    let ty = rdata::type_code(&record.ty).ok_or_else(|| Error::UnsupportedRecordType(record.ty.clone()))?;
+                    let add = matches!(change, Change::Add(_));
+                    ResourceRecord {
+                        name: normalize(&record.name),
+                        ty,
+                        class: if add { wire::CLASS_IN } else { wire::CLASS_NONE },
+                        ttl: if add { record.ttl } else { 0 },
+                        rdata: rdata::to_wire(ty, &record.data)?,
+                    }
+                },
+                Change::DeleteRrset { name, ty } => ResourceRecord {
+                    name: normalize(name),
+                    ty: rdata::type_code(ty).ok_or_else(|| Error::UnsupportedRecordType(ty.clone()))?,
+                    class: wire::CLASS_ANY,
+                    ttl: 0,
+                    rdata: Vec::new(),
+                },
+                Change::DeleteName(name) => ResourceRecord {
+                    name: normalize(name),
+                    ty: wire::TYPE_ANY,
+                    class: wire::CLASS_ANY,
+                    ttl: 0,
+                    rdata: Vec::new(),
+                },
+            });
+        }
+        let id = random_id();
+        let (request, request_mac) = self.encode_request(&Message {
+            id,
+            opcode: wire::OPCODE_UPDATE,
+            questions: vec![Question { name: zone.clone(), ty: wire::TYPE_SOA, class: wire::CLASS_IN }],
+            authority: updates,
+            ..Message::default()
+        })?;
+        self.with_deadline(async {
+            let mut stream = self.connect().await?;
+            write_message(&mut stream, &request).await?;
+            let buf = read_message(&mut stream, self.timeouts.read).await?;
+            self.check_response(id, &buf, request_mac.as_deref(), false)?;
+            Ok(())
+        }).await
+    }
+
+}
+
+/// Whether the priority of records of the given type is stored in front of their data.
+fn has_priority(ty: &DnsRecordType) -> bool {
+    matches!(ty, DnsRecordType::MX | DnsRecordType::SRV)
+}
+
+fn relative_name(name: &str, zone: &str) -> String {
+    if name == zone {
+        String::new()
+    } else {
+        name.strip_suffix(zone)
+            .and_then(|name| name.strip_suffix('.'))
+            .unwrap_or(name)
+            .to_string()
+    }
+}
+
+fn absolute_name(name: &str, zone: &str) -> String {
+    if name.is_empty() || name == "@" {
+        zone.to_string()
+    } else {
+        format!("{}.{}", name, zone)
+    }
+}
+
+/// Convert a record to the shared model, which keeps the priority in a separate field.
+fn to_common(record: Record, zone: &str) -> DnsRecord {
+    let (priority, content) = match record.data.split_once(' ') {
+        Some((priority, content)) if has_priority(&record.ty) => match priority.parse() {
+            Ok(priority) => (Some(priority), content.to_string()),
+            Err(_) => (None, record.data),
+        },
+        _ => (None, record.data),
+    };
+    DnsRecord {
+        name: relative_name(&record.name, zone),
+        ty: record.ty,
+        content,
+        ttl: Some(record.ttl),
+        priority,
+    }
+}
+
+impl Client {
+
+    /// The reverse of [`to_common`].
+    fn to_record(&self, record: &DnsRecord, zone: &str) -> Record {
+        let data = if has_priority(&record.ty) {
+            format!("{} {}", record.priority.unwrap_or(0), record.content)
+        } else {
+            record.content.clone()
+        };
+        Record {
+            name: absolute_name(&record.name, zone),
+            ty: record.ty.clone(),
+            ttl: record.ttl.unwrap_or(self.default_ttl),
+            data,
+        }
+    }
+
+    /// Find the record of a zone whose [content identifier](content_id) is the given one.
+    async fn find_record(&self, zone: &str, id: &str) -> Result<Record> {
+        self.transfer(zone)
+            .await?
+            .into_iter()
+            .find(|record| content_id(&to_common(record.clone(), zone)) == id)
+            .ok_or_else(|| Error::RecordNotFound(id.to_string()))
+    }
+
+}
+
+#[async_trait]
+impl Registrar for Client {
+
+    async fn list_zones(&self) -> Result<Vec<String>> {
+        Ok(self.zones.clone())
+    }
+
+    fn record_identity(&self) -> RecordIdentity {
+        RecordIdentity::Content
+    }
+
+    fn supports_type(&self, ty: &DnsRecordType) -> bool {
+        rdata::type_code(ty).is_some()
+    }
+
+    async fn list_records_with_ids(&self, zone: &str) -> Result<Vec<(DnsRecordId, DnsRecord)>> {
+        let zone = normalize(zone);
+        Ok(self.transfer(&zone)
+            .await?
+            .into_iter()
+            .map(|record| {
+                let record = to_common(record, &zone);
+                (content_id(&record), record)
+            })
+            .collect())
+    }
+
+    /// DNS does not assign identifiers to records, so the returned identifier is derived from the
+    /// contents of the new record.
+    async fn create_record(&self, zone: &str, record: &DnsRecord) -> Result<DnsRecordId> {
+        let zone = normalize(zone);
+        self.update(&zone, &[Change::Add(self.to_record(record, &zone))]).await?;
+        Ok(content_id(record))
+    }
+
+    /// The old record is deleted and the new one added in the same dynamic update.
+    async fn update_record(&self, zone: &str, id: &str, record: &DnsRecord) -> Result<DnsRecordId> {
+        let zone = normalize(zone);
+        let original = self.find_record(&zone, id).await?;
+        self.update(&zone, &[
+            Change::Delete(original),
+            Change::Add(self.to_record(record, &zone)),
+        ]).await?;
+        Ok(content_id(record))
+    }
+
+    async fn delete_record(&self, zone: &str, id: &str) -> Result<()> {
+        let zone = normalize(zone);
+        let original = self.find_record(&zone, id).await?;
+        self.update(&zone, &[Change::Delete(original)]).await
+    }
+
+}
@@ -0,0 +1,338 @@
+//! An in-process stub of an authoritative DNS server, meant for testing.
+//!
+//! The stub serves a single zone over TCP. It answers zone transfers, applies dynamic updates
+//! without prerequisites and, if it was given a key, only accepts messages that are signed with
+//! it. Transfers are split over several messages, so that clients have to verify the signatures
+//! of all of them.
+//!
+//! ```no_run
+//! use rfc2136::mock::MockServer;
+//! use registrar_common::DnsRecordType;
+//!
+//! let key = rfc2136::Key::new("update-key", "c2VjcmV0IGtleSBmb3IgdGVzdGluZw==")?;
+//! let server = MockServer::start("example.com", Some(key.clone())).await;
+//! server.add_record("www", DnsRecordType::A, 300, "192.0.2.1");
+//!
+//! let client = rfc2136::Builder::new()
+//!     .server(server.address().to_string())
+//!     .key(key)
+//!     .zone("example.com")
+//!     .build();
+//!
+//! assert_eq!(client.transfer("example.com").await?.len(), 2);
+//! ```
+
+use std::collections::VecDeque;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+
+use registrar_common::DnsRecordType;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::task::JoinHandle;
+
+use crate::tsig::TsigError;
+use crate::wire::{self, Message, ResourceRecord};
+use crate::{normalize, rdata, Key, Record};
+
+/// The amount of records that are sent in each message of a zone transfer.
+const TRANSFER_CHUNK_SIZE: usize = 20;
+
+const RCODE_FORMERR: u8 = 1;
+const RCODE_NOTIMP: u8 = 4;
+const RCODE_REFUSED: u8 = 5;
+const RCODE_NOTZONE: u8 = 10;
+
+struct State {
+    zone: String,
+    key: Option<Key>,
+    soa: ResourceRecord,
+    records: Vec<ResourceRecord>,
+    errors: VecDeque<u8>,
+    requests: usize,
+}
+
+impl State {
+
+    fn serial(&self) -> u32 {
+        let offset = self.soa.rdata.len() - 20;
+        u32::from_be_bytes(self.soa.rdata[offset..offset + 4].try_into().unwrap())
+    }
+
+    fn bump_serial(&mut self) {
+        let offset = self.soa.rdata.len() - 20;
+        let serial = self.serial().wrapping_add(1);
+        self.soa.rdata[offset..offset + 4].copy_from_slice(&serial.to_be_bytes());
+    }
+
+    fn in_zone(&self, name: &str) -> bool {
+        name == self.zone || name.ends_with(&format!(".{}", self.zone))
+    }
+
+}
+
+/// A stub DNS server running on a random port on the loopback interface.
+///
+/// The server stops when this object is dropped.
+pub struct MockServer {
+    address: SocketAddr,
+    task: JoinHandle<()>,
+    state: Arc<Mutex<State>>,
+}
+
+impl Drop for MockServer {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+impl MockServer {
+
+    /// Start a new server for the given zone in the background of the current Tokio runtime.
+    ///
+    /// If a key is given, all requests have to be signed with it.
+    pub async fn start<S: AsRef<str>>(zone: S, key: Option<Key>) -> Self {
+        let zone = normalize(zone.as_ref());
+        let soa_data = format!("ns1.{} hostmaster.{} 1 3600 600 604800 3600", zone, zone);
+        let state = Arc::new(Mutex::new(State {
+            soa: ResourceRecord {
+                name: zone.clone(),
+                ty: wire::TYPE_SOA,
+                class: wire::CLASS_IN,
+                ttl: 3600,
+                rdata: rdata::to_wire(wire::TYPE_SOA, &soa_data).unwrap(),
+            },
+            zone,
+            key,
+            records: Vec::new(),
+            errors: VecDeque::new(),
+            requests: 0,
+        }));
+        let listener = TcpListener::bind("127.0.0.1:0").await
+            .expect("failed to bind mock server to a local port");
+        let address = listener.local_addr().unwrap();
+        let handler_state = state.clone();
+        let task = tokio::spawn(async move {
+            loop {
+                let (stream, _) = match listener.accept().await {
+                    Ok(connection) => connection,
+                    Err(error) => {
+                        log::warn!("mock server failed to accept a connection: {}", error);
+                        continue;
+                    },
+                };
+                tokio::spawn(serve(handler_state.clone(), stream));
+            }
+        });
+        Self { address, task, state }
+    }
+
+    pub fn address(&self) -> SocketAddr {
+        self.address
+    }
+
+    /// Add a record to the zone.
+    ///
+    /// The name is relative to the zone, with an empty name referring to the zone apex. The data
+    /// is in the presentation format, e.g. `10 mail.example.com` for an MX record.
+    pub fn add_record(&self, name: &str, ty: DnsRecordType, ttl: u32, data: &str) {
+        let mut state = self.state.lock().unwrap();
+        let name = if name.is_empty() { state.zone.clone() } else { format!("{}.{}", normalize(name), state.zone) };
+        let ty = rdata::type_code(&ty).expect("record type is not supported by mock server");
+        state.records.push(ResourceRecord {
+            name,
+            ty,
+            class: wire::CLASS_IN,
+            ttl,
+            rdata: rdata::to_wire(ty, data).expect("invalid record data"),
+        });
+    }
+
+    /// Get the records of the zone as they are currently stored on the server, excluding the SOA
+    /// record.
+    pub fn records(&self) -> Vec<Record> {
+        self.state.lock().unwrap()
+            .records
+            .iter()
+            .map(|record| Record {
+                name: record.name.clone(),
+                ty: rdata::record_type(record.ty).unwrap(),
+                ttl: record.ttl,
+                data: rdata::from_wire(record.ty, &record.rdata).unwrap(),
+            })
+            .collect()
+    }
+
+    /// The serial number of the zone, which is increased by every update that changes it.
+    pub fn serial(&self) -> u32 {
+        self.state.lock().unwrap().serial()
+    }
+
+    /// Make the next request fail with the given response code, e.g. 2 for SERVFAIL.
+    ///
+    /// Calling this multiple times queues up errors for the requests that follow.
+    pub fn push_error(&self, rcode: u8) {
+        self.state.lock().unwrap().errors.push_back(rcode);
+    }
+
+    /// The amount of requests the server has received so far.
+    pub fn request_count(&self) -> usize {
+        self.state.lock().unwrap().requests
+    }
+
+}
+
+async fn serve(state: Arc<Mutex<State>>, mut stream: TcpStream) {
+    loop {
+        let Ok(length) = stream.read_u16().await else {
+            return;
+        };
+        let mut buf = vec![0; length as usize];
+        if stream.read_exact(&mut buf).await.is_err() {
+            return;
+        }
+        let responses = handle(&mut state.lock().unwrap(), &buf);
+        for response in responses {
+            let mut frame = (response.len() as u16).to_be_bytes().to_vec();
+            frame.extend_from_slice(&response);
+            if stream.write_all(&frame).await.is_err() {
+                return;
+            }
+        }
+    }
+}
+
+/// Build the header of a response to the given request.
+fn reply(request: &Message, rcode: u8) -> Message {
+    Message {
+        id: request.id,
+        response: true,
+        opcode: request.opcode,
+        authoritative: true,
+        rcode,
+        questions: request.questions.clone(),
+        ..Message::default()
+    }
+}
+
+/// Encode a response, signing it with the key of the server if the request was signed.
+fn finish(state: &State, response: &Message, request_mac: Option<&[u8]>, tsig_error: u16) -> Vec<u8> {
+    let mut buf = response.encode().unwrap();
+    if let (Some(key), Some(mac)) = (&state.key, request_mac) {
+        key.sign(&mut buf, Some(mac), false, tsig_error).unwrap();
+    }
+    buf
+}
+
+fn handle(state: &mut State, buf: &[u8]) -> Vec<Vec<u8>> {
+
+    state.requests += 1;
+
+    let request = match Message::decode(buf) {
+        Ok(request) => request,
+        Err(_) if buf.len() >= 2 => {
+            let response = Message {
+                id: u16::from_be_bytes([buf[0], buf[1]]),
+                response: true,
+                rcode: RCODE_FORMERR,
+                ..Message::default()
+            };
+            return vec![response.encode().unwrap()];
+        },
+        Err(_) => return Vec::new(),
+    };
+
+    let request_mac = match &state.key {
+        None => None,
+        Some(key) => match key.verify(buf, &request, None, false) {
+            Ok(mac) => Some(mac),
+            Err(TsigError::Unsigned) => {
+                return vec![finish(state, &reply(&request, wire::RCODE_NOTAUTH), None, 0)];
+            },
+            Err(error) => {
+                // The MAC of the request is not known to be valid, so it is not covered by the
+                // unsigned answer.
+                let response = reply(&request, wire::RCODE_NOTAUTH);
+                let mut buf = response.encode().unwrap();
+                key.sign(&mut buf, Some(&[]), false, error.code()).unwrap();
+                return vec![buf];
+            },
+        },
+    };
+
+    if let Some(rcode) = state.errors.pop_front() {
+        return vec![finish(state, &reply(&request, rcode), request_mac.as_deref(), 0)];
+    }
+
+    let question = request.questions.first().cloned();
+    match (request.opcode, question) {
+        (wire::OPCODE_QUERY, Some(question)) if question.ty == wire::TYPE_AXFR => {
+            if normalize(&question.name) != state.zone {
+                return vec![finish(state, &reply(&request, RCODE_REFUSED), request_mac.as_deref(), 0)];
+            }
+            let mut records = vec![state.soa.clone()];
+            records.extend(state.records.iter().cloned());
+            records.push(state.soa.clone());
+            let mut responses = Vec::new();
+            let mut prior_mac = request_mac;
+            for (i, chunk) in records.chunks(TRANSFER_CHUNK_SIZE).enumerate() {
+                let mut response = reply(&request, wire::RCODE_NOERROR);
+                response.answers = chunk.to_vec();
+                let mut buf = response.encode().unwrap();
+                if let (Some(key), Some(mac)) = (&state.key, &prior_mac) {
+                    prior_mac = Some(key.sign(&mut buf, Some(mac), i > 0, 0).unwrap());
+                }
+                responses.push(buf);
+            }
+            responses
+        },
+        (wire::OPCODE_UPDATE, Some(question)) if question.ty == wire::TYPE_SOA => {
+            let rcode = apply_update(state, &request);
+            vec![finish(state, &reply(&request, rcode), request_mac.as_deref(), 0)]
+        },
+        _ => vec![finish(state, &reply(&request, RCODE_NOTIMP), request_mac.as_deref(), 0)],
+    }
+}
+
+/// Apply the update section of a request to the zone and return the response code.
+///
+/// Nothing is changed unless all updates are valid.
+fn apply_update(state: &mut State, request: &Message) -> u8 {
+    if normalize(&request.questions[0].name) != state.zone {
+        return RCODE_NOTZONE;
+    }
+    if !request.answers.is_empty() {
+        return RCODE_NOTIMP;
+    }
+    let mut records = state.records.clone();
+    for update in &request.authority {
+        let name = normalize(&update.name);
+        if !state.in_zone(&name) {
+            return RCODE_NOTZONE;
+        }
+        if update.ty == wire::TYPE_SOA {
+            return RCODE_REFUSED;
+        }
+        match update.class {
+            wire::CLASS_IN => {
+                for record in records.iter_mut().filter(|record| record.name == name && record.ty == update.ty) {
+                    record.ttl = update.ttl;
+                }
+                if !records.iter().any(|record| record.name == name && record.ty == update.ty && record.rdata == update.rdata) {
+                    records.push(ResourceRecord { name, ..update.clone() });
+                }
+            },
+            wire::CLASS_ANY if update.ty == wire::TYPE_ANY => records.retain(|record| record.name != name),
+            wire::CLASS_ANY => records.retain(|record| record.name != name || record.ty != update.ty),
+            wire::CLASS_NONE => records.retain(|record| {
+                record.name != name || record.ty != update.ty || record.rdata != update.rdata
+            }),
+            _ => return RCODE_FORMERR,
+        }
+    }
+    if records != state.records {
+        state.records = records;
+        state.bump_serial();
+    }
+    wire::RCODE_NOERROR
+}
@@ -0,0 +1,162 @@
+
+//! Conversion between the presentation format of records and their RDATA.
+//!
+//! Names in the presentation format may carry a trailing dot, but are always taken to be fully
+//! qualified. Names are written without the trailing dot, just like the HTTP APIs of the other
+//! providers return them.
+
+use std::net::{Ipv4Addr, Ipv6Addr};
+
+use registrar_common::{DnsRecordType, Error, Result};
+
+use crate::wire::{self, Reader};
+
+/// Get the numeric type of a record type, if it is supported by this crate.
+pub(crate) fn type_code(ty: &DnsRecordType) -> Option<u16> {
+    Some(match ty {
+        DnsRecordType::A => wire::TYPE_A,
+        DnsRecordType::NS => wire::TYPE_NS,
+        DnsRecordType::CNAME => wire::TYPE_CNAME,
+        DnsRecordType::SOA => wire::TYPE_SOA,
+        DnsRecordType::PTR => wire::TYPE_PTR,
+        DnsRecordType::MX => wire::TYPE_MX,
+        DnsRecordType::TXT => wire::TYPE_TXT,
+        DnsRecordType::AAAA => wire::TYPE_AAAA,
+        DnsRecordType::SRV => wire::TYPE_SRV,
+        DnsRecordType::DNAME => wire::TYPE_DNAME,
+        DnsRecordType::SPF => wire::TYPE_SPF,
+        DnsRecordType::CAA => wire::TYPE_CAA,
+        _ => return None,
+    })
+}
+
+/// Get the record type belonging to a numeric type, if it is supported by this crate.
+pub(crate) fn record_type(code: u16) -> Option<DnsRecordType> {
+    Some(match code {
+        wire::TYPE_A => DnsRecordType::A,
+        wire::TYPE_NS => DnsRecordType::NS,
+        wire::TYPE_CNAME => DnsRecordType::CNAME,
+        wire::TYPE_SOA => DnsRecordType::SOA,
+        wire::TYPE_PTR => DnsRecordType::PTR,
+        wire::TYPE_MX => DnsRecordType::MX,
+        wire::TYPE_TXT => DnsRecordType::TXT,
+        wire::TYPE_AAAA => DnsRecordType::AAAA,
+        wire::TYPE_SRV => DnsRecordType::SRV,
+        wire::TYPE_DNAME => DnsRecordType::DNAME,
+        wire::TYPE_SPF => DnsRecordType::SPF,
+        wire::TYPE_CAA => DnsRecordType::CAA,
+        _ => return None,
+    })
+}
+
+fn invalid(ty: u16, data: &str) -> Error {
+    Error::Generic(format!("invalid data for record of type {}: {}", ty, data))
+}
+
+fn number<T: std::str::FromStr>(ty: u16, data: &str, field: Option<&str>) -> Result<T> {
+    field.and_then(|field| field.parse().ok()).ok_or_else(|| invalid(ty, data))
+}
+
+/// Encode the presentation format of a record into RDATA.
+pub(crate) fn to_wire(ty: u16, data: &str) -> Result<Vec<u8>> {
+    let mut rdata = Vec::new();
+    let mut fields = data.split_whitespace();
+    match ty {
+        wire::TYPE_A => {
+            let address: Ipv4Addr = data.trim().parse().map_err(|_| invalid(ty, data))?;
+            rdata.extend_from_slice(&address.octets());
+        },
+        wire::TYPE_AAAA => {
+            let address: Ipv6Addr = data.trim().parse().map_err(|_| invalid(ty, data))?;
+            rdata.extend_from_slice(&address.octets());
+        },
+        wire::TYPE_NS | wire::TYPE_CNAME | wire::TYPE_PTR | wire::TYPE_DNAME => {
+            let name = fields.next().ok_or_else(|| invalid(ty, data))?;
+            wire::write_name(&mut rdata, name)?;
+        },
+        wire::TYPE_MX => {
+            rdata.extend_from_slice(&number::<u16>(ty, data, fields.next())?.to_be_bytes());
+            wire::write_name(&mut rdata, fields.next().ok_or_else(|| invalid(ty, data))?)?;
+        },
+        wire::TYPE_SRV => {
+            for _ in 0..3 {
+                rdata.extend_from_slice(&number::<u16>(ty, data, fields.next())?.to_be_bytes());
+            }
+            wire::write_name(&mut rdata, fields.next().ok_or_else(|| invalid(ty, data))?)?;
+        },
+        wire::TYPE_SOA => {
+            wire::write_name(&mut rdata, fields.next().ok_or_else(|| invalid(ty, data))?)?;
+            wire::write_name(&mut rdata, fields.next().ok_or_else(|| invalid(ty, data))?)?;
+            for _ in 0..5 {
+                rdata.extend_from_slice(&number::<u32>(ty, data, fields.next())?.to_be_bytes());
+            }
+        },
+        wire::TYPE_TXT | wire::TYPE_SPF => {
+            let bytes = data.as_bytes();
+            if bytes.is_empty() {
+                rdata.push(0);
+            }
+            for chunk in bytes.chunks(255) {
+                rdata.push(chunk.len() as u8);
+                rdata.extend_from_slice(chunk);
+            }
+        },
+        wire::TYPE_CAA => {
+            let flags = number::<u8>(ty, data, fields.next())?;
+            let tag = fields.next().ok_or_else(|| invalid(ty, data))?;
+            let value = fields.collect::<Vec<_>>().join(" ");
+            let value = value.strip_prefix('"').and_then(|value| value.strip_suffix('"')).unwrap_or(&value);
+            if tag.is_empty() || tag.len() > 255 {
+                return Err(invalid(ty, data));
+            }
+            rdata.push(flags);
+            rdata.push(tag.len() as u8);
+            rdata.extend_from_slice(tag.as_bytes());
+            rdata.extend_from_slice(value.as_bytes());
+        },
+        _ => return Err(Error::Generic(format!("records of type {} are not supported", ty))),
+    }
+    Ok(rdata)
+}
+
+/// Decode RDATA into the presentation format of a record.
+pub(crate) fn from_wire(ty: u16, rdata: &[u8]) -> Result<String> {
+    let mut reader = Reader::new(rdata);
+    let data = match ty {
+        wire::TYPE_A => {
+            let octets: [u8; 4] = rdata.try_into().map_err(|_| wire::malformed("A record of wrong length"))?;
+            return Ok(Ipv4Addr::from(octets).to_string());
+        },
+        wire::TYPE_AAAA => {
+            let octets: [u8; 16] = rdata.try_into().map_err(|_| wire::malformed("AAAA record of wrong length"))?;
+            return Ok(Ipv6Addr::from(octets).to_string());
+        },
+        wire::TYPE_NS | wire::TYPE_CNAME | wire::TYPE_PTR | wire::TYPE_DNAME => reader.name()?,
+        wire::TYPE_MX => format!("{} {}", reader.u16()?, reader.name()?),
+        wire::TYPE_SRV => format!("{} {} {} {}", reader.u16()?, reader.u16()?, reader.u16()?, reader.name()?),
+        wire::TYPE_SOA => format!(
+            "{} {} {} {} {} {} {}",
+            reader.name()?, reader.name()?, reader.u32()?, reader.u32()?, reader.u32()?, reader.u32()?, reader.u32()?
+        ),
+        wire::TYPE_TXT | wire::TYPE_SPF => {
+            let mut text = Vec::new();
+            while !reader.is_empty() {
+                let length = reader.u8()? as usize;
+                text.extend_from_slice(reader.bytes(length)?);
+            }
+            String::from_utf8_lossy(&text).into_owned()
+        },
+        wire::TYPE_CAA => {
+            let flags = reader.u8()?;
+            let length = reader.u8()? as usize;
+            let tag = String::from_utf8_lossy(reader.bytes(length)?).into_owned();
+            let value = String::from_utf8_lossy(reader.bytes(rdata.len() - reader.pos)?).into_owned();
+            format!("{} {} \"{}\"", flags, tag, value)
+        },
+        _ => return Err(Error::Generic(format!("records of type {} are not supported", ty))),
+    };
+    if !reader.is_empty() {
+        return Err(wire::malformed(format!("trailing data in record of type {}", ty)));
+    }
+    Ok(data)
+}
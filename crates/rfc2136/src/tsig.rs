@@ -0,0 +1,222 @@
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use registrar_common::{Error, Result};
+use ring::hmac;
+
+use crate::wire::{self, Message, Reader, ResourceRecord};
+
+/// The only algorithm that is supported.
+pub const HMAC_SHA256: &str = "hmac-sha256";
+
+/// The amount of seconds the clocks of client and server may differ.
+const FUDGE: u16 = 300;
+
+pub(crate) const TSIG_BADSIG: u16 = 16;
+pub(crate) const TSIG_BADKEY: u16 = 17;
+pub(crate) const TSIG_BADTIME: u16 = 18;
+
+/// Why the signature of a message was not accepted.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum TsigError {
+    /// The message did not carry a TSIG record at all.
+    Unsigned,
+    /// The message was signed with another key or algorithm.
+    BadKey,
+    /// The MAC did not match the contents of the message.
+    BadSig,
+    /// The message was signed too long ago or in the future.
+    BadTime,
+    /// The TSIG record could not be parsed.
+    Malformed,
+}
+
+impl TsigError {
+
+    /// The value of the error field in the TSIG record that reports this error.
+    #[cfg(feature = "mock")]
+    pub fn code(&self) -> u16 {
+        match self {
+            Self::BadKey => TSIG_BADKEY,
+            Self::BadTime => TSIG_BADTIME,
+            _ => TSIG_BADSIG,
+        }
+    }
+
+}
+
+/// The fields of the RDATA of a TSIG record.
+struct TsigData {
+    algorithm: String,
+    time_signed: u64,
+    fudge: u16,
+    mac: Vec<u8>,
+    original_id: u16,
+    error: u16,
+    other: Vec<u8>,
+}
+
+impl TsigData {
+
+    fn decode(rdata: &[u8]) -> Result<Self> {
+        let mut reader = Reader::new(rdata);
+        let algorithm = reader.name()?;
+        let time_signed = reader.u48()?;
+        let fudge = reader.u16()?;
+        let mac_size = reader.u16()? as usize;
+        let mac = reader.bytes(mac_size)?.to_vec();
+        let original_id = reader.u16()?;
+        let error = reader.u16()?;
+        let other_size = reader.u16()? as usize;
+        let other = reader.bytes(other_size)?.to_vec();
+        Ok(Self { algorithm, time_signed, fudge, mac, original_id, error, other })
+    }
+
+    fn encode(&self) -> Result<Vec<u8>> {
+        let mut rdata = Vec::new();
+        wire::write_name(&mut rdata, &self.algorithm)?;
+        rdata.extend_from_slice(&self.time_signed.to_be_bytes()[2..]);
+        rdata.extend_from_slice(&self.fudge.to_be_bytes());
+        rdata.extend_from_slice(&(self.mac.len() as u16).to_be_bytes());
+        rdata.extend_from_slice(&self.mac);
+        rdata.extend_from_slice(&self.original_id.to_be_bytes());
+        rdata.extend_from_slice(&self.error.to_be_bytes());
+        rdata.extend_from_slice(&(self.other.len() as u16).to_be_bytes());
+        rdata.extend_from_slice(&self.other);
+        Ok(rdata)
+    }
+
+}
+
+/// A shared secret that is used to sign DNS messages with TSIG, as described in RFC 8945.
+///
+/// Only HMAC-SHA256 is supported.
+///
+/// ```no_run
+/// let key = rfc2136::Key::new("update-key", "c2VjcmV0IGtleSBmb3IgdGVzdGluZw==")?;
+/// ```
+#[derive(Clone)]
+pub struct Key {
+    name: String,
+    key: hmac::Key,
+}
+
+impl std::fmt::Debug for Key {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Key").field("name", &self.name).finish_non_exhaustive()
+    }
+}
+
+fn now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|time| time.as_secs()).unwrap_or(0)
+}
+
+impl Key {
+
+    /// Create a key with the given name from a base64-encoded secret, as it appears in the
+    /// configuration of BIND, Knot or PowerDNS.
+    pub fn new<S: Into<String>>(name: S, secret: &str) -> Result<Self> {
+        let secret = STANDARD.decode(secret.trim())
+            .map_err(|error| Error::Generic(format!("TSIG secret is not valid base64: {}", error)))?;
+        Ok(Self {
+            name: name.into().trim_end_matches('.').to_ascii_lowercase(),
+            key: hmac::Key::new(hmac::HMAC_SHA256, &secret),
+        })
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Compose the data that is fed into the HMAC.
+    fn digest_input(&self, prior_mac: Option<&[u8]>, message: &[u8], data: &TsigData, timers_only: bool) -> Result<Vec<u8>> {
+        let mut input = Vec::with_capacity(message.len() + 128);
+        if let Some(mac) = prior_mac {
+            input.extend_from_slice(&(mac.len() as u16).to_be_bytes());
+            input.extend_from_slice(mac);
+        }
+        input.extend_from_slice(message);
+        if !timers_only {
+            wire::write_name(&mut input, &self.name)?;
+            input.extend_from_slice(&wire::CLASS_ANY.to_be_bytes());
+            input.extend_from_slice(&0u32.to_be_bytes());
+            wire::write_name(&mut input, &data.algorithm.to_ascii_lowercase())?;
+        }
+        input.extend_from_slice(&data.time_signed.to_be_bytes()[2..]);
+        input.extend_from_slice(&data.fudge.to_be_bytes());
+        if !timers_only {
+            input.extend_from_slice(&data.error.to_be_bytes());
+            input.extend_from_slice(&(data.other.len() as u16).to_be_bytes());
+            input.extend_from_slice(&data.other);
+        }
+        Ok(input)
+    }
+
+    /// Append a TSIG record to an encoded message and return its MAC.
+    ///
+    /// Responses pass the MAC of the request, or of the previous message of the same response, as
+    /// the prior MAC. Only the first message of a response covers all TSIG variables; the ones
+    /// that follow only cover the timers.
+    pub(crate) fn sign(&self, message: &mut Vec<u8>, prior_mac: Option<&[u8]>, timers_only: bool, error: u16) -> Result<Vec<u8>> {
+        let mut data = TsigData {
+            algorithm: HMAC_SHA256.to_string(),
+            time_signed: now(),
+            fudge: FUDGE,
+            mac: Vec::new(),
+            original_id: u16::from_be_bytes([message[0], message[1]]),
+            error,
+            other: Vec::new(),
+        };
+        // A server that could not verify the key or signature of a request does not sign its
+        // answer.
+        if error != TSIG_BADKEY && error != TSIG_BADSIG {
+            let input = self.digest_input(prior_mac, message, &data, timers_only)?;
+            data.mac = hmac::sign(&self.key, &input).as_ref().to_vec();
+        }
+        let record = ResourceRecord {
+            name: self.name.clone(),
+            ty: wire::TYPE_TSIG,
+            class: wire::CLASS_ANY,
+            ttl: 0,
+            rdata: data.encode()?,
+        };
+        let mut tail = Message { additional: vec![record], ..Message::default() }.encode()?;
+        message.extend_from_slice(&tail.split_off(12));
+        let count = u16::from_be_bytes([message[10], message[11]]) + 1;
+        message[10..12].copy_from_slice(&count.to_be_bytes());
+        Ok(data.mac)
+    }
+
+    /// Check the TSIG record of a decoded message and return its MAC.
+    ///
+    /// The error field of the record is reported as well, so that an unsigned response that
+    /// rejects the request is recognized as such.
+    pub(crate) fn verify(&self, buf: &[u8], message: &Message, prior_mac: Option<&[u8]>, timers_only: bool) -> std::result::Result<Vec<u8>, TsigError> {
+        let Some((offset, record)) = &message.tsig else {
+            return Err(TsigError::Unsigned);
+        };
+        let data = TsigData::decode(&record.rdata).map_err(|_| TsigError::Malformed)?;
+        match data.error {
+            0 => {},
+            TSIG_BADKEY => return Err(TsigError::BadKey),
+            TSIG_BADTIME => return Err(TsigError::BadTime),
+            _ => return Err(TsigError::BadSig),
+        }
+        if !record.name.eq_ignore_ascii_case(&self.name) || !data.algorithm.eq_ignore_ascii_case(HMAC_SHA256) {
+            return Err(TsigError::BadKey);
+        }
+        let mut unsigned = buf[..*offset].to_vec();
+        unsigned[0..2].copy_from_slice(&data.original_id.to_be_bytes());
+        let count = u16::from_be_bytes([unsigned[10], unsigned[11]]).saturating_sub(1);
+        unsigned[10..12].copy_from_slice(&count.to_be_bytes());
+        let input = self.digest_input(prior_mac, &unsigned, &data, timers_only).map_err(|_| TsigError::Malformed)?;
+        hmac::verify(&self.key, &input, &data.mac).map_err(|_| TsigError::BadSig)?;
+        if now().abs_diff(data.time_signed) > data.fudge as u64 {
+            return Err(TsigError::BadTime);
+        }
+        Ok(data.mac)
+    }
+
+}
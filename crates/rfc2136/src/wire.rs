@@ -0,0 +1,315 @@
+
+//! Encoding and decoding of DNS messages as described in RFC 1035.
+//!
+//! Names are represented as strings without the trailing dot, so the root is the empty string.
+//! Compression is understood when decoding but never used when encoding, and the RDATA of record
+//! types that contain names is decompressed while decoding, so it can be copied between messages.
+
+use registrar_common::{Error, Result};
+
+pub(crate) const CLASS_IN: u16 = 1;
+pub(crate) const CLASS_NONE: u16 = 254;
+pub(crate) const CLASS_ANY: u16 = 255;
+
+pub(crate) const TYPE_A: u16 = 1;
+pub(crate) const TYPE_NS: u16 = 2;
+pub(crate) const TYPE_CNAME: u16 = 5;
+pub(crate) const TYPE_SOA: u16 = 6;
+pub(crate) const TYPE_PTR: u16 = 12;
+pub(crate) const TYPE_MX: u16 = 15;
+pub(crate) const TYPE_TXT: u16 = 16;
+pub(crate) const TYPE_AAAA: u16 = 28;
+pub(crate) const TYPE_SRV: u16 = 33;
+pub(crate) const TYPE_DNAME: u16 = 39;
+pub(crate) const TYPE_SPF: u16 = 99;
+pub(crate) const TYPE_TSIG: u16 = 250;
+pub(crate) const TYPE_AXFR: u16 = 252;
+pub(crate) const TYPE_ANY: u16 = 255;
+pub(crate) const TYPE_CAA: u16 = 257;
+
+pub(crate) const OPCODE_QUERY: u8 = 0;
+pub(crate) const OPCODE_UPDATE: u8 = 5;
+
+pub(crate) const RCODE_NOERROR: u8 = 0;
+pub(crate) const RCODE_NOTAUTH: u8 = 9;
+
+/// The largest message that fits into the two byte length prefix of DNS over TCP.
+pub(crate) const MAX_MESSAGE_SIZE: usize = 65535;
+
+const MAX_POINTERS: usize = 64;
+
+/// Get the mnemonic of a response code, for use in error messages.
+pub(crate) fn rcode_name(rcode: u8) -> &'static str {
+    match rcode {
+        0 => "NOERROR",
+        1 => "FORMERR",
+        2 => "SERVFAIL",
+        3 => "NXDOMAIN",
+        4 => "NOTIMP",
+        5 => "REFUSED",
+        6 => "YXDOMAIN",
+        7 => "YXRRSET",
+        8 => "NXRRSET",
+        9 => "NOTAUTH",
+        10 => "NOTZONE",
+        _ => "unknown response code",
+    }
+}
+
+pub(crate) fn malformed<S: AsRef<str>>(reason: S) -> Error {
+    Error::Generic(format!("malformed DNS message: {}", reason.as_ref()))
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct Question {
+    pub name: String,
+    pub ty: u16,
+    pub class: u16,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct ResourceRecord {
+    pub name: String,
+    pub ty: u16,
+    pub class: u16,
+    pub ttl: u32,
+    pub rdata: Vec<u8>,
+}
+
+/// A DNS message. For UPDATE messages, the four sections are called zone, prerequisite, update and
+/// additional instead.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct Message {
+    pub id: u16,
+    pub response: bool,
+    pub opcode: u8,
+    pub authoritative: bool,
+    pub rcode: u8,
+    pub questions: Vec<Question>,
+    pub answers: Vec<ResourceRecord>,
+    pub authority: Vec<ResourceRecord>,
+    pub additional: Vec<ResourceRecord>,
+    /// The TSIG record at the end of the message together with the offset it started at.
+    ///
+    /// This is only filled in when decoding; [`Message::encode`] never writes it.
+    pub tsig: Option<(usize, ResourceRecord)>,
+}
+
+impl Message {
+
+    pub fn encode(&self) -> Result<Vec<u8>> {
+        let mut buf = Vec::with_capacity(512);
+        let mut flags = ((self.opcode as u16 & 0xf) << 11) | (self.rcode as u16 & 0xf);
+        if self.response {
+            flags |= 0x8000;
+        }
+        if self.authoritative {
+            flags |= 0x0400;
+        }
+        buf.extend_from_slice(&self.id.to_be_bytes());
+        buf.extend_from_slice(&flags.to_be_bytes());
+        for count in [self.questions.len(), self.answers.len(), self.authority.len(), self.additional.len()] {
+            let count = u16::try_from(count).map_err(|_| malformed("too many records"))?;
+            buf.extend_from_slice(&count.to_be_bytes());
+        }
+        for question in &self.questions {
+            write_name(&mut buf, &question.name)?;
+            buf.extend_from_slice(&question.ty.to_be_bytes());
+            buf.extend_from_slice(&question.class.to_be_bytes());
+        }
+        for record in self.answers.iter().chain(&self.authority).chain(&self.additional) {
+            write_record(&mut buf, record)?;
+        }
+        if buf.len() > MAX_MESSAGE_SIZE {
+            return Err(malformed("message is too large"));
+        }
+        Ok(buf)
+    }
+
+    pub fn decode(buf: &[u8]) -> Result<Self> {
+        let mut reader = Reader { buf, pos: 0 };
+        let id = reader.u16()?;
+        let flags = reader.u16()?;
+        let counts = [reader.u16()?, reader.u16()?, reader.u16()?, reader.u16()?];
+        let mut message = Message {
+            id,
+            response: flags & 0x8000 != 0,
+            opcode: ((flags >> 11) & 0xf) as u8,
+            authoritative: flags & 0x0400 != 0,
+            rcode: (flags & 0xf) as u8,
+            ..Message::default()
+        };
+        for _ in 0..counts[0] {
+            message.questions.push(Question {
+                name: reader.name()?,
+                ty: reader.u16()?,
+                class: reader.u16()?,
+            });
+        }
+        for _ in 0..counts[1] {
+            message.answers.push(reader.record()?);
+        }
+        for _ in 0..counts[2] {
+            message.authority.push(reader.record()?);
+        }
+        for i in 0..counts[3] {
+            let offset = reader.pos;
+            let record = reader.record()?;
+            if record.ty == TYPE_TSIG {
+                if i + 1 != counts[3] {
+                    return Err(malformed("TSIG record is not the last record"));
+                }
+                message.tsig = Some((offset, record));
+            } else {
+                message.additional.push(record);
+            }
+        }
+        Ok(message)
+    }
+
+}
+
+pub(crate) fn write_name(buf: &mut Vec<u8>, name: &str) -> Result<()> {
+    let start = buf.len();
+    for label in name.split('.').filter(|label| !label.is_empty()) {
+        if label.len() > 63 {
+            return Err(malformed(format!("label {} is longer than 63 bytes", label)));
+        }
+        buf.push(label.len() as u8);
+        buf.extend_from_slice(label.as_bytes());
+    }
+    buf.push(0);
+    if buf.len() - start > 255 {
+        return Err(malformed(format!("name {} is longer than 255 bytes", name)));
+    }
+    Ok(())
+}
+
+fn write_record(buf: &mut Vec<u8>, record: &ResourceRecord) -> Result<()> {
+    write_name(buf, &record.name)?;
+    buf.extend_from_slice(&record.ty.to_be_bytes());
+    buf.extend_from_slice(&record.class.to_be_bytes());
+    buf.extend_from_slice(&record.ttl.to_be_bytes());
+    let length = u16::try_from(record.rdata.len()).map_err(|_| malformed("RDATA is too long"))?;
+    buf.extend_from_slice(&length.to_be_bytes());
+    buf.extend_from_slice(&record.rdata);
+    Ok(())
+}
+
+/// Reads the fields of a DNS message one after the other.
+pub(crate) struct Reader<'a> {
+    pub buf: &'a [u8],
+    pub pos: usize,
+}
+
+impl<'a> Reader<'a> {
+
+    pub fn new(buf: &'a [u8]) -> Self {
+        Self { buf, pos: 0 }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pos >= self.buf.len()
+    }
+
+    pub fn bytes(&mut self, count: usize) -> Result<&'a [u8]> {
+        let bytes = self.buf.get(self.pos..self.pos + count).ok_or_else(|| malformed("unexpected end"))?;
+        self.pos += count;
+        Ok(bytes)
+    }
+
+    pub fn u8(&mut self) -> Result<u8> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    pub fn u16(&mut self) -> Result<u16> {
+        let bytes = self.bytes(2)?;
+        Ok(u16::from_be_bytes([bytes[0], bytes[1]]))
+    }
+
+    pub fn u32(&mut self) -> Result<u32> {
+        let bytes = self.bytes(4)?;
+        Ok(u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    pub fn u48(&mut self) -> Result<u64> {
+        let bytes = self.bytes(6)?;
+        Ok(bytes.iter().fold(0, |value, byte| (value << 8) | *byte as u64))
+    }
+
+    /// Read a possibly compressed name.
+    pub fn name(&mut self) -> Result<String> {
+        let mut labels = Vec::new();
+        let mut pos = self.pos;
+        let mut end = None;
+        let mut pointers = 0;
+        loop {
+            let length = *self.buf.get(pos).ok_or_else(|| malformed("unexpected end of name"))? as usize;
+            match length & 0xc0 {
+                0x00 if length == 0 => {
+                    pos += 1;
+                    break;
+                },
+                0x00 => {
+                    let label = self.buf.get(pos + 1..pos + 1 + length).ok_or_else(|| malformed("unexpected end of label"))?;
+                    labels.push(String::from_utf8_lossy(label).into_owned());
+                    pos += 1 + length;
+                },
+                0xc0 => {
+                    let low = *self.buf.get(pos + 1).ok_or_else(|| malformed("unexpected end of pointer"))? as usize;
+                    pointers += 1;
+                    if pointers > MAX_POINTERS {
+                        return Err(malformed("too many compression pointers"));
+                    }
+                    end.get_or_insert(pos + 2);
+                    pos = ((length & 0x3f) << 8) | low;
+                },
+                _ => return Err(malformed("unsupported label type")),
+            }
+        }
+        self.pos = end.unwrap_or(pos);
+        Ok(labels.join("."))
+    }
+
+    fn record(&mut self) -> Result<ResourceRecord> {
+        let name = self.name()?;
+        let ty = self.u16()?;
+        let class = self.u16()?;
+        let ttl = self.u32()?;
+        let length = self.u16()? as usize;
+        let end = self.pos + length;
+        if end > self.buf.len() {
+            return Err(malformed("unexpected end of RDATA"));
+        }
+        let rdata = match ty {
+            TYPE_NS | TYPE_CNAME | TYPE_PTR | TYPE_DNAME if length > 0 => {
+                let mut rdata = Vec::new();
+                write_name(&mut rdata, &self.name()?)?;
+                rdata
+            },
+            TYPE_MX if length > 0 => {
+                let mut rdata = self.bytes(2)?.to_vec();
+                write_name(&mut rdata, &self.name()?)?;
+                rdata
+            },
+            TYPE_SRV if length > 0 => {
+                let mut rdata = self.bytes(6)?.to_vec();
+                write_name(&mut rdata, &self.name()?)?;
+                rdata
+            },
+            TYPE_SOA if length > 0 => {
+                let mut rdata = Vec::new();
+                write_name(&mut rdata, &self.name()?)?;
+                write_name(&mut rdata, &self.name()?)?;
+                rdata.extend_from_slice(self.bytes(20)?);
+                rdata
+            },
+            _ => self.bytes(length)?.to_vec(),
+        };
+        if self.pos != end {
+            return Err(malformed("RDATA length does not match its contents"));
+        }
+        Ok(ResourceRecord { name, ty, class, ttl, rdata })
+    }
+
+}
@@ -0,0 +1,192 @@
+
+use std::time::Duration;
+
+use registrar_common::{DnsRecord, DnsRecordType, Error, Registrar};
+use rfc2136::mock::MockServer;
+use rfc2136::{Builder, Change, Client, Key, Record};
+
+const SECRET: &str = "c2VjcmV0IGtleSBmb3IgdGVzdGluZyB0aGUgc3R1Yg==";
+
+fn key() -> Key {
+    Key::new("update-key.", SECRET).unwrap()
+}
+
+async fn setup() -> (MockServer, Client) {
+    let server = MockServer::start("example.com", Some(key())).await;
+    server.add_record("", DnsRecordType::NS, 3600, "ns1.example.com.");
+    server.add_record("", DnsRecordType::MX, 3600, "10 mail.example.com.");
+    server.add_record("www", DnsRecordType::A, 300, "192.0.2.1");
+    let client = Builder::new()
+        .server(server.address().to_string())
+        .key(key())
+        .zone("example.com")
+        .timeout(Duration::from_secs(5))
+        .build();
+    (server, client)
+}
+
+fn record(name: &str, ty: DnsRecordType, ttl: u32, data: &str) -> Record {
+    Record {
+        name: name.to_string(),
+        ty,
+        ttl,
+        data: data.to_string(),
+    }
+}
+
+#[tokio::test]
+async fn transfer_lists_all_records() {
+    let (server, client) = setup().await;
+    let records = client.transfer("example.com.").await.unwrap();
+    assert_eq!(records.len(), 4);
+    assert_eq!(records[0].ty, DnsRecordType::SOA);
+    assert!(records.contains(&record("example.com", DnsRecordType::MX, 3600, "10 mail.example.com")));
+    assert!(records.contains(&record("www.example.com", DnsRecordType::A, 300, "192.0.2.1")));
+    assert_eq!(server.request_count(), 1);
+}
+
+#[tokio::test]
+async fn transfers_spanning_many_messages_are_verified() {
+    let (server, client) = setup().await;
+    for i in 0..100 {
+        server.add_record(&format!("host{}", i), DnsRecordType::TXT, 60, &format!("record number {}", i));
+    }
+    let records = client.transfer("example.com").await.unwrap();
+    assert_eq!(records.len(), 104);
+    assert!(records.contains(&record("host42.example.com", DnsRecordType::TXT, 60, "record number 42")));
+}
+
+#[tokio::test]
+async fn updates_add_and_delete_records() {
+    let (server, client) = setup().await;
+    let serial = server.serial();
+    client.update("example.com", &[
+        Change::Add(record("mail.example.com", DnsRecordType::A, 300, "192.0.2.25")),
+        Change::Add(record("mail.example.com", DnsRecordType::AAAA, 300, "2001:db8::25")),
+        Change::Add(record("example.com", DnsRecordType::CAA, 3600, "0 issue \"letsencrypt.org\"")),
+        Change::Delete(record("www.example.com", DnsRecordType::A, 0, "192.0.2.1")),
+    ]).await.unwrap();
+    assert_eq!(server.serial(), serial + 1);
+    let records = server.records();
+    assert!(records.contains(&record("mail.example.com", DnsRecordType::AAAA, 300, "2001:db8::25")));
+    assert!(records.contains(&record("example.com", DnsRecordType::CAA, 3600, "0 issue \"letsencrypt.org\"")));
+    assert!(!records.iter().any(|record| record.name == "www.example.com"));
+
+    client.update("example.com", &[
+        Change::DeleteRrset { name: "mail.example.com".to_string(), ty: DnsRecordType::A },
+    ]).await.unwrap();
+    assert!(!server.records().iter().any(|record| record.ty == DnsRecordType::A));
+
+    client.update("example.com", &[Change::DeleteName("mail.example.com".to_string())]).await.unwrap();
+    assert_eq!(server.records().len(), 3);
+}
+
+#[tokio::test]
+async fn updates_outside_the_zone_are_rejected() {
+    let (server, client) = setup().await;
+    let result = client.update("example.com", &[
+        Change::Add(record("www.example.com", DnsRecordType::A, 300, "192.0.2.2")),
+        Change::Add(record("www.example.org", DnsRecordType::A, 300, "192.0.2.3")),
+    ]).await;
+    assert!(matches!(result, Err(Error::Api { code: Some(10), .. })));
+    assert_eq!(server.records().len(), 3);
+}
+
+#[tokio::test]
+async fn wrong_or_missing_keys_are_rejected() {
+    let (server, _) = setup().await;
+    let wrong = Builder::new()
+        .server(server.address().to_string())
+        .key(Key::new("update-key", "d3Jvbmcgc2VjcmV0").unwrap())
+        .build();
+    assert!(matches!(wrong.transfer("example.com").await, Err(Error::AuthenticationFailed)));
+
+    let other_name = Builder::new()
+        .server(server.address().to_string())
+        .key(Key::new("other-key", SECRET).unwrap())
+        .build();
+    assert!(matches!(other_name.transfer("example.com").await, Err(Error::AuthenticationFailed)));
+
+    let unsigned = Builder::new()
+        .server(server.address().to_string())
+        .build();
+    let result = unsigned.update("example.com", &[Change::DeleteName("www.example.com".to_string())]).await;
+    assert!(matches!(result, Err(Error::AuthenticationFailed)));
+    assert_eq!(server.records().len(), 3);
+}
+
+#[tokio::test]
+async fn unsigned_servers_are_not_trusted() {
+    let server = MockServer::start("example.com", None).await;
+    let client = Builder::new()
+        .server(server.address().to_string())
+        .key(key())
+        .build();
+    assert!(matches!(client.transfer("example.com").await, Err(Error::AuthenticationFailed)));
+}
+
+#[tokio::test]
+async fn injected_errors_are_reported() {
+    let (server, client) = setup().await;
+    server.push_error(2);
+    match client.transfer("example.com").await {
+        Err(Error::Api { code, message }) => {
+            assert_eq!(code, Some(2));
+            assert_eq!(message, "SERVFAIL");
+        },
+        other => panic!("unexpected result: {:?}", other),
+    }
+    assert!(client.transfer("example.com").await.is_ok());
+}
+
+#[tokio::test]
+async fn client_is_a_registrar() {
+    let (server, client) = setup().await;
+    let registrar: &dyn Registrar = &client;
+    assert_eq!(registrar.list_zones().await.unwrap(), vec!["example.com".to_string()]);
+
+    registrar.create_record("example.com", &DnsRecord {
+        name: "_sip._tcp".to_string(),
+        ty: DnsRecordType::SRV,
+        content: "5 5060 sip.example.com".to_string(),
+        ttl: None,
+        priority: Some(10),
+    }).await.unwrap();
+    assert!(server.records().contains(&record("_sip._tcp.example.com", DnsRecordType::SRV, 3600, "10 5 5060 sip.example.com")));
+
+    let records = registrar.list_records("example.com").await.unwrap();
+    assert_eq!(records.len(), 5);
+    assert!(records.iter().any(|record| {
+        record.name.is_empty() && record.ty == DnsRecordType::MX && record.priority == Some(10) && record.content == "mail.example.com"
+    }));
+    assert!(records.iter().any(|record| record.name == "www" && record.ttl == Some(300)));
+
+    let unsupported = registrar.create_record("example.com", &DnsRecord {
+        name: "".to_string(),
+        ty: DnsRecordType::HTTPS,
+        content: "1 . alpn=h2".to_string(),
+        ttl: None,
+        priority: None,
+    }).await;
+    assert!(matches!(unsupported, Err(Error::UnsupportedRecordType(DnsRecordType::HTTPS))));
+}
+
+#[tokio::test]
+async fn registrar_addresses_records_by_content() {
+    let (server, client) = setup().await;
+    let registrar: &dyn Registrar = &client;
+    let (id, mut www) = registrar.list_records_with_ids("example.com").await.unwrap()
+        .into_iter()
+        .find(|(_, record)| record.name == "www")
+        .unwrap();
+
+    www.content = "192.0.2.2".to_string();
+    let new_id = registrar.update_record("example.com", &id, &www).await.unwrap();
+    assert_ne!(new_id, id);
+    assert!(server.records().contains(&record("www.example.com", DnsRecordType::A, 300, "192.0.2.2")));
+    assert!(!server.records().contains(&record("www.example.com", DnsRecordType::A, 300, "192.0.2.1")));
+    assert!(matches!(registrar.delete_record("example.com", &id).await, Err(Error::RecordNotFound(_))));
+
+    registrar.delete_record("example.com", &new_id).await.unwrap();
+    assert!(server.records().iter().all(|record| record.name != "www.example.com"));
+}